@@ -0,0 +1,58 @@
+use std::{
+    fs,
+    path::{self, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use log::info;
+use sinjoh_nds::rom::{banner::BannerLanguage, reader::RomReader};
+
+use crate::image;
+
+/// Prints the identity of a ROM and the titles of its banner, and optionally exports its icon to
+/// a PNG file.
+pub fn show_banner(rom_path: &Path, icon_path: Option<&PathBuf>) -> Result<()> {
+    let mut rom_reader = RomReader::read_from_file(rom_path).context("Failed to read the ROM")?;
+    let header = rom_reader.header();
+
+    println!("Title: {}", header.title);
+    println!("Game code: {}", header.game_code);
+    println!("Maker code: {}", header.maker_code);
+    println!("ROM version: {}", header.rom_version);
+
+    let raw_banner = rom_reader
+        .raw_banner()
+        .context("Failed to read the banner of the ROM")?;
+    let banner = rom_reader
+        .banner()
+        .context("Failed to parse the banner of the ROM")?;
+
+    println!(
+        "Banner version: 0x{:04X} (CRC16 {})",
+        banner.version,
+        match banner.is_crc16_valid(&raw_banner) {
+            true => "valid",
+            false => "invalid",
+        }
+    );
+
+    for language in BannerLanguage::ALL {
+        println!(
+            "{:?} title: {}",
+            language,
+            banner.title(language).replace('\n', " / ")
+        );
+    }
+
+    if let Some(icon_path) = icon_path {
+        let png_bytes = image::encode_png(&banner.icon())?;
+        fs::write(icon_path, png_bytes).context("Failed to write the icon")?;
+
+        info!(
+            "Finished exporting icon to: {}",
+            path::absolute(icon_path)?.display()
+        );
+    }
+
+    Ok(())
+}
@@ -14,6 +14,20 @@ const BUILD_MODEL_MATSHP_DAT_REPO_BUILD_PATH: &str =
     "build/res/prebuilt/fielddata/build_model/build_model_matshp.dat";
 const MAP_MATRIX_NARC_REPO_BUILD_PATH: &str = "build/res/field/matrices/map_matrix.narc";
 const LAND_DATA_NARC_REPO_BUILD_PATH: &str = "build/res/field/maps/data/land_data.narc";
+const BUILD_MODEL_NARC_REPO_BUILD_PATH: &str = "build/res/field/props/models/build_model.narc";
+const MAP_TEX_SET_NARC_REPO_BUILD_PATH: &str = "build/res/field/maps/textures/map_tex_set.narc";
+const SOUND_DATA_SDAT_REPO_BUILD_PATH: &str = "build/res/sound/pl_sound_data.sdat";
+
+const AREA_DATA_NARC_ROM_PATH: &str = "fielddata/areadata/area_data.narc";
+const AREA_LIGHT_NARC_ROM_PATH: &str = "fielddata/arealight/arealight.narc";
+const AREA_BUILD_NARC_ROM_PATH: &str = "fielddata/areadata/area_build_model/area_build.narc";
+const BM_ANIME_LIST_NARC_ROM_PATH: &str = "arc/bm_anime_list.narc";
+const BUILD_MODEL_MATSHP_DAT_ROM_PATH: &str = "fielddata/build_model/build_model_matshp.dat";
+const MAP_MATRIX_NARC_ROM_PATH: &str = "fielddata/mapmatrix/map_matrix.narc";
+const LAND_DATA_NARC_ROM_PATH: &str = "fielddata/land_data/land_data.narc";
+const BUILD_MODEL_NARC_ROM_PATH: &str = "fielddata/build_model/build_model.narc";
+const MAP_TEX_SET_NARC_ROM_PATH: &str = "fielddata/areadata/area_map_tex/map_tex_set.narc";
+const SOUND_DATA_SDAT_ROM_PATH: &str = "data/sound/pl_sound_data.sdat";
 
 #[derive(Debug, Parser)]
 #[command(about, author, version, long_about = format!("{} {}", PROJECT_NAME, CLAP_LONG_VERSION))]
@@ -37,14 +51,49 @@ pub(crate) struct ResourcesArgs {
     /// Path to the checkout of the `pret/pokeplatinum` Git repository.
     #[arg(long)]
     pub pokeplatinum_repo_path: Option<PathBuf>,
+
+    /// Path to a Pokémon Platinum ROM image (`.nds` file).
+    #[arg(long, conflicts_with = "pokeplatinum_repo_path")]
+    pub rom_path: Option<PathBuf>,
+}
+
+/// Where the resources files used by the game are located.
+#[derive(Debug, Clone)]
+pub(crate) enum ResourcesLocation {
+    /// The resources files are on the file system of the machine.
+    FileSystem(NarcPaths),
+
+    /// The resources files are in the file system of a ROM image.
+    ///
+    /// The paths are relative to the root of the file system of the ROM.
+    Rom {
+        rom_path: PathBuf,
+        narc_paths: NarcPaths,
+    },
 }
 
 impl ResourcesArgs {
-    pub fn narc_paths(&self) -> NarcPaths {
+    pub fn location(&self) -> ResourcesLocation {
         if let Some(narc_paths) = &self.narc_paths {
-            return narc_paths.clone();
+            return ResourcesLocation::FileSystem(narc_paths.clone());
+        } else if let Some(rom_path) = &self.rom_path {
+            return ResourcesLocation::Rom {
+                rom_path: rom_path.clone(),
+                narc_paths: NarcPaths {
+                    area_data_narc_path: AREA_DATA_NARC_ROM_PATH.into(),
+                    area_light_narc_path: AREA_LIGHT_NARC_ROM_PATH.into(),
+                    area_build_narc_path: AREA_BUILD_NARC_ROM_PATH.into(),
+                    bm_anime_list_narc_path: BM_ANIME_LIST_NARC_ROM_PATH.into(),
+                    build_model_matshp_dat_path: BUILD_MODEL_MATSHP_DAT_ROM_PATH.into(),
+                    map_matrix_narc_path: MAP_MATRIX_NARC_ROM_PATH.into(),
+                    land_data_narc_path: LAND_DATA_NARC_ROM_PATH.into(),
+                    build_model_narc_path: Some(BUILD_MODEL_NARC_ROM_PATH.into()),
+                    map_tex_set_narc_path: Some(MAP_TEX_SET_NARC_ROM_PATH.into()),
+                    sound_data_sdat_path: Some(SOUND_DATA_SDAT_ROM_PATH.into()),
+                },
+            };
         } else if let Some(pokeplatinum_repo_path) = &self.pokeplatinum_repo_path {
-            return NarcPaths {
+            return ResourcesLocation::FileSystem(NarcPaths {
                 area_data_narc_path: pokeplatinum_repo_path.join(AREA_DATA_NARC_REPO_BUILD_PATH),
                 area_light_narc_path: pokeplatinum_repo_path.join(AREA_LIGHT_NARC_REPO_BUILD_PATH),
                 area_build_narc_path: pokeplatinum_repo_path.join(AREA_BUILD_NARC_REPO_BUILD_PATH),
@@ -54,7 +103,16 @@ impl ResourcesArgs {
                     .join(BUILD_MODEL_MATSHP_DAT_REPO_BUILD_PATH),
                 map_matrix_narc_path: pokeplatinum_repo_path.join(MAP_MATRIX_NARC_REPO_BUILD_PATH),
                 land_data_narc_path: pokeplatinum_repo_path.join(LAND_DATA_NARC_REPO_BUILD_PATH),
-            };
+                build_model_narc_path: Some(
+                    pokeplatinum_repo_path.join(BUILD_MODEL_NARC_REPO_BUILD_PATH),
+                ),
+                map_tex_set_narc_path: Some(
+                    pokeplatinum_repo_path.join(MAP_TEX_SET_NARC_REPO_BUILD_PATH),
+                ),
+                sound_data_sdat_path: Some(
+                    pokeplatinum_repo_path.join(SOUND_DATA_SDAT_REPO_BUILD_PATH),
+                ),
+            });
         }
 
         // Clap should have required exactly one of the arguments to be present in the CLI
@@ -68,7 +126,7 @@ impl ResourcesArgs {
 //
 // See [`clap-rs/clap#5092`](https://github.com/clap-rs/clap/issues/5092)
 #[derive(Debug, Args, Clone)]
-#[group(conflicts_with = "pokeplatinum_repo_path")]
+#[group(conflicts_with_all = ["pokeplatinum_repo_path", "rom_path"])]
 #[group(requires_all = ["area_data_narc_path", "area_light_narc_path", "area_build_narc_path", "bm_anime_list_narc_path", "build_model_matshp_dat_path", "map_matrix_narc_path", "land_data_narc_path"])]
 pub(crate) struct NarcPaths {
     /// Path to the `area_data.narc` file.
@@ -98,6 +156,25 @@ pub(crate) struct NarcPaths {
     /// Path to the `land_data.narc` file.
     #[arg(long, required = false)]
     pub land_data_narc_path: PathBuf,
+
+    /// Path to the `build_model.narc` file.
+    ///
+    /// Only required by the glTF export commands.
+    #[arg(long)]
+    pub build_model_narc_path: Option<PathBuf>,
+
+    /// Path to the `map_tex_set.narc` file.
+    ///
+    /// Only required by the glTF export commands.
+    #[arg(long)]
+    pub map_tex_set_narc_path: Option<PathBuf>,
+
+    /// Path to the `pl_sound_data.sdat` file.
+    ///
+    /// Only required by the sound commands. If not specified, the music names of the SQL tables
+    /// are left empty.
+    #[arg(long)]
+    pub sound_data_sdat_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -107,6 +184,23 @@ pub(crate) enum Commands {
         #[command(subcommand)]
         command: SqlCommands,
     },
+
+    /// Export game data to standard file formats.
+    Export {
+        #[command(subcommand)]
+        command: ExportCommands,
+    },
+
+    /// Print the titles found in the banner of the ROM, and optionally export its icon.
+    ///
+    /// This requires `--rom-path`, and does not load any other game data.
+    Banner {
+        /// The file path where the icon of the ROM will be saved, as a PNG file.
+        /// If the file does not exist, it will be created.
+        /// If it exists, it will be overwritten.
+        #[arg(long)]
+        icon_path: Option<PathBuf>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -122,3 +216,80 @@ pub(crate) enum SqlCommands {
         export_path: PathBuf,
     },
 }
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum ExportCommands {
+    /// Export a land data map, along with its map props, to a binary glTF 2.0 file.
+    Gltf {
+        /// The ID of the land data to export.
+        land_data_id: usize,
+
+        /// The ID of the area data whose textures are used by the map.
+        /// If not specified, it is found using the map headers.
+        #[arg(long)]
+        area_data_id: Option<usize>,
+
+        /// The file path where the binary glTF file (`.glb`) will be saved.
+        /// If the file does not exist, it will be created.
+        /// If it exists, it will be overwritten.
+        export_path: PathBuf,
+    },
+    /// Export a whole map matrix, made of land data maps placed on a grid, to a binary glTF 2.0
+    /// file.
+    GltfMatrix {
+        /// The ID of the map matrix to export.
+        map_matrix_id: usize,
+
+        /// The ID of the area data whose textures are used by every map.
+        /// If not specified, it is found for each map using the map headers.
+        #[arg(long)]
+        area_data_id: Option<usize>,
+
+        /// The file path where the binary glTF file (`.glb`) will be saved.
+        /// If the file does not exist, it will be created.
+        /// If it exists, it will be overwritten.
+        export_path: PathBuf,
+    },
+    /// Export a sequence of the sound archive to a MIDI file.
+    Midi {
+        /// The ID of the sequence to export.
+        sequence_id: usize,
+
+        /// The number of times the looping part of the sequence is played.
+        #[arg(long, default_value_t = 2)]
+        loop_count: u32,
+
+        /// The file path where the MIDI file (`.mid`) will be saved.
+        /// If the file does not exist, it will be created.
+        /// If it exists, it will be overwritten.
+        export_path: PathBuf,
+    },
+    /// Export the waves of a wave archive of the sound archive to WAV files.
+    Wav {
+        /// The ID of the wave archive to export.
+        wave_archive_id: usize,
+
+        /// The directory where the WAV files (`.wav`) will be saved, one per wave.
+        /// If the directory does not exist, it will be created.
+        /// Existing files will be overwritten.
+        export_directory: PathBuf,
+    },
+    /// Export the music played on a map to a MIDI file.
+    MidiMap {
+        /// The ID of the map header whose music is exported.
+        map_header_id: usize,
+
+        /// Export the music played at night, instead of the one played during the day.
+        #[arg(long)]
+        night: bool,
+
+        /// The number of times the looping part of the sequence is played.
+        #[arg(long, default_value_t = 2)]
+        loop_count: u32,
+
+        /// The file path where the MIDI file (`.mid`) will be saved.
+        /// If the file does not exist, it will be created.
+        /// If it exists, it will be overwritten.
+        export_path: PathBuf,
+    },
+}
@@ -0,0 +1,336 @@
+use std::{collections::HashMap, io::Write};
+
+use anyhow::Result;
+use serde_json::{Value, json};
+use sinjoh_nds::{gx::pipeline::Triangle, image::RgbaImage};
+
+use crate::image;
+
+/// The magic number of binary glTF files.
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+
+/// The version of the binary glTF container.
+const GLB_VERSION: u32 = 2;
+
+/// The type of the chunk holding the JSON document.
+const GLB_CHUNK_TYPE_JSON: &[u8; 4] = b"JSON";
+
+/// The type of the chunk holding the binary buffer.
+const GLB_CHUNK_TYPE_BIN: &[u8; 4] = b"BIN\0";
+
+/// The component type of accessors holding 32-bit floats.
+const COMPONENT_TYPE_FLOAT: u32 = 5126;
+
+/// The target of buffer views holding vertex attributes.
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+
+/// The wrapping mode of samplers repeating the texture.
+pub(crate) const WRAP_REPEAT: u32 = 10497;
+
+/// The wrapping mode of samplers repeating the texture, flipping it on every repetition.
+pub(crate) const WRAP_MIRRORED_REPEAT: u32 = 33648;
+
+/// The wrapping mode of samplers clamping the texture coordinates.
+pub(crate) const WRAP_CLAMP_TO_EDGE: u32 = 33071;
+
+/// The filter of samplers using the nearest texel, as done by the DS.
+const FILTER_NEAREST: u32 = 9728;
+
+/// Builds a binary glTF 2.0 file (`.glb`), made of a single scene.
+///
+/// All the binary data (vertex attributes and images) is stored in the single buffer of the file.
+#[derive(Default)]
+pub(crate) struct GltfBuilder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    images: Vec<Value>,
+    samplers: Vec<Value>,
+    textures: Vec<Value>,
+    materials: Vec<Value>,
+    meshes: Vec<Value>,
+    nodes: Vec<Value>,
+    scene_nodes: Vec<usize>,
+
+    /// Indices of the images, by PNG data.
+    image_indices: HashMap<Vec<u8>, usize>,
+
+    /// Indices of the samplers, by wrapping modes.
+    sampler_indices: HashMap<(u32, u32), usize>,
+
+    /// Indices of the textures, by image and sampler indices.
+    texture_indices: HashMap<(usize, usize), usize>,
+}
+
+impl GltfBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a buffer view over the specified bytes, which are appended to the buffer.
+    fn add_buffer_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // Buffer views of vertex attributes must be aligned to their component size
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+
+        let mut buffer_view = json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": bytes.len(),
+        });
+
+        if let Some(target) = target {
+            buffer_view["target"] = json!(target);
+        }
+
+        self.buffer.extend_from_slice(bytes);
+        self.buffer_views.push(buffer_view);
+
+        self.buffer_views.len() - 1
+    }
+
+    /// Adds an accessor over a list of float vectors, and returns its index.
+    ///
+    /// The minimum and maximum values of each component are added to the accessor if `bounds` is
+    /// true, as required for the positions of vertices.
+    fn add_float_accessor<const N: usize>(&mut self, values: &[[f32; N]], bounds: bool) -> usize {
+        let bytes = values
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        let buffer_view = self.add_buffer_view(&bytes, Some(TARGET_ARRAY_BUFFER));
+
+        let mut accessor = json!({
+            "bufferView": buffer_view,
+            "componentType": COMPONENT_TYPE_FLOAT,
+            "count": values.len(),
+            "type": format!("VEC{N}"),
+        });
+
+        if bounds {
+            let (min, max) = values.iter().fold(
+                ([f32::INFINITY; N], [f32::NEG_INFINITY; N]),
+                |(mut min, mut max), value| {
+                    for i in 0..N {
+                        min[i] = min[i].min(value[i]);
+                        max[i] = max[i].max(value[i]);
+                    }
+
+                    (min, max)
+                },
+            );
+
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Adds a texture made from the specified image, and returns its index.
+    ///
+    /// Identical images and samplers are shared between the textures.
+    pub fn add_texture(&mut self, image: &RgbaImage, wrap_s: u32, wrap_t: u32) -> Result<usize> {
+        let image_index = self.add_image(image)?;
+
+        let sampler_count = self.samplers.len();
+        let sampler_index = *self
+            .sampler_indices
+            .entry((wrap_s, wrap_t))
+            .or_insert(sampler_count);
+        if sampler_index == sampler_count {
+            self.samplers.push(json!({
+                "magFilter": FILTER_NEAREST,
+                "minFilter": FILTER_NEAREST,
+                "wrapS": wrap_s,
+                "wrapT": wrap_t,
+            }));
+        }
+
+        let texture_count = self.textures.len();
+        let texture_index = *self
+            .texture_indices
+            .entry((image_index, sampler_index))
+            .or_insert(texture_count);
+        if texture_index == texture_count {
+            self.textures.push(json!({
+                "source": image_index,
+                "sampler": sampler_index,
+            }));
+        }
+
+        Ok(texture_index)
+    }
+
+    /// Encodes the specified image to PNG, adds it to the buffer, and returns its index.
+    fn add_image(&mut self, image: &RgbaImage) -> Result<usize> {
+        let png_bytes = image::encode_png(image)?;
+
+        if let Some(&image_index) = self.image_indices.get(&png_bytes) {
+            return Ok(image_index);
+        }
+
+        let buffer_view = self.add_buffer_view(&png_bytes, None);
+        self.images.push(json!({
+            "bufferView": buffer_view,
+            "mimeType": "image/png",
+        }));
+
+        let image_index = self.images.len() - 1;
+        self.image_indices.insert(png_bytes, image_index);
+
+        Ok(image_index)
+    }
+
+    /// Adds a material, and returns its index.
+    pub fn add_material(&mut self, material: Value) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    /// Adds the specified triangles to the buffer, and returns a primitive drawing them.
+    ///
+    /// Texture coordinates are only added if the size of the texture is specified, as they are
+    /// normalized using it. Normals are only added if every vertex has one.
+    pub fn add_primitive(
+        &mut self,
+        triangles: &[Triangle],
+        texture_size: Option<(f32, f32)>,
+        material: Option<usize>,
+    ) -> Value {
+        let vertices = || {
+            triangles
+                .iter()
+                .flat_map(|triangle| triangle.vertices.iter())
+        };
+
+        let positions = vertices()
+            .map(|vertex| vertex.position.into())
+            .collect::<Vec<[f32; 3]>>();
+        let colors = vertices()
+            .map(|vertex| {
+                [vertex.color.red, vertex.color.green, vertex.color.blue]
+                    .map(|component| component as f32 / 31.0)
+            })
+            .collect::<Vec<_>>();
+
+        let mut attributes = json!({
+            "POSITION": self.add_float_accessor(&positions, true),
+            "COLOR_0": self.add_float_accessor(&colors, false),
+        });
+
+        if let Some(normals) = vertices()
+            .map(|vertex| vertex.normal.map(Into::<[f32; 3]>::into))
+            .collect::<Option<Vec<_>>>()
+        {
+            attributes["NORMAL"] = json!(self.add_float_accessor(&normals, false));
+        }
+
+        if let Some((width, height)) = texture_size {
+            let texcoords = vertices()
+                .map(|vertex| [vertex.texcoord.x / width, vertex.texcoord.y / height])
+                .collect::<Vec<_>>();
+
+            attributes["TEXCOORD_0"] = json!(self.add_float_accessor(&texcoords, false));
+        }
+
+        let mut primitive = json!({ "attributes": attributes });
+        if let Some(material) = material {
+            primitive["material"] = json!(material);
+        }
+
+        primitive
+    }
+
+    /// Adds a mesh made of the specified primitives, and returns its index.
+    pub fn add_mesh(&mut self, name: &str, primitives: Vec<Value>) -> usize {
+        self.meshes.push(json!({
+            "name": name,
+            "primitives": primitives,
+        }));
+        self.meshes.len() - 1
+    }
+
+    /// Adds a node, and returns its index.
+    ///
+    /// The node is not part of the scene until it is added to it, or to the children of another
+    /// node.
+    pub fn add_node(&mut self, node: Value) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Adds a node to the root nodes of the scene.
+    pub fn add_scene_node(&mut self, node: usize) {
+        self.scene_nodes.push(node);
+    }
+
+    /// Returns the JSON document describing the file.
+    fn document(&self) -> Value {
+        let mut document = json!({
+            "asset": {
+                "version": "2.0",
+                "generator": env!("CARGO_PKG_NAME"),
+            },
+            "scene": 0,
+            "scenes": [{ "nodes": self.scene_nodes }],
+            "nodes": self.nodes,
+        });
+
+        let arrays = [
+            ("meshes", &self.meshes),
+            ("materials", &self.materials),
+            ("textures", &self.textures),
+            ("samplers", &self.samplers),
+            ("images", &self.images),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+        ];
+
+        // Empty arrays are not allowed by the specification
+        for (name, values) in arrays {
+            if !values.is_empty() {
+                document[name] = json!(values);
+            }
+        }
+
+        if !self.buffer.is_empty() {
+            document["buffers"] = json!([{ "byteLength": self.buffer.len() }]);
+        }
+
+        document
+    }
+
+    /// Writes the binary glTF file.
+    pub fn write_glb<W: Write>(&self, mut writer: W) -> Result<()> {
+        // Chunks must be aligned to 4 bytes, with spaces for the JSON chunk and zeros for the
+        // binary chunk
+        let mut json_chunk = serde_json::to_vec(&self.document())?;
+        json_chunk.resize(json_chunk.len().next_multiple_of(4), b' ');
+
+        let mut bin_chunk = self.buffer.clone();
+        bin_chunk.resize(bin_chunk.len().next_multiple_of(4), 0);
+
+        let mut total_length = 12 + 8 + json_chunk.len();
+        if !bin_chunk.is_empty() {
+            total_length += 8 + bin_chunk.len();
+        }
+
+        writer.write_all(GLB_MAGIC)?;
+        writer.write_all(&GLB_VERSION.to_le_bytes())?;
+        writer.write_all(&(total_length as u32).to_le_bytes())?;
+
+        writer.write_all(&(json_chunk.len() as u32).to_le_bytes())?;
+        writer.write_all(GLB_CHUNK_TYPE_JSON)?;
+        writer.write_all(&json_chunk)?;
+
+        if !bin_chunk.is_empty() {
+            writer.write_all(&(bin_chunk.len() as u32).to_le_bytes())?;
+            writer.write_all(GLB_CHUNK_TYPE_BIN)?;
+            writer.write_all(&bin_chunk)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::{self, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use log::{debug, info, warn};
+use serde_json::json;
+use sinjoh_nds::{
+    DsFixed32,
+    g3d::{bmd::Nsbmd, btx::Nsbtx, tex0::Tex0},
+};
+use sinjoh_plat::{
+    data::map_headers::PLATINUM_MAP_HEADERS, land_data::MapPropInstance, map_matrix::MapMatrix,
+};
+
+use super::builder::GltfBuilder;
+use crate::plat_loader::{MapAssets, PlatResources};
+
+/// Builds a glTF scene made of land data maps, along with their map props.
+pub(crate) struct MapSceneBuilder<'a> {
+    builder: GltfBuilder,
+    resources: &'a PlatResources,
+    map_assets: &'a mut MapAssets,
+
+    /// Map texture sets, by ID. `None` if the texture set could not be read.
+    texture_sets: HashMap<u16, Option<Nsbtx>>,
+
+    /// Meshes of the terrains, by land data ID and map texture set ID. `None` if the terrain has
+    /// no mesh.
+    terrain_meshes: HashMap<(usize, u16), Option<usize>>,
+
+    /// Meshes of the map props, by model ID and map texture set ID. `None` if the model has no
+    /// mesh.
+    map_prop_meshes: HashMap<(u32, u16), Option<usize>>,
+}
+
+impl<'a> MapSceneBuilder<'a> {
+    pub fn new(resources: &'a PlatResources, map_assets: &'a mut MapAssets) -> Self {
+        Self {
+            builder: GltfBuilder::new(),
+            resources,
+            map_assets,
+            texture_sets: HashMap::new(),
+            terrain_meshes: HashMap::new(),
+            map_prop_meshes: HashMap::new(),
+        }
+    }
+
+    /// Adds a land data map to the scene, along with its map props, and returns the index of its
+    /// node.
+    ///
+    /// The map uses the textures of the specified area data, and is placed at the specified
+    /// translation.
+    pub fn add_land_data(
+        &mut self,
+        land_data_id: usize,
+        area_data_id: usize,
+        translation: Vector3<f32>,
+    ) -> Result<usize> {
+        let land_data = self
+            .resources
+            .land_data
+            .get(land_data_id)
+            .ok_or_else(|| anyhow!("Land data {land_data_id} does not exist"))?;
+        let area_data = self
+            .resources
+            .area_data
+            .get(area_data_id)
+            .ok_or_else(|| anyhow!("Area data {area_data_id} does not exist"))?;
+
+        let texture_set_id = area_data.map_texture_archive_id;
+        self.load_texture_set(texture_set_id)?;
+
+        let mut children = Vec::with_capacity(land_data.map_props.len() + 1);
+
+        // Add the terrain
+        if let Some(mesh) = self.terrain_mesh(land_data_id, texture_set_id)? {
+            children.push(self.builder.add_node(json!({
+                "name": "terrain",
+                "mesh": mesh,
+            })));
+        }
+
+        // Add the map props
+        for (index, map_prop) in land_data.map_props.iter().enumerate() {
+            let Some(mesh) = self.map_prop_mesh(map_prop.map_prop_model_id, texture_set_id)? else {
+                continue;
+            };
+
+            children.push(self.builder.add_node(json!({
+                "name": format!("map_prop_{index}"),
+                "mesh": mesh,
+                "translation": Into::<[f32; 3]>::into(map_prop.position.map(|value| value.to_num::<f32>())),
+                "rotation": map_prop_rotation(map_prop),
+                "scale": Into::<[f32; 3]>::into(map_prop.scale.map(|value| value.to_num::<f32>())),
+            })));
+        }
+
+        let mut node = json!({
+            "name": format!("land_data_{land_data_id}"),
+            "translation": Into::<[f32; 3]>::into(translation),
+        });
+        if !children.is_empty() {
+            node["children"] = json!(children);
+        }
+
+        Ok(self.builder.add_node(node))
+    }
+
+    /// Adds a node to the root nodes of the scene.
+    pub fn add_scene_node(&mut self, node: usize) {
+        self.builder.add_scene_node(node);
+    }
+
+    /// Writes the scene to a binary glTF file.
+    pub fn write_glb(&self, path: &PathBuf) -> Result<()> {
+        let file = File::create(path).context("Failed to create the file at the export path")?;
+        self.builder.write_glb(BufWriter::new(file))
+    }
+
+    /// Reads and parses the map texture set with the specified ID, if it was not already.
+    fn load_texture_set(&mut self, texture_set_id: u16) -> Result<()> {
+        if self.texture_sets.contains_key(&texture_set_id) {
+            return Ok(());
+        }
+
+        let texture_set = match self.map_assets.map_texture_sets.get_file(texture_set_id) {
+            Ok(bytes) => Some(Nsbtx::parse_bytes(&bytes).with_context(|| {
+                format!("Failed to parse the map texture set {texture_set_id}")
+            })?),
+            Err(err) => {
+                warn!("Unable to read the map texture set {texture_set_id}: {err}");
+                None
+            }
+        };
+
+        self.texture_sets.insert(texture_set_id, texture_set);
+        Ok(())
+    }
+
+    /// Returns the mesh of the terrain of the land data with the specified ID, adding it to the
+    /// glTF file if it was not already.
+    fn terrain_mesh(&mut self, land_data_id: usize, texture_set_id: u16) -> Result<Option<usize>> {
+        if let Some(&mesh) = self.terrain_meshes.get(&(land_data_id, texture_set_id)) {
+            return Ok(mesh);
+        }
+
+        let land_data = &self.resources.land_data[land_data_id];
+        let nsbmd = Nsbmd::parse_bytes(&land_data.map_model).with_context(|| {
+            format!("Failed to parse the map model of land data {land_data_id}")
+        })?;
+
+        let mesh = match nsbmd.models.get_by_index(0) {
+            Some(model) => {
+                let texture_blocks =
+                    texture_blocks(nsbmd.textures.as_ref(), &self.texture_sets, texture_set_id);
+
+                super::add_model(
+                    &mut self.builder,
+                    &format!("land_data_{land_data_id}"),
+                    model,
+                    &texture_blocks,
+                )?
+            }
+            None => {
+                warn!("The map model of land data {land_data_id} has no model");
+                None
+            }
+        };
+
+        self.terrain_meshes
+            .insert((land_data_id, texture_set_id), mesh);
+        Ok(mesh)
+    }
+
+    /// Returns the mesh of the map prop model with the specified ID, adding it to the glTF file
+    /// if it was not already.
+    fn map_prop_mesh(&mut self, model_id: u32, texture_set_id: u16) -> Result<Option<usize>> {
+        if let Some(&mesh) = self.map_prop_meshes.get(&(model_id, texture_set_id)) {
+            return Ok(mesh);
+        }
+
+        let bytes = u16::try_from(model_id)
+            .ok()
+            .and_then(|model_id| self.map_assets.build_models.get_file(model_id).ok());
+
+        let mesh = match bytes {
+            Some(bytes) => {
+                let nsbmd = Nsbmd::parse_bytes(&bytes)
+                    .with_context(|| format!("Failed to parse the map prop model {model_id}"))?;
+
+                match nsbmd.models.iter().next() {
+                    Some(model) => {
+                        // Map props usually embed their textures, but can also use the ones of
+                        // the area
+                        let texture_blocks = texture_blocks(
+                            nsbmd.textures.as_ref(),
+                            &self.texture_sets,
+                            texture_set_id,
+                        );
+
+                        super::add_model(
+                            &mut self.builder,
+                            &model.name,
+                            &model.value,
+                            &texture_blocks,
+                        )?
+                    }
+                    None => None,
+                }
+            }
+            None => {
+                warn!("Unable to read the map prop model {model_id}, ignoring it");
+                None
+            }
+        };
+
+        debug!("Added map prop model {model_id} as mesh {mesh:?}");
+
+        self.map_prop_meshes
+            .insert((model_id, texture_set_id), mesh);
+        Ok(mesh)
+    }
+}
+
+/// Returns the texture blocks in which the textures of a model are looked up: the ones embedded
+/// in the model first, then the ones of the map texture set.
+fn texture_blocks<'a>(
+    embedded_textures: Option<&'a Tex0>,
+    texture_sets: &'a HashMap<u16, Option<Nsbtx>>,
+    texture_set_id: u16,
+) -> Vec<&'a Tex0> {
+    let texture_set = texture_sets
+        .get(&texture_set_id)
+        .and_then(|texture_set| texture_set.as_ref())
+        .map(|texture_set| &texture_set.textures);
+
+    [embedded_textures, texture_set]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Returns the rotation of a map prop, as a glTF quaternion (`[x, y, z, w]`).
+///
+/// The rotation is applied around the X axis, then the Y axis, then the Z axis.
+fn map_prop_rotation(map_prop: &MapPropInstance) -> [f32; 4] {
+    // Angles are stored in the raw value, where 65536 is a full turn
+    let angle = |value: DsFixed32| Deg((value.to_bits() & 0xFFFF) as f32 * 360.0 / 65536.0);
+
+    let rotation = Quaternion::from_angle_z(angle(map_prop.rotation.z))
+        * Quaternion::from_angle_y(angle(map_prop.rotation.y))
+        * Quaternion::from_angle_x(angle(map_prop.rotation.x));
+
+    [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s]
+}
+
+/// Size of a land data map on the X and Z axes, in world units (32 tiles of 16 units).
+const LAND_DATA_SIZE: f32 = 512.0;
+
+/// Height of an altitude step of a map matrix, in world units (half a tile).
+const ALTITUDE_STEP: f32 = 8.0;
+
+/// Finds the area data used by the map at the specified index of a map matrix, using the map
+/// headers.
+///
+/// The map header is the one of the cell of the map matrix, or if the map matrix has no map header
+/// IDs, the first one using the map matrix.
+fn map_matrix_cell_area_data_id(
+    map_matrix_id: usize,
+    map_matrix: &MapMatrix,
+    index: usize,
+) -> Option<usize> {
+    let map_header = match &map_matrix.map_header_ids {
+        Some(map_header_ids) => map_header_ids
+            .get(index)
+            .and_then(|&map_header_id| PLATINUM_MAP_HEADERS.get(&(map_header_id as usize))),
+        None => PLATINUM_MAP_HEADERS
+            .iter()
+            .filter(|(_, map_header)| map_header.map_matrix_id as usize == map_matrix_id)
+            .min_by_key(|(map_header_id, _)| **map_header_id)
+            .map(|(_, map_header)| map_header),
+    };
+
+    map_header.map(|map_header| map_header.area_data_archive_id as usize)
+}
+
+/// Finds the area data used by a land data, using the map headers of the map matrices holding
+/// it.
+pub(crate) fn find_area_data_id(resources: &PlatResources, land_data_id: usize) -> Result<usize> {
+    resources
+        .map_matrices
+        .iter()
+        .enumerate()
+        .flat_map(|(map_matrix_id, map_matrix)| {
+            map_matrix
+                .land_data_ids
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| **id as usize == land_data_id)
+                .map(move |(index, _)| (map_matrix_id, map_matrix, index))
+        })
+        .find_map(|(map_matrix_id, map_matrix, index)| {
+            map_matrix_cell_area_data_id(map_matrix_id, map_matrix, index)
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "Unable to find the area data of land data {land_data_id}, please specify it manually"
+            )
+        })
+}
+
+pub fn export_land_data(
+    resources: &PlatResources,
+    map_assets: &mut MapAssets,
+    land_data_id: usize,
+    area_data_id: Option<usize>,
+    path: &PathBuf,
+) -> Result<()> {
+    let area_data_id = match area_data_id {
+        Some(area_data_id) => area_data_id,
+        None => find_area_data_id(resources, land_data_id)?,
+    };
+    info!("Exporting land data {land_data_id} with area data {area_data_id}");
+
+    let mut scene_builder = MapSceneBuilder::new(resources, map_assets);
+    let node =
+        scene_builder.add_land_data(land_data_id, area_data_id, Vector3::new(0.0, 0.0, 0.0))?;
+    scene_builder.add_scene_node(node);
+    scene_builder.write_glb(path)?;
+
+    info!(
+        "Finished exporting glTF file to: {}",
+        path::absolute(path)?.display()
+    );
+
+    Ok(())
+}
+
+pub fn export_map_matrix(
+    resources: &PlatResources,
+    map_assets: &mut MapAssets,
+    map_matrix_id: usize,
+    area_data_id: Option<usize>,
+    path: &PathBuf,
+) -> Result<()> {
+    let map_matrix = resources
+        .map_matrices
+        .get(map_matrix_id)
+        .ok_or_else(|| anyhow!("Map matrix {map_matrix_id} does not exist"))?;
+    info!(
+        "Exporting map matrix {map_matrix_id} ({}x{} maps)",
+        map_matrix.width, map_matrix.height
+    );
+
+    let mut scene_builder = MapSceneBuilder::new(resources, map_assets);
+    let mut land_data_count = 0;
+
+    for (index, &land_data_id) in map_matrix.land_data_ids.iter().enumerate() {
+        let land_data_id = land_data_id as usize;
+
+        // Cells without a map hold an invalid land data ID
+        if land_data_id >= resources.land_data.len() {
+            continue;
+        }
+
+        let Some(area_data_id) =
+            area_data_id.or_else(|| map_matrix_cell_area_data_id(map_matrix_id, map_matrix, index))
+        else {
+            warn!("Unable to find the area data of land data {land_data_id}, ignoring it");
+            continue;
+        };
+
+        // Maps are centered on their cell
+        let (x, z) = map_matrix.map_index_to_coords(index as u16)?;
+        let altitude = map_matrix
+            .altitudes
+            .as_ref()
+            .and_then(|altitudes| altitudes.get(index))
+            .copied()
+            .unwrap_or(0);
+
+        let translation = Vector3::new(
+            (x as f32 + 0.5) * LAND_DATA_SIZE,
+            altitude as f32 * ALTITUDE_STEP,
+            (z as f32 + 0.5) * LAND_DATA_SIZE,
+        );
+
+        debug!("Adding land data {land_data_id} at ({x}, {z}), with area data {area_data_id}");
+
+        let node = scene_builder.add_land_data(land_data_id, area_data_id, translation)?;
+        scene_builder.add_scene_node(node);
+        land_data_count += 1;
+    }
+
+    scene_builder.write_glb(path)?;
+
+    info!(
+        "Finished exporting glTF file with {land_data_count} maps to: {}",
+        path::absolute(path)?.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use log::warn;
+use serde_json::{Value, json};
+use sinjoh_nds::g3d::{
+    model::{Material, Model},
+    tex0::{Tex0, TextureFormat, TextureParams},
+};
+
+use builder::{GltfBuilder, WRAP_CLAMP_TO_EDGE, WRAP_MIRRORED_REPEAT, WRAP_REPEAT};
+
+pub(crate) mod builder;
+pub(crate) mod export;
+
+/// A material converted to glTF, along with the size of its texture.
+struct ConvertedMaterial {
+    index: usize,
+    texture_size: Option<(f32, f32)>,
+}
+
+/// Adds the mesh of a model to the glTF file, and returns its index.
+///
+/// Textures are looked up in the specified texture blocks, in order. Returns `None` if the model
+/// has no triangles.
+pub(crate) fn add_model(
+    builder: &mut GltfBuilder,
+    name: &str,
+    model: &Model,
+    texture_blocks: &[&Tex0],
+) -> Result<Option<usize>> {
+    let mesh = model
+        .to_mesh()
+        .with_context(|| format!("Failed to extract the mesh of the model `{name}`"))?;
+
+    // Primitives are grouped by material, so each material is only converted once
+    let mut primitives = Vec::with_capacity(mesh.primitives.len());
+    for primitive in &mesh.primitives {
+        let material = primitive
+            .material_id
+            .and_then(|material_id| model.materials.get_by_index(material_id as usize))
+            .map(|material| add_material(builder, material, texture_blocks))
+            .transpose()?;
+
+        primitives.push(builder.add_primitive(
+            &primitive.triangles,
+            material.as_ref().and_then(|material| material.texture_size),
+            material.map(|material| material.index),
+        ));
+    }
+
+    if primitives.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(builder.add_mesh(name, primitives)))
+}
+
+/// Converts a material of a model to glTF, along with its texture.
+fn add_material(
+    builder: &mut GltfBuilder,
+    material: &Material,
+    texture_blocks: &[&Tex0],
+) -> Result<ConvertedMaterial> {
+    // An alpha of 0 draws the polygons as wireframes, which is not supported by glTF
+    let alpha = match material.alpha() {
+        0 => 1.0,
+        alpha => alpha as f32 / 31.0,
+    };
+
+    // The diffuse color only affects the polygons if they are lit, or if it is used as the
+    // vertex color (in which case, it is already part of the vertices)
+    let base_color = if material.lights_mask() != 0 && !material.diffuse_as_vertex_color() {
+        let diffuse = material.diffuse();
+        [diffuse.red, diffuse.green, diffuse.blue].map(|component| component as f32 / 31.0)
+    } else {
+        [1.0; 3]
+    };
+
+    let mut pbr = json!({
+        "baseColorFactor": [base_color[0], base_color[1], base_color[2], alpha],
+        "metallicFactor": 0.0,
+        "roughnessFactor": 1.0,
+    });
+    let mut alpha_mode = if alpha < 1.0 { "BLEND" } else { "OPAQUE" };
+    let mut texture_size = None;
+
+    if let Some(texture_name) = &material.texture_name {
+        match add_texture(builder, material, texture_name, texture_blocks)? {
+            Some((texture_index, params, has_transparency)) => {
+                pbr["baseColorTexture"] = json!({ "index": texture_index });
+                texture_size = Some((params.width as f32, params.height as f32));
+
+                if matches!(params.format, TextureFormat::A3I5 | TextureFormat::A5I3) {
+                    alpha_mode = "BLEND";
+                } else if has_transparency && alpha_mode == "OPAQUE" {
+                    alpha_mode = "MASK";
+                }
+            }
+            None => warn!("Texture `{texture_name}` was not found, ignoring it"),
+        }
+    }
+
+    let mut gltf_material = json!({
+        "pbrMetallicRoughness": pbr,
+        "alphaMode": alpha_mode,
+        "doubleSided": material.render_back_faces(),
+    });
+
+    if let Some(texture_name) = &material.texture_name {
+        gltf_material["name"] = Value::String(texture_name.clone());
+    }
+
+    Ok(ConvertedMaterial {
+        index: builder.add_material(gltf_material),
+        texture_size,
+    })
+}
+
+/// Decodes the texture of a material, and adds it to the glTF file.
+///
+/// Returns the index of the texture, its parameters, and whether some of its texels are
+/// transparent. Returns `None` if the texture is not in any of the texture blocks.
+fn add_texture(
+    builder: &mut GltfBuilder,
+    material: &Material,
+    texture_name: &str,
+    texture_blocks: &[&Tex0],
+) -> Result<Option<(usize, TextureParams, bool)>> {
+    let Some((texture_block, texture)) = texture_blocks.iter().find_map(|texture_block| {
+        texture_block
+            .textures
+            .get(texture_name)
+            .map(|texture| (texture_block, texture))
+    }) else {
+        return Ok(None);
+    };
+
+    let image = texture_block
+        .decode_texture(texture_name, material.palette_name.as_deref())
+        .with_context(|| format!("Failed to decode the texture `{texture_name}`"))?;
+    let has_transparency = image.pixels.chunks_exact(4).any(|pixel| pixel[3] < 0xFF);
+
+    // The wrapping of the texture is set by the material
+    let wrap_params = TextureParams::from_raw(material.texture_params);
+    let wrap = |repeat, flip| match (repeat, flip) {
+        (true, true) => WRAP_MIRRORED_REPEAT,
+        (true, false) => WRAP_REPEAT,
+        _ => WRAP_CLAMP_TO_EDGE,
+    };
+
+    let texture_index = builder.add_texture(
+        &image,
+        wrap(wrap_params.repeat_s, wrap_params.flip_s),
+        wrap(wrap_params.repeat_t, wrap_params.flip_t),
+    )?;
+
+    Ok(Some((texture_index, texture.params, has_transparency)))
+}
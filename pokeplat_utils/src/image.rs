@@ -0,0 +1,23 @@
+use anyhow::{Context, Result};
+use sinjoh_nds::image::RgbaImage;
+
+/// Encodes the specified image to PNG.
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>> {
+    let mut png_bytes = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut png_bytes, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder
+        .write_header()
+        .context("Failed to write the header of a PNG image")?;
+    writer
+        .write_image_data(&image.pixels)
+        .context("Failed to write the data of a PNG image")?;
+    writer
+        .finish()
+        .context("Failed to finish writing a PNG image")?;
+
+    Ok(png_bytes)
+}
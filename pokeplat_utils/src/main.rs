@@ -4,12 +4,10 @@
 //!
 //! Please check the [repository](https://github.com/Kuruyia/sinjoh) for more information.
 
-#![feature(iterator_try_collect)]
-
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use build::{COMMIT_DATE_3339, COMMIT_HASH, PKG_VERSION, PROJECT_NAME};
 use clap::Parser;
-use cli::{Cli, Commands, SqlCommands};
+use cli::{Cli, Commands, ExportCommands, SqlCommands};
 use log::info;
 use plat_loader::PlatLoader;
 use shadow_rs::shadow;
@@ -17,8 +15,12 @@ use sql::repl::SqlRepl;
 
 shadow!(build);
 
+mod banner;
 mod cli;
+mod gltf;
+mod image;
 mod plat_loader;
+mod sound;
 mod sql;
 
 fn main() -> Result<()> {
@@ -34,10 +36,21 @@ fn main() -> Result<()> {
         PROJECT_NAME, PKG_VERSION, COMMIT_HASH, COMMIT_DATE_3339
     );
 
+    // The banner is read straight from the ROM, without loading any game data
+    if let Commands::Banner { icon_path } = &cli.command {
+        let Some(rom_path) = &cli.resources.rom_path else {
+            bail!("Reading the banner requires a ROM, specified with `--rom-path`");
+        };
+
+        return banner::show_banner(rom_path, icon_path.as_ref());
+    }
+
     // Parse the game resources
-    let narc_paths = cli.resources.narc_paths();
-    let plat_resources = PlatLoader::load_resources(&narc_paths).with_context(|| {
-        if cli.resources.pokeplatinum_repo_path.is_some() {
+    let resources_location = cli.resources.location();
+    let plat_resources = PlatLoader::load_resources(&resources_location).with_context(|| {
+        if cli.resources.rom_path.is_some() {
+            "Failed to load the Pokémon Platinum data files from the ROM. Make sure that the ROM is a Pokémon Platinum ROM."
+        } else if cli.resources.pokeplatinum_repo_path.is_some() {
             "Failed to load the Pokémon Platinum data files. This could be due to multiple reasons:
             - You didn't build the ROM. Make sure that a `build` directory is present in the `pokeplatinum` repo.
             - Path(s) were changed in a newer revision of the `pokeplatinum` repo. Please file an issue in the `sinjoh` project."
@@ -57,6 +70,59 @@ fn main() -> Result<()> {
                 sql::export::export_plat_resources(plat_resources, &export_path)?
             }
         },
+        Commands::Export { command } => match command {
+            ExportCommands::Gltf {
+                land_data_id,
+                area_data_id,
+                export_path,
+            } => {
+                let mut map_assets = PlatLoader::load_map_assets(&resources_location)?;
+                gltf::export::export_land_data(
+                    &plat_resources,
+                    &mut map_assets,
+                    land_data_id,
+                    area_data_id,
+                    &export_path,
+                )?
+            }
+            ExportCommands::GltfMatrix {
+                map_matrix_id,
+                area_data_id,
+                export_path,
+            } => {
+                let mut map_assets = PlatLoader::load_map_assets(&resources_location)?;
+                gltf::export::export_map_matrix(
+                    &plat_resources,
+                    &mut map_assets,
+                    map_matrix_id,
+                    area_data_id,
+                    &export_path,
+                )?
+            }
+            ExportCommands::Midi {
+                sequence_id,
+                loop_count,
+                export_path,
+            } => sound::export_sequence(&plat_resources, sequence_id, loop_count, &export_path)?,
+            ExportCommands::Wav {
+                wave_archive_id,
+                export_directory,
+            } => sound::export_wave_archive(&plat_resources, wave_archive_id, &export_directory)?,
+            ExportCommands::MidiMap {
+                map_header_id,
+                night,
+                loop_count,
+                export_path,
+            } => sound::export_map_music(
+                &plat_resources,
+                map_header_id,
+                night,
+                loop_count,
+                &export_path,
+            )?,
+        },
+        // Handled before loading the game resources
+        Commands::Banner { .. } => unreachable!(),
     }
 
     Ok(())
@@ -1,15 +1,31 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs::{self, File},
+    io::{BufReader, Cursor},
+    path::Path,
+};
 
 use anyhow::{Context, Result, anyhow};
-use log::{debug, info};
-use sinjoh_nds::narc::reader::{NarcReader, NarcReaderFlags};
+use log::{debug, info, warn};
+use sinjoh_nds::{
+    bytes::FromBytes,
+    narc::{
+        reader::{NarcReader, NarcReaderFlags},
+        typed::TypedNarc,
+    },
+    rom::reader::RomReader,
+    sound::sdat::Sdat,
+};
 use sinjoh_plat::{
-    area_data::AreaData, area_light::AreaLight, area_map_props::AreaMapProps, land_data::LandData,
-    map_matrix::MapMatrix, map_prop_animation_list::MapPropAnimationList,
-    map_prop_material_shapes::MapPropMaterialShapes,
+    area_data::AreaData,
+    area_light::AreaLight,
+    area_map_props::AreaMapProps,
+    land_data::LandData,
+    map_matrix::MapMatrix,
+    map_prop_animation_list::MapPropAnimationList,
+    map_prop_material_shapes::{MapPropMaterialShapes, MapPropMaterialShapesList},
 };
 
-use crate::cli::NarcPaths;
+use crate::cli::ResourcesLocation;
 
 pub(crate) struct PlatResources {
     pub area_data: Vec<AreaData>,
@@ -19,30 +35,112 @@ pub(crate) struct PlatResources {
     pub map_prop_material_shapes: Vec<Option<MapPropMaterialShapes>>,
     pub map_matrices: Vec<MapMatrix>,
     pub land_data: Vec<LandData>,
+
+    /// The sound archive, which is `None` if it could not be loaded.
+    ///
+    /// It is only required by the sound commands, and is otherwise used to name the music of the
+    /// maps.
+    pub sound_data: Option<Sdat>,
+}
+
+/// The archives holding the 3D assets of the maps.
+///
+/// Those archives are large, so they are only loaded when needed, and their files are read
+/// lazily.
+pub(crate) struct MapAssets {
+    /// The `build_model.narc` archive, holding the models of the map props (NSBMD files).
+    pub build_models: NarcReader<Cursor<Vec<u8>>>,
+
+    /// The `map_tex_set.narc` archive, holding the texture sets of the areas (NSBTX files).
+    pub map_texture_sets: NarcReader<Cursor<Vec<u8>>>,
+}
+
+/// Reads the raw resources files, either from the file system or from a ROM image.
+enum ResourcesReader {
+    FileSystem,
+    Rom(Box<RomReader<BufReader<File>>>),
+}
+
+impl ResourcesReader {
+    fn open(location: &ResourcesLocation) -> Result<Self> {
+        match location {
+            ResourcesLocation::FileSystem(_) => Ok(Self::FileSystem),
+            ResourcesLocation::Rom { rom_path, .. } => {
+                info!("Reading ROM at: {}", rom_path.display());
+
+                let rom_reader =
+                    RomReader::read_from_file(rom_path).context("Failed to read the ROM file")?;
+
+                info!(
+                    "Read ROM: {} ({})",
+                    rom_reader.header().title,
+                    rom_reader.header().game_code
+                );
+                debug!("Read ROM header:\n{:#?}", rom_reader.header());
+
+                Ok(Self::Rom(Box::new(rom_reader)))
+            }
+        }
+    }
+
+    fn read(&mut self, path: &Path) -> Result<Vec<u8>> {
+        match self {
+            Self::FileSystem => {
+                info!("Reading file at: {}", path.display());
+                Ok(fs::read(path)?)
+            }
+            Self::Rom(rom_reader) => {
+                info!("Reading file from the ROM at: {}", path.display());
+
+                let path = path
+                    .to_str()
+                    .ok_or_else(|| anyhow!("Invalid ROM file path: {}", path.display()))?;
+
+                Ok(rom_reader.get_file_by_path(path)?)
+            }
+        }
+    }
 }
 
 pub(crate) struct PlatLoader {}
 
 impl PlatLoader {
-    pub fn load_resources(narc_paths: &NarcPaths) -> Result<PlatResources> {
+    pub fn load_resources(location: &ResourcesLocation) -> Result<PlatResources> {
+        let mut reader = ResourcesReader::open(location)?;
+        let narc_paths = match location {
+            ResourcesLocation::FileSystem(narc_paths) => narc_paths,
+            ResourcesLocation::Rom { narc_paths, .. } => narc_paths,
+        };
+
         // Read area data
-        let area_data = Self::read_area_data(&narc_paths.area_data_narc_path)?;
+        let area_data: Vec<AreaData> =
+            Self::read_narc(&mut reader, &narc_paths.area_data_narc_path, "area data")?;
         info!("Read {} area data files", area_data.len());
         debug!("Read area data:\n{:#?}", area_data);
 
         // Read area lights
-        let area_lights = Self::read_area_lights(&narc_paths.area_light_narc_path)?;
+        let mut area_lights: Vec<AreaLight> =
+            Self::read_narc(&mut reader, &narc_paths.area_light_narc_path, "area light")?;
+
+        for area_light in area_lights.iter_mut() {
+            area_light.fix();
+        }
+
         info!("Read {} area lights", area_lights.len());
         debug!("Read area lights:\n{:#?}", area_lights);
 
         // Read area map props
-        let area_map_props = Self::read_area_map_props(&narc_paths.area_build_narc_path)?;
+        let area_map_props: Vec<AreaMapProps> =
+            Self::read_narc(&mut reader, &narc_paths.area_build_narc_path, "map props")?;
         info!("Read {} area map props", area_map_props.len());
         debug!("Read area map props:\n{:#?}", area_map_props);
 
         // Read map prop animation lsit
-        let map_prop_animation_lists =
-            Self::read_area_map_prop_animation_lists(&narc_paths.bm_anime_list_narc_path)?;
+        let map_prop_animation_lists: Vec<MapPropAnimationList> = Self::read_narc(
+            &mut reader,
+            &narc_paths.bm_anime_list_narc_path,
+            "map prop animation list",
+        )?;
         info!(
             "Read {} map prop animation lists",
             map_prop_animation_lists.len()
@@ -53,8 +151,14 @@ impl PlatLoader {
         );
 
         // Read map prop material and shapes
-        let map_prop_material_shapes =
-            Self::read_map_prop_material_shapes(&narc_paths.build_model_matshp_dat_path)?;
+        let MapPropMaterialShapesList {
+            map_prop_material_shapes,
+            ..
+        } = Self::read_file(
+            &mut reader,
+            &narc_paths.build_model_matshp_dat_path,
+            "map prop material shapes data",
+        )?;
         info!(
             "Read {} map prop material & shapes",
             map_prop_material_shapes.len()
@@ -65,15 +169,44 @@ impl PlatLoader {
         );
 
         // Read map matrices
-        let map_matrices = Self::read_map_matrices(&narc_paths.map_matrix_narc_path)?;
+        let map_matrices: Vec<MapMatrix> =
+            Self::read_narc(&mut reader, &narc_paths.map_matrix_narc_path, "map matrix")?;
         info!("Read {} map matrices", map_matrices.len());
         debug!("Read map matrices:\n{:#?}", map_matrices);
 
         // Read land data
-        let land_data = Self::read_land_data(&narc_paths.land_data_narc_path)?;
+        let land_data: Vec<LandData> =
+            Self::read_narc(&mut reader, &narc_paths.land_data_narc_path, "land data")?;
         info!("Read {} land data files", land_data.len());
         debug!("Read land data:\n{:#?}", land_data);
 
+        // Read sound data, which is optional
+        let sound_data = match &narc_paths.sound_data_sdat_path {
+            Some(sound_data_sdat_path) => {
+                match Self::read_file::<Sdat>(&mut reader, sound_data_sdat_path, "sound data SDAT")
+                {
+                    Ok(sound_data) => {
+                        info!(
+                            "Read {} sound sequences",
+                            sound_data.sequences.iter().flatten().count()
+                        );
+
+                        Some(sound_data)
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Unable to load the sound data, music names will be missing: {err:#}"
+                        );
+                        None
+                    }
+                }
+            }
+            None => {
+                info!("No sound data SDAT file specified, music names will be missing");
+                None
+            }
+        };
+
         Ok(PlatResources {
             area_data,
             area_lights,
@@ -82,199 +215,95 @@ impl PlatLoader {
             map_prop_material_shapes,
             map_matrices,
             land_data,
+            sound_data,
         })
     }
 
-    fn read_area_data(area_data_narc_path: &PathBuf) -> Result<Vec<AreaData>> {
-        // Read the area data NARC
-        info!(
-            "Reading `area_data.narc` at: {}",
-            area_data_narc_path.display()
-        );
-
-        let mut area_data_narc_reader =
-            NarcReader::read_from_file(area_data_narc_path, NarcReaderFlags::default())
-                .context("Failed to read the area data NARC file")?;
-
-        debug!("Read area data NARC:\n{:#?}", area_data_narc_reader);
-
-        // Parse each area data
-        let area_data = area_data_narc_reader
-            .files_iter()
-            .map(|file| -> Result<AreaData> {
-                Ok(AreaData::from_bytes(
-                    file.context("Unable to read an area data file from the NARC")?
-                        .try_into()
-                        .map_err(|_| anyhow!("Unable to convert the area data to an array"))?,
-                ))
-            })
-            .try_collect::<Vec<_>>()?;
-
-        Ok(area_data)
-    }
+    pub fn load_map_assets(location: &ResourcesLocation) -> Result<MapAssets> {
+        let mut reader = ResourcesReader::open(location)?;
+        let narc_paths = match location {
+            ResourcesLocation::FileSystem(narc_paths) => narc_paths,
+            ResourcesLocation::Rom { narc_paths, .. } => narc_paths,
+        };
+
+        // Read the map prop models
+        info!("Reading `build_model.narc`");
+
+        let build_model_narc_path = narc_paths.build_model_narc_path.as_ref().ok_or_else(|| {
+            anyhow!(
+                "The `build_model.narc` file is required to export maps (it can be specified with `--build-model-narc-path`)"
+            )
+        })?;
+        let narc_bytes = reader
+            .read(build_model_narc_path)
+            .context("Failed to read the map prop models NARC file")?;
+        let build_models = NarcReader::from_bytes(narc_bytes, NarcReaderFlags::default())
+            .context("Failed to read the map prop models NARC file")?;
+
+        info!("Read {} map prop models", build_models.number_of_files());
+
+        // Read the map texture sets
+        info!("Reading `map_tex_set.narc`");
+
+        let map_tex_set_narc_path = narc_paths.map_tex_set_narc_path.as_ref().ok_or_else(|| {
+            anyhow!(
+                "The `map_tex_set.narc` file is required to export maps (it can be specified with `--map-tex-set-narc-path`)"
+            )
+        })?;
+        let narc_bytes = reader
+            .read(map_tex_set_narc_path)
+            .context("Failed to read the map texture sets NARC file")?;
+        let map_texture_sets = NarcReader::from_bytes(narc_bytes, NarcReaderFlags::default())
+            .context("Failed to read the map texture sets NARC file")?;
 
-    fn read_area_lights(area_light_narc_path: &PathBuf) -> Result<Vec<AreaLight>> {
-        // Read the area light NARC
         info!(
-            "Reading `arealight.narc` at: {}",
-            area_light_narc_path.display()
+            "Read {} map texture sets",
+            map_texture_sets.number_of_files()
         );
 
-        let mut area_light_narc_reader =
-            NarcReader::read_from_file(area_light_narc_path, NarcReaderFlags::default())
-                .context("Failed to read the area light NARC file")?;
-
-        debug!("Read area light NARC:\n{:#?}", area_light_narc_reader);
-
-        // Parse each area light
-        let mut area_lights = area_light_narc_reader
-            .files_iter()
-            .map(|file| -> Result<AreaLight> {
-                Ok(AreaLight::parse_bytes(
-                    file.context("Unable to read an area light file from the NARC")?
-                        .as_slice(),
-                )?)
-            })
-            .try_collect::<Vec<_>>()?;
-
-        // Fix each area light
-        for area_light in area_lights.iter_mut() {
-            area_light.fix();
-        }
-
-        Ok(area_lights)
-    }
-
-    fn read_area_map_props(area_build_narc_path: &PathBuf) -> Result<Vec<AreaMapProps>> {
-        // Read the map props NARC
-        info!(
-            "Reading `area_build.narc` at: {}",
-            area_build_narc_path.display()
-        );
-
-        let mut map_props_narc_reader =
-            NarcReader::read_from_file(area_build_narc_path, NarcReaderFlags::default())
-                .context("Failed to read the map props NARC file")?;
-
-        debug!("Read map props NARC:\n{:#?}", map_props_narc_reader);
-
-        // Parse each area map props
-        let area_map_props = map_props_narc_reader
-            .files_iter()
-            .map(|file| -> Result<AreaMapProps> {
-                Ok(AreaMapProps::parse_bytes(
-                    file.context("Unable to read an area build file from the NARC")?
-                        .as_slice(),
-                )?)
-            })
-            .try_collect::<Vec<_>>()?;
-
-        Ok(area_map_props)
-    }
-
-    fn read_area_map_prop_animation_lists(
-        bm_anime_list_narc_path: &PathBuf,
-    ) -> Result<Vec<MapPropAnimationList>> {
-        // Read the map prop animation list NARC
-        info!(
-            "Reading `bm_anime_list.narc` at: {}",
-            bm_anime_list_narc_path.display()
-        );
-
-        let mut bm_anime_list_narc_reader =
-            NarcReader::read_from_file(bm_anime_list_narc_path, NarcReaderFlags::default())
-                .context("Failed to read the map prop animation list NARC file")?;
-
-        debug!(
-            "Read map prop anime list NARC:\n{:#?}",
-            bm_anime_list_narc_reader
-        );
-
-        // Parse each map prop animation list
-        let map_prop_animation_lists = bm_anime_list_narc_reader
-            .files_iter()
-            .map(|file| -> Result<MapPropAnimationList> {
-                Ok(MapPropAnimationList::parse_bytes(
-                    file.context("Unable to read a map prop animation list file from the NARC")?
-                        .as_slice(),
-                )?)
-            })
-            .try_collect::<Vec<_>>()?;
-
-        Ok(map_prop_animation_lists)
-    }
-
-    fn read_map_prop_material_shapes(
-        build_model_matshp_dat_path: &PathBuf,
-    ) -> Result<Vec<Option<MapPropMaterialShapes>>> {
-        // Read the map prop material shapes data
-        info!(
-            "Reading `build_model_matshp.dat` at: {}",
-            build_model_matshp_dat_path.display()
-        );
-
-        let map_prop_material_shapes_data = fs::read(build_model_matshp_dat_path)
-            .context("Failed to read the map prop material shapes data file")?;
-
-        // Parse the data
-        let map_prop_material_shapes =
-            MapPropMaterialShapes::parse_bytes(&map_prop_material_shapes_data)
-                .context("Failed to parse the map prop material shapes data file")?;
-
-        Ok(map_prop_material_shapes)
+        Ok(MapAssets {
+            build_models,
+            map_texture_sets,
+        })
     }
 
-    fn read_map_matrices(map_matrix_narc_path: &PathBuf) -> Result<Vec<MapMatrix>> {
-        // Read the map matrix NARC
-        info!(
-            "Reading `map_matrix.narc` at: {}",
-            map_matrix_narc_path.display()
-        );
-
-        let mut map_matrix_narc_reader =
-            NarcReader::read_from_file(map_matrix_narc_path, NarcReaderFlags::default())
-                .context("Failed to read the map matrix NARC file")?;
-
-        debug!("Read map matrix NARC:\n{:#?}", map_matrix_narc_reader);
-
-        // Parse each map matrix
-        let map_matrices = map_matrix_narc_reader
-            .files_iter()
-            .map(|file| -> Result<MapMatrix> {
-                Ok(MapMatrix::parse_bytes(
-                    file.context("Unable to read a map matrix file from the NARC")?
-                        .as_slice(),
-                )?)
-            })
-            .try_collect::<Vec<_>>()?;
-
-        Ok(map_matrices)
+    /// Reads a NARC file, and parses each of its files.
+    fn read_narc<T: FromBytes>(
+        reader: &mut ResourcesReader,
+        narc_path: &Path,
+        description: &str,
+    ) -> Result<Vec<T>> {
+        // Read the NARC
+        info!("Reading the {description} NARC file");
+
+        let narc_bytes = reader
+            .read(narc_path)
+            .with_context(|| format!("Failed to read the {description} NARC file"))?;
+        let mut narc_reader = NarcReader::from_bytes(narc_bytes, NarcReaderFlags::default())
+            .with_context(|| format!("Failed to read the {description} NARC file"))?;
+
+        debug!("Read {description} NARC:\n{:#?}", narc_reader);
+
+        // Parse each file
+        let files = TypedNarc::from_narc_reader(&mut narc_reader, false)
+            .and_then(TypedNarc::into_vec)
+            .with_context(|| format!("Failed to parse the {description} NARC file"))?;
+
+        Ok(files)
     }
 
-    fn read_land_data(land_data_narc_path: &PathBuf) -> Result<Vec<LandData>> {
-        // Read the land data NARC
-        info!(
-            "Reading `land_data.narc` at: {}",
-            land_data_narc_path.display()
-        );
-
-        let mut land_data_narc_reader =
-            NarcReader::read_from_file(land_data_narc_path, NarcReaderFlags::default())
-                .context("Failed to read the land data NARC file")?;
-
-        debug!("Read land data NARC:\n{:#?}", land_data_narc_reader);
+    /// Reads a file that is not in a NARC, and parses it.
+    fn read_file<T: FromBytes>(
+        reader: &mut ResourcesReader,
+        path: &Path,
+        description: &str,
+    ) -> Result<T> {
+        info!("Reading the {description} file");
 
-        // Parse each land data
-        let land_data = land_data_narc_reader
-            .files_iter()
-            .map(|file| -> Result<LandData> {
-                Ok(LandData::parse_bytes(
-                    file.context("Unable to read a land data file from the NARC")?
-                        .as_slice(),
-                )?)
-            })
-            .try_collect::<Vec<_>>()?;
+        let bytes = reader
+            .read(path)
+            .with_context(|| format!("Failed to read the {description} file"))?;
 
-        Ok(land_data)
+        T::from_bytes(&bytes).with_context(|| format!("Failed to parse the {description} file"))
     }
 }
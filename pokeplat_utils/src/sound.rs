@@ -0,0 +1,122 @@
+use std::{
+    fs,
+    path::{self, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use log::info;
+use sinjoh_nds::sound::{sdat::Sdat, sseq::Sseq, swar::Swar};
+use sinjoh_plat::data::map_headers::PLATINUM_MAP_HEADERS;
+
+use crate::plat_loader::PlatResources;
+
+/// Exports a sequence of the sound archive to a MIDI file, playing its loop `loop_count` times.
+pub fn export_sequence(
+    resources: &PlatResources,
+    sequence_id: usize,
+    loop_count: u32,
+    path: &PathBuf,
+) -> Result<()> {
+    let sound_data = sound_data(resources)?;
+    let sequence = sound_data
+        .sequences
+        .get(sequence_id)
+        .and_then(Option::as_ref)
+        .ok_or_else(|| anyhow!("Sequence {sequence_id} does not exist"))?;
+    info!(
+        "Exporting sequence {sequence_id} ({})",
+        sequence.name.as_deref().unwrap_or("unnamed")
+    );
+
+    let file_id = sequence.info.file_id as usize;
+    let sseq_bytes = sound_data
+        .file(file_id)
+        .ok_or_else(|| anyhow!("File {file_id} of sequence {sequence_id} does not exist"))?;
+    let sseq = Sseq::parse_bytes(sseq_bytes)
+        .with_context(|| format!("Failed to parse sequence {sequence_id}"))?;
+
+    let midi = sseq
+        .to_midi(loop_count)
+        .with_context(|| format!("Failed to convert sequence {sequence_id} to MIDI"))?;
+    fs::write(path, midi).context("Failed to write the MIDI file")?;
+
+    info!(
+        "Finished exporting MIDI file to: {}",
+        path::absolute(path)?.display()
+    );
+
+    Ok(())
+}
+
+/// Exports each wave of a wave archive of the sound archive to a WAV file, in the specified
+/// directory.
+pub fn export_wave_archive(
+    resources: &PlatResources,
+    wave_archive_id: usize,
+    directory: &PathBuf,
+) -> Result<()> {
+    let sound_data = sound_data(resources)?;
+    let wave_archive = sound_data
+        .wave_archives
+        .get(wave_archive_id)
+        .and_then(Option::as_ref)
+        .ok_or_else(|| anyhow!("Wave archive {wave_archive_id} does not exist"))?;
+    info!(
+        "Exporting wave archive {wave_archive_id} ({})",
+        wave_archive.name.as_deref().unwrap_or("unnamed")
+    );
+
+    let file_id = wave_archive.info.file_id as usize;
+    let swar_bytes = sound_data.file(file_id).ok_or_else(|| {
+        anyhow!("File {file_id} of wave archive {wave_archive_id} does not exist")
+    })?;
+    let swar = Swar::parse_bytes(swar_bytes)
+        .with_context(|| format!("Failed to parse wave archive {wave_archive_id}"))?;
+
+    fs::create_dir_all(directory).context("Failed to create the export directory")?;
+
+    for (wave_id, wave) in swar.waves.iter().enumerate() {
+        fs::write(
+            directory.join(format!("wave_{wave_id:03}.wav")),
+            wave.to_wav(),
+        )
+        .with_context(|| format!("Failed to write the WAV file of wave {wave_id}"))?;
+    }
+
+    info!(
+        "Finished exporting {} WAV files to: {}",
+        swar.waves.len(),
+        path::absolute(directory)?.display()
+    );
+
+    Ok(())
+}
+
+/// Exports the music played on a map, during the day or at night, to a MIDI file.
+pub fn export_map_music(
+    resources: &PlatResources,
+    map_header_id: usize,
+    night: bool,
+    loop_count: u32,
+    path: &PathBuf,
+) -> Result<()> {
+    let map_header = PLATINUM_MAP_HEADERS
+        .get(&map_header_id)
+        .ok_or_else(|| anyhow!("Map header {map_header_id} does not exist"))?;
+
+    let sequence_id = match night {
+        true => map_header.night_music_id,
+        false => map_header.day_music_id,
+    };
+
+    export_sequence(resources, sequence_id.into(), loop_count, path)
+}
+
+/// Returns the sound archive, or an error if it could not be loaded.
+fn sound_data(resources: &PlatResources) -> Result<&Sdat> {
+    resources.sound_data.as_ref().ok_or_else(|| {
+        anyhow!(
+            "The sound data SDAT file is required by this command, but it could not be loaded (it can be specified with `--sound-data-sdat-path`)"
+        )
+    })
+}
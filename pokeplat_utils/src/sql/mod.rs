@@ -4,7 +4,7 @@ use anyhow::Result;
 use log::info;
 use rusqlite::Connection;
 use sinjoh_plat::data::map_headers::PLATINUM_MAP_HEADERS;
-use tables::PopulateSql;
+use tables::{MapHeaders, PopulateSql};
 
 use crate::plat_loader::PlatResources;
 
@@ -31,7 +31,15 @@ fn prepare_db_from_plat_resources(resources: PlatResources, conn: &mut Connectio
         .map_prop_material_shapes
         .create_and_populate_sql_tables(conn)?;
 
-    PLATINUM_MAP_HEADERS.create_and_populate_sql_tables(conn)?;
+    if let Some(sound_data) = &resources.sound_data {
+        sound_data.create_and_populate_sql_tables(conn)?;
+    }
+
+    MapHeaders {
+        map_headers: &PLATINUM_MAP_HEADERS,
+        sound_data: resources.sound_data.as_ref(),
+    }
+    .create_and_populate_sql_tables(conn)?;
 
     let populate_end = Instant::now();
     info!(
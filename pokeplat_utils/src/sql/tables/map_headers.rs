@@ -2,11 +2,18 @@ use std::collections::HashMap;
 
 use anyhow::{Context, Result};
 use rusqlite::{Connection, params};
+use sinjoh_nds::sound::sdat::Sdat;
 use sinjoh_plat::data::MapHeader;
 
 use super::PopulateSql;
 
-impl PopulateSql for HashMap<usize, MapHeader> {
+/// The map headers, along with the sound archive used to name their music, if it is loaded.
+pub(in crate::sql) struct MapHeaders<'a> {
+    pub map_headers: &'a HashMap<usize, MapHeader>,
+    pub sound_data: Option<&'a Sdat>,
+}
+
+impl PopulateSql for MapHeaders<'_> {
     fn create_sql_tables(&self, conn: &Connection) -> Result<()> {
         conn.execute(
             "CREATE TABLE map_header (
@@ -19,6 +26,8 @@ impl PopulateSql for HashMap<usize, MapHeader> {
                 msg_archive_id              INTEGER NOT NULL,
                 day_music_id                INTEGER NOT NULL,
                 night_music_id              INTEGER NOT NULL,
+                day_music_name              TEXT,
+                night_music_name            TEXT,
                 wild_encounters_archive_id  INTEGER NOT NULL,
                 events_archive_id           INTEGER NOT NULL,
                 map_label_text_id           INTEGER NOT NULL,
@@ -40,7 +49,7 @@ impl PopulateSql for HashMap<usize, MapHeader> {
     }
 
     fn populate_sql_tables(&self, conn: &mut Connection) -> Result<()> {
-        for (&map_header_id, map_header) in self.iter() {
+        for (&map_header_id, map_header) in self.map_headers.iter() {
             conn.execute(
                 "INSERT INTO map_header (
                     id,
@@ -52,6 +61,8 @@ impl PopulateSql for HashMap<usize, MapHeader> {
                     msg_archive_id,
                     day_music_id,
                     night_music_id,
+                    day_music_name,
+                    night_music_name,
                     wild_encounters_archive_id,
                     events_archive_id,
                     map_label_text_id,
@@ -65,7 +76,7 @@ impl PopulateSql for HashMap<usize, MapHeader> {
                     is_escape_rope_allowed,
                     is_fly_allowed
                 )
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 params![
                     map_header_id as u64,
                     map_header.area_data_archive_id,
@@ -76,6 +87,12 @@ impl PopulateSql for HashMap<usize, MapHeader> {
                     map_header.msg_archive_id,
                     map_header.day_music_id,
                     map_header.night_music_id,
+                    self.sound_data.and_then(
+                        |sound_data| sound_data.sequence_name(map_header.day_music_id.into())
+                    ),
+                    self.sound_data.and_then(
+                        |sound_data| sound_data.sequence_name(map_header.night_music_id.into())
+                    ),
                     map_header.wild_encounters_archive_id,
                     map_header.events_archive_id,
                     map_header.map_label_text_id,
@@ -44,7 +44,7 @@ impl PopulateSql for Vec<MapPropAnimationList> {
                 ],
             ).context("Failed to populate the `map_prop_animation_list` table")?;
 
-            for animation_id in map_prop_animation_list.map_prop_animation_ids.iter() {
+            for animation_id in map_prop_animation_list.valid_map_prop_animation_ids() {
                 conn.execute(
                     "INSERT INTO map_prop_animation_list_ids (animation_id, map_prop_animation_list_id)
                     VALUES (?1, ?2)",
@@ -9,6 +9,9 @@ mod map_headers;
 mod map_matrices;
 mod map_prop_animation_lists;
 mod map_prop_material_shapes;
+mod sound_data;
+
+pub(super) use map_headers::MapHeaders;
 
 pub(super) trait PopulateSql {
     fn create_sql_tables(&self, conn: &Connection) -> Result<()>;
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use sinjoh_nds::sound::sdat::Sdat;
+
+use super::PopulateSql;
+
+impl PopulateSql for Sdat {
+    fn create_sql_tables(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE sound_sequence (
+                id                  INTEGER NOT NULL PRIMARY KEY,
+                name                TEXT,
+                file_id             INTEGER NOT NULL,
+                bank_id             INTEGER NOT NULL,
+                volume              INTEGER NOT NULL,
+                channel_priority    INTEGER NOT NULL,
+                player_priority     INTEGER NOT NULL,
+                player_id           INTEGER NOT NULL
+            )",
+            (),
+        )
+        .context("Failed to create the `sound_sequence` table")?;
+
+        conn.execute(
+            "CREATE TABLE sound_bank (
+                id          INTEGER NOT NULL PRIMARY KEY,
+                name        TEXT,
+                file_id     INTEGER NOT NULL
+            )",
+            (),
+        )
+        .context("Failed to create the `sound_bank` table")?;
+
+        conn.execute(
+            "CREATE TABLE sound_bank_wave_archive_id (
+                sound_bank_id       INTEGER NOT NULL,
+                idx                 INTEGER NOT NULL,
+                wave_archive_id     INTEGER NOT NULL,
+                PRIMARY KEY (sound_bank_id, idx),
+                FOREIGN KEY (sound_bank_id) REFERENCES sound_bank(id)
+            )",
+            (),
+        )
+        .context("Failed to create the `sound_bank_wave_archive_id` table")?;
+
+        conn.execute(
+            "CREATE TABLE sound_wave_archive (
+                id          INTEGER NOT NULL PRIMARY KEY,
+                name        TEXT,
+                file_id     INTEGER NOT NULL,
+                flags       INTEGER NOT NULL
+            )",
+            (),
+        )
+        .context("Failed to create the `sound_wave_archive` table")?;
+
+        conn.execute(
+            "CREATE TABLE sound_player (
+                id              INTEGER NOT NULL PRIMARY KEY,
+                name            TEXT,
+                max_sequences   INTEGER NOT NULL,
+                channel_mask    INTEGER NOT NULL,
+                heap_size       INTEGER NOT NULL
+            )",
+            (),
+        )
+        .context("Failed to create the `sound_player` table")?;
+
+        Ok(())
+    }
+
+    fn populate_sql_tables(&self, conn: &mut Connection) -> Result<()> {
+        for (sequence_id, sequence) in self.sequences.iter().enumerate() {
+            let Some(sequence) = sequence else {
+                continue;
+            };
+
+            conn.execute(
+                "INSERT INTO sound_sequence (
+                    id,
+                    name,
+                    file_id,
+                    bank_id,
+                    volume,
+                    channel_priority,
+                    player_priority,
+                    player_id
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    sequence_id as u64,
+                    sequence.name,
+                    sequence.info.file_id,
+                    sequence.info.bank_id,
+                    sequence.info.volume,
+                    sequence.info.channel_priority,
+                    sequence.info.player_priority,
+                    sequence.info.player_id
+                ],
+            )
+            .context("Failed to populate the `sound_sequence` table")?;
+        }
+
+        for (bank_id, bank) in self.banks.iter().enumerate() {
+            let Some(bank) = bank else {
+                continue;
+            };
+
+            conn.execute(
+                "INSERT INTO sound_bank (id, name, file_id)
+                VALUES (?1, ?2, ?3)",
+                params![bank_id as u64, bank.name, bank.info.file_id],
+            )
+            .context("Failed to populate the `sound_bank` table")?;
+
+            for (idx, wave_archive_id) in bank.info.wave_archive_ids.iter().enumerate() {
+                let Some(wave_archive_id) = wave_archive_id else {
+                    continue;
+                };
+
+                conn.execute(
+                    "INSERT INTO sound_bank_wave_archive_id (sound_bank_id, idx, wave_archive_id)
+                    VALUES (?1, ?2, ?3)",
+                    params![bank_id as u64, idx as u64, wave_archive_id],
+                )
+                .context("Failed to populate the `sound_bank_wave_archive_id` table")?;
+            }
+        }
+
+        for (wave_archive_id, wave_archive) in self.wave_archives.iter().enumerate() {
+            let Some(wave_archive) = wave_archive else {
+                continue;
+            };
+
+            conn.execute(
+                "INSERT INTO sound_wave_archive (id, name, file_id, flags)
+                VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    wave_archive_id as u64,
+                    wave_archive.name,
+                    wave_archive.info.file_id,
+                    wave_archive.info.flags
+                ],
+            )
+            .context("Failed to populate the `sound_wave_archive` table")?;
+        }
+
+        for (player_id, player) in self.players.iter().enumerate() {
+            let Some(player) = player else {
+                continue;
+            };
+
+            conn.execute(
+                "INSERT INTO sound_player (id, name, max_sequences, channel_mask, heap_size)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    player_id as u64,
+                    player.name,
+                    player.info.max_sequences,
+                    player.info.channel_mask,
+                    player.info.heap_size
+                ],
+            )
+            .context("Failed to populate the `sound_player` table")?;
+        }
+
+        Ok(())
+    }
+}
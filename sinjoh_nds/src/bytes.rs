@@ -0,0 +1,29 @@
+//! Traits shared by the data structures that can be parsed from, and serialized to, raw bytes.
+//!
+//! Those traits let generic code, such as [`TypedNarc`](crate::narc::typed::TypedNarc), work
+//! with any file format without knowing the parser of each one.
+
+use std::error::Error;
+
+/// A data structure that can be parsed from raw bytes.
+pub trait FromBytes: Sized {
+    /// The error returned when the bytes cannot be parsed.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Parses the data structure from a byte slice.
+    ///
+    /// It is expected that the slice is in the same format as the one used by the game.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error>;
+}
+
+/// A data structure that can be serialized to raw bytes.
+pub trait ToBytes {
+    /// The error returned when the data structure cannot be serialized.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Serializes the data structure to a byte vector.
+    ///
+    /// The bytes are in the same format as the one used by the game, such that serializing a
+    /// parsed data structure gives back the bytes it was parsed from.
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error>;
+}
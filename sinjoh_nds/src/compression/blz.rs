@@ -0,0 +1,388 @@
+//! Backward LZ77 compression (BLZ), which is used for the ARM9 binary and the overlays.
+//!
+//! Unlike the other compression formats, BLZ-compressed data does not start with a header.
+//! Instead, it ends with an 8-byte footer, and the compressed data is read backwards from the end
+//! while the decompressed data is written backwards from the end of the output buffer. This
+//! allows the data to be decompressed in-place.
+//!
+//! The beginning of the data can be left uncompressed, which is typically done for the first
+//! 16 KiB of the ARM9 binary (that contain the code responsible for decompressing the rest).
+//!
+//! The footer is made of:
+//!
+//! - A 24-bit field holding the size of the compressed part, including the footer and its
+//!   padding.
+//! - An 8-bit field holding the size of the footer, including its padding.
+//! - A 32-bit field holding the difference between the decompressed size and the compressed size.
+
+use thiserror::Error;
+
+use super::match_finder::MatchFinder;
+
+/// The size of the footer of BLZ-compressed data, without its padding.
+pub const BLZ_FOOTER_SIZE: usize = 8;
+
+/// The minimum length of a back-reference.
+pub const BLZ_MIN_MATCH_LENGTH: usize = 3;
+
+/// The maximum length of a back-reference.
+pub const BLZ_MAX_MATCH_LENGTH: usize = 0x0F + BLZ_MIN_MATCH_LENGTH;
+
+/// The minimum distance of a back-reference.
+pub const BLZ_MIN_DISTANCE: usize = 3;
+
+/// The maximum distance of a back-reference.
+pub const BLZ_MAX_DISTANCE: usize = 0x0FFF + BLZ_MIN_DISTANCE;
+
+/// Error type for BLZ compression and decompression.
+#[derive(Error, Debug)]
+pub enum BlzError {
+    /// The data is too short to contain the footer.
+    #[error("the data is too short to contain the BLZ footer")]
+    TruncatedData,
+
+    /// The footer describes a compressed part that does not fit in the data.
+    #[error("invalid BLZ footer (compressed size is {0}, footer size is {1}, data size is {2})")]
+    InvalidFooter(usize, usize, usize),
+
+    /// A back-reference points after the end of the decompressed data.
+    #[error("invalid back-reference at offset 0x{0:X} of the decompressed data (distance is {1})")]
+    InvalidBackReference(usize, usize),
+
+    /// The data is too large to be compressed.
+    #[error("the data is too large to be compressed (size is {0})")]
+    DataTooLarge(usize),
+}
+
+/// Represents the footer of BLZ-compressed data.
+#[derive(Debug, Clone, Copy)]
+pub struct BlzFooter {
+    /// Size of the compressed part of the data, including the footer and its padding.
+    pub compressed_size: u32,
+
+    /// Size of the footer, including its padding.
+    pub footer_size: u8,
+
+    /// Difference between the size of the decompressed data and the size of the compressed data.
+    pub size_increase: u32,
+}
+
+impl BlzFooter {
+    /// Parses a [`BlzFooter`] from a byte array.
+    ///
+    /// It is expected that the array is in the same format as the one found at the end of
+    /// BLZ-compressed data.
+    pub fn from_bytes(bytes: &[u8; BLZ_FOOTER_SIZE]) -> Self {
+        // Safety: slice length is explicitly specified, and the length of the `bytes` array is
+        // known
+        let compressed_size_and_footer_size = u32::from_le_bytes(bytes[0..=3].try_into().unwrap());
+
+        Self {
+            compressed_size: compressed_size_and_footer_size & 0x00FF_FFFF,
+            footer_size: (compressed_size_and_footer_size >> 24) as u8,
+            size_increase: u32::from_le_bytes(bytes[4..=7].try_into().unwrap()),
+        }
+    }
+
+    /// Serializes the footer to a byte array.
+    pub fn to_bytes(&self) -> [u8; BLZ_FOOTER_SIZE] {
+        let mut bytes = [0; BLZ_FOOTER_SIZE];
+
+        let compressed_size_and_footer_size =
+            (self.compressed_size & 0x00FF_FFFF) | ((self.footer_size as u32) << 24);
+        bytes[0..=3].copy_from_slice(&compressed_size_and_footer_size.to_le_bytes());
+        bytes[4..=7].copy_from_slice(&self.size_increase.to_le_bytes());
+
+        bytes
+    }
+}
+
+/// Decompresses BLZ-compressed data.
+///
+/// If the footer tells that the size does not increase, the data is considered as not being
+/// compressed, and is returned as-is.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, BlzError> {
+    if data.len() < BLZ_FOOTER_SIZE {
+        return Err(BlzError::TruncatedData);
+    }
+
+    // Safety: slice length is explicitly specified, and the data was checked to be long enough
+    let footer = BlzFooter::from_bytes(data[data.len() - BLZ_FOOTER_SIZE..].try_into().unwrap());
+
+    if footer.size_increase == 0 {
+        return Ok(data.to_vec());
+    }
+
+    let compressed_size = footer.compressed_size as usize;
+    let footer_size = footer.footer_size as usize;
+
+    if compressed_size > data.len()
+        || footer_size > compressed_size
+        || footer_size < BLZ_FOOTER_SIZE
+    {
+        return Err(BlzError::InvalidFooter(
+            compressed_size,
+            footer_size,
+            data.len(),
+        ));
+    }
+
+    // Locate the uncompressed and compressed parts
+    let uncompressed_size = data.len() - compressed_size;
+    let decompressed_size = data.len() + footer.size_increase as usize;
+
+    let mut decompressed = vec![0u8; decompressed_size];
+    decompressed[..uncompressed_size].copy_from_slice(&data[..uncompressed_size]);
+
+    // Decompress the data, going backwards
+    let mut source = data.len() - footer_size;
+    let mut destination = decompressed_size;
+
+    let read_byte = |source: &mut usize| {
+        if *source <= uncompressed_size {
+            return Err(BlzError::InvalidFooter(
+                compressed_size,
+                footer_size,
+                data.len(),
+            ));
+        }
+
+        *source -= 1;
+        Ok(data[*source])
+    };
+
+    while destination > uncompressed_size {
+        let flags = read_byte(&mut source)?;
+
+        for bit in (0..8).rev() {
+            if destination <= uncompressed_size {
+                break;
+            }
+
+            if flags & (1 << bit) == 0 {
+                // Raw byte
+                destination -= 1;
+                decompressed[destination] = read_byte(&mut source)?;
+            } else {
+                // Back-reference
+                let reference =
+                    ((read_byte(&mut source)? as usize) << 8) | read_byte(&mut source)? as usize;
+
+                let length =
+                    ((reference >> 12) + BLZ_MIN_MATCH_LENGTH).min(destination - uncompressed_size);
+                let distance = (reference & 0x0FFF) + BLZ_MIN_DISTANCE;
+
+                if destination + distance > decompressed_size {
+                    return Err(BlzError::InvalidBackReference(destination, distance));
+                }
+
+                for _ in 0..length {
+                    destination -= 1;
+                    decompressed[destination] = decompressed[destination + distance];
+                }
+            }
+        }
+    }
+
+    Ok(decompressed)
+}
+
+/// Compresses data with BLZ.
+///
+/// The first `uncompressed_prefix_size` bytes of the data are always left uncompressed. More of
+/// the beginning of the data may be left uncompressed, in order to guarantee that the data can be
+/// safely decompressed in-place.
+///
+/// Returns `None` if compressing the data would not make it smaller.
+pub fn compress(data: &[u8], uncompressed_prefix_size: usize) -> Result<Option<Vec<u8>>, BlzError> {
+    if data.len() > 0x00FF_FFFF {
+        return Err(BlzError::DataTooLarge(data.len()));
+    }
+
+    let uncompressed_prefix_size = uncompressed_prefix_size.min(data.len());
+
+    // The data is compressed backwards: compress the reversed data, as regular LZ
+    let reversed: Vec<u8> = data[uncompressed_prefix_size..]
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    let mut match_finder = MatchFinder::new(
+        &reversed,
+        BLZ_MIN_MATCH_LENGTH,
+        BLZ_MIN_DISTANCE,
+        BLZ_MAX_DISTANCE,
+    );
+
+    let mut compressed = Vec::with_capacity(reversed.len() + reversed.len() / 8 + 1);
+    let mut position = 0;
+    let mut flags_position = 0;
+
+    // Keep track of the point where the gain is the largest. Stopping the compression at that
+    // point gives the smallest size, and guarantees that the decompression never overwrites
+    // compressed data that has not been read yet.
+    let mut best_cut: Option<(usize, usize)> = None;
+    let mut best_gain = 0;
+
+    while position < reversed.len() {
+        for bit in (0..8).rev() {
+            if position >= reversed.len() {
+                break;
+            }
+
+            if bit == 7 {
+                flags_position = compressed.len();
+                compressed.push(0);
+            }
+
+            match match_finder.find_longest_match(position, BLZ_MAX_MATCH_LENGTH) {
+                Some((length, distance)) => {
+                    let reference =
+                        ((length - BLZ_MIN_MATCH_LENGTH) << 12) | (distance - BLZ_MIN_DISTANCE);
+
+                    compressed[flags_position] |= 1 << bit;
+                    compressed.push((reference >> 8) as u8);
+                    compressed.push(reference as u8);
+
+                    match_finder.insert_range(position, position + length);
+                    position += length;
+                }
+                None => {
+                    compressed.push(reversed[position]);
+
+                    match_finder.insert_range(position, position + 1);
+                    position += 1;
+                }
+            }
+
+            if position > compressed.len() && position - compressed.len() > best_gain {
+                best_gain = position - compressed.len();
+                best_cut = Some((position, compressed.len()));
+            }
+        }
+    }
+
+    let Some((compressed_raw_size, compressed_size)) = best_cut else {
+        return Ok(None);
+    };
+
+    // Build the compressed data: the uncompressed part, followed by the compressed part and the
+    // footer
+    let uncompressed_size = data.len() - compressed_raw_size;
+
+    let mut output = Vec::with_capacity(uncompressed_size + compressed_size + 2 * BLZ_FOOTER_SIZE);
+    output.extend_from_slice(&data[..uncompressed_size]);
+    output.extend(compressed[..compressed_size].iter().rev());
+
+    let padded_size = output.len().next_multiple_of(4);
+    let footer_size = BLZ_FOOTER_SIZE + (padded_size - output.len());
+    output.resize(padded_size, 0xFF);
+
+    if data.len() <= output.len() + BLZ_FOOTER_SIZE {
+        return Ok(None);
+    }
+
+    let footer = BlzFooter {
+        compressed_size: (compressed_size + footer_size) as u32,
+        footer_size: footer_size as u8,
+        size_increase: (data.len() - output.len() - BLZ_FOOTER_SIZE) as u32,
+    };
+
+    output.extend_from_slice(&footer.to_bytes());
+
+    Ok(Some(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compressed data made of a 4-byte uncompressed prefix, and a compressed part holding 3 raw
+    /// bytes and a back-reference of 15 bytes at a distance of 3.
+    const COMPRESSED: [u8; 20] = [
+        b'P', b'R', b'E', b'!', 0x00, 0xC0, b'A', b'B', b'C', 0x10, 0xFF, 0xFF, 0x10, 0x00, 0x00,
+        0x0A, 0x02, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn footer_round_trip() {
+        let bytes = COMPRESSED[COMPRESSED.len() - BLZ_FOOTER_SIZE..]
+            .try_into()
+            .unwrap();
+        let footer = BlzFooter::from_bytes(bytes);
+
+        assert_eq!(footer.compressed_size, 0x10);
+        assert_eq!(footer.footer_size, 0x0A);
+        assert_eq!(footer.size_increase, 0x02);
+        assert_eq!(&footer.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn decompress_fixed_vector() {
+        let mut expected = b"PRE!".to_vec();
+        expected.extend_from_slice(&b"ABC".repeat(6));
+
+        assert_eq!(decompress(&COMPRESSED).unwrap(), expected);
+    }
+
+    #[test]
+    fn decompress_uncompressed_data() {
+        // A footer without size increase marks data that is not compressed
+        let data = [
+            0x01, 0x02, 0x03, 0x04, 0x08, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(decompress(&data).unwrap(), data);
+    }
+
+    #[test]
+    fn decompress_invalid_data() {
+        assert!(matches!(
+            decompress(&[0x00; 4]),
+            Err(BlzError::TruncatedData)
+        ));
+
+        // The compressed part is larger than the data
+        let mut data = COMPRESSED;
+        data[12] = 0x20;
+        assert!(matches!(
+            decompress(&data),
+            Err(BlzError::InvalidFooter(0x20, 0x0A, 20))
+        ));
+    }
+
+    #[test]
+    fn blz_round_trip() {
+        let data: Vec<u8> = (0..0x2000u32)
+            .map(|i| {
+                if i % 7 == 0 {
+                    (i >> 3) as u8
+                } else {
+                    (i % 13) as u8
+                }
+            })
+            .collect();
+
+        for uncompressed_prefix_size in [0, 5, 0x100, 0x1000] {
+            let compressed = compress(&data, uncompressed_prefix_size).unwrap().unwrap();
+
+            assert!(compressed.len() < data.len());
+            assert_eq!(compressed.len() % 4, 0);
+            assert_eq!(
+                compressed[..uncompressed_prefix_size],
+                data[..uncompressed_prefix_size]
+            );
+            assert_eq!(decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn compress_incompressible_data() {
+        let data: Vec<u8> = (0..0x100u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+
+        assert!(compress(&data, 0).unwrap().is_none());
+        assert!(compress(&b"ABC".repeat(16), 48).unwrap().is_none());
+    }
+}
@@ -0,0 +1,520 @@
+//! Huffman compression, as implemented by the BIOS of the Nintendo DS.
+//!
+//! The data is split in either 4-bit units (header byte `0x24`) or 8-bit units (header byte
+//! `0x28`), which are encoded using a Huffman tree that is stored after the header.
+//!
+//! The tree is stored as a table of nodes, starting with a byte that holds the size of the table,
+//! followed by the root node. Each node is a byte, where:
+//!
+//! - Bits 0-5 are the offset to the pair of children of the node, where the children are located
+//!   at `(node_address & !1) + offset * 2 + 2` and the following byte.
+//! - Bit 6 tells whether the right child is a leaf.
+//! - Bit 7 tells whether the left child is a leaf.
+//!
+//! Leaves directly hold the value of a data unit. The tree is followed by the encoded data, which
+//! is read as 32-bit little-endian words, from the most significant bit to the least significant
+//! one.
+
+use std::collections::VecDeque;
+
+use thiserror::Error;
+
+use super::{read_compression_header, write_compression_header};
+
+/// The header byte of Huffman-compressed data with 4-bit units.
+pub const HUFFMAN_4_MAGIC: u8 = 0x24;
+
+/// The header byte of Huffman-compressed data with 8-bit units.
+pub const HUFFMAN_8_MAGIC: u8 = 0x28;
+
+/// The maximum offset that can be stored in a node of the tree.
+const HUFFMAN_MAX_NODE_OFFSET: usize = 0x3F;
+
+/// The bit of a node that tells whether its left child is a leaf.
+const HUFFMAN_LEFT_LEAF_FLAG: u8 = 0x80;
+
+/// The bit of a node that tells whether its right child is a leaf.
+const HUFFMAN_RIGHT_LEAF_FLAG: u8 = 0x40;
+
+/// Error type for Huffman compression and decompression.
+#[derive(Error, Debug)]
+pub enum HuffmanError {
+    /// The header byte is not the one of a supported Huffman variant.
+    #[error("unknown Huffman header byte (found 0x{0:02X})")]
+    UnknownMagic(u8),
+
+    /// The compressed data ended before the whole decompressed data could be produced.
+    #[error("the compressed data is truncated")]
+    TruncatedData,
+
+    /// A node of the tree points outside of the tree.
+    #[error("a node of the Huffman tree points outside of the tree (at offset 0x{0:X})")]
+    InvalidTree(usize),
+
+    /// The data is too large to be compressed.
+    #[error("the data is too large to be compressed (size is {0})")]
+    DataTooLarge(usize),
+}
+
+/// The size of the data units of Huffman-compressed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HuffmanUnitSize {
+    /// 4-bit data units.
+    FourBits,
+
+    /// 8-bit data units.
+    EightBits,
+}
+
+impl HuffmanUnitSize {
+    /// Returns the number of bits of a data unit.
+    pub fn bits(&self) -> u32 {
+        match self {
+            HuffmanUnitSize::FourBits => 4,
+            HuffmanUnitSize::EightBits => 8,
+        }
+    }
+
+    /// Returns the header byte of data compressed with this unit size.
+    pub fn magic(&self) -> u8 {
+        match self {
+            HuffmanUnitSize::FourBits => HUFFMAN_4_MAGIC,
+            HuffmanUnitSize::EightBits => HUFFMAN_8_MAGIC,
+        }
+    }
+}
+
+/// Decompresses data compressed with Huffman, with either 4-bit or 8-bit units.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+    let (magic, decompressed_size, header_size) =
+        read_compression_header(data).ok_or(HuffmanError::TruncatedData)?;
+
+    let unit_size = match magic {
+        HUFFMAN_4_MAGIC => HuffmanUnitSize::FourBits,
+        HUFFMAN_8_MAGIC => HuffmanUnitSize::EightBits,
+        _ => return Err(HuffmanError::UnknownMagic(magic)),
+    };
+
+    // Locate the tree and the encoded data
+    let tree_start = header_size;
+    let tree_size = (*data.get(tree_start).ok_or(HuffmanError::TruncatedData)? as usize + 1) * 2;
+    let tree = data
+        .get(tree_start..tree_start + tree_size)
+        .ok_or(HuffmanError::TruncatedData)?;
+
+    let mut position = tree_start + tree_size;
+
+    // Decode the data
+    let units_per_byte = 8 / unit_size.bits() as usize;
+    let decompressed_units = decompressed_size * units_per_byte;
+
+    // The capacity is bounded, so that a corrupted header can't trigger a huge allocation
+    let mut units = Vec::with_capacity(decompressed_units.min(data.len() * 8));
+    let mut node_index = 1;
+
+    while units.len() < decompressed_units {
+        // Safety: slice length is explicitly specified
+        let word = u32::from_le_bytes(
+            data.get(position..position + 4)
+                .ok_or(HuffmanError::TruncatedData)?
+                .try_into()
+                .unwrap(),
+        );
+        position += 4;
+
+        for bit in (0..32).rev() {
+            if units.len() >= decompressed_units {
+                break;
+            }
+
+            let node = tree[node_index];
+            let children_index =
+                (node_index & !1) + (node as usize & HUFFMAN_MAX_NODE_OFFSET) * 2 + 2;
+
+            let (child_index, is_leaf) = if word & (1 << bit) == 0 {
+                (children_index, node & HUFFMAN_LEFT_LEAF_FLAG != 0)
+            } else {
+                (children_index + 1, node & HUFFMAN_RIGHT_LEAF_FLAG != 0)
+            };
+
+            let child = *tree
+                .get(child_index)
+                .ok_or(HuffmanError::InvalidTree(tree_start + node_index))?;
+
+            if is_leaf {
+                units.push(child);
+                node_index = 1;
+            } else {
+                node_index = child_index;
+            }
+        }
+    }
+
+    // Pack the data units into bytes, starting with the least significant bits
+    Ok(match unit_size {
+        HuffmanUnitSize::FourBits => units
+            .chunks(2)
+            .map(|nibbles| (nibbles[0] & 0x0F) | (nibbles.get(1).unwrap_or(&0) << 4))
+            .collect(),
+        HuffmanUnitSize::EightBits => units,
+    })
+}
+
+/// A node of a Huffman tree that is being built.
+#[derive(Debug, Clone, Copy)]
+enum HuffmanNode {
+    /// A leaf, holding a data unit.
+    Leaf(u8),
+
+    /// An internal node, holding the indices of its children in the tree.
+    Internal(usize, usize),
+}
+
+/// Compresses data with Huffman, using data units of the specified size.
+pub fn compress(data: &[u8], unit_size: HuffmanUnitSize) -> Result<Vec<u8>, HuffmanError> {
+    if data.len() > u32::MAX as usize {
+        return Err(HuffmanError::DataTooLarge(data.len()));
+    }
+
+    // Split the data in units, starting with the least significant bits
+    let units: Vec<u8> = match unit_size {
+        HuffmanUnitSize::FourBits => data
+            .iter()
+            .flat_map(|byte| [byte & 0x0F, byte >> 4])
+            .collect(),
+        HuffmanUnitSize::EightBits => data.to_vec(),
+    };
+
+    // Build a tree that can be stored in the table, and get the codes of each data unit
+    let mut frequencies = vec![0usize; 1 << unit_size.bits()];
+    for &unit in units.iter() {
+        frequencies[unit as usize] += 1;
+    }
+
+    let (nodes, root) = build_optimal_tree(&frequencies);
+    let (tree, codes) = match layout_tree(&nodes, root)
+        .and_then(|tree| compute_codes(&tree).map(|codes| (tree, codes)))
+    {
+        Some(tree_and_codes) => tree_and_codes,
+        None => {
+            // Fallback to a balanced tree, which has shorter codes and is easier to lay out
+            let (nodes, root) = build_balanced_tree(&frequencies);
+            let tree = layout_tree(&nodes, root).ok_or(HuffmanError::InvalidTree(0))?;
+            let codes = compute_codes(&tree).ok_or(HuffmanError::InvalidTree(0))?;
+
+            (tree, codes)
+        }
+    };
+
+    // Write the header and the tree
+    let mut output = Vec::new();
+    write_compression_header(&mut output, unit_size.magic(), data.len());
+    output.extend_from_slice(&tree);
+
+    // Write the encoded data
+    let mut word: u32 = 0;
+    let mut word_bits = 0;
+
+    for &unit in units.iter() {
+        let (code, code_length) = codes[unit as usize];
+
+        for bit in (0..code_length).rev() {
+            word = (word << 1) | ((code >> bit) & 1);
+            word_bits += 1;
+
+            if word_bits == 32 {
+                output.extend_from_slice(&word.to_le_bytes());
+                word = 0;
+                word_bits = 0;
+            }
+        }
+    }
+
+    if word_bits > 0 {
+        word <<= 32 - word_bits;
+        output.extend_from_slice(&word.to_le_bytes());
+    }
+
+    Ok(output)
+}
+
+/// Builds an optimal Huffman tree for the given frequencies of data units.
+///
+/// Returns the nodes of the tree, and the index of its root. The root is always an internal node.
+fn build_optimal_tree(frequencies: &[usize]) -> (Vec<HuffmanNode>, usize) {
+    let mut nodes = Vec::new();
+    let mut queue: Vec<(usize, usize)> = Vec::new();
+
+    for (unit, &frequency) in frequencies.iter().enumerate() {
+        if frequency > 0 {
+            nodes.push(HuffmanNode::Leaf(unit as u8));
+            queue.push((frequency, nodes.len() - 1));
+        }
+    }
+
+    // The root must be an internal node, so there must be at least two leaves
+    while queue.len() < 2 {
+        let unit = match nodes.first() {
+            Some(HuffmanNode::Leaf(0)) => 1,
+            _ => 0,
+        };
+
+        nodes.push(HuffmanNode::Leaf(unit));
+        queue.push((0, nodes.len() - 1));
+    }
+
+    // Merge the two least frequent nodes until only the root remains
+    while queue.len() > 1 {
+        queue.sort_by(|a, b| b.cmp(a));
+
+        // Safety: there are at least two nodes in the queue
+        let (right_frequency, right) = queue.pop().unwrap();
+        let (left_frequency, left) = queue.pop().unwrap();
+
+        nodes.push(HuffmanNode::Internal(left, right));
+        queue.push((left_frequency + right_frequency, nodes.len() - 1));
+    }
+
+    let root = queue[0].1;
+    (nodes, root)
+}
+
+/// Builds a balanced tree holding the data units that have a non-zero frequency.
+///
+/// Returns the nodes of the tree, and the index of its root. The root is always an internal node.
+fn build_balanced_tree(frequencies: &[usize]) -> (Vec<HuffmanNode>, usize) {
+    let mut nodes = Vec::new();
+    let mut level: Vec<usize> = Vec::new();
+
+    for (unit, &frequency) in frequencies.iter().enumerate() {
+        if frequency > 0 {
+            nodes.push(HuffmanNode::Leaf(unit as u8));
+            level.push(nodes.len() - 1);
+        }
+    }
+
+    while level.len() < 2 {
+        nodes.push(HuffmanNode::Leaf(0));
+        level.push(nodes.len() - 1);
+    }
+
+    // Pair the nodes of each level, until only the root remains
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    nodes.push(HuffmanNode::Internal(*left, *right));
+                    nodes.len() - 1
+                }
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    (nodes, level[0])
+}
+
+/// Lays out a tree in the table format expected by the BIOS.
+///
+/// Because the offset to the children of a node is limited, not all trees can be stored in the
+/// table: `None` is returned in that case.
+fn layout_tree(nodes: &[HuffmanNode], root: usize) -> Option<Vec<u8>> {
+    let mut table = vec![0u8, 0u8];
+
+    // Internal nodes whose children have not been placed yet, with their index in the table
+    let mut pending: VecDeque<(usize, usize)> = VecDeque::from([(root, 1)]);
+
+    while !pending.is_empty() {
+        let next_pair = table.len() / 2;
+
+        // Pick the node to place the children of. Nodes with the smallest subtrees are preferred,
+        // as long as picking them does not make the other pending nodes miss their deadline
+        // (which is the last pair where their children can be placed).
+        let deadline = |index: usize| index / 2 + HUFFMAN_MAX_NODE_OFFSET + 1;
+        if deadline(pending[0].1) < next_pair {
+            return None;
+        }
+
+        let picked = (0..pending.len())
+            .filter(|&picked| {
+                pending
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != picked)
+                    .enumerate()
+                    .all(|(order, (_, &(_, index)))| deadline(index) > next_pair + order)
+            })
+            .min_by_key(|&i| internal_nodes_count(nodes, pending[i].0))
+            .unwrap_or(0);
+
+        // Safety: `picked` is a valid index in the pending nodes
+        let (node, node_index) = pending.remove(picked).unwrap();
+        let HuffmanNode::Internal(left, right) = nodes[node] else {
+            unreachable!();
+        };
+
+        let mut node_byte = (next_pair - node_index / 2 - 1) as u8;
+
+        for (child, leaf_flag) in [
+            (left, HUFFMAN_LEFT_LEAF_FLAG),
+            (right, HUFFMAN_RIGHT_LEAF_FLAG),
+        ] {
+            match nodes[child] {
+                HuffmanNode::Leaf(unit) => {
+                    node_byte |= leaf_flag;
+                    table.push(unit);
+                }
+                HuffmanNode::Internal(_, _) => {
+                    // Keep the pending nodes sorted by deadline
+                    let child_index = table.len();
+                    let insert_at = pending.partition_point(|&(_, index)| index < child_index);
+                    pending.insert(insert_at, (child, child_index));
+
+                    table.push(0);
+                }
+            }
+        }
+
+        table[node_index] = node_byte;
+    }
+
+    // Pad the table so that the encoded data is aligned to 4 bytes
+    table.resize(table.len().next_multiple_of(4), 0);
+    table[0] = (table.len() / 2 - 1) as u8;
+
+    Some(table)
+}
+
+/// Returns the number of internal nodes in the subtree of a node, including itself.
+fn internal_nodes_count(nodes: &[HuffmanNode], node: usize) -> usize {
+    match nodes[node] {
+        HuffmanNode::Internal(left, right) => {
+            1 + internal_nodes_count(nodes, left) + internal_nodes_count(nodes, right)
+        }
+        HuffmanNode::Leaf(_) => 0,
+    }
+}
+
+/// Computes the code of each data unit from a laid out tree.
+///
+/// Returns the code and its length (in bits) for each possible data unit, or `None` if a code
+/// is longer than 32 bits.
+fn compute_codes(table: &[u8]) -> Option<Vec<(u32, u32)>> {
+    let mut codes = vec![(0, 0); 256];
+    let mut stack = vec![(1usize, 0u32, 0u32)];
+
+    while let Some((node_index, code, code_length)) = stack.pop() {
+        if code_length >= 32 {
+            return None;
+        }
+
+        let node = table[node_index];
+        let children_index = (node_index & !1) + (node as usize & HUFFMAN_MAX_NODE_OFFSET) * 2 + 2;
+
+        for (child_index, bit, leaf_flag) in [
+            (children_index, 0, HUFFMAN_LEFT_LEAF_FLAG),
+            (children_index + 1, 1, HUFFMAN_RIGHT_LEAF_FLAG),
+        ] {
+            let child_code = (code << 1) | bit;
+
+            if node & leaf_flag != 0 {
+                let unit = table[child_index] as usize;
+
+                // Keep the first code found, in case of duplicate leaves
+                if codes[unit].1 == 0 {
+                    codes[unit] = (child_code, code_length + 1);
+                }
+            } else {
+                stack.push((child_index, child_code, code_length + 1));
+            }
+        }
+    }
+
+    Some(codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns inputs exercising degenerate and unbalanced trees.
+    fn inputs() -> Vec<Vec<u8>> {
+        // Exponentially decreasing frequencies, which make for a deep and unbalanced tree
+        let skewed = (0..24u32)
+            .flat_map(|unit| std::iter::repeat_n(unit as u8, 1 << (23 - unit).min(16)))
+            .collect();
+
+        vec![
+            Vec::new(),
+            vec![0x42],
+            vec![0x00; 4],
+            vec![0x55; 200],
+            (0..=255).collect(),
+            (0..0x1000)
+                .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+                .collect(),
+            skewed,
+        ]
+    }
+
+    #[test]
+    fn huffman_round_trip() {
+        for unit_size in [HuffmanUnitSize::FourBits, HuffmanUnitSize::EightBits] {
+            for input in inputs() {
+                let compressed = compress(&input, unit_size).unwrap();
+
+                assert_eq!(compressed[0], unit_size.magic());
+                assert_eq!(compressed.len() % 4, 0);
+                assert_eq!(decompress(&compressed).unwrap(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn huffman_8_fixed_vector() {
+        // A root node with two leaves, and the codes 1, 0, 0, 1
+        let compressed = [
+            0x28, 0x04, 0x00, 0x00, 0x01, 0xC0, b'B', b'A', 0x00, 0x00, 0x00, 0x90,
+        ];
+
+        assert_eq!(
+            compress(b"ABBA", HuffmanUnitSize::EightBits).unwrap(),
+            compressed
+        );
+        assert_eq!(decompress(&compressed).unwrap(), b"ABBA");
+    }
+
+    #[test]
+    fn huffman_4_fixed_vector() {
+        // Units are read from the least significant nibble, with the codes 0 and 1
+        let compressed = [
+            0x24, 0x01, 0x00, 0x00, 0x01, 0xC0, 0x01, 0x02, 0x00, 0x00, 0x00, 0x40,
+        ];
+
+        assert_eq!(decompress(&compressed).unwrap(), [0x21]);
+    }
+
+    #[test]
+    fn decompress_invalid_data() {
+        assert!(matches!(
+            decompress(&[0x20, 0x01, 0x00, 0x00]),
+            Err(HuffmanError::UnknownMagic(0x20))
+        ));
+
+        // The data ends before the encoded data
+        assert!(matches!(
+            decompress(&[0x28, 0x04, 0x00, 0x00, 0x01, 0xC0, b'B', b'A']),
+            Err(HuffmanError::TruncatedData)
+        ));
+
+        // The root node points past the end of the tree
+        assert!(matches!(
+            decompress(&[
+                0x28, 0x01, 0x00, 0x00, 0x01, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+            ]),
+            Err(HuffmanError::InvalidTree(5))
+        ));
+    }
+}
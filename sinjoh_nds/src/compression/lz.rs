@@ -0,0 +1,405 @@
+//! LZ77 compression, as implemented by the BIOS of the Nintendo DS.
+//!
+//! Two variants are supported:
+//!
+//! - LZ10 (header byte `0x10`), which is the variant implemented by the BIOS. Back-references
+//!   can copy between 3 and 18 bytes.
+//! - LZ11 (header byte `0x11`), which is an extension of LZ10 allowing longer back-references
+//!   (up to 65808 bytes).
+//!
+//! Both variants use a 4 KiB sliding window.
+
+use thiserror::Error;
+
+use super::match_finder::MatchFinder;
+use super::{COMPRESSION_HEADER_SIZE, read_compression_header, write_compression_header};
+
+/// The header byte of LZ10-compressed data.
+pub const LZ10_MAGIC: u8 = 0x10;
+
+/// The header byte of LZ11-compressed data.
+pub const LZ11_MAGIC: u8 = 0x11;
+
+/// The size of the sliding window, which is the maximum distance of a back-reference.
+pub const LZ_WINDOW_SIZE: usize = 0x1000;
+
+/// The minimum length of a back-reference.
+pub const LZ_MIN_MATCH_LENGTH: usize = 3;
+
+/// The maximum length of a back-reference in LZ10.
+pub const LZ10_MAX_MATCH_LENGTH: usize = 0x12;
+
+/// The maximum length of a back-reference in LZ11.
+pub const LZ11_MAX_MATCH_LENGTH: usize = 0x10110;
+
+/// The minimum distance of a back-reference produced by the compressor.
+///
+/// A distance of 1 is valid, but is not supported by the BIOS functions that decompress to VRAM
+/// (which writes 16 bits at a time). It is thus never produced by the compressor.
+const LZ_MIN_DISTANCE: usize = 2;
+
+/// Error type for LZ77 compression and decompression.
+#[derive(Error, Debug)]
+pub enum LzError {
+    /// The header byte is not the one of the expected variant.
+    #[error("wrong LZ header byte (expected 0x{0:02X}, found 0x{1:02X})")]
+    WrongMagic(u8, u8),
+
+    /// The header byte is not the one of a supported LZ variant.
+    #[error("unknown LZ header byte (found 0x{0:02X})")]
+    UnknownMagic(u8),
+
+    /// The compressed data ended before the whole decompressed data could be produced.
+    #[error("the compressed data is truncated")]
+    TruncatedData,
+
+    /// A back-reference points before the beginning of the decompressed data.
+    #[error("invalid back-reference at offset 0x{0:X} of the decompressed data (distance is {1})")]
+    InvalidBackReference(usize, usize),
+
+    /// The data is too large to be compressed.
+    #[error("the data is too large to be compressed (size is {0})")]
+    DataTooLarge(usize),
+}
+
+/// Decompresses data compressed with LZ10 or LZ11, depending on its header byte.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, LzError> {
+    match data.first() {
+        Some(&LZ10_MAGIC) => decompress_lz10(data),
+        Some(&LZ11_MAGIC) => decompress_lz11(data),
+        Some(&magic) => Err(LzError::UnknownMagic(magic)),
+        None => Err(LzError::TruncatedData),
+    }
+}
+
+/// Decompresses data compressed with LZ10.
+pub fn decompress_lz10(data: &[u8]) -> Result<Vec<u8>, LzError> {
+    decompress_with(data, LZ10_MAGIC, |data, position| {
+        let bytes = data
+            .get(position..position + 2)
+            .ok_or(LzError::TruncatedData)?;
+
+        let length = (bytes[0] >> 4) as usize + LZ_MIN_MATCH_LENGTH;
+        let distance = ((((bytes[0] & 0x0F) as usize) << 8) | bytes[1] as usize) + 1;
+
+        Ok((length, distance, 2))
+    })
+}
+
+/// Decompresses data compressed with LZ11.
+pub fn decompress_lz11(data: &[u8]) -> Result<Vec<u8>, LzError> {
+    decompress_with(data, LZ11_MAGIC, |data, position| {
+        let indicator = data.get(position).ok_or(LzError::TruncatedData)? >> 4;
+
+        match indicator {
+            0 => {
+                let bytes = data
+                    .get(position..position + 3)
+                    .ok_or(LzError::TruncatedData)?;
+
+                let length =
+                    ((((bytes[0] & 0x0F) as usize) << 4) | (bytes[1] >> 4) as usize) + 0x11;
+                let distance = ((((bytes[1] & 0x0F) as usize) << 8) | bytes[2] as usize) + 1;
+
+                Ok((length, distance, 3))
+            }
+            1 => {
+                let bytes = data
+                    .get(position..position + 4)
+                    .ok_or(LzError::TruncatedData)?;
+
+                let length = ((((bytes[0] & 0x0F) as usize) << 12)
+                    | ((bytes[1] as usize) << 4)
+                    | (bytes[2] >> 4) as usize)
+                    + 0x111;
+                let distance = ((((bytes[2] & 0x0F) as usize) << 8) | bytes[3] as usize) + 1;
+
+                Ok((length, distance, 4))
+            }
+            _ => {
+                let bytes = data
+                    .get(position..position + 2)
+                    .ok_or(LzError::TruncatedData)?;
+
+                let length = indicator as usize + 1;
+                let distance = ((((bytes[0] & 0x0F) as usize) << 8) | bytes[1] as usize) + 1;
+
+                Ok((length, distance, 2))
+            }
+        }
+    })
+}
+
+/// Decompresses LZ data, using the given function to decode back-references.
+///
+/// The function returns the length, the distance and the encoded size of the back-reference
+/// starting at the given position.
+fn decompress_with<F>(data: &[u8], magic: u8, decode_reference: F) -> Result<Vec<u8>, LzError>
+where
+    F: Fn(&[u8], usize) -> Result<(usize, usize, usize), LzError>,
+{
+    let (found_magic, decompressed_size, mut position) =
+        read_compression_header(data).ok_or(LzError::TruncatedData)?;
+
+    if found_magic != magic {
+        return Err(LzError::WrongMagic(magic, found_magic));
+    }
+
+    // The capacity is bounded, so that a corrupted header can't trigger a huge allocation
+    let mut decompressed = Vec::with_capacity(decompressed_size.min(data.len() * 8));
+
+    while decompressed.len() < decompressed_size {
+        let flags = *data.get(position).ok_or(LzError::TruncatedData)?;
+        position += 1;
+
+        for bit in (0..8).rev() {
+            if decompressed.len() >= decompressed_size {
+                break;
+            }
+
+            if flags & (1 << bit) == 0 {
+                // Raw byte
+                decompressed.push(*data.get(position).ok_or(LzError::TruncatedData)?);
+                position += 1;
+            } else {
+                // Back-reference
+                let (length, distance, size) = decode_reference(data, position)?;
+                position += size;
+
+                if distance > decompressed.len() {
+                    return Err(LzError::InvalidBackReference(decompressed.len(), distance));
+                }
+
+                // Bytes are copied one by one, as the reference may overlap with the bytes it
+                // produces
+                let length = length.min(decompressed_size - decompressed.len());
+                for _ in 0..length {
+                    decompressed.push(decompressed[decompressed.len() - distance]);
+                }
+            }
+        }
+    }
+
+    Ok(decompressed)
+}
+
+/// Compresses data with LZ10.
+///
+/// The data must be smaller than 16 MiB.
+pub fn compress_lz10(data: &[u8]) -> Result<Vec<u8>, LzError> {
+    if data.len() > 0x00FF_FFFF {
+        return Err(LzError::DataTooLarge(data.len()));
+    }
+
+    compress_with(
+        data,
+        LZ10_MAGIC,
+        LZ10_MAX_MATCH_LENGTH,
+        |output, length, distance| {
+            let length = length - LZ_MIN_MATCH_LENGTH;
+            let distance = distance - 1;
+
+            output.push(((length << 4) | (distance >> 8)) as u8);
+            output.push(distance as u8);
+        },
+    )
+}
+
+/// Compresses data with LZ11.
+///
+/// Data that is 16 MiB or larger will use an extended header to store its size.
+pub fn compress_lz11(data: &[u8]) -> Result<Vec<u8>, LzError> {
+    if data.len() > u32::MAX as usize {
+        return Err(LzError::DataTooLarge(data.len()));
+    }
+
+    compress_with(
+        data,
+        LZ11_MAGIC,
+        LZ11_MAX_MATCH_LENGTH,
+        |output, length, distance| {
+            let distance = distance - 1;
+
+            if length > 0x110 {
+                let length = length - 0x111;
+
+                output.push((0x10 | (length >> 12)) as u8);
+                output.push((length >> 4) as u8);
+                output.push((((length & 0x0F) << 4) | (distance >> 8)) as u8);
+                output.push(distance as u8);
+            } else if length > 0x10 {
+                let length = length - 0x11;
+
+                output.push((length >> 4) as u8);
+                output.push((((length & 0x0F) << 4) | (distance >> 8)) as u8);
+                output.push(distance as u8);
+            } else {
+                let length = length - 1;
+
+                output.push(((length << 4) | (distance >> 8)) as u8);
+                output.push(distance as u8);
+            }
+        },
+    )
+}
+
+/// Compresses data with LZ, using the given function to encode back-references.
+fn compress_with<F>(
+    data: &[u8],
+    magic: u8,
+    max_match_length: usize,
+    encode_reference: F,
+) -> Result<Vec<u8>, LzError>
+where
+    F: Fn(&mut Vec<u8>, usize, usize),
+{
+    let mut output = Vec::with_capacity(COMPRESSION_HEADER_SIZE + data.len() + data.len() / 8 + 1);
+    write_compression_header(&mut output, magic, data.len());
+
+    let mut match_finder =
+        MatchFinder::new(data, LZ_MIN_MATCH_LENGTH, LZ_MIN_DISTANCE, LZ_WINDOW_SIZE);
+    let mut position = 0;
+
+    while position < data.len() {
+        let flags_position = output.len();
+        output.push(0);
+
+        for bit in (0..8).rev() {
+            if position >= data.len() {
+                break;
+            }
+
+            match match_finder.find_longest_match(position, max_match_length) {
+                Some((length, distance)) => {
+                    output[flags_position] |= 1 << bit;
+                    encode_reference(&mut output, length, distance);
+
+                    match_finder.insert_range(position, position + length);
+                    position += length;
+                }
+                None => {
+                    output.push(data[position]);
+
+                    match_finder.insert_range(position, position + 1);
+                    position += 1;
+                }
+            }
+        }
+    }
+
+    // Pad the compressed data to a multiple of 4 bytes
+    output.resize(output.len().next_multiple_of(4), 0);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns inputs exercising raw bytes, short and long back-references.
+    fn inputs() -> Vec<Vec<u8>> {
+        vec![
+            Vec::new(),
+            vec![0x42],
+            b"ABCABCABCD".to_vec(),
+            (0..=255).collect(),
+            (0..0x3000)
+                .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+                .collect(),
+            b"sinjoh".repeat(1000),
+            vec![0xAA; 0x11000],
+        ]
+    }
+
+    #[test]
+    fn lz10_round_trip() {
+        for input in inputs() {
+            let compressed = compress_lz10(&input).unwrap();
+
+            assert_eq!(compressed[0], LZ10_MAGIC);
+            assert_eq!(compressed.len() % 4, 0);
+            assert_eq!(decompress_lz10(&compressed).unwrap(), input);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn lz11_round_trip() {
+        for input in inputs() {
+            let compressed = compress_lz11(&input).unwrap();
+
+            assert_eq!(compressed[0], LZ11_MAGIC);
+            assert_eq!(compressed.len() % 4, 0);
+            assert_eq!(decompress_lz11(&compressed).unwrap(), input);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn lz10_decompress_bios_stream() {
+        // 3 raw bytes, a back-reference of 6 bytes at a distance of 3, and a raw byte
+        let compressed = [
+            0x10, 0x0A, 0x00, 0x00, 0x10, b'A', b'B', b'C', 0x30, 0x02, b'D', 0x00,
+        ];
+
+        assert_eq!(decompress_lz10(&compressed).unwrap(), b"ABCABCABCD");
+        assert!(matches!(
+            decompress_lz11(&compressed),
+            Err(LzError::WrongMagic(LZ11_MAGIC, LZ10_MAGIC))
+        ));
+    }
+
+    #[test]
+    fn lz11_decompress_bios_stream() {
+        // A raw byte, back-references of 32, 3 and 273 bytes at a distance of 1 (using the 3, 2
+        // and 4-byte encodings), and a raw byte
+        let compressed = [
+            0x11, 0x36, 0x01, 0x00, 0x70, b'A', 0x00, 0xF0, 0x00, 0x20, 0x00, 0x10, 0x00, 0x00,
+            0x00, b'B',
+        ];
+
+        let mut expected = vec![b'A'; 1 + 32 + 3 + 273];
+        expected.push(b'B');
+
+        assert_eq!(decompress_lz11(&compressed).unwrap(), expected);
+    }
+
+    #[test]
+    fn decompress_extended_header() {
+        let compressed = [
+            0x10, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x10, b'A', b'B', b'C', 0x30, 0x02,
+            b'D', 0x00,
+        ];
+        assert_eq!(decompress_lz10(&compressed).unwrap(), b"ABCABCABCD");
+
+        // Empty data is written with an extended header, as its size is zero
+        let compressed = compress_lz11(&[]).unwrap();
+        assert_eq!(compressed, [0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert!(decompress_lz11(&compressed).unwrap().is_empty());
+
+        // Data of 16 MiB or more does not fit in the regular header
+        let data = vec![0x00; 0x0100_0000];
+        let compressed = compress_lz11(&data).unwrap();
+        assert_eq!(
+            compressed[..8],
+            [0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]
+        );
+        assert_eq!(decompress_lz11(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn decompress_invalid_data() {
+        // Back-reference before the beginning of the data
+        assert!(matches!(
+            decompress_lz10(&[0x10, 0x03, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00]),
+            Err(LzError::InvalidBackReference(0, 1))
+        ));
+
+        // Missing raw bytes
+        assert!(matches!(
+            decompress_lz10(&[0x10, 0x03, 0x00, 0x00, 0x00, b'A']),
+            Err(LzError::TruncatedData)
+        ));
+    }
+}
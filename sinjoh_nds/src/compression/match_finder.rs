@@ -0,0 +1,111 @@
+//! Search of matches for LZ-based compression formats.
+
+/// Finds matches in a sliding window, using hash chains.
+pub(crate) struct MatchFinder<'a> {
+    /// The data being compressed.
+    data: &'a [u8],
+
+    /// The minimum length of a match.
+    min_match_length: usize,
+
+    /// The minimum distance of a match.
+    min_distance: usize,
+
+    /// The maximum distance of a match.
+    max_distance: usize,
+
+    /// The most recent position for each hash of the first bytes of a match.
+    head: Vec<Option<usize>>,
+
+    /// The previous position with the same hash, for each position.
+    previous: Vec<Option<usize>>,
+}
+
+impl<'a> MatchFinder<'a> {
+    /// The number of bits of the hashes.
+    const HASH_BITS: u32 = 15;
+
+    /// Creates a new match finder for the given data.
+    ///
+    /// The minimum match length must be between 1 and 4.
+    pub(crate) fn new(
+        data: &'a [u8],
+        min_match_length: usize,
+        min_distance: usize,
+        max_distance: usize,
+    ) -> Self {
+        Self {
+            data,
+            min_match_length,
+            min_distance,
+            max_distance,
+            head: vec![None; 1 << Self::HASH_BITS],
+            previous: vec![None; data.len()],
+        }
+    }
+
+    /// Returns the hash of the bytes at the given position, if there are enough bytes.
+    fn hash(&self, position: usize) -> Option<usize> {
+        let bytes = self.data.get(position..position + self.min_match_length)?;
+        let value = bytes
+            .iter()
+            .fold(0u32, |value, &byte| (value << 8) | byte as u32);
+
+        Some((value.wrapping_mul(0x9E37_79B1) >> (32 - Self::HASH_BITS)) as usize)
+    }
+
+    /// Inserts the positions in the given range in the hash chains.
+    pub(crate) fn insert_range(&mut self, start: usize, end: usize) {
+        for position in start..end {
+            if let Some(hash) = self.hash(position) {
+                self.previous[position] = self.head[hash];
+                self.head[hash] = Some(position);
+            }
+        }
+    }
+
+    /// Finds the longest match for the bytes at the given position.
+    ///
+    /// Returns the length and distance of the match, if one of at least the minimum length was
+    /// found.
+    pub(crate) fn find_longest_match(
+        &self,
+        position: usize,
+        max_match_length: usize,
+    ) -> Option<(usize, usize)> {
+        let max_length = max_match_length.min(self.data.len() - position);
+        if max_length < self.min_match_length {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[self.hash(position)?];
+
+        while let Some(candidate_position) = candidate {
+            let distance = position - candidate_position;
+            if distance > self.max_distance {
+                break;
+            }
+
+            if distance >= self.min_distance {
+                let length = self.data[candidate_position..]
+                    .iter()
+                    .zip(self.data[position..position + max_length].iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+
+                if length >= self.min_match_length && best.is_none_or(|(best, _)| length > best) {
+                    best = Some((length, distance));
+
+                    if length == max_length {
+                        break;
+                    }
+                }
+            }
+
+            candidate = self.previous[candidate_position];
+        }
+
+        best
+    }
+}
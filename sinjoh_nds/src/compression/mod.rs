@@ -0,0 +1,262 @@
+//! Utils for working with the compression formats supported by the Nintendo DS BIOS.
+//!
+//! Compressed data starts with a 4-byte header: the first byte identifies the compression
+//! format, and the 3 remaining bytes hold the size of the decompressed data. When this size is
+//! zero, it is instead stored in the 4 following bytes (this is an extension that is not
+//! supported by the BIOS, but that is used by some tools for large files).
+//!
+//! The format of compressed data can be detected from its header byte (See
+//! [`CompressionType::detect`]), and [`decompress`] can be used to decompress data without
+//! knowing its format beforehand.
+
+use thiserror::Error;
+
+use huffman::{HUFFMAN_4_MAGIC, HUFFMAN_8_MAGIC, HuffmanError, HuffmanUnitSize};
+use lz::{LZ10_MAGIC, LZ11_MAGIC, LzError};
+use rle::{RLE_MAGIC, RleError};
+
+pub mod blz;
+pub mod huffman;
+pub mod lz;
+mod match_finder;
+pub mod rle;
+
+/// The size of the header of compressed data.
+pub const COMPRESSION_HEADER_SIZE: usize = 4;
+
+/// The size of the extended header of compressed data, which is used for large files.
+pub const COMPRESSION_EXTENDED_HEADER_SIZE: usize = 8;
+
+/// Error type for compression and decompression.
+#[derive(Error, Debug)]
+pub enum CompressionError {
+    /// The header byte is not the one of a supported compression format.
+    #[error("unknown compression header byte (found 0x{0:02X})")]
+    UnknownFormat(u8),
+
+    /// The data is empty, and does not have a header.
+    #[error("the data is empty")]
+    EmptyData,
+
+    /// An error has occurred while compressing or decompressing with LZ.
+    #[error("LZ compression error")]
+    LzError(#[source] LzError),
+
+    /// An error has occurred while compressing or decompressing with Huffman.
+    #[error("Huffman compression error")]
+    HuffmanError(#[source] HuffmanError),
+
+    /// An error has occurred while compressing or decompressing with RLE.
+    #[error("RLE compression error")]
+    RleError(#[source] RleError),
+}
+
+/// A compression format supported by the BIOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionType {
+    /// LZ77 compression, with short back-references (See [`lz`]).
+    Lz10,
+
+    /// LZ77 compression, with long back-references (See [`lz`]).
+    Lz11,
+
+    /// Huffman compression, with 4-bit data units (See [`huffman`]).
+    Huffman4,
+
+    /// Huffman compression, with 8-bit data units (See [`huffman`]).
+    Huffman8,
+
+    /// Run-length encoding (See [`rle`]).
+    Rle,
+}
+
+impl CompressionType {
+    /// Returns the compression format identified by the given header byte, if any.
+    pub fn from_magic(magic: u8) -> Option<Self> {
+        match magic {
+            LZ10_MAGIC => Some(Self::Lz10),
+            LZ11_MAGIC => Some(Self::Lz11),
+            HUFFMAN_4_MAGIC => Some(Self::Huffman4),
+            HUFFMAN_8_MAGIC => Some(Self::Huffman8),
+            RLE_MAGIC => Some(Self::Rle),
+            _ => None,
+        }
+    }
+
+    /// Detects the compression format of the given data from its header byte, if any.
+    ///
+    /// Please note that this only looks at the header byte: uncompressed data that happens to
+    /// start with the header byte of a compression format will also be detected as compressed.
+    /// Use [`CompressionType::detect_checked`] to also check the header against the size of the
+    /// data.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        data.first().and_then(|&magic| Self::from_magic(magic))
+    }
+
+    /// Detects the compression format of the given data from its header, if any, checking that
+    /// the decompressed size declared by the header is consistent with the size of the data.
+    ///
+    /// The data is only detected as compressed if it is not larger than the largest output of
+    /// the format for the declared size, and if the declared size is not larger than what the
+    /// format can encode in the data. This rules out most uncompressed data that happens to start
+    /// with the header byte of a compression format, but very short data can still be ambiguous.
+    pub fn detect_checked(data: &[u8]) -> Option<Self> {
+        let compression_type = Self::detect(data)?;
+        let (_, decompressed_size, header_size) = read_compression_header(data)?;
+        let payload_size = data.len().checked_sub(header_size)?;
+
+        if decompressed_size == 0 {
+            return None;
+        }
+
+        // Largest payload for the declared size (every data unit is stored as-is, along with
+        // the flags or tree of the format), and largest decompressed size for the payload
+        let (max_payload_size, max_decompressed_size) = match compression_type {
+            Self::Lz10 => (
+                decompressed_size + decompressed_size.div_ceil(8),
+                payload_size.saturating_mul(9),
+            ),
+            Self::Lz11 => (
+                decompressed_size + decompressed_size.div_ceil(8),
+                payload_size.saturating_mul(0x10110 / 4 + 1),
+            ),
+            Self::Huffman4 | Self::Huffman8 => {
+                (decompressed_size + 0x200, payload_size.saturating_mul(8))
+            }
+            Self::Rle => (
+                decompressed_size + decompressed_size.div_ceil(0x80),
+                payload_size.saturating_mul(0x82 / 2 + 1),
+            ),
+        };
+
+        // Compressed data is padded to a multiple of 4 bytes
+        let max_payload_size = (max_payload_size + header_size).next_multiple_of(4) - header_size;
+
+        (payload_size <= max_payload_size && decompressed_size <= max_decompressed_size)
+            .then_some(compression_type)
+    }
+
+    /// Returns the header byte of data compressed with this format.
+    pub fn magic(&self) -> u8 {
+        match self {
+            Self::Lz10 => LZ10_MAGIC,
+            Self::Lz11 => LZ11_MAGIC,
+            Self::Huffman4 => HUFFMAN_4_MAGIC,
+            Self::Huffman8 => HUFFMAN_8_MAGIC,
+            Self::Rle => RLE_MAGIC,
+        }
+    }
+}
+
+/// Decompresses data, detecting its compression format from its header byte.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let magic = *data.first().ok_or(CompressionError::EmptyData)?;
+    let compression_type =
+        CompressionType::from_magic(magic).ok_or(CompressionError::UnknownFormat(magic))?;
+
+    match compression_type {
+        CompressionType::Lz10 | CompressionType::Lz11 => {
+            lz::decompress(data).map_err(CompressionError::LzError)
+        }
+        CompressionType::Huffman4 | CompressionType::Huffman8 => {
+            huffman::decompress(data).map_err(CompressionError::HuffmanError)
+        }
+        CompressionType::Rle => rle::decompress(data).map_err(CompressionError::RleError),
+    }
+}
+
+/// Compresses data with the specified compression format.
+pub fn compress(
+    data: &[u8],
+    compression_type: CompressionType,
+) -> Result<Vec<u8>, CompressionError> {
+    match compression_type {
+        CompressionType::Lz10 => lz::compress_lz10(data).map_err(CompressionError::LzError),
+        CompressionType::Lz11 => lz::compress_lz11(data).map_err(CompressionError::LzError),
+        CompressionType::Huffman4 => huffman::compress(data, HuffmanUnitSize::FourBits)
+            .map_err(CompressionError::HuffmanError),
+        CompressionType::Huffman8 => huffman::compress(data, HuffmanUnitSize::EightBits)
+            .map_err(CompressionError::HuffmanError),
+        CompressionType::Rle => rle::compress(data).map_err(CompressionError::RleError),
+    }
+}
+
+/// Reads the header of compressed data.
+///
+/// Returns the header byte, the size of the decompressed data and the size of the header, or
+/// `None` if the data is too short to contain a header.
+pub(crate) fn read_compression_header(data: &[u8]) -> Option<(u8, usize, usize)> {
+    // Safety: slice length is explicitly specified
+    let header = u32::from_le_bytes(data.get(0..COMPRESSION_HEADER_SIZE)?.try_into().unwrap());
+    let magic = header as u8;
+    let decompressed_size = (header >> 8) as usize;
+
+    if decompressed_size == 0 && data.len() >= COMPRESSION_EXTENDED_HEADER_SIZE {
+        let decompressed_size = u32::from_le_bytes(
+            data[COMPRESSION_HEADER_SIZE..COMPRESSION_EXTENDED_HEADER_SIZE]
+                .try_into()
+                .unwrap(),
+        );
+
+        return Some((
+            magic,
+            decompressed_size as usize,
+            COMPRESSION_EXTENDED_HEADER_SIZE,
+        ));
+    }
+
+    Some((magic, decompressed_size, COMPRESSION_HEADER_SIZE))
+}
+
+/// Writes the header of compressed data.
+///
+/// The extended header is used if the decompressed size does not fit in 24 bits, or if it is zero
+/// (as a zero size is what identifies the extended header). It is expected that the decompressed
+/// size fits in 32 bits.
+pub(crate) fn write_compression_header(output: &mut Vec<u8>, magic: u8, decompressed_size: usize) {
+    if decompressed_size == 0 || decompressed_size > 0x00FF_FFFF {
+        output.extend_from_slice(&(magic as u32).to_le_bytes());
+        output.extend_from_slice(&(decompressed_size as u32).to_le_bytes());
+    } else {
+        output
+            .extend_from_slice(&((magic as u32) | ((decompressed_size as u32) << 8)).to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_checked_compressed_data() {
+        let data = b"hello hello hello, this is some data to compress".repeat(4);
+
+        for compression_type in [
+            CompressionType::Lz10,
+            CompressionType::Lz11,
+            CompressionType::Huffman4,
+            CompressionType::Huffman8,
+            CompressionType::Rle,
+        ] {
+            let compressed = compress(&data, compression_type).unwrap();
+
+            assert_eq!(
+                CompressionType::detect_checked(&compressed),
+                Some(compression_type)
+            );
+        }
+    }
+
+    #[test]
+    fn detect_checked_uncompressed_data() {
+        // 1x16 map matrix without optional sections, which starts with the LZ10 header byte
+        let mut map_matrix = vec![0x10, 0x01, 0x00, 0x00, 0x00];
+        map_matrix.extend(std::iter::repeat_n([0x01, 0x00], 16).flatten());
+
+        assert_eq!(
+            CompressionType::detect(&map_matrix),
+            Some(CompressionType::Lz10)
+        );
+        assert_eq!(CompressionType::detect_checked(&map_matrix), None);
+    }
+}
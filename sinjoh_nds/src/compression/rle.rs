@@ -0,0 +1,186 @@
+//! Run-length encoding, as implemented by the BIOS of the Nintendo DS.
+//!
+//! The data is made of blocks, each starting with a flag byte:
+//!
+//! - If bit 7 is set, the block is a run: the following byte is repeated `(flag & 0x7F) + 3`
+//!   times.
+//! - Otherwise, the block is made of the `(flag & 0x7F) + 1` following bytes, copied as-is.
+
+use thiserror::Error;
+
+use super::{read_compression_header, write_compression_header};
+
+/// The header byte of RLE-compressed data.
+pub const RLE_MAGIC: u8 = 0x30;
+
+/// The minimum length of a run.
+pub const RLE_MIN_RUN_LENGTH: usize = 3;
+
+/// The maximum length of a run.
+pub const RLE_MAX_RUN_LENGTH: usize = 0x7F + RLE_MIN_RUN_LENGTH;
+
+/// The maximum length of a block of bytes copied as-is.
+pub const RLE_MAX_RAW_LENGTH: usize = 0x7F + 1;
+
+/// The bit of the flag byte that tells whether a block is a run.
+const RLE_RUN_FLAG: u8 = 0x80;
+
+/// Error type for RLE compression and decompression.
+#[derive(Error, Debug)]
+pub enum RleError {
+    /// The header byte is not the one of RLE.
+    #[error("wrong RLE header byte (expected 0x{RLE_MAGIC:02X}, found 0x{0:02X})")]
+    WrongMagic(u8),
+
+    /// The compressed data ended before the whole decompressed data could be produced.
+    #[error("the compressed data is truncated")]
+    TruncatedData,
+
+    /// The data is too large to be compressed.
+    #[error("the data is too large to be compressed (size is {0})")]
+    DataTooLarge(usize),
+}
+
+/// Decompresses data compressed with RLE.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, RleError> {
+    let (magic, decompressed_size, mut position) =
+        read_compression_header(data).ok_or(RleError::TruncatedData)?;
+
+    if magic != RLE_MAGIC {
+        return Err(RleError::WrongMagic(magic));
+    }
+
+    // The capacity is bounded, so that a corrupted header can't trigger a huge allocation
+    let mut decompressed = Vec::with_capacity(decompressed_size.min(data.len() * 8));
+
+    while decompressed.len() < decompressed_size {
+        let flag = *data.get(position).ok_or(RleError::TruncatedData)?;
+        position += 1;
+
+        let remaining = decompressed_size - decompressed.len();
+
+        if flag & RLE_RUN_FLAG != 0 {
+            let length = ((flag & !RLE_RUN_FLAG) as usize + RLE_MIN_RUN_LENGTH).min(remaining);
+            let byte = *data.get(position).ok_or(RleError::TruncatedData)?;
+            position += 1;
+
+            decompressed.resize(decompressed.len() + length, byte);
+        } else {
+            let length = (flag as usize + 1).min(remaining);
+            let bytes = data
+                .get(position..position + length)
+                .ok_or(RleError::TruncatedData)?;
+            position += flag as usize + 1;
+
+            decompressed.extend_from_slice(bytes);
+        }
+    }
+
+    Ok(decompressed)
+}
+
+/// Compresses data with RLE.
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, RleError> {
+    if data.len() > u32::MAX as usize {
+        return Err(RleError::DataTooLarge(data.len()));
+    }
+
+    let mut output = Vec::with_capacity(data.len() + data.len() / RLE_MAX_RAW_LENGTH + 8);
+    write_compression_header(&mut output, RLE_MAGIC, data.len());
+
+    let mut raw_start = 0;
+    let mut position = 0;
+
+    while position < data.len() {
+        // Measure the run starting at the current position
+        let run_length = data[position..]
+            .iter()
+            .take(RLE_MAX_RUN_LENGTH)
+            .take_while(|&&byte| byte == data[position])
+            .count();
+
+        if run_length >= RLE_MIN_RUN_LENGTH {
+            write_raw_blocks(&mut output, &data[raw_start..position]);
+
+            output.push(RLE_RUN_FLAG | (run_length - RLE_MIN_RUN_LENGTH) as u8);
+            output.push(data[position]);
+
+            position += run_length;
+            raw_start = position;
+        } else {
+            position += 1;
+        }
+    }
+
+    write_raw_blocks(&mut output, &data[raw_start..]);
+
+    // Pad the compressed data to a multiple of 4 bytes
+    output.resize(output.len().next_multiple_of(4), 0);
+
+    Ok(output)
+}
+
+/// Writes bytes as blocks of bytes copied as-is.
+fn write_raw_blocks(output: &mut Vec<u8>, bytes: &[u8]) {
+    for block in bytes.chunks(RLE_MAX_RAW_LENGTH) {
+        output.push((block.len() - 1) as u8);
+        output.extend_from_slice(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_round_trip() {
+        let inputs = [
+            Vec::new(),
+            vec![0x42],
+            vec![0x07; RLE_MAX_RUN_LENGTH],
+            vec![0x07; 200],
+            (0..300).map(|i| i as u8).collect(),
+            [vec![0x01, 0x02], vec![0x03; 2], vec![0x04; 3], vec![0x05]].concat(),
+        ];
+
+        for input in inputs {
+            let compressed = compress(&input).unwrap();
+
+            assert_eq!(compressed[0], RLE_MAGIC);
+            assert_eq!(compressed.len() % 4, 0);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn rle_fixed_vector() {
+        // A run of 5 bytes, followed by 2 bytes copied as-is
+        let compressed = [
+            0x30, 0x07, 0x00, 0x00, 0x82, b'A', 0x01, b'B', b'C', 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(compress(b"AAAAABC").unwrap(), compressed);
+        assert_eq!(decompress(&compressed).unwrap(), b"AAAAABC");
+    }
+
+    #[test]
+    fn rle_long_run() {
+        // Runs longer than 130 bytes are split in several blocks
+        let compressed = [0x30, 0xC8, 0x00, 0x00, 0xFF, 0x07, 0xC3, 0x07];
+
+        assert_eq!(compress(&[0x07; 200]).unwrap(), compressed);
+        assert_eq!(decompress(&compressed).unwrap(), [0x07; 200]);
+    }
+
+    #[test]
+    fn decompress_invalid_data() {
+        assert!(matches!(
+            decompress(&[0x10, 0x01, 0x00, 0x00]),
+            Err(RleError::WrongMagic(0x10))
+        ));
+        assert!(matches!(
+            decompress(&[0x30, 0x04, 0x00, 0x00, 0x03, b'A']),
+            Err(RleError::TruncatedData)
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+//! Utils for computing checksums used on the Nintendo DS.
+
+/// The initial value of the CRC16 used on the Nintendo DS.
+pub const CRC16_INITIAL_VALUE: u16 = 0xFFFF;
+
+/// The (reflected) polynomial of the CRC16 used on the Nintendo DS.
+pub const CRC16_POLYNOMIAL: u16 = 0xA001;
+
+/// Computes the CRC16 of the given data, as done by the Nintendo DS.
+///
+/// This is the CRC16 used for the checksums of the ROM header, secure area and Nintendo logo,
+/// among others.
+pub fn crc16(data: &[u8]) -> u16 {
+    crc16_with_initial_value(data, CRC16_INITIAL_VALUE)
+}
+
+/// Computes the CRC16 of the given data, starting from the given initial value.
+///
+/// This can be used to compute the CRC16 of data split across multiple buffers, by passing the
+/// CRC16 of the previous buffers as the initial value.
+pub fn crc16_with_initial_value(data: &[u8], initial_value: u16) -> u16 {
+    let mut crc = initial_value;
+
+    for &byte in data {
+        crc ^= byte as u16;
+
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ CRC16_POLYNOMIAL;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b""), CRC16_INITIAL_VALUE);
+        assert_eq!(crc16(b"123456789"), 0x4B37);
+    }
+
+    #[test]
+    fn crc16_split_data() {
+        let crc = crc16_with_initial_value(b"6789", crc16(b"12345"));
+        assert_eq!(crc, crc16(b"123456789"));
+    }
+}
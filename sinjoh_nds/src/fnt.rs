@@ -0,0 +1,486 @@
+//! Utils for working with File Name Tables.
+//!
+//! A File Name Table (FNT) describes a tree of directories, and gives a name to each file
+//! contained in them. Files themselves are only referenced by their ID, which is an index in the
+//! associated File Allocation Table.
+//!
+//! This format is used by the `FNTB` chunk of NARC archives, and by the file system of Nintendo
+//! DS ROMs.
+
+use std::{
+    io::{self, Cursor, Read},
+    string::FromUtf8Error,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use thiserror::Error;
+
+/// The ID of the root directory.
+pub const ROOT_DIRECTORY_ID: u16 = 0xF000;
+
+/// The size of an entry in the main table of a File Name Table.
+pub const MAIN_TABLE_ENTRY_SIZE: u32 = 8;
+
+/// The type/length byte marking the end of a sub-table.
+const SUB_TABLE_END: u8 = 0x00;
+
+/// The mask of the type/length byte telling whether an entry is a directory.
+const SUB_TABLE_DIRECTORY_MASK: u8 = 0x80;
+
+/// The mask of the type/length byte giving the length of an entry name.
+const SUB_TABLE_NAME_LENGTH_MASK: u8 = 0x7F;
+
+/// The separator used between the components of a path.
+pub const PATH_SEPARATOR: char = '/';
+
+/// Error type for File Name Table parsing and writing.
+#[derive(Error, Debug)]
+pub enum FileNameTableError {
+    /// An I/O error has occurred while trying to read from the buffer.
+    #[error("an error has occurred while reading the buffer")]
+    ReadError(#[source] io::Error),
+
+    /// The offset of a sub-table points outside of the File Name Table.
+    #[error("the sub-table of directory 0x{0:X} is out of bounds (offset is {1})")]
+    SubTableOutOfBounds(u16, u32),
+
+    /// A directory ID is outside of the range of directories described by the main table.
+    #[error("invalid directory ID 0x{0:X} (there are {1} directories)")]
+    InvalidDirectoryId(u16, u16),
+
+    /// A directory is referenced more than once in the tree.
+    #[error("directory 0x{0:X} is referenced more than once")]
+    DuplicateDirectory(u16),
+
+    /// A sub-table entry uses the reserved type/length byte.
+    #[error("a sub-table entry of directory 0x{0:X} has the reserved type 0x80")]
+    ReservedEntryType(u16),
+
+    /// A name could not be converted to a UTF-8 string.
+    #[error("unable to convert an entry name into a string")]
+    NameConversion(#[source] FromUtf8Error),
+
+    /// A name is too long to be written in a sub-table.
+    #[error("the name {0:?} is too long (maximum length is 127 bytes)")]
+    NameTooLong(String),
+
+    /// There are too many directories to be written in a File Name Table.
+    #[error("too many directories to fit in a File Name Table (got {0})")]
+    TooManyDirectories(usize),
+}
+
+/// Represents an entry of a directory.
+#[derive(Debug, Clone)]
+pub enum FileNameTableEntry {
+    /// A file, with its name and the ID of the file it refers to.
+    File {
+        /// The name of the file.
+        name: String,
+
+        /// The ID of the file, which is its index in the File Allocation Table.
+        file_id: u16,
+    },
+
+    /// A sub-directory.
+    Directory(FileNameTableDirectory),
+}
+
+/// Represents a directory of a File Name Table.
+#[derive(Debug, Clone)]
+pub struct FileNameTableDirectory {
+    /// The ID of the directory.
+    ///
+    /// The root directory has the ID [`ROOT_DIRECTORY_ID`], and other directories have the
+    /// following IDs.
+    pub id: u16,
+
+    /// The name of the directory.
+    ///
+    /// This is an empty string for the root directory.
+    pub name: String,
+
+    /// The ID of the first file of this directory.
+    ///
+    /// Files contained in a directory have consecutive IDs.
+    pub first_file_id: u16,
+
+    /// The entries contained in this directory, in the order they are stored.
+    pub entries: Vec<FileNameTableEntry>,
+}
+
+impl FileNameTableDirectory {
+    /// Returns the entry with the given name in this directory, if any.
+    pub fn get_entry(&self, name: &str) -> Option<&FileNameTableEntry> {
+        self.entries.iter().find(|entry| match entry {
+            FileNameTableEntry::File {
+                name: entry_name, ..
+            } => entry_name == name,
+            FileNameTableEntry::Directory(directory) => directory.name == name,
+        })
+    }
+
+    /// Returns the number of directories in this directory, including itself and all its
+    /// sub-directories.
+    fn directories_count(&self) -> usize {
+        1 + self
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                FileNameTableEntry::File { .. } => 0,
+                FileNameTableEntry::Directory(directory) => directory.directories_count(),
+            })
+            .sum::<usize>()
+    }
+}
+
+/// An iterator over the files of a File Name Table.
+///
+/// Each item is the full path of a file, along with its ID.
+#[derive(Debug)]
+pub struct FileNameTableFilesIter<'a> {
+    /// The directories being walked, along with their path and the index of the next entry to
+    /// visit.
+    stack: Vec<(&'a FileNameTableDirectory, String, usize)>,
+}
+
+impl<'a> FileNameTableFilesIter<'a> {
+    /// Creates a new iterator over the files of a File Name Table.
+    ///
+    /// See [`FileNameTable::files_iter`] for usage.
+    pub fn new(file_name_table: &'a FileNameTable) -> Self {
+        Self {
+            stack: vec![(&file_name_table.root, String::new(), 0)],
+        }
+    }
+}
+
+impl Iterator for FileNameTableFilesIter<'_> {
+    type Item = (String, u16);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (directory, path, curr) = self.stack.last_mut()?;
+
+            let Some(entry) = directory.entries.get(*curr) else {
+                self.stack.pop();
+                continue;
+            };
+
+            *curr += 1;
+
+            match entry {
+                FileNameTableEntry::File { name, file_id } => {
+                    return Some((format!("{}{}", path, name), *file_id));
+                }
+                FileNameTableEntry::Directory(sub_directory) => {
+                    let sub_path = format!("{}{}{}", path, sub_directory.name, PATH_SEPARATOR);
+                    self.stack.push((sub_directory, sub_path, 0));
+                }
+            }
+        }
+    }
+}
+
+/// Represents a File Name Table.
+#[derive(Debug, Clone)]
+pub struct FileNameTable {
+    /// The root directory of the table.
+    pub root: FileNameTableDirectory,
+}
+
+impl Default for FileNameTable {
+    /// Creates a File Name Table which does not name any file.
+    ///
+    /// This is what is found in most NARC archives.
+    fn default() -> Self {
+        Self {
+            root: FileNameTableDirectory {
+                id: ROOT_DIRECTORY_ID,
+                name: String::new(),
+                first_file_id: 0,
+                entries: Vec::new(),
+            },
+        }
+    }
+}
+
+impl FileNameTable {
+    /// Parses a [`FileNameTable`] from a byte slice.
+    ///
+    /// It is expected that the slice starts with the main table, and that offsets are relative to
+    /// the beginning of the slice.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, FileNameTableError> {
+        let mut reader = Cursor::new(bytes);
+
+        // The root entry of the main table stores the number of directories in place of its parent
+        reader.set_position(6);
+        let directories_count = reader
+            .read_u16::<LittleEndian>()
+            .map_err(FileNameTableError::ReadError)?;
+
+        let mut visited = vec![false; directories_count.into()];
+        let root = Self::parse_directory(bytes, ROOT_DIRECTORY_ID, String::new(), &mut visited)?;
+
+        Ok(Self { root })
+    }
+
+    /// Parses a directory, and all its sub-directories.
+    fn parse_directory(
+        bytes: &[u8],
+        id: u16,
+        name: String,
+        visited: &mut [bool],
+    ) -> Result<FileNameTableDirectory, FileNameTableError> {
+        // Make sure the directory is described in the main table
+        let index = id.wrapping_sub(ROOT_DIRECTORY_ID);
+        let directories_count = visited.len() as u16;
+
+        match visited.get_mut(index as usize) {
+            Some(true) => return Err(FileNameTableError::DuplicateDirectory(id)),
+            Some(visited) => *visited = true,
+            None => {
+                return Err(FileNameTableError::InvalidDirectoryId(
+                    id,
+                    directories_count,
+                ));
+            }
+        }
+
+        // Read the main table entry of this directory
+        let mut reader = Cursor::new(bytes);
+        reader.set_position(index as u64 * MAIN_TABLE_ENTRY_SIZE as u64);
+
+        let sub_table_offset = reader
+            .read_u32::<LittleEndian>()
+            .map_err(FileNameTableError::ReadError)?;
+
+        let first_file_id = reader
+            .read_u16::<LittleEndian>()
+            .map_err(FileNameTableError::ReadError)?;
+
+        if sub_table_offset as usize >= bytes.len() {
+            return Err(FileNameTableError::SubTableOutOfBounds(
+                id,
+                sub_table_offset,
+            ));
+        }
+
+        // Read the sub-table of this directory
+        reader.set_position(sub_table_offset.into());
+
+        let mut entries = Vec::new();
+        let mut next_file_id = first_file_id;
+
+        loop {
+            let type_length = reader.read_u8().map_err(FileNameTableError::ReadError)?;
+
+            if type_length == SUB_TABLE_END {
+                break;
+            } else if type_length == SUB_TABLE_DIRECTORY_MASK {
+                return Err(FileNameTableError::ReservedEntryType(id));
+            }
+
+            let mut raw_name = vec![0; (type_length & SUB_TABLE_NAME_LENGTH_MASK).into()];
+            reader
+                .read_exact(&mut raw_name)
+                .map_err(FileNameTableError::ReadError)?;
+
+            let entry_name =
+                String::from_utf8(raw_name).map_err(FileNameTableError::NameConversion)?;
+
+            if type_length & SUB_TABLE_DIRECTORY_MASK != 0 {
+                let sub_directory_id = reader
+                    .read_u16::<LittleEndian>()
+                    .map_err(FileNameTableError::ReadError)?;
+
+                entries.push(FileNameTableEntry::Directory(Self::parse_directory(
+                    bytes,
+                    sub_directory_id,
+                    entry_name,
+                    visited,
+                )?));
+            } else {
+                entries.push(FileNameTableEntry::File {
+                    name: entry_name,
+                    file_id: next_file_id,
+                });
+
+                next_file_id = next_file_id.wrapping_add(1);
+            }
+        }
+
+        Ok(FileNameTableDirectory {
+            id,
+            name,
+            first_file_id,
+            entries,
+        })
+    }
+
+    /// Serializes the [`FileNameTable`] to bytes.
+    ///
+    /// The main table is written first, followed by the sub-tables of each directory, ordered by
+    /// directory ID. Offsets are relative to the beginning of the returned bytes.
+    ///
+    /// If the table does not name any file, the compact form found in most NARC archives is
+    /// written instead, where the root directory points to an empty sub-table inside of the main
+    /// table.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FileNameTableError> {
+        if self.root.entries.is_empty() {
+            let mut bytes = Vec::with_capacity(MAIN_TABLE_ENTRY_SIZE as usize);
+            bytes.extend_from_slice(&4u32.to_le_bytes());
+            bytes.extend_from_slice(&self.root.first_file_id.to_le_bytes());
+            bytes.extend_from_slice(&1u16.to_le_bytes());
+
+            return Ok(bytes);
+        }
+
+        // Gather all directories, and their parent, ordered by ID
+        let directories_count = self.root.directories_count();
+        let directories_count_u16: u16 = directories_count
+            .try_into()
+            .ok()
+            .filter(|&count| count <= u16::MAX - ROOT_DIRECTORY_ID + 1)
+            .ok_or(FileNameTableError::TooManyDirectories(directories_count))?;
+
+        let mut directories = vec![None; directories_count];
+        Self::collect_directories(
+            &self.root,
+            directories_count_u16,
+            &mut directories,
+            directories_count_u16,
+        )?;
+
+        // Write the sub-tables
+        let mut sub_tables = Vec::new();
+        let mut sub_table_offsets = Vec::with_capacity(directories_count);
+        let main_table_size = directories_count as u32 * MAIN_TABLE_ENTRY_SIZE;
+
+        for (directory, _) in directories.iter().flatten() {
+            sub_table_offsets.push(main_table_size + sub_tables.len() as u32);
+
+            for entry in directory.entries.iter() {
+                match entry {
+                    FileNameTableEntry::File { name, .. } => {
+                        sub_tables.push(Self::name_length(name)?);
+                        sub_tables.extend_from_slice(name.as_bytes());
+                    }
+                    FileNameTableEntry::Directory(sub_directory) => {
+                        sub_tables.push(
+                            Self::name_length(&sub_directory.name)? | SUB_TABLE_DIRECTORY_MASK,
+                        );
+                        sub_tables.extend_from_slice(sub_directory.name.as_bytes());
+                        sub_tables.extend_from_slice(&sub_directory.id.to_le_bytes());
+                    }
+                }
+            }
+
+            sub_tables.push(SUB_TABLE_END);
+        }
+
+        // Write the main table, followed by the sub-tables
+        let mut bytes = Vec::with_capacity(main_table_size as usize + sub_tables.len());
+
+        for ((directory, parent_id), sub_table_offset) in
+            directories.iter().flatten().zip(sub_table_offsets)
+        {
+            bytes.extend_from_slice(&sub_table_offset.to_le_bytes());
+            bytes.extend_from_slice(&directory.first_file_id.to_le_bytes());
+            bytes.extend_from_slice(&parent_id.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&sub_tables);
+
+        Ok(bytes)
+    }
+
+    /// Places a directory and its sub-directories at the index given by their ID.
+    fn collect_directories<'a>(
+        directory: &'a FileNameTableDirectory,
+        parent_id: u16,
+        directories: &mut [Option<(&'a FileNameTableDirectory, u16)>],
+        directories_count: u16,
+    ) -> Result<(), FileNameTableError> {
+        let index = directory.id.wrapping_sub(ROOT_DIRECTORY_ID);
+
+        match directories.get_mut(index as usize) {
+            Some(Some(_)) => return Err(FileNameTableError::DuplicateDirectory(directory.id)),
+            Some(slot) => *slot = Some((directory, parent_id)),
+            None => {
+                return Err(FileNameTableError::InvalidDirectoryId(
+                    directory.id,
+                    directories_count,
+                ));
+            }
+        }
+
+        for entry in directory.entries.iter() {
+            if let FileNameTableEntry::Directory(sub_directory) = entry {
+                Self::collect_directories(
+                    sub_directory,
+                    directory.id,
+                    directories,
+                    directories_count,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the length of a name, as written in the type/length byte of a sub-table entry.
+    fn name_length(name: &str) -> Result<u8, FileNameTableError> {
+        u8::try_from(name.len())
+            .ok()
+            .filter(|&length| length <= SUB_TABLE_NAME_LENGTH_MASK && length > 0)
+            .ok_or_else(|| FileNameTableError::NameTooLong(name.to_string()))
+    }
+
+    /// Returns whether this table gives a name to at least one file or directory.
+    pub fn has_names(&self) -> bool {
+        !self.root.entries.is_empty()
+    }
+
+    /// Returns the directory at the given path, if any.
+    ///
+    /// Path components are separated by [`PATH_SEPARATOR`]. Leading, trailing and repeated
+    /// separators are ignored, and an empty path designates the root directory.
+    pub fn get_directory_by_path(&self, path: &str) -> Option<&FileNameTableDirectory> {
+        let mut directory = &self.root;
+
+        for component in path.split(PATH_SEPARATOR).filter(|c| !c.is_empty()) {
+            match directory.get_entry(component)? {
+                FileNameTableEntry::Directory(sub_directory) => directory = sub_directory,
+                FileNameTableEntry::File { .. } => return None,
+            }
+        }
+
+        Some(directory)
+    }
+
+    /// Returns the ID of the file at the given path, if any.
+    ///
+    /// Path components are separated by [`PATH_SEPARATOR`], such as in `a/b.bin`.
+    pub fn get_file_id_by_path(&self, path: &str) -> Option<u16> {
+        let (directory_path, file_name) = match path.rsplit_once(PATH_SEPARATOR) {
+            Some((directory_path, file_name)) => (directory_path, file_name),
+            None => ("", path),
+        };
+
+        match self
+            .get_directory_by_path(directory_path)?
+            .get_entry(file_name)?
+        {
+            FileNameTableEntry::File { file_id, .. } => Some(*file_id),
+            FileNameTableEntry::Directory(_) => None,
+        }
+    }
+
+    /// Returns an iterator over the files of the table.
+    ///
+    /// Each item is the full path of a file, along with its ID. Files are visited depth-first, in
+    /// the order they are stored.
+    pub fn files_iter(&self) -> FileNameTableFilesIter<'_> {
+        FileNameTableFilesIter::new(self)
+    }
+}
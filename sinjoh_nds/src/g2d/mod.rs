@@ -0,0 +1,122 @@
+//! Utils for working with the 2D file formats of the Nintendo DS SDK.
+//!
+//! The following formats are supported:
+//!
+//! - NCLR (`RLCN`), which holds palettes (See [`nclr::Nclr`]).
+//! - NCGR (`RGCN`), which holds character graphics, either as 8x8 tiles or as a linear bitmap
+//!   (See [`ncgr::Ncgr`]).
+//! - NSCR (`RCSN`), which holds screens, arranging tiles of character graphics into a background
+//!   (See [`nscr::Nscr`]).
+//! - NCER (`RECN`), which holds cells, assembling tiles of character graphics into sprites (See
+//!   [`ncer::Ncer`]).
+//! - NANR (`RNAN`), which holds cell animations (See [`nanr::Nanr`]).
+//!
+//! Those formats are made of blocks that directly follow each other (See
+//! [`nitro`](crate::nitro)). Once parsed, a screen can be rendered to an RGBA image along with
+//! its graphics and palette (See [`nscr::Nscr::render`]), and so can a cell (See
+//! [`ncer::Ncer::render_cell`]).
+
+use thiserror::Error;
+
+use crate::nitro::{self, NitroError};
+
+pub mod nanr;
+pub mod ncer;
+pub mod ncgr;
+pub mod nclr;
+pub mod nscr;
+
+/// The width and height of a tile, in pixels.
+pub const TILE_SIZE: usize = 8;
+
+/// The number of pixels in a tile.
+pub const TILE_PIXEL_COUNT: usize = TILE_SIZE * TILE_SIZE;
+
+/// The number of colors in a palette used by 4-bit graphics.
+pub const PALETTE_16_SIZE: usize = 16;
+
+/// Error type for 2D file parsing.
+#[derive(Error, Debug)]
+pub enum G2dError {
+    /// The file structure could not be parsed.
+    #[error("unable to parse the file structure")]
+    NitroError(#[source] NitroError),
+
+    /// The data ended before a structure could be fully read.
+    #[error("the data is truncated (tried to read at offset 0x{0:X})")]
+    TruncatedData(usize),
+
+    /// A block required by the file is missing.
+    #[error("the block {:?} is missing", String::from_utf8_lossy(.0))]
+    MissingBlock([u8; 4]),
+
+    /// The bit depth of the graphics or palette is not supported.
+    #[error("unsupported bit depth value {0}")]
+    UnsupportedBitDepth(u32),
+
+    /// A screen refers to a tile that is not in the graphics.
+    #[error("tile {0} not found")]
+    TileNotFound(usize),
+
+    /// No cell has the specified index.
+    #[error("cell {0} not found")]
+    CellNotFound(usize),
+
+    /// A pixel refers to a color that is not in the palette.
+    #[error("color {0} not found in the palette")]
+    ColorNotFound(usize),
+}
+
+/// The number of bits used to store a pixel of character graphics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitDepth {
+    /// 4 bits per pixel, indexing a palette of 16 colors.
+    Bpp4,
+
+    /// 8 bits per pixel, indexing a palette of 256 colors.
+    Bpp8,
+}
+
+impl BitDepth {
+    /// Returns the bit depth corresponding to the value found in palettes and graphics.
+    pub fn from_raw(raw: u32) -> Result<Self, G2dError> {
+        match raw {
+            3 => Ok(Self::Bpp4),
+            4 => Ok(Self::Bpp8),
+            _ => Err(G2dError::UnsupportedBitDepth(raw)),
+        }
+    }
+
+    /// Returns the value corresponding to the bit depth, as found in palettes and graphics.
+    pub fn to_raw(&self) -> u32 {
+        match self {
+            Self::Bpp4 => 3,
+            Self::Bpp8 => 4,
+        }
+    }
+
+    /// Returns the number of bits used to store a pixel.
+    pub fn bits_per_pixel(&self) -> usize {
+        match self {
+            Self::Bpp4 => 4,
+            Self::Bpp8 => 8,
+        }
+    }
+}
+
+/// Reads a `u16` at the specified offset.
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, G2dError> {
+    nitro::read_u16(bytes, offset).ok_or(G2dError::TruncatedData(offset))
+}
+
+/// Reads a `u32` at the specified offset.
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, G2dError> {
+    nitro::read_u32(bytes, offset).ok_or(G2dError::TruncatedData(offset))
+}
+
+/// Returns the slice of the specified size, at the specified offset.
+pub(crate) fn read_slice(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8], G2dError> {
+    bytes
+        .get(offset..offset + size)
+        .ok_or(G2dError::TruncatedData(offset))
+}
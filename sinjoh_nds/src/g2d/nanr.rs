@@ -0,0 +1,233 @@
+//! Cell animation files (NANR, `RNAN`), which animate the cells of a cell bank.
+//!
+//! A cell animation file contains an animation bank block (`ABNK`), which is a list of
+//! animations. Each animation is a sequence of frames, which display a cell of a cell bank (See
+//! [`Ncer`]) for a number of frames, optionally transformed.
+
+use crate::DsFixed32;
+use crate::image::RgbaImage;
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::ncer::Ncer;
+use super::ncgr::Ncgr;
+use super::nclr::Nclr;
+use super::{G2dError, read_u16, read_u32};
+
+/// The magic number of cell animation files.
+pub const NANR_MAGIC: [u8; 4] = *b"RNAN";
+
+/// The magic number of animation bank blocks.
+pub const ABNK_MAGIC: [u8; 4] = *b"KNBA";
+
+/// The size of an animation entry.
+const ANIMATION_SIZE: usize = 16;
+
+/// The size of a frame entry.
+const FRAME_SIZE: usize = 8;
+
+/// How an animation is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// The frames are played once, from the first to the last.
+    Forward,
+
+    /// The frames are played from the first to the last, then from the loop start frame again.
+    ForwardLoop,
+
+    /// The frames are played once forward, then once backward.
+    PingPong,
+
+    /// The frames are played forward then backward, repeatedly.
+    PingPongLoop,
+
+    /// Unknown playback mode.
+    Unknown(u32),
+}
+
+impl PlaybackMode {
+    /// Returns the playback mode corresponding to the value found in the animation entry.
+    pub fn from_raw(raw: u32) -> Self {
+        match raw {
+            1 => Self::Forward,
+            2 => Self::ForwardLoop,
+            3 => Self::PingPong,
+            4 => Self::PingPongLoop,
+            _ => Self::Unknown(raw),
+        }
+    }
+}
+
+/// Represents a frame of a cell animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationFrame {
+    /// Index of the displayed cell, in the cell bank.
+    pub cell_index: u16,
+
+    /// Number of video frames during which the frame is displayed.
+    pub duration: u16,
+
+    /// Rotation of the cell, where 65536 is a full turn, if the animation has one.
+    pub rotation: Option<u16>,
+
+    /// Scale of the cell on the X and Y axes, if the animation has one.
+    pub scale: Option<(DsFixed32, DsFixed32)>,
+
+    /// Translation of the cell on the X and Y axes, if the animation has one.
+    pub translation: Option<(i16, i16)>,
+}
+
+/// Represents a cell animation.
+#[derive(Debug, Clone)]
+pub struct CellAnimation {
+    /// Index of the frame from which looping animations start again.
+    pub loop_start_frame: u16,
+
+    /// How the animation is played.
+    pub playback_mode: PlaybackMode,
+
+    /// Frames of the animation.
+    pub frames: Vec<AnimationFrame>,
+}
+
+impl CellAnimation {
+    /// Returns the total number of video frames of the frames of the animation.
+    pub fn duration(&self) -> u32 {
+        self.frames.iter().map(|frame| frame.duration as u32).sum()
+    }
+
+    /// Returns the frame displayed at the specified video frame, when playing the frames of the
+    /// animation once, forward.
+    ///
+    /// Returns `None` if the video frame is after the end of the animation.
+    pub fn frame_at(&self, video_frame: u32) -> Option<&AnimationFrame> {
+        let mut start = 0;
+
+        self.frames.iter().find(|frame| {
+            start += frame.duration as u32;
+            video_frame < start
+        })
+    }
+}
+
+/// Represents a cell animation file.
+#[derive(Debug, Clone)]
+pub struct Nanr {
+    /// Animations of the file.
+    pub animations: Vec<CellAnimation>,
+}
+
+impl Nanr {
+    /// Parses a cell animation file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g2d::nanr::Nanr;
+    ///
+    /// let bytes = std::fs::read("sprite.nanr").unwrap();
+    /// let nanr = Nanr::parse_bytes(&bytes)?;
+    ///
+    /// for (i, animation) in nanr.animations.iter().enumerate() {
+    ///     println!("animation {i}: {} frames", animation.frames.len());
+    /// }
+    /// # Ok::<(), sinjoh_nds::g2d::G2dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G2dError> {
+        let file = NitroFile::parse_bytes(bytes, NANR_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(G2dError::NitroError)?;
+
+        let block = file
+            .block(ABNK_MAGIC)
+            .ok_or(G2dError::MissingBlock(ABNK_MAGIC))?
+            .data;
+
+        let animation_count = read_u16(block, 0x08)? as usize;
+        let animations_offset = 0x08 + read_u32(block, 0x0C)? as usize;
+        let frames_offset = 0x08 + read_u32(block, 0x10)? as usize;
+        let frame_data_offset = 0x08 + read_u32(block, 0x14)? as usize;
+
+        let animations = (0..animation_count)
+            .map(|i| {
+                let offset = animations_offset + i * ANIMATION_SIZE;
+                let frame_count = read_u16(block, offset)? as usize;
+                let loop_start_frame = read_u16(block, offset + 0x02)?;
+                let element_type = read_u16(block, offset + 0x04)?;
+                let playback_mode = PlaybackMode::from_raw(read_u32(block, offset + 0x08)?);
+                let first_frame_offset = frames_offset + read_u32(block, offset + 0x0C)? as usize;
+
+                let frames = (0..frame_count)
+                    .map(|j| {
+                        let offset = first_frame_offset + j * FRAME_SIZE;
+                        let data_offset = frame_data_offset + read_u32(block, offset)? as usize;
+                        let duration = read_u16(block, offset + 0x04)?;
+
+                        Self::parse_frame(block, data_offset, element_type, duration)
+                    })
+                    .collect::<Result<_, G2dError>>()?;
+
+                Ok(CellAnimation {
+                    loop_start_frame,
+                    playback_mode,
+                    frames,
+                })
+            })
+            .collect::<Result<_, G2dError>>()?;
+
+        Ok(Self { animations })
+    }
+
+    /// Parses the data of a frame, whose layout depends on the type of the elements of the
+    /// animation.
+    fn parse_frame(
+        bytes: &[u8],
+        offset: usize,
+        element_type: u16,
+        duration: u16,
+    ) -> Result<AnimationFrame, G2dError> {
+        let read_i16 = |offset| -> Result<i16, G2dError> { Ok(read_u16(bytes, offset)? as i16) };
+        let read_fx32 = |offset| -> Result<DsFixed32, G2dError> {
+            Ok(DsFixed32::from_bits(read_u32(bytes, offset)? as i32))
+        };
+
+        let mut frame = AnimationFrame {
+            cell_index: read_u16(bytes, offset)?,
+            duration,
+            rotation: None,
+            scale: None,
+            translation: None,
+        };
+
+        match element_type {
+            // Cell index, rotation, scale and translation
+            1 => {
+                frame.rotation = Some(read_u16(bytes, offset + 0x02)?);
+                frame.scale = Some((read_fx32(offset + 0x04)?, read_fx32(offset + 0x08)?));
+                frame.translation = Some((read_i16(offset + 0x0C)?, read_i16(offset + 0x0E)?));
+            }
+            // Cell index and translation
+            2 => {
+                frame.translation = Some((read_i16(offset + 0x04)?, read_i16(offset + 0x06)?));
+            }
+            // Cell index only
+            _ => {}
+        }
+
+        Ok(frame)
+    }
+}
+
+impl Ncer {
+    /// Renders the cell displayed by a frame of a cell animation to an RGBA image, using the
+    /// specified graphics and palette.
+    ///
+    /// The rotation, scale and translation of the frame are not applied (See
+    /// [`Ncer::render_cell`]).
+    pub fn render_animation_frame(
+        &self,
+        frame: &AnimationFrame,
+        graphics: &Ncgr,
+        palette: &Nclr,
+    ) -> Result<RgbaImage, G2dError> {
+        self.render_cell(frame.cell_index as usize, graphics, palette)
+    }
+}
@@ -0,0 +1,404 @@
+//! Cell bank files (NCER, `RECN`), which assemble objects (OAM entries) into sprites.
+//!
+//! A cell bank file contains a cell bank block (`CEBK`), which is a list of cells. Each cell is
+//! made of OAM entries, which place tiles of character graphics (See [`Ncgr`]) relative to the
+//! origin of the cell.
+
+use crate::image::RgbaImage;
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::ncgr::{self, Ncgr};
+use super::nclr::Nclr;
+use super::{BitDepth, G2dError, TILE_SIZE, read_u16, read_u32};
+
+/// The magic number of cell bank files.
+pub const NCER_MAGIC: [u8; 4] = *b"RECN";
+
+/// The magic number of cell bank blocks.
+pub const CEBK_MAGIC: [u8; 4] = *b"KBEC";
+
+/// The size of an OAM entry.
+const OAM_SIZE: usize = 6;
+
+/// The number of tiles in a row of character graphics using the 2D mapping mode.
+const MAPPING_2D_WIDTH_IN_TILES: usize = 32;
+
+/// The shape of an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectShape {
+    /// The object is as wide as it is tall.
+    Square,
+
+    /// The object is wider than it is tall.
+    Horizontal,
+
+    /// The object is taller than it is wide.
+    Vertical,
+
+    /// Prohibited value.
+    Prohibited,
+}
+
+/// The mapping mode of the tiles of objects in VRAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingMode {
+    /// Tiles of an object are consecutive, and tile indices are in units of the specified
+    /// number of bytes (32, 64, 128 or 256).
+    OneDimensional(usize),
+
+    /// Tiles of an object are laid out in a 2D grid, 32 tiles wide.
+    TwoDimensional,
+}
+
+impl MappingMode {
+    /// Returns the mapping mode corresponding to the value found in the cell bank block.
+    pub fn from_raw(raw: u32) -> Self {
+        match raw {
+            0 => Self::OneDimensional(32),
+            1 => Self::OneDimensional(64),
+            2 => Self::OneDimensional(128),
+            3 => Self::OneDimensional(256),
+            _ => Self::TwoDimensional,
+        }
+    }
+}
+
+/// Represents an OAM entry, placing an object in a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Oam {
+    /// Raw value of the first attribute (position on the Y axis, mode and shape).
+    pub attribute0: u16,
+
+    /// Raw value of the second attribute (position on the X axis, flip and size).
+    pub attribute1: u16,
+
+    /// Raw value of the third attribute (tile, priority and palette).
+    pub attribute2: u16,
+}
+
+impl Oam {
+    /// Returns the position of the object on the X axis, relative to the origin of the cell.
+    pub fn x(&self) -> i16 {
+        // Sign-extend the 9-bit value
+        ((self.attribute1 << 7) as i16) >> 7
+    }
+
+    /// Returns the position of the object on the Y axis, relative to the origin of the cell.
+    pub fn y(&self) -> i16 {
+        (self.attribute0 & 0xFF) as u8 as i8 as i16
+    }
+
+    /// Returns whether the object is rotated and scaled using affine parameters.
+    pub fn affine(&self) -> bool {
+        self.attribute0 & (1 << 8) != 0
+    }
+
+    /// Returns whether the object is drawn in an area twice as large, for affine objects.
+    pub fn double_size(&self) -> bool {
+        self.affine() && self.attribute0 & (1 << 9) != 0
+    }
+
+    /// Returns whether the object is hidden, for non-affine objects.
+    pub fn disabled(&self) -> bool {
+        !self.affine() && self.attribute0 & (1 << 9) != 0
+    }
+
+    /// Returns the mode of the object (0: normal, 1: semi-transparent, 2: window, 3: bitmap).
+    pub fn mode(&self) -> u8 {
+        ((self.attribute0 >> 10) & 0x03) as u8
+    }
+
+    /// Returns whether the object is drawn with the mosaic effect.
+    pub fn mosaic(&self) -> bool {
+        self.attribute0 & (1 << 12) != 0
+    }
+
+    /// Returns the bit depth of the tiles of the object.
+    pub fn bit_depth(&self) -> BitDepth {
+        if self.attribute0 & (1 << 13) != 0 {
+            BitDepth::Bpp8
+        } else {
+            BitDepth::Bpp4
+        }
+    }
+
+    /// Returns the shape of the object.
+    pub fn shape(&self) -> ObjectShape {
+        match self.attribute0 >> 14 {
+            0 => ObjectShape::Square,
+            1 => ObjectShape::Horizontal,
+            2 => ObjectShape::Vertical,
+            _ => ObjectShape::Prohibited,
+        }
+    }
+
+    /// Returns the index of the affine parameters of the object, for affine objects.
+    pub fn affine_parameters_index(&self) -> Option<u8> {
+        self.affine()
+            .then_some(((self.attribute1 >> 9) & 0x1F) as u8)
+    }
+
+    /// Returns whether the object is flipped horizontally, for non-affine objects.
+    pub fn flip_horizontally(&self) -> bool {
+        !self.affine() && self.attribute1 & (1 << 12) != 0
+    }
+
+    /// Returns whether the object is flipped vertically, for non-affine objects.
+    pub fn flip_vertically(&self) -> bool {
+        !self.affine() && self.attribute1 & (1 << 13) != 0
+    }
+
+    /// Returns the size of the object (from 0 to 3), which gives its dimensions along with its
+    /// shape.
+    pub fn size(&self) -> u8 {
+        (self.attribute1 >> 14) as u8
+    }
+
+    /// Returns the width and height of the object, in pixels.
+    pub fn dimensions(&self) -> (usize, usize) {
+        const SQUARE: [(usize, usize); 4] = [(8, 8), (16, 16), (32, 32), (64, 64)];
+        const HORIZONTAL: [(usize, usize); 4] = [(16, 8), (32, 8), (32, 16), (64, 32)];
+        const VERTICAL: [(usize, usize); 4] = [(8, 16), (8, 32), (16, 32), (32, 64)];
+
+        let size = self.size() as usize;
+        match self.shape() {
+            ObjectShape::Square => SQUARE[size],
+            ObjectShape::Horizontal => HORIZONTAL[size],
+            ObjectShape::Vertical => VERTICAL[size],
+            ObjectShape::Prohibited => (0, 0),
+        }
+    }
+
+    /// Returns the index of the first tile of the object, in units of the mapping mode.
+    pub fn tile_index(&self) -> u16 {
+        self.attribute2 & 0x03FF
+    }
+
+    /// Returns the priority of the object (0 being drawn over the others).
+    pub fn priority(&self) -> u8 {
+        ((self.attribute2 >> 10) & 0x03) as u8
+    }
+
+    /// Returns the index of the palette of the object.
+    pub fn palette_index(&self) -> u8 {
+        (self.attribute2 >> 12) as u8
+    }
+
+    /// Returns the position of the object on the X and Y axes, relative to the origin of the
+    /// cell, and its width and height.
+    ///
+    /// Double-size affine objects are drawn in the middle of an area twice as large as them.
+    fn drawn_area(&self) -> (i32, i32, usize, usize) {
+        let (width, height) = self.dimensions();
+        let (mut x, mut y) = (self.x() as i32, self.y() as i32);
+
+        if self.double_size() {
+            x += width as i32 / 2;
+            y += height as i32 / 2;
+        }
+
+        (x, y, width, height)
+    }
+}
+
+/// Represents a cell, made of objects.
+#[derive(Debug, Clone)]
+pub struct Cell {
+    /// Attributes of the cell.
+    pub attributes: u16,
+
+    /// Bounding rectangle of the cell (maximum X, maximum Y, minimum X, minimum Y), if the bank
+    /// stores it.
+    pub bounding_rectangle: Option<[i16; 4]>,
+
+    /// Objects of the cell, the first one being drawn over the others.
+    pub oams: Vec<Oam>,
+}
+
+impl Cell {
+    /// Returns the area covered by the visible objects of the cell, relative to its origin, as
+    /// the minimum and maximum X and Y positions (maximums being exclusive).
+    ///
+    /// Returns `None` if the cell has no visible objects.
+    pub fn bounds(&self) -> Option<(i32, i32, i32, i32)> {
+        self.oams
+            .iter()
+            .filter(|oam| !oam.disabled())
+            .map(Oam::drawn_area)
+            .map(|(x, y, width, height)| (x, y, x + width as i32, y + height as i32))
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+    }
+}
+
+/// Represents a cell bank file.
+#[derive(Debug, Clone)]
+pub struct Ncer {
+    /// Mapping mode of the tiles of the objects.
+    pub mapping: MappingMode,
+
+    /// Cells of the bank.
+    pub cells: Vec<Cell>,
+}
+
+impl Ncer {
+    /// Parses a cell bank file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g2d::ncer::Ncer;
+    ///
+    /// let bytes = std::fs::read("sprite.ncer").unwrap();
+    /// let ncer = Ncer::parse_bytes(&bytes)?;
+    ///
+    /// for (i, cell) in ncer.cells.iter().enumerate() {
+    ///     for oam in &cell.oams {
+    ///         println!("cell {i}: {:?} at ({}, {})", oam.dimensions(), oam.x(), oam.y());
+    ///     }
+    /// }
+    /// # Ok::<(), sinjoh_nds::g2d::G2dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G2dError> {
+        let file = NitroFile::parse_bytes(bytes, NCER_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(G2dError::NitroError)?;
+
+        let block = file
+            .block(CEBK_MAGIC)
+            .ok_or(G2dError::MissingBlock(CEBK_MAGIC))?
+            .data;
+
+        let cell_count = read_u16(block, 0x08)? as usize;
+        let has_bounding_rectangles = read_u16(block, 0x0A)? & 0x01 != 0;
+        let cells_offset = 0x08 + read_u32(block, 0x0C)? as usize;
+        let mapping = MappingMode::from_raw(read_u32(block, 0x10)?);
+
+        // The OAM entries directly follow the cells
+        let cell_size = if has_bounding_rectangles { 16 } else { 8 };
+        let oams_offset = cells_offset + cell_count * cell_size;
+
+        let cells = (0..cell_count)
+            .map(|i| {
+                let offset = cells_offset + i * cell_size;
+                let oam_count = read_u16(block, offset)? as usize;
+                let attributes = read_u16(block, offset + 0x02)?;
+                let first_oam_offset = oams_offset + read_u32(block, offset + 0x04)? as usize;
+
+                let bounding_rectangle = if has_bounding_rectangles {
+                    let mut rectangle = [0; 4];
+                    for (j, value) in rectangle.iter_mut().enumerate() {
+                        *value = read_u16(block, offset + 0x08 + j * 2)? as i16;
+                    }
+
+                    Some(rectangle)
+                } else {
+                    None
+                };
+
+                let oams = (0..oam_count)
+                    .map(|j| {
+                        let offset = first_oam_offset + j * OAM_SIZE;
+                        Ok(Oam {
+                            attribute0: read_u16(block, offset)?,
+                            attribute1: read_u16(block, offset + 0x02)?,
+                            attribute2: read_u16(block, offset + 0x04)?,
+                        })
+                    })
+                    .collect::<Result<_, G2dError>>()?;
+
+                Ok(Cell {
+                    attributes,
+                    bounding_rectangle,
+                    oams,
+                })
+            })
+            .collect::<Result<_, G2dError>>()?;
+
+        Ok(Self { mapping, cells })
+    }
+
+    /// Renders the cell with the specified index to an RGBA image, using the specified graphics
+    /// and palette.
+    ///
+    /// The image covers the area of the visible objects of the cell (See [`Cell::bounds`]).
+    /// Affine objects are drawn without their rotation and scale.
+    pub fn render_cell(
+        &self,
+        index: usize,
+        graphics: &Ncgr,
+        palette: &Nclr,
+    ) -> Result<RgbaImage, G2dError> {
+        let cell = self.cells.get(index).ok_or(G2dError::CellNotFound(index))?;
+
+        let Some((min_x, min_y, max_x, max_y)) = cell.bounds() else {
+            return Ok(RgbaImage::new(0, 0));
+        };
+
+        let mut image = RgbaImage::new((max_x - min_x) as usize, (max_y - min_y) as usize);
+
+        // The first objects are drawn over the last ones
+        for oam in cell.oams.iter().rev().filter(|oam| !oam.disabled()) {
+            let (x, y, width, height) = oam.drawn_area();
+            let bit_depth = oam.bit_depth();
+            let palette_colors = palette.palette(
+                match bit_depth {
+                    BitDepth::Bpp4 => oam.palette_index().into(),
+                    BitDepth::Bpp8 => 0,
+                },
+                bit_depth,
+            );
+
+            let width_in_tiles = width / TILE_SIZE;
+            for tile_y in 0..height / TILE_SIZE {
+                for tile_x in 0..width_in_tiles {
+                    let tile_index = self.tile_index(oam, tile_x, tile_y, width_in_tiles);
+                    let tile = graphics
+                        .tile(tile_index)
+                        .ok_or(G2dError::TileNotFound(tile_index))?;
+
+                    // Flipping the object also flips the position of its tiles
+                    let tile_x = if oam.flip_horizontally() {
+                        width_in_tiles - 1 - tile_x
+                    } else {
+                        tile_x
+                    };
+                    let tile_y = if oam.flip_vertically() {
+                        height / TILE_SIZE - 1 - tile_y
+                    } else {
+                        tile_y
+                    };
+
+                    ncgr::draw_tile(
+                        &mut image,
+                        &tile,
+                        palette_colors,
+                        (x - min_x) as usize + tile_x * TILE_SIZE,
+                        (y - min_y) as usize + tile_y * TILE_SIZE,
+                        oam.flip_horizontally(),
+                        oam.flip_vertically(),
+                    )?;
+                }
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// Returns the index in the character graphics of a tile of an object.
+    fn tile_index(&self, oam: &Oam, tile_x: usize, tile_y: usize, width_in_tiles: usize) -> usize {
+        let first_tile = oam.tile_index() as usize;
+
+        // Tile indices are in units of 32 bytes, which is half of a 256-color tile
+        let (first_tile, row_width) = match self.mapping {
+            MappingMode::OneDimensional(boundary) => (first_tile * boundary / 32, width_in_tiles),
+            MappingMode::TwoDimensional => (first_tile, MAPPING_2D_WIDTH_IN_TILES),
+        };
+
+        match oam.bit_depth() {
+            BitDepth::Bpp4 => first_tile + tile_y * row_width + tile_x,
+            BitDepth::Bpp8 => match self.mapping {
+                MappingMode::OneDimensional(_) => first_tile / 2 + tile_y * row_width + tile_x,
+                MappingMode::TwoDimensional => (first_tile + tile_y * row_width) / 2 + tile_x,
+            },
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Character graphics files (NCGR, `RGCN`), which hold the pixels of backgrounds and objects.
+//!
+//! A character graphics file contains a character block (`CHAR`), whose pixels are indices in a
+//! palette (See [`Nclr`](super::nclr::Nclr)). Pixels are either grouped by 8x8 tiles, or stored
+//! as a linear bitmap, row by row.
+
+use crate::DsRgb;
+use crate::image::RgbaImage;
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::{BitDepth, G2dError, TILE_PIXEL_COUNT, TILE_SIZE, read_slice, read_u16, read_u32};
+
+/// The magic number of character graphics files.
+pub const NCGR_MAGIC: [u8; 4] = *b"RGCN";
+
+/// The magic number of character blocks.
+pub const CHAR_MAGIC: [u8; 4] = *b"RAHC";
+
+/// The value of the width or height of graphics whose size is not defined.
+const UNDEFINED_SIZE: u16 = 0xFFFF;
+
+/// The width used to lay out graphics whose size is not defined, in tiles.
+pub const DEFAULT_WIDTH_IN_TILES: usize = 32;
+
+/// How the pixels of character graphics are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsLayout {
+    /// Pixels are grouped by 8x8 tiles, each tile being stored row by row.
+    Tiled,
+
+    /// Pixels are stored row by row, over the whole width of the graphics.
+    Linear,
+}
+
+/// Represents a character graphics file.
+#[derive(Debug, Clone)]
+pub struct Ncgr {
+    /// Width of the graphics in tiles, if defined.
+    pub width_in_tiles: Option<u16>,
+
+    /// Height of the graphics in tiles, if defined.
+    pub height_in_tiles: Option<u16>,
+
+    /// Number of bits used to store a pixel.
+    pub bit_depth: BitDepth,
+
+    /// Mapping mode of the graphics in VRAM.
+    pub mapping: u32,
+
+    /// How the pixels are stored.
+    pub layout: GraphicsLayout,
+
+    /// Raw pixel data, as palette indices.
+    pub data: Vec<u8>,
+}
+
+impl Ncgr {
+    /// Parses a character graphics file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g2d::ncgr::Ncgr;
+    ///
+    /// let bytes = std::fs::read("graphics.ncgr").unwrap();
+    /// let ncgr = Ncgr::parse_bytes(&bytes)?;
+    ///
+    /// println!("{} tiles, {:?}", ncgr.tile_count(), ncgr.bit_depth);
+    /// # Ok::<(), sinjoh_nds::g2d::G2dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G2dError> {
+        let file = NitroFile::parse_bytes(bytes, NCGR_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(G2dError::NitroError)?;
+
+        let block = file
+            .block(CHAR_MAGIC)
+            .ok_or(G2dError::MissingBlock(CHAR_MAGIC))?
+            .data;
+
+        let size = |raw: u16| (raw != UNDEFINED_SIZE).then_some(raw);
+        let height_in_tiles = size(read_u16(block, 0x08)?);
+        let width_in_tiles = size(read_u16(block, 0x0A)?);
+        let bit_depth = BitDepth::from_raw(read_u32(block, 0x0C)?)?;
+        let mapping = read_u32(block, 0x10)?;
+        let layout = if read_u32(block, 0x14)? & 0xFF != 0 {
+            GraphicsLayout::Linear
+        } else {
+            GraphicsLayout::Tiled
+        };
+
+        let data_size = read_u32(block, 0x18)? as usize;
+        let data_offset = 0x08 + read_u32(block, 0x1C)? as usize;
+        let data = read_slice(block, data_offset, data_size)?.to_vec();
+
+        Ok(Self {
+            width_in_tiles,
+            height_in_tiles,
+            bit_depth,
+            mapping,
+            layout,
+            data,
+        })
+    }
+
+    /// Returns the number of tiles of the graphics.
+    pub fn tile_count(&self) -> usize {
+        self.data.len() * 8 / self.bit_depth.bits_per_pixel() / TILE_PIXEL_COUNT
+    }
+
+    /// Returns the width of the graphics in tiles, using a default width if it is not defined.
+    pub fn layout_width_in_tiles(&self) -> usize {
+        match self.width_in_tiles {
+            Some(width) if width > 0 => width as usize,
+            _ => DEFAULT_WIDTH_IN_TILES.min(self.tile_count().max(1)),
+        }
+    }
+
+    /// Returns the palette index of the pixel with the specified index in the pixel data.
+    fn pixel_at(&self, index: usize) -> Option<u8> {
+        match self.bit_depth {
+            BitDepth::Bpp4 => self
+                .data
+                .get(index / 2)
+                .map(|&byte| (byte >> ((index % 2) * 4)) & 0x0F),
+            BitDepth::Bpp8 => self.data.get(index).copied(),
+        }
+    }
+
+    /// Returns the palette indices of the pixels of the tile with the specified index, row by row.
+    ///
+    /// For linear graphics, tiles are the 8x8 blocks of the bitmap, from left to right and top to
+    /// bottom.
+    pub fn tile(&self, index: usize) -> Option<[u8; TILE_PIXEL_COUNT]> {
+        if index >= self.tile_count() {
+            return None;
+        }
+
+        let mut tile = [0; TILE_PIXEL_COUNT];
+
+        for (i, pixel) in tile.iter_mut().enumerate() {
+            let pixel_index = match self.layout {
+                GraphicsLayout::Tiled => index * TILE_PIXEL_COUNT + i,
+                GraphicsLayout::Linear => {
+                    let width = self.layout_width_in_tiles();
+                    let x = (index % width) * TILE_SIZE + i % TILE_SIZE;
+                    let y = (index / width) * TILE_SIZE + i / TILE_SIZE;
+
+                    y * width * TILE_SIZE + x
+                }
+            };
+
+            *pixel = self.pixel_at(pixel_index)?;
+        }
+
+        Some(tile)
+    }
+
+    /// Renders all the tiles of the graphics to an RGBA image, using the specified palette.
+    ///
+    /// Tiles are laid out from left to right and top to bottom, over the width of the graphics.
+    /// The first color of the palette is transparent.
+    pub fn render(&self, palette: &[DsRgb]) -> Result<RgbaImage, G2dError> {
+        let tile_count = self.tile_count();
+        let width = self.layout_width_in_tiles();
+        let height = tile_count.div_ceil(width);
+
+        let mut image = RgbaImage::new(width * TILE_SIZE, height * TILE_SIZE);
+
+        for index in 0..tile_count {
+            let tile = self.tile(index).ok_or(G2dError::TileNotFound(index))?;
+            draw_tile(
+                &mut image,
+                &tile,
+                palette,
+                (index % width) * TILE_SIZE,
+                (index / width) * TILE_SIZE,
+                false,
+                false,
+            )?;
+        }
+
+        Ok(image)
+    }
+}
+
+/// Draws a tile to an image, at the specified position, optionally flipping it.
+///
+/// The first color of the palette is transparent.
+pub(crate) fn draw_tile(
+    image: &mut RgbaImage,
+    tile: &[u8; TILE_PIXEL_COUNT],
+    palette: &[DsRgb],
+    x: usize,
+    y: usize,
+    flip_horizontally: bool,
+    flip_vertically: bool,
+) -> Result<(), G2dError> {
+    for (i, &color_index) in tile.iter().enumerate() {
+        if color_index == 0 {
+            continue;
+        }
+
+        let color = palette
+            .get(color_index as usize)
+            .ok_or(G2dError::ColorNotFound(color_index as usize))?;
+        let [red, green, blue] = color.to_rgb8();
+
+        let mut tile_x = i % TILE_SIZE;
+        let mut tile_y = i / TILE_SIZE;
+        if flip_horizontally {
+            tile_x = TILE_SIZE - 1 - tile_x;
+        }
+        if flip_vertically {
+            tile_y = TILE_SIZE - 1 - tile_y;
+        }
+
+        image.set_pixel(x + tile_x, y + tile_y, [red, green, blue, 0xFF]);
+    }
+
+    Ok(())
+}
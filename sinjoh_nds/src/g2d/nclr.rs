@@ -0,0 +1,128 @@
+//! Palette files (NCLR, `RLCN`), which hold the colors used by character graphics.
+//!
+//! A palette file contains a palette block (`PLTT`), holding the colors, and optionally a palette
+//! compression block (`PCMP`), listing the IDs of the palettes that are actually stored when only
+//! some of the 16-color palettes are.
+
+use crate::DsRgb;
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::{BitDepth, G2dError, PALETTE_16_SIZE, read_slice, read_u16, read_u32};
+
+/// The magic number of palette files.
+pub const NCLR_MAGIC: [u8; 4] = *b"RLCN";
+
+/// The magic number of palette blocks.
+pub const PLTT_MAGIC: [u8; 4] = *b"TTLP";
+
+/// The magic number of palette compression blocks.
+pub const PCMP_MAGIC: [u8; 4] = *b"PMCP";
+
+/// Represents a palette file.
+#[derive(Debug, Clone)]
+pub struct Nclr {
+    /// Bit depth of the graphics the palette is made for.
+    pub bit_depth: BitDepth,
+
+    /// Whether the palette is an extended palette (256-color palettes for backgrounds and
+    /// objects).
+    pub extended: bool,
+
+    /// Colors of the palette.
+    pub colors: Vec<DsRgb>,
+
+    /// IDs of the 16-color palettes stored in the file, if they are compressed.
+    ///
+    /// When present, the n-th 16 colors of the palette are the palette with the n-th ID.
+    pub palette_ids: Option<Vec<u16>>,
+}
+
+impl Nclr {
+    /// Parses a palette file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g2d::nclr::Nclr;
+    ///
+    /// let bytes = std::fs::read("palette.nclr").unwrap();
+    /// let nclr = Nclr::parse_bytes(&bytes)?;
+    ///
+    /// println!("{} colors", nclr.colors.len());
+    /// # Ok::<(), sinjoh_nds::g2d::G2dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G2dError> {
+        let file = NitroFile::parse_bytes(bytes, NCLR_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(G2dError::NitroError)?;
+
+        // Read the colors
+        let palette_block = file
+            .block(PLTT_MAGIC)
+            .ok_or(G2dError::MissingBlock(PLTT_MAGIC))?
+            .data;
+
+        let bit_depth = BitDepth::from_raw(read_u32(palette_block, 0x08)?)?;
+        let extended = read_u32(palette_block, 0x0C)? != 0;
+        let data_size = read_u32(palette_block, 0x10)? as usize;
+        let data_offset = 0x08 + read_u32(palette_block, 0x14)? as usize;
+
+        // The size of the data is sometimes larger than the block, so it is clamped to it
+        let data_size = data_size.min(palette_block.len().saturating_sub(data_offset));
+        let colors = read_slice(palette_block, data_offset, data_size)?
+            .chunks_exact(2)
+            .map(|color| DsRgb::from_bgr555(u16::from_le_bytes([color[0], color[1]])))
+            .collect();
+
+        // Read the IDs of the palettes
+        let palette_ids = file
+            .block(PCMP_MAGIC)
+            .map(|block| -> Result<_, G2dError> {
+                let count = read_u16(block.data, 0x08)? as usize;
+                let ids_offset = 0x08 + read_u32(block.data, 0x0C)? as usize;
+
+                (0..count)
+                    .map(|i| read_u16(block.data, ids_offset + i * 2))
+                    .collect()
+            })
+            .transpose()?;
+
+        Ok(Self {
+            bit_depth,
+            extended,
+            colors,
+            palette_ids,
+        })
+    }
+
+    /// Returns the colors of the palette with the specified index, as used by graphics of the
+    /// specified bit depth.
+    ///
+    /// For 4-bit graphics, this is the 16-color palette with the specified index (or ID, if the
+    /// palettes are compressed). For 8-bit graphics, this is the 256-color palette with the
+    /// specified index, which is only different from 0 for extended palettes.
+    ///
+    /// The returned slice is shorter than the size of the palette if the file does not hold
+    /// enough colors, and empty if the palette does not exist.
+    pub fn palette(&self, index: usize, bit_depth: BitDepth) -> &[DsRgb] {
+        let (index, size) = match bit_depth {
+            BitDepth::Bpp4 => {
+                let index = match &self.palette_ids {
+                    Some(palette_ids) => {
+                        match palette_ids.iter().position(|&id| id as usize == index) {
+                            Some(position) => position,
+                            None => return &[],
+                        }
+                    }
+                    None => index,
+                };
+
+                (index, PALETTE_16_SIZE)
+            }
+            BitDepth::Bpp8 => (index, 256),
+        };
+
+        let start = (index * size).min(self.colors.len());
+        let end = (start + size).min(self.colors.len());
+        &self.colors[start..end]
+    }
+}
@@ -0,0 +1,200 @@
+//! Screen files (NSCR, `RCSN`), which arrange tiles of character graphics into backgrounds.
+//!
+//! A screen file contains a screen block (`SCRN`), which is a grid of entries. Each entry refers
+//! to a tile of character graphics (See [`Ncgr`]), and for text backgrounds, to the palette used
+//! by the tile and whether it is flipped.
+
+use crate::image::RgbaImage;
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::ncgr::{self, Ncgr};
+use super::nclr::Nclr;
+use super::{BitDepth, G2dError, TILE_SIZE, read_slice, read_u16, read_u32};
+
+/// The magic number of screen files.
+pub const NSCR_MAGIC: [u8; 4] = *b"RCSN";
+
+/// The magic number of screen blocks.
+pub const SCRN_MAGIC: [u8; 4] = *b"NRCS";
+
+/// The format of the entries of a screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenFormat {
+    /// 16-bit entries, with a tile index, flip flags and a palette index.
+    Text,
+
+    /// 8-bit entries, with only a tile index.
+    Affine,
+
+    /// 16-bit entries, like text screens, used by extended affine backgrounds.
+    AffineExtended,
+}
+
+impl ScreenFormat {
+    /// Returns the format corresponding to the value found in the screen block.
+    pub fn from_raw(raw: u16) -> Self {
+        match raw {
+            1 => Self::Affine,
+            2 => Self::AffineExtended,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Represents an entry of a screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenEntry {
+    /// Index of the tile in the character graphics.
+    pub tile_index: u16,
+
+    /// Whether the tile is flipped horizontally.
+    pub flip_horizontally: bool,
+
+    /// Whether the tile is flipped vertically.
+    pub flip_vertically: bool,
+
+    /// Index of the 16-color palette (or of the extended palette) used by the tile.
+    pub palette_index: u8,
+}
+
+impl ScreenEntry {
+    /// Parses a 16-bit screen entry from its raw value.
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            tile_index: raw & 0x03FF,
+            flip_horizontally: raw & (1 << 10) != 0,
+            flip_vertically: raw & (1 << 11) != 0,
+            palette_index: (raw >> 12) as u8,
+        }
+    }
+}
+
+/// Represents a screen file.
+#[derive(Debug, Clone)]
+pub struct Nscr {
+    /// Width of the screen, in pixels.
+    pub width: u16,
+
+    /// Height of the screen, in pixels.
+    pub height: u16,
+
+    /// Bit depth of the graphics used by the screen.
+    pub bit_depth: BitDepth,
+
+    /// Format of the entries of the screen.
+    pub format: ScreenFormat,
+
+    /// Entries of the screen, row by row.
+    pub entries: Vec<ScreenEntry>,
+}
+
+impl Nscr {
+    /// Parses a screen file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g2d::ncgr::Ncgr;
+    /// use sinjoh_nds::g2d::nclr::Nclr;
+    /// use sinjoh_nds::g2d::nscr::Nscr;
+    ///
+    /// let nscr = Nscr::parse_bytes(&std::fs::read("screen.nscr").unwrap())?;
+    /// let ncgr = Ncgr::parse_bytes(&std::fs::read("graphics.ncgr").unwrap())?;
+    /// let nclr = Nclr::parse_bytes(&std::fs::read("palette.nclr").unwrap())?;
+    ///
+    /// let image = nscr.render(&ncgr, &nclr)?;
+    /// println!("{}x{}", image.width, image.height);
+    /// # Ok::<(), sinjoh_nds::g2d::G2dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G2dError> {
+        let file = NitroFile::parse_bytes(bytes, NSCR_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(G2dError::NitroError)?;
+
+        let block = file
+            .block(SCRN_MAGIC)
+            .ok_or(G2dError::MissingBlock(SCRN_MAGIC))?
+            .data;
+
+        let width = read_u16(block, 0x08)?;
+        let height = read_u16(block, 0x0A)?;
+        let bit_depth = match read_u16(block, 0x0C)? {
+            0 => BitDepth::Bpp4,
+            _ => BitDepth::Bpp8,
+        };
+        let format = ScreenFormat::from_raw(read_u16(block, 0x0E)?);
+        let data_size = read_u32(block, 0x10)? as usize;
+        let data = read_slice(block, 0x14, data_size)?;
+
+        let entries = match format {
+            ScreenFormat::Affine => data
+                .iter()
+                .map(|&tile_index| ScreenEntry {
+                    tile_index: tile_index.into(),
+                    flip_horizontally: false,
+                    flip_vertically: false,
+                    palette_index: 0,
+                })
+                .collect(),
+            ScreenFormat::Text | ScreenFormat::AffineExtended => data
+                .chunks_exact(2)
+                .map(|entry| ScreenEntry::from_raw(u16::from_le_bytes([entry[0], entry[1]])))
+                .collect(),
+        };
+
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            format,
+            entries,
+        })
+    }
+
+    /// Returns the width of the screen, in tiles.
+    pub fn width_in_tiles(&self) -> usize {
+        self.width as usize / TILE_SIZE
+    }
+
+    /// Returns the height of the screen, in tiles.
+    pub fn height_in_tiles(&self) -> usize {
+        self.height as usize / TILE_SIZE
+    }
+
+    /// Renders the screen to an RGBA image, using the specified graphics and palette.
+    ///
+    /// The first color of each palette is transparent.
+    pub fn render(&self, graphics: &Ncgr, palette: &Nclr) -> Result<RgbaImage, G2dError> {
+        let width_in_tiles = self.width_in_tiles();
+        let mut image = RgbaImage::new(self.width.into(), self.height.into());
+
+        for (index, entry) in self
+            .entries
+            .iter()
+            .take(width_in_tiles * self.height_in_tiles())
+            .enumerate()
+        {
+            let tile_index = entry.tile_index as usize;
+            let tile = graphics
+                .tile(tile_index)
+                .ok_or(G2dError::TileNotFound(tile_index))?;
+
+            // 256-color graphics only use the palette index of the entry for extended palettes
+            let palette_index = match graphics.bit_depth {
+                BitDepth::Bpp8 if !palette.extended => 0,
+                _ => entry.palette_index as usize,
+            };
+
+            ncgr::draw_tile(
+                &mut image,
+                &tile,
+                palette.palette(palette_index, graphics.bit_depth),
+                (index % width_in_tiles) * TILE_SIZE,
+                (index / width_in_tiles) * TILE_SIZE,
+                entry.flip_horizontally,
+                entry.flip_vertically,
+            )?;
+        }
+
+        Ok(image)
+    }
+}
@@ -0,0 +1,241 @@
+//! Utils shared by the animation files.
+//!
+//! Animation files hold a single block, which is a dictionary of animations. Each animation
+//! starts with a header identifying its kind, followed by its number of frames and the data
+//! specific to its kind.
+//!
+//! Animated values are stored as tracks (See [`Track`]), which are either a constant value, or
+//! values sampled every 1, 2 or 4 frames. When values are not sampled on every frame, the values
+//! of the frames in-between are interpolated.
+
+use crate::nitro::{NitroBlockLayout, NitroFile};
+use crate::{DsFixed16, DsFixed32, DsRgb};
+
+use super::dictionary::Dictionary;
+use super::{G3dError, read_u32};
+
+/// Offset of the animation dictionary in an animation block.
+const ANIMATION_DICTIONARY_OFFSET: usize = 0x08;
+
+/// A value that can be interpolated between two samples of a track.
+pub trait Interpolate: Clone {
+    /// Returns the value located at `numerator / denominator` of the way between `self` and
+    /// `next`.
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self;
+}
+
+/// Linearly interpolates between two integers.
+fn interpolate_integer(start: i64, end: i64, numerator: u32, denominator: u32) -> i64 {
+    start + (end - start) * numerator as i64 / denominator as i64
+}
+
+impl Interpolate for DsFixed16 {
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self {
+        Self::from_bits(interpolate_integer(
+            self.to_bits().into(),
+            next.to_bits().into(),
+            numerator,
+            denominator,
+        ) as i16)
+    }
+}
+
+impl Interpolate for DsFixed32 {
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self {
+        Self::from_bits(interpolate_integer(
+            self.to_bits().into(),
+            next.to_bits().into(),
+            numerator,
+            denominator,
+        ) as i32)
+    }
+}
+
+impl Interpolate for u8 {
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self {
+        interpolate_integer((*self).into(), (*next).into(), numerator, denominator) as u8
+    }
+}
+
+impl Interpolate for DsRgb {
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self {
+        Self {
+            red: self.red.interpolate(&next.red, numerator, denominator),
+            green: self.green.interpolate(&next.green, numerator, denominator),
+            blue: self.blue.interpolate(&next.blue, numerator, denominator),
+        }
+    }
+}
+
+impl<A: Interpolate, B: Interpolate> Interpolate for (A, B) {
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self {
+        (
+            self.0.interpolate(&next.0, numerator, denominator),
+            self.1.interpolate(&next.1, numerator, denominator),
+        )
+    }
+}
+
+impl<T: Interpolate, const N: usize> Interpolate for [T; N] {
+    fn interpolate(&self, next: &Self, numerator: u32, denominator: u32) -> Self {
+        std::array::from_fn(|i| self[i].interpolate(&next[i], numerator, denominator))
+    }
+}
+
+/// Represents values sampled at a regular interval of frames.
+///
+/// Values are stored for every `step` frames up to the last interpolated frame. Frames that come
+/// after it have their own values stored at the end, as there is no following sample to
+/// interpolate with.
+#[derive(Debug, Clone)]
+pub struct SampledTrack<T> {
+    /// Number of frames between two samples (1, 2 or 4).
+    pub step: u16,
+
+    /// Last frame whose value is interpolated.
+    pub last_interpolated_frame: u16,
+
+    /// Sampled values.
+    pub values: Vec<T>,
+}
+
+impl<T> SampledTrack<T> {
+    /// Returns the index of the sample to use for the specified frame, along with the position
+    /// of the frame between this sample and the next one (from 0 to `step - 1`).
+    fn sample_index(step: u16, last_interpolated_frame: u16, frame: u16) -> (usize, u16) {
+        let remainder = frame % step;
+
+        if remainder == 0 {
+            ((frame / step) as usize, 0)
+        } else if frame > last_interpolated_frame {
+            ((last_interpolated_frame / step + remainder) as usize, 0)
+        } else {
+            ((frame / step) as usize, remainder)
+        }
+    }
+
+    /// Returns the number of samples needed to cover the specified number of frames.
+    fn sample_count(step: u16, last_interpolated_frame: u16, frame_count: u16) -> usize {
+        (0..frame_count)
+            .map(|frame| {
+                let (index, remainder) = Self::sample_index(step, last_interpolated_frame, frame);
+                if remainder == 0 { index + 1 } else { index + 2 }
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl<T: Interpolate> SampledTrack<T> {
+    /// Returns the value of the track at the specified frame.
+    ///
+    /// Returns `None` if the track has no values.
+    pub fn value_at(&self, frame: u16) -> Option<T> {
+        let last = self.values.len().checked_sub(1)?;
+        let (index, remainder) = Self::sample_index(self.step, self.last_interpolated_frame, frame);
+
+        let value = &self.values[index.min(last)];
+        if remainder == 0 || index >= last {
+            return Some(value.clone());
+        }
+
+        Some(value.interpolate(&self.values[index + 1], remainder.into(), self.step.into()))
+    }
+}
+
+/// Represents an animated value.
+#[derive(Debug, Clone)]
+pub enum Track<T> {
+    /// The value is the same for every frame.
+    Constant(T),
+
+    /// The value changes over time.
+    Sampled(SampledTrack<T>),
+}
+
+impl<T: Interpolate> Track<T> {
+    /// Returns the value of the track at the specified frame.
+    ///
+    /// Returns `None` if the track has no values.
+    pub fn value_at(&self, frame: u16) -> Option<T> {
+        match self {
+            Self::Constant(value) => Some(value.clone()),
+            Self::Sampled(track) => track.value_at(frame),
+        }
+    }
+
+    /// Returns the value of the track for every frame of an animation.
+    pub fn frames(&self, frame_count: u16) -> Vec<T> {
+        (0..frame_count)
+            .filter_map(|frame| self.value_at(frame))
+            .collect()
+    }
+}
+
+/// Returns the number of frames between two samples, from the step bits of an animation info.
+pub(crate) fn step_from_info(info: u32) -> u16 {
+    match info >> 30 {
+        0 => 1,
+        1 => 2,
+        _ => 4,
+    }
+}
+
+/// Parses a sampled track.
+///
+/// Each value is `value_size` bytes long, and is read using the given function.
+pub(crate) fn parse_sampled_track<T, F>(
+    bytes: &[u8],
+    offset: usize,
+    step: u16,
+    last_interpolated_frame: u16,
+    frame_count: u16,
+    value_size: usize,
+    mut read_value: F,
+) -> Result<SampledTrack<T>, G3dError>
+where
+    F: FnMut(&[u8], usize) -> Result<T, G3dError>,
+{
+    let sample_count = SampledTrack::<T>::sample_count(step, last_interpolated_frame, frame_count);
+
+    let values = (0..sample_count)
+        .map(|i| read_value(bytes, offset + i * value_size))
+        .collect::<Result<_, G3dError>>()?;
+
+    Ok(SampledTrack {
+        step,
+        last_interpolated_frame,
+        values,
+    })
+}
+
+/// Parses an animation file, made of a single block holding a dictionary of animations.
+///
+/// Each animation is parsed using the given function, which receives the bytes of the animation
+/// block, starting at the beginning of the animation.
+pub(crate) fn parse_animation_file<T, F>(
+    bytes: &[u8],
+    file_magic: [u8; 4],
+    block_magic: [u8; 4],
+    mut parse_animation: F,
+) -> Result<Dictionary<T>, G3dError>
+where
+    F: FnMut(&[u8]) -> Result<T, G3dError>,
+{
+    let file = NitroFile::parse_bytes(bytes, file_magic, NitroBlockLayout::OffsetTable)
+        .map_err(G3dError::NitroError)?;
+
+    // Read the animations, whose offsets are relative to the animation block
+    match file.block(block_magic) {
+        Some(block) => Dictionary::parse_bytes(block.data, ANIMATION_DICTIONARY_OFFSET, |entry| {
+            let animation_offset = read_u32(entry, 0)? as usize;
+            let animation_bytes = block
+                .data
+                .get(animation_offset..)
+                .ok_or(G3dError::TruncatedData(block.offset + animation_offset))?;
+
+            parse_animation(animation_bytes)
+        }),
+        None => Ok(Dictionary::default()),
+    }
+}
@@ -0,0 +1,338 @@
+//! Joint animation files (NSBCA, `BCA0`), which animate the nodes of a model.
+//!
+//! For each node, the translation, rotation and scale can either be left untouched (identity),
+//! taken from the model (base), or animated.
+
+use crate::{DsFixed16, DsFixed32};
+
+use super::animation::{self, Track};
+use super::dictionary::Dictionary;
+use super::model::NodeRotation;
+use super::{G3dError, read_fx32, read_u16, read_u32};
+
+/// The magic number of joint animation files.
+pub const BCA0_MAGIC: [u8; 4] = *b"BCA0";
+
+/// The magic number of joint animation blocks.
+pub const JNT0_MAGIC: [u8; 4] = *b"JNT0";
+
+/// Represents a transformation component of a node animation.
+#[derive(Debug, Clone)]
+pub enum JointComponent<T> {
+    /// The component is the identity (no translation, no rotation or a scale of 1).
+    Identity,
+
+    /// The component is the one of the node in the model.
+    Base,
+
+    /// The component is animated.
+    Animated(T),
+}
+
+/// Represents the animation of a node.
+#[derive(Debug, Clone)]
+pub struct NodeAnimation {
+    /// ID of the animated node.
+    pub node_id: u8,
+
+    /// Translation of the node, for each axis.
+    pub translation: JointComponent<[Track<DsFixed32>; 3]>,
+
+    /// Rotation of the node, as a 3x3 matrix (row-major).
+    pub rotation: JointComponent<Track<[DsFixed16; 9]>>,
+
+    /// Scale of the node and its inverse, for each axis.
+    pub scale: JointComponent<[Track<(DsFixed32, DsFixed32)>; 3]>,
+}
+
+/// Represents a joint animation.
+#[derive(Debug, Clone)]
+pub struct JointAnimation {
+    /// Number of frames of the animation.
+    pub frame_count: u16,
+
+    /// Animations of the nodes.
+    pub nodes: Vec<NodeAnimation>,
+}
+
+/// Parses the tracks of the animation of a node.
+struct NodeAnimationParser<'a> {
+    bytes: &'a [u8],
+    frame_count: u16,
+    pivot_rotations_offset: usize,
+    basic_rotations_offset: usize,
+}
+
+impl NodeAnimationParser<'_> {
+    /// The whole node is the identity.
+    const FLAG_IDENTITY: u32 = 0x0000_0001;
+
+    /// The translation is the identity.
+    const FLAG_TRANSLATION_IDENTITY: u32 = 0x0000_0002;
+
+    /// The translation is the one of the model.
+    const FLAG_TRANSLATION_BASE: u32 = 0x0000_0004;
+
+    /// The translation on the X axis is constant (the following flags are for Y and Z).
+    const FLAG_TRANSLATION_X_CONSTANT: u32 = 0x0000_0008;
+
+    /// The rotation is the identity.
+    const FLAG_ROTATION_IDENTITY: u32 = 0x0000_0040;
+
+    /// The rotation is the one of the model.
+    const FLAG_ROTATION_BASE: u32 = 0x0000_0080;
+
+    /// The rotation is constant.
+    const FLAG_ROTATION_CONSTANT: u32 = 0x0000_0100;
+
+    /// The scale is the identity.
+    const FLAG_SCALE_IDENTITY: u32 = 0x0000_0200;
+
+    /// The scale is the one of the model.
+    const FLAG_SCALE_BASE: u32 = 0x0000_0400;
+
+    /// The scale on the X axis is constant (the following flags are for Y and Z).
+    const FLAG_SCALE_X_CONSTANT: u32 = 0x0000_0800;
+
+    /// Values of the track are stored as [`DsFixed16`] instead of [`DsFixed32`].
+    const INFO_FX16: u32 = 0x2000_0000;
+
+    /// Parses the animation of a node located at the specified offset.
+    fn parse(&self, offset: usize) -> Result<NodeAnimation, G3dError> {
+        let flags = read_u32(self.bytes, offset)?;
+        let node_id = (flags >> 24) as u8;
+        let mut position = offset + 4;
+
+        if flags & Self::FLAG_IDENTITY != 0 {
+            return Ok(NodeAnimation {
+                node_id,
+                translation: JointComponent::Identity,
+                rotation: JointComponent::Identity,
+                scale: JointComponent::Identity,
+            });
+        }
+
+        // Read the translation
+        let translation = if flags & Self::FLAG_TRANSLATION_IDENTITY != 0 {
+            JointComponent::Identity
+        } else if flags & Self::FLAG_TRANSLATION_BASE != 0 {
+            JointComponent::Base
+        } else {
+            let mut tracks = Vec::with_capacity(3);
+            for axis in 0..3 {
+                if flags & (Self::FLAG_TRANSLATION_X_CONSTANT << axis) != 0 {
+                    tracks.push(Track::Constant(read_fx32(self.bytes, position)?));
+                    position += 4;
+                } else {
+                    tracks.push(self.parse_track(position, read_fx, 4)?);
+                    position += 8;
+                }
+            }
+
+            // Safety: exactly 3 tracks were pushed
+            JointComponent::Animated(tracks.try_into().unwrap())
+        };
+
+        // Read the rotation
+        let rotation = if flags & Self::FLAG_ROTATION_IDENTITY != 0 {
+            JointComponent::Identity
+        } else if flags & Self::FLAG_ROTATION_BASE != 0 {
+            JointComponent::Base
+        } else if flags & Self::FLAG_ROTATION_CONSTANT != 0 {
+            let index = read_u32(self.bytes, position)? as u16;
+            position += 4;
+
+            JointComponent::Animated(Track::Constant(self.read_rotation(index)?))
+        } else {
+            let info = read_u32(self.bytes, position)?;
+            let data_offset = read_u32(self.bytes, position + 4)? as usize;
+            position += 8;
+
+            JointComponent::Animated(Track::Sampled(animation::parse_sampled_track(
+                self.bytes,
+                data_offset,
+                animation::step_from_info(info),
+                last_interpolated_frame(info),
+                self.frame_count,
+                2,
+                |bytes, offset| self.read_rotation(read_u16(bytes, offset)?),
+            )?))
+        };
+
+        // Read the scale
+        let scale = if flags & Self::FLAG_SCALE_IDENTITY != 0 {
+            JointComponent::Identity
+        } else if flags & Self::FLAG_SCALE_BASE != 0 {
+            JointComponent::Base
+        } else {
+            let mut tracks = Vec::with_capacity(3);
+            for axis in 0..3 {
+                if flags & (Self::FLAG_SCALE_X_CONSTANT << axis) != 0 {
+                    tracks.push(Track::Constant((
+                        read_fx32(self.bytes, position)?,
+                        read_fx32(self.bytes, position + 4)?,
+                    )));
+                } else {
+                    tracks.push(self.parse_track(
+                        position,
+                        |bytes, offset, fx16| {
+                            let size = if fx16 { 2 } else { 4 };
+                            Ok((
+                                read_fx(bytes, offset, fx16)?,
+                                read_fx(bytes, offset + size, fx16)?,
+                            ))
+                        },
+                        8,
+                    )?);
+                }
+
+                position += 8;
+            }
+
+            // Safety: exactly 3 tracks were pushed
+            JointComponent::Animated(tracks.try_into().unwrap())
+        };
+
+        Ok(NodeAnimation {
+            node_id,
+            translation,
+            rotation,
+            scale,
+        })
+    }
+
+    /// Parses a sampled track of fixed-point values, whose info is located at the specified
+    /// offset.
+    ///
+    /// `value_size` is the size of a value when stored as [`DsFixed32`], which is halved when the
+    /// values are stored as [`DsFixed16`].
+    fn parse_track<T, F>(
+        &self,
+        offset: usize,
+        read_value: F,
+        value_size: usize,
+    ) -> Result<Track<T>, G3dError>
+    where
+        F: Fn(&[u8], usize, bool) -> Result<T, G3dError>,
+    {
+        let info = read_u32(self.bytes, offset)?;
+        let data_offset = read_u32(self.bytes, offset + 4)? as usize;
+        let fx16 = info & Self::INFO_FX16 != 0;
+
+        Ok(Track::Sampled(animation::parse_sampled_track(
+            self.bytes,
+            data_offset,
+            animation::step_from_info(info),
+            last_interpolated_frame(info),
+            self.frame_count,
+            if fx16 { value_size / 2 } else { value_size },
+            |bytes, offset| read_value(bytes, offset, fx16),
+        )?))
+    }
+
+    /// Reads the rotation matrix with the specified index.
+    ///
+    /// If bit 15 of the index is set, the matrix is a pivot-compressed one. Otherwise, it is a
+    /// basic one.
+    fn read_rotation(&self, index: u16) -> Result<[DsFixed16; 9], G3dError> {
+        if index & 0x8000 != 0 {
+            let offset = self.pivot_rotations_offset + (index & 0x7FFF) as usize * 6;
+            let info = read_u16(self.bytes, offset)?;
+
+            Ok(NodeRotation::pivot_matrix(
+                (info & 0x0F) as u8,
+                info & 0x10 != 0,
+                info & 0x20 != 0,
+                info & 0x40 != 0,
+                DsFixed16::from_bits(read_u16(self.bytes, offset + 2)? as i16),
+                DsFixed16::from_bits(read_u16(self.bytes, offset + 4)? as i16),
+            ))
+        } else {
+            let offset = self.basic_rotations_offset + index as usize * 10;
+
+            // The first 5 elements are stored in the upper 13 bits of 5 values, whose lower 3
+            // bits hold the 6th element
+            let mut raw = [0i16; 5];
+            for (i, value) in raw.iter_mut().enumerate() {
+                *value = read_u16(self.bytes, offset + i * 2)? as i16;
+            }
+
+            let packed = raw
+                .iter()
+                .fold(0u16, |packed, &value| (packed << 3) | (value as u16 & 0x07));
+
+            let mut matrix = [0i32; 9];
+            for (element, &value) in matrix.iter_mut().zip(raw.iter()) {
+                *element = (value >> 3) as i32;
+            }
+            matrix[5] = ((packed << 1) as i16 >> 1) as i32;
+
+            // The last row is the cross product of the first two
+            let cross = |a: usize, b: usize, c: usize, d: usize| {
+                (matrix[a] * matrix[b] - matrix[c] * matrix[d]) >> 12
+            };
+            let last_row = [cross(1, 5, 2, 4), cross(2, 3, 0, 5), cross(0, 4, 1, 3)];
+            matrix[6..].copy_from_slice(&last_row);
+
+            Ok(matrix.map(|element| DsFixed16::from_bits(element as i16)))
+        }
+    }
+}
+
+impl JointAnimation {
+    /// Parses a joint animation.
+    ///
+    /// It is expected that the slice starts at the beginning of the animation.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let frame_count = read_u16(bytes, 0x04)?;
+        let number_of_nodes = read_u16(bytes, 0x06)? as usize;
+
+        let parser = NodeAnimationParser {
+            bytes,
+            frame_count,
+            pivot_rotations_offset: read_u32(bytes, 0x0C)? as usize,
+            basic_rotations_offset: read_u32(bytes, 0x10)? as usize,
+        };
+
+        let nodes = (0..number_of_nodes)
+            .map(|i| parser.parse(read_u16(bytes, 0x14 + i * 2)? as usize))
+            .collect::<Result<_, G3dError>>()?;
+
+        Ok(Self { frame_count, nodes })
+    }
+}
+
+/// Represents a joint animation file.
+#[derive(Debug, Clone)]
+pub struct Nsbca {
+    /// Animations of the file.
+    pub animations: Dictionary<JointAnimation>,
+}
+
+impl Nsbca {
+    /// Parses a joint animation file.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        Ok(Self {
+            animations: animation::parse_animation_file(
+                bytes,
+                BCA0_MAGIC,
+                JNT0_MAGIC,
+                JointAnimation::parse_bytes,
+            )?,
+        })
+    }
+}
+
+/// Returns the last interpolated frame, from the info of a joint animation track.
+fn last_interpolated_frame(info: u32) -> u16 {
+    ((info >> 16) & 0x1FFF) as u16
+}
+
+/// Reads a fixed-point value, stored either as a [`DsFixed16`] or as a [`DsFixed32`].
+fn read_fx(bytes: &[u8], offset: usize, fx16: bool) -> Result<DsFixed32, G3dError> {
+    if fx16 {
+        Ok(DsFixed32::from_bits(read_u16(bytes, offset)? as i16 as i32))
+    } else {
+        read_fx32(bytes, offset)
+    }
+}
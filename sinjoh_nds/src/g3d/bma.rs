@@ -0,0 +1,134 @@
+//! Material color animation files (NSBMA, `BMA0`), which animate the colors of the materials of
+//! a model.
+//!
+//! For each material, the diffuse, ambient, specular and emission colors are animated, along
+//! with the alpha of the polygons.
+
+use crate::DsRgb;
+
+use super::animation::{self, Track};
+use super::dictionary::Dictionary;
+use super::{G3dError, read_u16, read_u32};
+
+/// The magic number of material color animation files.
+pub const BMA0_MAGIC: [u8; 4] = *b"BMA0";
+
+/// The magic number of material color animation blocks.
+pub const MAT0_MAGIC: [u8; 4] = *b"MAT0";
+
+/// Offset of the material dictionary in a material color animation.
+const MATERIAL_COLOR_DICTIONARY_OFFSET: usize = 0x08;
+
+/// Represents the animation of the colors of a material.
+#[derive(Debug, Clone)]
+pub struct MaterialColorTracks {
+    /// Diffuse color.
+    pub diffuse: Track<DsRgb>,
+
+    /// Ambient color.
+    pub ambient: Track<DsRgb>,
+
+    /// Specular color.
+    pub specular: Track<DsRgb>,
+
+    /// Emission color.
+    pub emission: Track<DsRgb>,
+
+    /// Alpha of the polygons (from 0 to 31).
+    pub alpha: Track<u8>,
+}
+
+/// Represents a material color animation.
+#[derive(Debug, Clone)]
+pub struct MaterialColorAnimation {
+    /// Number of frames of the animation.
+    pub frame_count: u16,
+
+    /// Animations of the materials, by material name.
+    pub materials: Dictionary<MaterialColorTracks>,
+}
+
+impl MaterialColorAnimation {
+    /// The value of the track is constant.
+    const INFO_CONSTANT: u32 = 0x2000_0000;
+
+    /// Parses a material color animation.
+    ///
+    /// It is expected that the slice starts at the beginning of the animation.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let frame_count = read_u16(bytes, 0x04)?;
+
+        let materials =
+            Dictionary::parse_bytes(bytes, MATERIAL_COLOR_DICTIONARY_OFFSET, |entry| {
+                let color_track = |index: usize| {
+                    Self::parse_track(bytes, read_u32(entry, index * 4)?, frame_count, |raw| {
+                        DsRgb::from_bgr555(raw)
+                    })
+                };
+
+                Ok(MaterialColorTracks {
+                    diffuse: color_track(0)?,
+                    ambient: color_track(1)?,
+                    specular: color_track(2)?,
+                    emission: color_track(3)?,
+                    alpha: Self::parse_track(bytes, read_u32(entry, 16)?, frame_count, |raw| {
+                        (raw & 0x1F) as u8
+                    })?,
+                })
+            })?;
+
+        Ok(Self {
+            frame_count,
+            materials,
+        })
+    }
+
+    /// Parses a track from its info.
+    ///
+    /// The lower 16 bits of the info hold either the constant value of the track, or the offset
+    /// of its sampled values.
+    fn parse_track<T, F>(
+        bytes: &[u8],
+        info: u32,
+        frame_count: u16,
+        convert: F,
+    ) -> Result<Track<T>, G3dError>
+    where
+        F: Fn(u16) -> T,
+    {
+        if info & Self::INFO_CONSTANT != 0 {
+            return Ok(Track::Constant(convert(info as u16)));
+        }
+
+        Ok(Track::Sampled(animation::parse_sampled_track(
+            bytes,
+            (info & 0xFFFF) as usize,
+            animation::step_from_info(info),
+            ((info >> 16) & 0x1FFF) as u16,
+            frame_count,
+            2,
+            |bytes, offset| Ok(convert(read_u16(bytes, offset)?)),
+        )?))
+    }
+}
+
+/// Represents a material color animation file.
+#[derive(Debug, Clone)]
+pub struct Nsbma {
+    /// Animations of the file.
+    pub animations: Dictionary<MaterialColorAnimation>,
+}
+
+impl Nsbma {
+    /// Parses a material color animation file.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        Ok(Self {
+            animations: animation::parse_animation_file(
+                bytes,
+                BMA0_MAGIC,
+                MAT0_MAGIC,
+                MaterialColorAnimation::parse_bytes,
+            )?,
+        })
+    }
+}
@@ -0,0 +1,76 @@
+//! Model files (NSBMD, `BMD0`), which hold models and optionally their textures.
+//!
+//! A model file contains a model block (`MDL0`), which is a dictionary of models (See
+//! [`Model`]), and optionally a texture block (`TEX0`, see [`Tex0`]) holding the textures and
+//! palettes used by the models.
+
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::G3dError;
+use super::dictionary::Dictionary;
+use super::model::Model;
+use super::read_u32;
+use super::tex0::{TEX0_MAGIC, Tex0};
+
+/// The magic number of model files.
+pub const BMD0_MAGIC: [u8; 4] = *b"BMD0";
+
+/// The magic number of model blocks.
+pub const MDL0_MAGIC: [u8; 4] = *b"MDL0";
+
+/// Offset of the model dictionary in a model block.
+const MDL0_DICTIONARY_OFFSET: usize = 0x08;
+
+/// Represents a model file.
+#[derive(Debug, Clone)]
+pub struct Nsbmd {
+    /// Models of the file.
+    pub models: Dictionary<Model>,
+
+    /// Textures and palettes of the file, if any.
+    pub textures: Option<Tex0>,
+}
+
+impl Nsbmd {
+    /// Parses a model file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g3d::bmd::Nsbmd;
+    ///
+    /// let bytes = std::fs::read("model.nsbmd").unwrap();
+    /// let nsbmd = Nsbmd::parse_bytes(&bytes)?;
+    ///
+    /// for model in nsbmd.models.iter() {
+    ///     println!("{}: {} nodes", model.name, model.value.nodes.len());
+    /// }
+    /// # Ok::<(), sinjoh_nds::g3d::G3dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let file = NitroFile::parse_bytes(bytes, BMD0_MAGIC, NitroBlockLayout::OffsetTable)
+            .map_err(G3dError::NitroError)?;
+
+        // Read the models, whose offsets are relative to the model block
+        let models = match file.block(MDL0_MAGIC) {
+            Some(block) => Dictionary::parse_bytes(block.data, MDL0_DICTIONARY_OFFSET, |entry| {
+                let model_offset = read_u32(entry, 0)? as usize;
+                let model_bytes = block
+                    .data
+                    .get(model_offset..)
+                    .ok_or(G3dError::TruncatedData(block.offset + model_offset))?;
+
+                Model::parse_bytes(model_bytes)
+            })?,
+            None => Dictionary::default(),
+        };
+
+        // Read the textures
+        let textures = file
+            .block(TEX0_MAGIC)
+            .map(|block| Tex0::parse_bytes(block.data))
+            .transpose()?;
+
+        Ok(Self { models, textures })
+    }
+}
@@ -0,0 +1,167 @@
+//! Texture SRT animation files (NSBTA, `BTA0`), which animate the texture matrices of the
+//! materials of a model.
+//!
+//! For each material, the scale, rotation and translation of the texture coordinates are
+//! animated.
+
+use crate::{DsFixed16, DsFixed32};
+
+use super::animation::{self, Track};
+use super::dictionary::Dictionary;
+use super::{G3dError, read_fx16, read_fx32, read_u8, read_u16, read_u32};
+
+/// The magic number of texture SRT animation files.
+pub const BTA0_MAGIC: [u8; 4] = *b"BTA0";
+
+/// The magic number of texture SRT animation blocks.
+pub const SRT0_MAGIC: [u8; 4] = *b"SRT0";
+
+/// Offset of the material dictionary in a texture SRT animation.
+const TEXTURE_SRT_DICTIONARY_OFFSET: usize = 0x08;
+
+/// Represents the animation of the texture matrix of a material.
+#[derive(Debug, Clone)]
+pub struct MaterialSrtAnimation {
+    /// Scale of the S texture coordinate.
+    pub scale_s: Track<DsFixed32>,
+
+    /// Scale of the T texture coordinate.
+    pub scale_t: Track<DsFixed32>,
+
+    /// Rotation of the texture coordinates, as its sine and cosine.
+    pub rotation: Track<(DsFixed16, DsFixed16)>,
+
+    /// Translation of the S texture coordinate.
+    pub translation_s: Track<DsFixed32>,
+
+    /// Translation of the T texture coordinate.
+    pub translation_t: Track<DsFixed32>,
+}
+
+/// Represents a texture SRT animation.
+#[derive(Debug, Clone)]
+pub struct TextureSrtAnimation {
+    /// Number of frames of the animation.
+    pub frame_count: u16,
+
+    /// Mode of transformation of the texture matrices (See
+    /// [`ModelInfo::texture_matrix_mode`](super::model::ModelInfo::texture_matrix_mode)).
+    pub texture_matrix_mode: u8,
+
+    /// Animations of the materials, by material name.
+    pub materials: Dictionary<MaterialSrtAnimation>,
+}
+
+impl TextureSrtAnimation {
+    /// The value of the track is constant.
+    const INFO_CONSTANT: u32 = 0x2000_0000;
+
+    /// Values of the track are stored as [`DsFixed16`] instead of [`DsFixed32`].
+    const INFO_FX16: u32 = 0x1000_0000;
+
+    /// Parses a texture SRT animation.
+    ///
+    /// It is expected that the slice starts at the beginning of the animation.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let frame_count = read_u16(bytes, 0x04)?;
+
+        let materials = Dictionary::parse_bytes(bytes, TEXTURE_SRT_DICTIONARY_OFFSET, |entry| {
+            let fixed_track = |index: usize| {
+                Self::parse_track(
+                    bytes,
+                    entry,
+                    index,
+                    frame_count,
+                    (4, 2),
+                    |bytes, offset, fx16| {
+                        if fx16 {
+                            Ok(DsFixed32::from_bits(read_u16(bytes, offset)? as i16 as i32))
+                        } else {
+                            read_fx32(bytes, offset)
+                        }
+                    },
+                )
+            };
+
+            Ok(MaterialSrtAnimation {
+                scale_s: fixed_track(0)?,
+                scale_t: fixed_track(1)?,
+                rotation: Self::parse_track(
+                    bytes,
+                    entry,
+                    2,
+                    frame_count,
+                    (4, 4),
+                    |bytes, offset, _| {
+                        Ok((read_fx16(bytes, offset)?, read_fx16(bytes, offset + 2)?))
+                    },
+                )?,
+                translation_s: fixed_track(3)?,
+                translation_t: fixed_track(4)?,
+            })
+        })?;
+
+        Ok(Self {
+            frame_count,
+            texture_matrix_mode: read_u8(bytes, 0x07)?,
+            materials,
+        })
+    }
+
+    /// Parses the track with the specified index in a dictionary entry.
+    ///
+    /// Each track is made of 2 values: its info, followed by either its constant value, or the
+    /// offset of its sampled values. `value_sizes` holds the size of a sampled value, when stored
+    /// normally and when stored as [`DsFixed16`].
+    fn parse_track<T, F>(
+        bytes: &[u8],
+        entry: &[u8],
+        index: usize,
+        frame_count: u16,
+        value_sizes: (usize, usize),
+        read_value: F,
+    ) -> Result<Track<T>, G3dError>
+    where
+        F: Fn(&[u8], usize, bool) -> Result<T, G3dError>,
+    {
+        let info = read_u32(entry, index * 8)?;
+
+        if info & Self::INFO_CONSTANT != 0 {
+            return Ok(Track::Constant(read_value(entry, index * 8 + 4, false)?));
+        }
+
+        let data_offset = read_u32(entry, index * 8 + 4)? as usize;
+        let fx16 = info & Self::INFO_FX16 != 0;
+
+        Ok(Track::Sampled(animation::parse_sampled_track(
+            bytes,
+            data_offset,
+            animation::step_from_info(info),
+            info as u16,
+            frame_count,
+            if fx16 { value_sizes.1 } else { value_sizes.0 },
+            |bytes, offset| read_value(bytes, offset, fx16),
+        )?))
+    }
+}
+
+/// Represents a texture SRT animation file.
+#[derive(Debug, Clone)]
+pub struct Nsbta {
+    /// Animations of the file.
+    pub animations: Dictionary<TextureSrtAnimation>,
+}
+
+impl Nsbta {
+    /// Parses a texture SRT animation file.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        Ok(Self {
+            animations: animation::parse_animation_file(
+                bytes,
+                BTA0_MAGIC,
+                SRT0_MAGIC,
+                TextureSrtAnimation::parse_bytes,
+            )?,
+        })
+    }
+}
@@ -0,0 +1,162 @@
+//! Texture pattern animation files (NSBTP, `BTP0`), which swap the textures and palettes of the
+//! materials of a model.
+//!
+//! For each material, keyframes give the texture and palette to use starting from a given frame.
+
+use super::animation;
+use super::dictionary::{self, DICTIONARY_NAME_SIZE, Dictionary};
+use super::{G3dError, read_slice, read_u8, read_u16};
+
+/// The magic number of texture pattern animation files.
+pub const BTP0_MAGIC: [u8; 4] = *b"BTP0";
+
+/// The magic number of texture pattern animation blocks.
+pub const PAT0_MAGIC: [u8; 4] = *b"PAT0";
+
+/// Offset of the material dictionary in a texture pattern animation.
+const TEXTURE_PATTERN_DICTIONARY_OFFSET: usize = 0x0C;
+
+/// The size of a keyframe of a texture pattern animation.
+const TEXTURE_PATTERN_KEYFRAME_SIZE: usize = 4;
+
+/// Represents a keyframe of the animation of a material.
+#[derive(Debug, Clone, Copy)]
+pub struct PatternKeyframe {
+    /// Frame from which the keyframe applies.
+    pub frame: u16,
+
+    /// Index of the texture in [`TexturePatternAnimation::texture_names`].
+    pub texture_index: u8,
+
+    /// Index of the palette in [`TexturePatternAnimation::palette_names`].
+    pub palette_index: u8,
+}
+
+/// Represents the animation of the texture of a material.
+#[derive(Debug, Clone)]
+pub struct MaterialPatternAnimation {
+    /// Flags of the animation.
+    pub flags: u8,
+
+    /// Keyframes of the animation, sorted by frame.
+    pub keyframes: Vec<PatternKeyframe>,
+}
+
+impl MaterialPatternAnimation {
+    /// Returns the keyframe that applies at the specified frame, if any.
+    pub fn keyframe_at(&self, frame: u16) -> Option<&PatternKeyframe> {
+        self.keyframes
+            .iter()
+            .take_while(|keyframe| keyframe.frame <= frame)
+            .last()
+            .or(self.keyframes.first())
+    }
+}
+
+/// Represents a texture pattern animation.
+#[derive(Debug, Clone)]
+pub struct TexturePatternAnimation {
+    /// Number of frames of the animation.
+    pub frame_count: u16,
+
+    /// Names of the textures used by the animation.
+    pub texture_names: Vec<String>,
+
+    /// Names of the palettes used by the animation.
+    pub palette_names: Vec<String>,
+
+    /// Animations of the materials, by material name.
+    pub materials: Dictionary<MaterialPatternAnimation>,
+}
+
+impl TexturePatternAnimation {
+    /// Parses a texture pattern animation.
+    ///
+    /// It is expected that the slice starts at the beginning of the animation.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let frame_count = read_u16(bytes, 0x04)?;
+        let number_of_textures = read_u8(bytes, 0x06)? as usize;
+        let number_of_palettes = read_u8(bytes, 0x07)? as usize;
+        let texture_names_offset = read_u16(bytes, 0x08)? as usize;
+        let palette_names_offset = read_u16(bytes, 0x0A)? as usize;
+
+        let materials =
+            Dictionary::parse_bytes(bytes, TEXTURE_PATTERN_DICTIONARY_OFFSET, |entry| {
+                let number_of_keyframes = read_u16(entry, 0)? as usize;
+                let keyframes_offset = read_u16(entry, 4)? as usize;
+
+                let keyframes = (0..number_of_keyframes)
+                    .map(|i| {
+                        let offset = keyframes_offset + i * TEXTURE_PATTERN_KEYFRAME_SIZE;
+
+                        Ok(PatternKeyframe {
+                            frame: read_u16(bytes, offset)?,
+                            texture_index: read_u8(bytes, offset + 2)?,
+                            palette_index: read_u8(bytes, offset + 3)?,
+                        })
+                    })
+                    .collect::<Result<_, G3dError>>()?;
+
+                Ok(MaterialPatternAnimation {
+                    flags: read_u8(entry, 2)?,
+                    keyframes,
+                })
+            })?;
+
+        Ok(Self {
+            frame_count,
+            texture_names: read_names(bytes, texture_names_offset, number_of_textures)?,
+            palette_names: read_names(bytes, palette_names_offset, number_of_palettes)?,
+            materials,
+        })
+    }
+
+    /// Returns the names of the texture and palette used by a material at the specified frame.
+    pub fn names_at(
+        &self,
+        material: &MaterialPatternAnimation,
+        frame: u16,
+    ) -> Option<(&str, Option<&str>)> {
+        let keyframe = material.keyframe_at(frame)?;
+        let texture_name = self.texture_names.get(keyframe.texture_index as usize)?;
+        let palette_name = self.palette_names.get(keyframe.palette_index as usize);
+
+        Some((texture_name, palette_name.map(String::as_str)))
+    }
+}
+
+/// Represents a texture pattern animation file.
+#[derive(Debug, Clone)]
+pub struct Nsbtp {
+    /// Animations of the file.
+    pub animations: Dictionary<TexturePatternAnimation>,
+}
+
+impl Nsbtp {
+    /// Parses a texture pattern animation file.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        Ok(Self {
+            animations: animation::parse_animation_file(
+                bytes,
+                BTP0_MAGIC,
+                PAT0_MAGIC,
+                TexturePatternAnimation::parse_bytes,
+            )?,
+        })
+    }
+}
+
+/// Reads an array of null-padded names.
+fn read_names(bytes: &[u8], offset: usize, count: usize) -> Result<Vec<String>, G3dError> {
+    (0..count)
+        .map(|i| {
+            let raw_name = read_slice(
+                bytes,
+                offset + i * DICTIONARY_NAME_SIZE,
+                DICTIONARY_NAME_SIZE,
+            )?;
+
+            Ok(dictionary::parse_name(raw_name))
+        })
+        .collect()
+}
@@ -0,0 +1,53 @@
+//! Texture files (NSBTX, `BTX0`), which hold textures and palettes.
+//!
+//! A texture file contains a single texture block (See [`Tex0`]).
+
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::G3dError;
+use super::tex0::{TEX0_MAGIC, Tex0};
+
+/// The magic number of texture files.
+pub const BTX0_MAGIC: [u8; 4] = *b"BTX0";
+
+/// Represents a texture file.
+#[derive(Debug, Clone)]
+pub struct Nsbtx {
+    /// Textures and palettes of the file.
+    pub textures: Tex0,
+}
+
+impl Nsbtx {
+    /// Parses a texture file.
+    ///
+    /// A file without a texture block is considered to have no textures.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g3d::btx::Nsbtx;
+    ///
+    /// let bytes = std::fs::read("textures.nsbtx").unwrap();
+    /// let nsbtx = Nsbtx::parse_bytes(&bytes)?;
+    ///
+    /// for texture in nsbtx.textures.textures.iter() {
+    ///     println!("{}: {:?}", texture.name, texture.value.params.format);
+    /// }
+    ///
+    /// let image = nsbtx.textures.decode_texture("grass", Some("grass_pl"))?;
+    /// println!("{}x{}", image.width, image.height);
+    /// # Ok::<(), sinjoh_nds::g3d::G3dError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let file = NitroFile::parse_bytes(bytes, BTX0_MAGIC, NitroBlockLayout::OffsetTable)
+            .map_err(G3dError::NitroError)?;
+
+        let textures = file
+            .block(TEX0_MAGIC)
+            .map(|block| Tex0::parse_bytes(block.data))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self { textures })
+    }
+}
@@ -0,0 +1,137 @@
+//! Dictionaries, which index the resources of 3D files by name.
+//!
+//! A dictionary starts with a header holding the number of entries, followed by a Patricia tree
+//! (used by the game to quickly look up a resource by name, and ignored here). Then comes the
+//! data of each entry, which all have the same size, followed by the names of the entries.
+//!
+//! Names are 16 bytes long, padded with null bytes.
+
+use super::{G3dError, read_slice, read_u8, read_u16};
+
+/// The size of the name of a dictionary entry.
+pub const DICTIONARY_NAME_SIZE: usize = 16;
+
+/// Represents an entry of a dictionary.
+#[derive(Debug, Clone)]
+pub struct DictionaryEntry<T> {
+    /// Name of the entry.
+    pub name: String,
+
+    /// Value of the entry.
+    pub value: T,
+}
+
+/// Represents a dictionary, which maps names to values.
+///
+/// The order of the entries is the one of the file: the index of an entry is the index that is
+/// used to refer to the resource in the file.
+#[derive(Debug, Clone)]
+pub struct Dictionary<T> {
+    /// Entries of the dictionary.
+    pub entries: Vec<DictionaryEntry<T>>,
+}
+
+impl<T> Default for Dictionary<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> Dictionary<T> {
+    /// Parses a dictionary located at the specified offset.
+    ///
+    /// The data of each entry is parsed using the given function, which receives the raw data of
+    /// the entry.
+    pub fn parse_bytes<F>(bytes: &[u8], offset: usize, mut parse_entry: F) -> Result<Self, G3dError>
+    where
+        F: FnMut(&[u8]) -> Result<T, G3dError>,
+    {
+        let number_of_entries = read_u8(bytes, offset + 1)? as usize;
+        let entries_offset = offset + read_u16(bytes, offset + 6)? as usize;
+
+        let entry_size = read_u16(bytes, entries_offset)? as usize;
+        let names_offset = offset + read_u16(bytes, entries_offset + 2)? as usize;
+
+        if entry_size == 0 && number_of_entries > 0 {
+            return Err(G3dError::InvalidDictionary(offset));
+        }
+
+        let mut entries = Vec::with_capacity(number_of_entries);
+
+        for i in 0..number_of_entries {
+            let raw_entry = read_slice(bytes, entries_offset + 4 + i * entry_size, entry_size)?;
+            let raw_name = read_slice(
+                bytes,
+                names_offset + i * DICTIONARY_NAME_SIZE,
+                DICTIONARY_NAME_SIZE,
+            )?;
+
+            entries.push(DictionaryEntry {
+                name: parse_name(raw_name),
+                value: parse_entry(raw_entry)?,
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Returns the number of entries in the dictionary.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the dictionary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the value of the entry with the specified name, if any.
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| &entry.value)
+    }
+
+    /// Returns the index of the entry with the specified name, if any.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    /// Returns the value of the entry at the specified index, if any.
+    pub fn get_by_index(&self, index: usize) -> Option<&T> {
+        self.entries.get(index).map(|entry| &entry.value)
+    }
+
+    /// Returns an iterator over the entries of the dictionary.
+    pub fn iter(&self) -> impl Iterator<Item = &DictionaryEntry<T>> {
+        self.entries.iter()
+    }
+
+    /// Maps the values of the dictionary, keeping the names and order of the entries.
+    pub fn try_map<U, F>(self, mut f: F) -> Result<Dictionary<U>, G3dError>
+    where
+        F: FnMut(T) -> Result<U, G3dError>,
+    {
+        Ok(Dictionary {
+            entries: self
+                .entries
+                .into_iter()
+                .map(|entry| {
+                    Ok(DictionaryEntry {
+                        name: entry.name,
+                        value: f(entry.value)?,
+                    })
+                })
+                .collect::<Result<_, G3dError>>()?,
+        })
+    }
+}
+
+/// Parses a null-padded name.
+pub(crate) fn parse_name(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
@@ -0,0 +1,174 @@
+//! Extraction of the meshes of models.
+//!
+//! The render commands of a model are evaluated to place its nodes, and the display lists of its
+//! shapes are run through an emulator of the vertex pipeline (See
+//! [`VertexPipeline`](crate::gx::pipeline::VertexPipeline)), which gives the triangles of the
+//! model in its rest pose.
+
+use cgmath::{Matrix4, SquareMatrix, Zero};
+
+use crate::gx::command;
+use crate::gx::pipeline::{Triangle, VertexPipeline};
+
+use super::G3dError;
+use super::model::{Model, RenderCommand};
+
+/// Represents triangles of a mesh drawn with the same material.
+#[derive(Debug, Clone)]
+pub struct MeshPrimitive {
+    /// ID of the material of the triangles, if one was applied before they were drawn.
+    pub material_id: Option<u8>,
+
+    /// Triangles of the primitive.
+    pub triangles: Vec<Triangle>,
+}
+
+/// Represents the triangles of a model, grouped by material.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    /// Primitives of the mesh, in the order their materials were first applied.
+    pub primitives: Vec<MeshPrimitive>,
+}
+
+impl Mesh {
+    /// Adds triangles drawn with the specified material to the mesh.
+    fn add_triangles(&mut self, material_id: Option<u8>, triangles: Vec<Triangle>) {
+        if triangles.is_empty() {
+            return;
+        }
+
+        match self
+            .primitives
+            .iter_mut()
+            .find(|primitive| primitive.material_id == material_id)
+        {
+            Some(primitive) => primitive.triangles.extend(triangles),
+            None => self.primitives.push(MeshPrimitive {
+                material_id,
+                triangles,
+            }),
+        }
+    }
+
+    /// Returns the total number of triangles of the mesh.
+    pub fn triangle_count(&self) -> usize {
+        self.primitives
+            .iter()
+            .map(|primitive| primitive.triangles.len())
+            .sum()
+    }
+}
+
+impl Model {
+    /// Evaluates the render commands of the model, and returns its triangles.
+    ///
+    /// Billboards are treated as regular nodes, and the visibility of nodes is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::g3d::bmd::Nsbmd;
+    ///
+    /// let bytes = std::fs::read("model.nsbmd").unwrap();
+    /// let nsbmd = Nsbmd::parse_bytes(&bytes)?;
+    ///
+    /// for model in nsbmd.models.iter() {
+    ///     let mesh = model.value.to_mesh()?;
+    ///     println!("{}: {} triangles", model.name, mesh.triangle_count());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_mesh(&self) -> Result<Mesh, G3dError> {
+        let mut mesh = Mesh::default();
+        let mut pipeline = VertexPipeline::new();
+        let mut material_id = None;
+
+        pipeline.set_position_scale(self.info.position_scale.to_num());
+
+        for render_command in &self.render_commands {
+            match render_command {
+                RenderCommand::Return => break,
+                RenderCommand::RestoreMatrix { stack_index } => {
+                    if let Some(matrix) = pipeline.stack_matrix(*stack_index as usize) {
+                        pipeline.set_current_matrix(matrix);
+                    }
+                }
+                RenderCommand::Material {
+                    material_id: new_material_id,
+                } => {
+                    material_id = Some(*new_material_id);
+
+                    // The diffuse color can be used as the vertex color, until the display list
+                    // sets its own
+                    if let Some(material) = self.materials.get_by_index(*new_material_id as usize)
+                        && material.diffuse_as_vertex_color()
+                    {
+                        pipeline.set_color(material.diffuse());
+                    }
+                }
+                RenderCommand::Shape { shape_id } => {
+                    let Some(shape) = self.shapes.get_by_index(*shape_id as usize) else {
+                        continue;
+                    };
+
+                    let commands = command::decode_display_list(&shape.display_list)
+                        .map_err(G3dError::DisplayListError)?;
+
+                    pipeline.run(&commands);
+                    mesh.add_triangles(material_id, pipeline.take_triangles());
+                }
+                RenderCommand::NodeDescription {
+                    node_id,
+                    destination,
+                    source,
+                    ..
+                }
+                | RenderCommand::Billboard {
+                    node_id,
+                    destination,
+                    source,
+                }
+                | RenderCommand::BillboardY {
+                    node_id,
+                    destination,
+                    source,
+                } => {
+                    if let Some(matrix) =
+                        source.and_then(|source| pipeline.stack_matrix(source as usize))
+                    {
+                        pipeline.set_current_matrix(matrix);
+                    }
+
+                    if let Some(node) = self.nodes.get_by_index(*node_id as usize) {
+                        pipeline.set_current_matrix(pipeline.current_matrix() * node.to_matrix());
+                    }
+
+                    if let Some(destination) = destination {
+                        pipeline.set_stack_matrix(*destination as usize, pipeline.current_matrix());
+                    }
+                }
+                RenderCommand::NodeMix {
+                    destination,
+                    sources,
+                } => {
+                    let matrix =
+                        sources
+                            .iter()
+                            .fold(Matrix4::zero(), |matrix: Matrix4<f32>, source| {
+                                let source_matrix = pipeline
+                                    .stack_matrix(source.stack_index as usize)
+                                    .unwrap_or_else(Matrix4::identity);
+
+                                matrix + source_matrix * (source.ratio as f32 / 256.0)
+                            });
+
+                    pipeline.set_current_matrix(matrix);
+                    pipeline.set_stack_matrix(*destination as usize, matrix);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(mesh)
+    }
+}
@@ -0,0 +1,106 @@
+//! Utils for working with the 3D file formats of the Nintendo DS SDK.
+//!
+//! The following formats are supported:
+//!
+//! - NSBMD (`BMD0`), which holds models and optionally their textures (See [`bmd::Nsbmd`]).
+//! - NSBTX (`BTX0`), which holds textures and palettes (See [`btx::Nsbtx`]).
+//! - NSBCA (`BCA0`), which holds joint animations (See [`bca::Nsbca`]).
+//! - NSBTA (`BTA0`), which holds texture SRT animations (See [`bta::Nsbta`]).
+//! - NSBTP (`BTP0`), which holds texture pattern animations (See [`btp::Nsbtp`]).
+//! - NSBMA (`BMA0`), which holds material color animations (See [`bma::Nsbma`]).
+//!
+//! Those formats are made of blocks (See [`nitro`](crate::nitro)), whose resources are indexed
+//! by name using dictionaries (See [`dictionary::Dictionary`]).
+//!
+//! The triangles of models can be extracted by evaluating their render commands (See
+//! [`model::Model::to_mesh`]).
+
+use thiserror::Error;
+
+use crate::gx::GxError;
+use crate::nitro::{self, NitroError};
+use crate::{DsFixed16, DsFixed32};
+use tex0::TextureFormat;
+
+pub mod animation;
+pub mod bca;
+pub mod bma;
+pub mod bmd;
+pub mod bta;
+pub mod btp;
+pub mod btx;
+pub mod dictionary;
+pub mod mesh;
+pub mod model;
+pub mod tex0;
+
+/// Error type for 3D file parsing.
+#[derive(Error, Debug)]
+pub enum G3dError {
+    /// The file structure could not be parsed.
+    #[error("unable to parse the file structure")]
+    NitroError(#[source] NitroError),
+
+    /// The data ended before a structure could be fully read.
+    #[error("the data is truncated (tried to read at offset 0x{0:X})")]
+    TruncatedData(usize),
+
+    /// A dictionary is malformed.
+    #[error("the dictionary at offset 0x{0:X} is malformed")]
+    InvalidDictionary(usize),
+
+    /// The display list of a shape could not be decoded.
+    #[error("unable to decode the display list of a shape")]
+    DisplayListError(#[source] GxError),
+
+    /// A render command has an unknown opcode.
+    #[error("unknown render command 0x{0:02X} (at offset 0x{1:X})")]
+    UnknownRenderCommand(u8, usize),
+
+    /// No texture has the specified name.
+    #[error("texture {0:?} not found")]
+    TextureNotFound(String),
+
+    /// No palette has the specified name.
+    #[error("palette {0:?} not found")]
+    PaletteNotFound(String),
+
+    /// The format of the texture requires a palette, but none was given.
+    #[error("a palette is required to decode a texture of format {0:?}")]
+    MissingPalette(TextureFormat),
+}
+
+/// Reads a `u8` at the specified offset.
+pub(crate) fn read_u8(bytes: &[u8], offset: usize) -> Result<u8, G3dError> {
+    bytes
+        .get(offset)
+        .copied()
+        .ok_or(G3dError::TruncatedData(offset))
+}
+
+/// Reads a `u16` at the specified offset.
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, G3dError> {
+    nitro::read_u16(bytes, offset).ok_or(G3dError::TruncatedData(offset))
+}
+
+/// Reads a `u32` at the specified offset.
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, G3dError> {
+    nitro::read_u32(bytes, offset).ok_or(G3dError::TruncatedData(offset))
+}
+
+/// Reads a [`DsFixed16`] at the specified offset.
+pub(crate) fn read_fx16(bytes: &[u8], offset: usize) -> Result<DsFixed16, G3dError> {
+    nitro::read_fx16(bytes, offset).ok_or(G3dError::TruncatedData(offset))
+}
+
+/// Reads a [`DsFixed32`] at the specified offset.
+pub(crate) fn read_fx32(bytes: &[u8], offset: usize) -> Result<DsFixed32, G3dError> {
+    nitro::read_fx32(bytes, offset).ok_or(G3dError::TruncatedData(offset))
+}
+
+/// Returns the slice of the specified size, at the specified offset.
+pub(crate) fn read_slice(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8], G3dError> {
+    bytes
+        .get(offset..offset + size)
+        .ok_or(G3dError::TruncatedData(offset))
+}
@@ -0,0 +1,915 @@
+//! Models, as found in the model block (`MDL0`) of model files.
+//!
+//! A model is made of:
+//!
+//! - Nodes, which are the joints of the model, each with its own transformation.
+//! - Materials, which describe how the polygons are lit and textured.
+//! - Shapes, which hold the polygons as display lists.
+//! - Render commands, a small bytecode that tells the game in which order to apply the nodes,
+//!   materials and shapes to draw the model.
+
+use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector3};
+
+use crate::{DsFixed16, DsFixed32, DsRgb, DsVecFixed32};
+
+use super::dictionary::Dictionary;
+use super::{G3dError, read_fx16, read_fx32, read_slice, read_u8, read_u16, read_u32};
+
+/// The size of the model info structure.
+pub const MODEL_INFO_SIZE: usize = 0x2C;
+
+/// Offset of the node dictionary in a model.
+const MODEL_NODE_DICTIONARY_OFFSET: usize = 0x14 + MODEL_INFO_SIZE;
+
+/// Represents general information about a model.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelInfo {
+    /// Type of the render commands.
+    pub render_commands_type: u8,
+
+    /// Rule used to apply the scale of the nodes (0 for standard, 1 for Maya, 2 for Softimage).
+    pub scaling_rule: u8,
+
+    /// Mode of transformation of the texture matrices (0 for Maya, 1 for Softimage, 2 for 3ds Max,
+    /// 3 for XSI).
+    pub texture_matrix_mode: u8,
+
+    /// Number of nodes.
+    pub number_of_nodes: u8,
+
+    /// Number of materials.
+    pub number_of_materials: u8,
+
+    /// Number of shapes.
+    pub number_of_shapes: u8,
+
+    /// First slot of the matrix stack that is not used by the model.
+    pub first_unused_matrix_stack_index: u8,
+
+    /// Scale applied to the vertex positions.
+    pub position_scale: DsFixed32,
+
+    /// Inverse of the scale applied to the vertex positions.
+    pub inverse_position_scale: DsFixed32,
+
+    /// Number of vertices.
+    pub number_of_vertices: u16,
+
+    /// Number of polygons.
+    pub number_of_polygons: u16,
+
+    /// Number of triangles.
+    pub number_of_triangles: u16,
+
+    /// Number of quads.
+    pub number_of_quads: u16,
+
+    /// Position of the bounding box.
+    pub bounding_box_position: Vector3<DsFixed16>,
+
+    /// Size of the bounding box.
+    pub bounding_box_size: Vector3<DsFixed16>,
+
+    /// Scale applied to the bounding box.
+    pub bounding_box_position_scale: DsFixed32,
+
+    /// Inverse of the scale applied to the bounding box.
+    pub bounding_box_inverse_position_scale: DsFixed32,
+}
+
+impl ModelInfo {
+    /// Parses the model info located at the specified offset.
+    fn parse_bytes(bytes: &[u8], offset: usize) -> Result<Self, G3dError> {
+        Ok(Self {
+            render_commands_type: read_u8(bytes, offset)?,
+            scaling_rule: read_u8(bytes, offset + 0x01)?,
+            texture_matrix_mode: read_u8(bytes, offset + 0x02)?,
+            number_of_nodes: read_u8(bytes, offset + 0x03)?,
+            number_of_materials: read_u8(bytes, offset + 0x04)?,
+            number_of_shapes: read_u8(bytes, offset + 0x05)?,
+            first_unused_matrix_stack_index: read_u8(bytes, offset + 0x06)?,
+            position_scale: read_fx32(bytes, offset + 0x08)?,
+            inverse_position_scale: read_fx32(bytes, offset + 0x0C)?,
+            number_of_vertices: read_u16(bytes, offset + 0x10)?,
+            number_of_polygons: read_u16(bytes, offset + 0x12)?,
+            number_of_triangles: read_u16(bytes, offset + 0x14)?,
+            number_of_quads: read_u16(bytes, offset + 0x16)?,
+            bounding_box_position: Vector3::new(
+                read_fx16(bytes, offset + 0x18)?,
+                read_fx16(bytes, offset + 0x1A)?,
+                read_fx16(bytes, offset + 0x1C)?,
+            ),
+            bounding_box_size: Vector3::new(
+                read_fx16(bytes, offset + 0x1E)?,
+                read_fx16(bytes, offset + 0x20)?,
+                read_fx16(bytes, offset + 0x22)?,
+            ),
+            bounding_box_position_scale: read_fx32(bytes, offset + 0x24)?,
+            bounding_box_inverse_position_scale: read_fx32(bytes, offset + 0x28)?,
+        })
+    }
+}
+
+/// For pivot-compressed rotation matrices, the indices of the elements of the matrix that are
+/// set from the A, B, C and D values, for each position of the pivot.
+const PIVOT_MATRIX_INDICES: [[usize; 4]; 9] = [
+    [4, 5, 7, 8],
+    [3, 5, 6, 8],
+    [3, 4, 6, 7],
+    [1, 2, 7, 8],
+    [0, 2, 6, 8],
+    [0, 1, 6, 7],
+    [1, 2, 4, 5],
+    [0, 2, 3, 5],
+    [0, 1, 3, 4],
+];
+
+/// Represents the rotation of a node.
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRotation {
+    /// No rotation.
+    Identity,
+
+    /// A rotation matrix compressed around a pivot.
+    ///
+    /// The pivot is an element of the matrix that is either 1 or -1, and whose row and column are
+    /// otherwise filled with zeros. The 4 remaining elements are A, B, C and D, where C and D are
+    /// derived from B and A.
+    Pivot {
+        /// Index of the pivot in the matrix (row-major).
+        pivot: u8,
+
+        /// Whether the pivot is -1 instead of 1.
+        negative: bool,
+
+        /// Whether C is the opposite of B, instead of being equal to it.
+        negate_c: bool,
+
+        /// Whether D is the opposite of A, instead of being equal to it.
+        negate_d: bool,
+
+        /// The A element of the matrix.
+        a: DsFixed16,
+
+        /// The B element of the matrix.
+        b: DsFixed16,
+    },
+
+    /// A full 3x3 rotation matrix (row-major).
+    Matrix([DsFixed16; 9]),
+}
+
+impl NodeRotation {
+    /// Decodes a pivot-compressed rotation matrix.
+    ///
+    /// Returns the 3x3 matrix, in row-major order.
+    pub fn pivot_matrix(
+        pivot: u8,
+        negative: bool,
+        negate_c: bool,
+        negate_d: bool,
+        a: DsFixed16,
+        b: DsFixed16,
+    ) -> [DsFixed16; 9] {
+        let pivot = pivot as usize % 9;
+        let mut matrix = [DsFixed16::ZERO; 9];
+
+        matrix[pivot] = if negative {
+            DsFixed16::NEG_ONE
+        } else {
+            DsFixed16::ONE
+        };
+
+        let [a_index, b_index, c_index, d_index] = PIVOT_MATRIX_INDICES[pivot];
+        matrix[a_index] = a;
+        matrix[b_index] = b;
+        matrix[c_index] = if negate_c { -b } else { b };
+        matrix[d_index] = if negate_d { -a } else { a };
+
+        matrix
+    }
+
+    /// Returns the rotation as a 3x3 matrix, in row-major order.
+    pub fn to_matrix(&self) -> [DsFixed16; 9] {
+        match *self {
+            Self::Identity => {
+                let mut matrix = [DsFixed16::ZERO; 9];
+                matrix[0] = DsFixed16::ONE;
+                matrix[4] = DsFixed16::ONE;
+                matrix[8] = DsFixed16::ONE;
+
+                matrix
+            }
+            Self::Pivot {
+                pivot,
+                negative,
+                negate_c,
+                negate_d,
+                a,
+                b,
+            } => Self::pivot_matrix(pivot, negative, negate_c, negate_d, a, b),
+            Self::Matrix(matrix) => matrix,
+        }
+    }
+}
+
+/// Represents a node of a model.
+#[derive(Debug, Clone, Copy)]
+pub struct Node {
+    /// Translation of the node, if any.
+    pub translation: Option<DsVecFixed32>,
+
+    /// Rotation of the node.
+    pub rotation: NodeRotation,
+
+    /// Scale of the node, if any.
+    pub scale: Option<DsVecFixed32>,
+
+    /// Inverse of the scale of the node, if any.
+    pub inverse_scale: Option<DsVecFixed32>,
+}
+
+impl Node {
+    /// The node has no translation.
+    const FLAG_TRANSLATION_ZERO: u16 = 0x0001;
+
+    /// The node has no rotation.
+    const FLAG_ROTATION_ZERO: u16 = 0x0002;
+
+    /// The node has no scale.
+    const FLAG_SCALE_ONE: u16 = 0x0004;
+
+    /// The rotation of the node is pivot-compressed.
+    const FLAG_PIVOT_EXISTS: u16 = 0x0008;
+
+    /// The pivot of the rotation is -1.
+    const FLAG_PIVOT_MINUS: u16 = 0x0100;
+
+    /// The C element of the rotation is the opposite of B.
+    const FLAG_SIGN_REVERSE_C: u16 = 0x0200;
+
+    /// The D element of the rotation is the opposite of A.
+    const FLAG_SIGN_REVERSE_D: u16 = 0x0400;
+
+    /// Parses the node located at the specified offset.
+    fn parse_bytes(bytes: &[u8], offset: usize) -> Result<Self, G3dError> {
+        let flags = read_u16(bytes, offset)?;
+        let m00 = read_fx16(bytes, offset + 2)?;
+        let mut position = offset + 4;
+
+        let translation = if flags & Self::FLAG_TRANSLATION_ZERO == 0 {
+            let translation = read_vec_fx32(bytes, position)?;
+            position += 12;
+
+            Some(translation)
+        } else {
+            None
+        };
+
+        let rotation = if flags & Self::FLAG_ROTATION_ZERO != 0 {
+            NodeRotation::Identity
+        } else if flags & Self::FLAG_PIVOT_EXISTS != 0 {
+            let rotation = NodeRotation::Pivot {
+                pivot: ((flags >> 4) & 0x0F) as u8,
+                negative: flags & Self::FLAG_PIVOT_MINUS != 0,
+                negate_c: flags & Self::FLAG_SIGN_REVERSE_C != 0,
+                negate_d: flags & Self::FLAG_SIGN_REVERSE_D != 0,
+                a: read_fx16(bytes, position)?,
+                b: read_fx16(bytes, position + 2)?,
+            };
+            position += 4;
+
+            rotation
+        } else {
+            let mut matrix = [m00; 9];
+            for (i, element) in matrix.iter_mut().enumerate().skip(1) {
+                *element = read_fx16(bytes, position + (i - 1) * 2)?;
+            }
+            position += 16;
+
+            NodeRotation::Matrix(matrix)
+        };
+
+        let (scale, inverse_scale) = if flags & Self::FLAG_SCALE_ONE == 0 {
+            (
+                Some(read_vec_fx32(bytes, position)?),
+                Some(read_vec_fx32(bytes, position + 12)?),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(Self {
+            translation,
+            rotation,
+            scale,
+            inverse_scale,
+        })
+    }
+
+    /// Returns the local transformation matrix of the node.
+    ///
+    /// The matrix applies the scale, then the rotation, then the translation of the node, and
+    /// is meant to be used with column vectors (See [`gx::pipeline`](crate::gx::pipeline)).
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        let translation = self
+            .translation
+            .map(|translation| {
+                Matrix4::from_translation(Vector3::new(
+                    translation.x.to_num(),
+                    translation.y.to_num(),
+                    translation.z.to_num(),
+                ))
+            })
+            .unwrap_or_else(Matrix4::identity);
+
+        // The rotation matrix is stored in row-major order, for use with row vectors: each of its
+        // rows is a column of the matrix used with column vectors
+        let m = self
+            .rotation
+            .to_matrix()
+            .map(|element| element.to_num::<f32>());
+        let rotation: Matrix4<f32> =
+            Matrix3::new(m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8]).into();
+
+        let scale = self
+            .scale
+            .map(|scale| {
+                Matrix4::from_nonuniform_scale(scale.x.to_num(), scale.y.to_num(), scale.z.to_num())
+            })
+            .unwrap_or_else(Matrix4::identity);
+
+        translation * rotation * scale
+    }
+}
+
+/// Represents the texture matrix of a material.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureMatrix {
+    /// Scale of the texture coordinates (S and T), if any.
+    pub scale: Option<(DsFixed32, DsFixed32)>,
+
+    /// Rotation of the texture coordinates (sine and cosine), if any.
+    pub rotation: Option<(DsFixed16, DsFixed16)>,
+
+    /// Translation of the texture coordinates (S and T), if any.
+    pub translation: Option<(DsFixed32, DsFixed32)>,
+}
+
+/// Represents a material of a model.
+#[derive(Debug, Clone)]
+pub struct Material {
+    /// Raw value of the diffuse and ambient colors, as given to the `DIF_AMB` command.
+    pub diffuse_ambient: u32,
+
+    /// Raw value of the specular and emission colors, as given to the `SPE_EMI` command.
+    pub specular_emission: u32,
+
+    /// Raw value of the polygon attributes, as given to the `POLYGON_ATTR` command.
+    pub polygon_attributes: u32,
+
+    /// Mask of the polygon attributes that are set by the material.
+    pub polygon_attributes_mask: u32,
+
+    /// Raw value of the texture parameters set by the material (repeat, flip, transformation).
+    pub texture_params: u32,
+
+    /// Mask of the texture parameters that are set by the material.
+    pub texture_params_mask: u32,
+
+    /// Base address of the palette.
+    pub palette_base: u16,
+
+    /// Flags of the material.
+    pub flags: u16,
+
+    /// Original width of the texture.
+    pub original_width: u16,
+
+    /// Original height of the texture.
+    pub original_height: u16,
+
+    /// Magnification applied to the width of the texture.
+    pub magnification_width: DsFixed32,
+
+    /// Magnification applied to the height of the texture.
+    pub magnification_height: DsFixed32,
+
+    /// Texture matrix of the material, if any.
+    pub texture_matrix: Option<TextureMatrix>,
+
+    /// Effect matrix of the material (4x4, row-major), if any.
+    pub effect_matrix: Option<[DsFixed32; 16]>,
+
+    /// Name of the texture used by the material, if any.
+    pub texture_name: Option<String>,
+
+    /// Name of the palette used by the material, if any.
+    pub palette_name: Option<String>,
+}
+
+impl Material {
+    /// The material uses a texture matrix.
+    pub const FLAG_TEXTURE_MATRIX_USE: u16 = 0x0001;
+
+    /// The texture matrix has no scale.
+    pub const FLAG_TEXTURE_MATRIX_SCALE_ONE: u16 = 0x0002;
+
+    /// The texture matrix has no rotation.
+    pub const FLAG_TEXTURE_MATRIX_ROTATION_ZERO: u16 = 0x0004;
+
+    /// The texture matrix has no translation.
+    pub const FLAG_TEXTURE_MATRIX_TRANSLATION_ZERO: u16 = 0x0008;
+
+    /// The material uses an effect matrix.
+    pub const FLAG_EFFECT_MATRIX: u16 = 0x2000;
+
+    /// Parses the material located at the specified offset.
+    fn parse_bytes(bytes: &[u8], offset: usize) -> Result<Self, G3dError> {
+        let flags = read_u16(bytes, offset + 0x1E)?;
+        let mut position = offset + 0x2C;
+
+        let texture_matrix = if flags & Self::FLAG_TEXTURE_MATRIX_USE != 0 {
+            let scale = if flags & Self::FLAG_TEXTURE_MATRIX_SCALE_ONE == 0 {
+                let scale = (read_fx32(bytes, position)?, read_fx32(bytes, position + 4)?);
+                position += 8;
+
+                Some(scale)
+            } else {
+                None
+            };
+
+            let rotation = if flags & Self::FLAG_TEXTURE_MATRIX_ROTATION_ZERO == 0 {
+                let rotation = (read_fx16(bytes, position)?, read_fx16(bytes, position + 2)?);
+                position += 4;
+
+                Some(rotation)
+            } else {
+                None
+            };
+
+            let translation = if flags & Self::FLAG_TEXTURE_MATRIX_TRANSLATION_ZERO == 0 {
+                let translation = (read_fx32(bytes, position)?, read_fx32(bytes, position + 4)?);
+                position += 8;
+
+                Some(translation)
+            } else {
+                None
+            };
+
+            Some(TextureMatrix {
+                scale,
+                rotation,
+                translation,
+            })
+        } else {
+            None
+        };
+
+        let effect_matrix = if flags & Self::FLAG_EFFECT_MATRIX != 0 {
+            let mut matrix = [DsFixed32::ZERO; 16];
+            for (i, element) in matrix.iter_mut().enumerate() {
+                *element = read_fx32(bytes, position + i * 4)?;
+            }
+
+            Some(matrix)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            diffuse_ambient: read_u32(bytes, offset + 0x04)?,
+            specular_emission: read_u32(bytes, offset + 0x08)?,
+            polygon_attributes: read_u32(bytes, offset + 0x0C)?,
+            polygon_attributes_mask: read_u32(bytes, offset + 0x10)?,
+            texture_params: read_u32(bytes, offset + 0x14)?,
+            texture_params_mask: read_u32(bytes, offset + 0x18)?,
+            palette_base: read_u16(bytes, offset + 0x1C)?,
+            flags,
+            original_width: read_u16(bytes, offset + 0x20)?,
+            original_height: read_u16(bytes, offset + 0x22)?,
+            magnification_width: read_fx32(bytes, offset + 0x24)?,
+            magnification_height: read_fx32(bytes, offset + 0x28)?,
+            texture_matrix,
+            effect_matrix,
+            texture_name: None,
+            palette_name: None,
+        })
+    }
+
+    /// Returns the diffuse color of the material.
+    pub fn diffuse(&self) -> DsRgb {
+        DsRgb::from_bgr555(self.diffuse_ambient as u16)
+    }
+
+    /// Returns the ambient color of the material.
+    pub fn ambient(&self) -> DsRgb {
+        DsRgb::from_bgr555((self.diffuse_ambient >> 16) as u16)
+    }
+
+    /// Returns the specular color of the material.
+    pub fn specular(&self) -> DsRgb {
+        DsRgb::from_bgr555(self.specular_emission as u16)
+    }
+
+    /// Returns the emission color of the material.
+    pub fn emission(&self) -> DsRgb {
+        DsRgb::from_bgr555((self.specular_emission >> 16) as u16)
+    }
+
+    /// Returns whether the diffuse color is used as the vertex color.
+    pub fn diffuse_as_vertex_color(&self) -> bool {
+        self.diffuse_ambient & 0x8000 != 0
+    }
+
+    /// Returns the alpha of the polygons (from 0 to 31).
+    pub fn alpha(&self) -> u8 {
+        ((self.polygon_attributes >> 16) & 0x1F) as u8
+    }
+
+    /// Returns the mask of the lights that are enabled for the polygons.
+    pub fn lights_mask(&self) -> u8 {
+        (self.polygon_attributes & 0x0F) as u8
+    }
+
+    /// Returns whether the back faces of the polygons are rendered.
+    pub fn render_back_faces(&self) -> bool {
+        self.polygon_attributes & (1 << 6) != 0
+    }
+
+    /// Returns whether the front faces of the polygons are rendered.
+    pub fn render_front_faces(&self) -> bool {
+        self.polygon_attributes & (1 << 7) != 0
+    }
+}
+
+/// Represents a shape of a model.
+#[derive(Debug, Clone)]
+pub struct Shape {
+    /// Flags of the shape.
+    pub flags: u32,
+
+    /// Display list holding the polygons of the shape.
+    pub display_list: Vec<u8>,
+}
+
+impl Shape {
+    /// Parses the shape located at the specified offset.
+    fn parse_bytes(bytes: &[u8], offset: usize) -> Result<Self, G3dError> {
+        let flags = read_u32(bytes, offset + 0x04)?;
+        let display_list_offset = offset + read_u32(bytes, offset + 0x08)? as usize;
+        let display_list_size = read_u32(bytes, offset + 0x0C)? as usize;
+
+        Ok(Self {
+            flags,
+            display_list: read_slice(bytes, display_list_offset, display_list_size)?.to_vec(),
+        })
+    }
+}
+
+/// Represents a source matrix of a [`RenderCommand::NodeMix`] command.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeMixSource {
+    /// Index of the matrix in the matrix stack.
+    pub stack_index: u8,
+
+    /// ID of the node.
+    pub node_id: u8,
+
+    /// Weight of the matrix (from 0 to 255).
+    pub ratio: u8,
+}
+
+/// Represents a render command of a model.
+#[derive(Debug, Clone)]
+pub enum RenderCommand {
+    /// Does nothing.
+    Nop,
+
+    /// Ends the render commands.
+    Return,
+
+    /// Sets the visibility of a node.
+    Node { node_id: u8, visible: bool },
+
+    /// Restores the current matrix from the matrix stack.
+    RestoreMatrix { stack_index: u8 },
+
+    /// Applies a material.
+    Material { material_id: u8 },
+
+    /// Draws a shape.
+    Shape { shape_id: u8 },
+
+    /// Applies the transformation of a node, multiplying the current matrix.
+    ///
+    /// The current matrix can be restored from the matrix stack before, and stored in the matrix
+    /// stack after.
+    NodeDescription {
+        node_id: u8,
+        parent_id: u8,
+        flags: u8,
+        destination: Option<u8>,
+        source: Option<u8>,
+    },
+
+    /// Applies the transformation of a node, and makes it face the camera.
+    Billboard {
+        node_id: u8,
+        destination: Option<u8>,
+        source: Option<u8>,
+    },
+
+    /// Applies the transformation of a node, and makes it face the camera around the Y axis.
+    BillboardY {
+        node_id: u8,
+        destination: Option<u8>,
+        source: Option<u8>,
+    },
+
+    /// Computes a weighted matrix from matrices of the matrix stack (used for skinning).
+    NodeMix {
+        destination: u8,
+        sources: Vec<NodeMixSource>,
+    },
+
+    /// Sends a display list to the geometry engine.
+    CallDisplayList { offset: u32, size: u32 },
+
+    /// Scales the current matrix by the position scale of the model (or its inverse).
+    PositionScale { inverse: bool },
+
+    /// Sets up environment mapping for a material.
+    EnvironmentMap { material_id: u8, flags: u8 },
+
+    /// Sets up projection mapping for a material.
+    ProjectionMap { material_id: u8, flags: u8 },
+}
+
+impl RenderCommand {
+    /// Parses the render commands located between the specified offsets.
+    ///
+    /// Parsing stops after the first [`RenderCommand::Return`] command, or at the end offset.
+    fn parse_list(bytes: &[u8], start: usize, end: usize) -> Result<Vec<Self>, G3dError> {
+        let mut commands = Vec::new();
+        let mut position = start;
+
+        while position < end {
+            let opcode = read_u8(bytes, position)?;
+            let param = |index: usize| read_u8(bytes, position + 1 + index);
+
+            // The upper bits of the opcode are used by some commands to enable optional
+            // parameters
+            let has_destination = opcode & 0x20 != 0;
+            let has_source = opcode & 0x40 != 0;
+
+            let (command, size) = match opcode & 0x1F {
+                0x00 => (Self::Nop, 1),
+                0x01 => (Self::Return, 1),
+                0x02 => (
+                    Self::Node {
+                        node_id: param(0)?,
+                        visible: param(1)? != 0,
+                    },
+                    3,
+                ),
+                0x03 => (
+                    Self::RestoreMatrix {
+                        stack_index: param(0)?,
+                    },
+                    2,
+                ),
+                0x04 => (
+                    Self::Material {
+                        material_id: param(0)?,
+                    },
+                    2,
+                ),
+                0x05 => (
+                    Self::Shape {
+                        shape_id: param(0)?,
+                    },
+                    2,
+                ),
+                0x06 => {
+                    let destination = has_destination.then(|| param(3)).transpose()?;
+                    let source_index = if has_destination { 4 } else { 3 };
+                    let source = has_source.then(|| param(source_index)).transpose()?;
+
+                    (
+                        Self::NodeDescription {
+                            node_id: param(0)?,
+                            parent_id: param(1)?,
+                            flags: param(2)?,
+                            destination,
+                            source,
+                        },
+                        4 + has_destination as usize + has_source as usize,
+                    )
+                }
+                0x07 | 0x08 => {
+                    let node_id = param(0)?;
+                    let destination = has_destination.then(|| param(1)).transpose()?;
+                    let source_index = if has_destination { 2 } else { 1 };
+                    let source = has_source.then(|| param(source_index)).transpose()?;
+
+                    let command = if opcode & 0x1F == 0x07 {
+                        Self::Billboard {
+                            node_id,
+                            destination,
+                            source,
+                        }
+                    } else {
+                        Self::BillboardY {
+                            node_id,
+                            destination,
+                            source,
+                        }
+                    };
+
+                    (command, 2 + has_destination as usize + has_source as usize)
+                }
+                0x09 => {
+                    let destination = param(0)?;
+                    let number_of_sources = param(1)? as usize;
+
+                    let sources = (0..number_of_sources)
+                        .map(|i| {
+                            Ok(NodeMixSource {
+                                stack_index: param(2 + i * 3)?,
+                                node_id: param(3 + i * 3)?,
+                                ratio: param(4 + i * 3)?,
+                            })
+                        })
+                        .collect::<Result<_, G3dError>>()?;
+
+                    (
+                        Self::NodeMix {
+                            destination,
+                            sources,
+                        },
+                        3 + number_of_sources * 3,
+                    )
+                }
+                0x0A => (
+                    Self::CallDisplayList {
+                        offset: read_u32(bytes, position + 1)?,
+                        size: read_u32(bytes, position + 5)?,
+                    },
+                    9,
+                ),
+                0x0B => (
+                    Self::PositionScale {
+                        inverse: has_destination,
+                    },
+                    1,
+                ),
+                0x0C => (
+                    Self::EnvironmentMap {
+                        material_id: param(0)?,
+                        flags: param(1)?,
+                    },
+                    3,
+                ),
+                0x0D => (
+                    Self::ProjectionMap {
+                        material_id: param(0)?,
+                        flags: param(1)?,
+                    },
+                    3,
+                ),
+                _ => return Err(G3dError::UnknownRenderCommand(opcode, position)),
+            };
+
+            position += size;
+
+            let is_return = matches!(command, Self::Return);
+            commands.push(command);
+
+            if is_return {
+                break;
+            }
+        }
+
+        Ok(commands)
+    }
+}
+
+/// Represents a model.
+#[derive(Debug, Clone)]
+pub struct Model {
+    /// General information about the model.
+    pub info: ModelInfo,
+
+    /// Nodes of the model.
+    pub nodes: Dictionary<Node>,
+
+    /// Materials of the model.
+    pub materials: Dictionary<Material>,
+
+    /// Shapes of the model.
+    pub shapes: Dictionary<Shape>,
+
+    /// Render commands of the model.
+    pub render_commands: Vec<RenderCommand>,
+}
+
+impl Model {
+    /// Parses a model.
+    ///
+    /// It is expected that the slice starts at the beginning of the model.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        let render_commands_offset = read_u32(bytes, 0x04)? as usize;
+        let materials_offset = read_u32(bytes, 0x08)? as usize;
+        let shapes_offset = read_u32(bytes, 0x0C)? as usize;
+
+        let info = ModelInfo::parse_bytes(bytes, 0x14)?;
+
+        // Read the nodes, whose offsets are relative to the node dictionary
+        let nodes = Dictionary::parse_bytes(bytes, MODEL_NODE_DICTIONARY_OFFSET, |entry| {
+            Node::parse_bytes(
+                bytes,
+                MODEL_NODE_DICTIONARY_OFFSET + read_u32(entry, 0)? as usize,
+            )
+        })?;
+
+        // Read the render commands
+        let render_commands =
+            RenderCommand::parse_list(bytes, render_commands_offset, materials_offset)?;
+
+        // Read the materials, whose offsets are relative to the material block
+        let texture_to_material_offset =
+            materials_offset + read_u16(bytes, materials_offset)? as usize;
+        let palette_to_material_offset =
+            materials_offset + read_u16(bytes, materials_offset + 2)? as usize;
+
+        let mut materials = Dictionary::parse_bytes(bytes, materials_offset + 4, |entry| {
+            Material::parse_bytes(bytes, materials_offset + read_u32(entry, 0)? as usize)
+        })?;
+
+        // Associate the textures and palettes to the materials
+        let texture_to_material = Self::parse_resource_to_material_dictionary(
+            bytes,
+            materials_offset,
+            texture_to_material_offset,
+        )?;
+
+        for entry in texture_to_material.iter() {
+            for &material_id in entry.value.iter() {
+                if let Some(material) = materials.entries.get_mut(material_id as usize) {
+                    material.value.texture_name = Some(entry.name.clone());
+                }
+            }
+        }
+
+        let palette_to_material = Self::parse_resource_to_material_dictionary(
+            bytes,
+            materials_offset,
+            palette_to_material_offset,
+        )?;
+
+        for entry in palette_to_material.iter() {
+            for &material_id in entry.value.iter() {
+                if let Some(material) = materials.entries.get_mut(material_id as usize) {
+                    material.value.palette_name = Some(entry.name.clone());
+                }
+            }
+        }
+
+        // Read the shapes, whose offsets are relative to the shape block
+        let shapes = Dictionary::parse_bytes(bytes, shapes_offset, |entry| {
+            Shape::parse_bytes(bytes, shapes_offset + read_u32(entry, 0)? as usize)
+        })?;
+
+        Ok(Self {
+            info,
+            nodes,
+            materials,
+            shapes,
+            render_commands,
+        })
+    }
+
+    /// Parses a dictionary associating a texture or palette to a list of material IDs.
+    fn parse_resource_to_material_dictionary(
+        bytes: &[u8],
+        materials_offset: usize,
+        offset: usize,
+    ) -> Result<Dictionary<Vec<u8>>, G3dError> {
+        Dictionary::parse_bytes(bytes, offset, |entry| {
+            let list_offset = materials_offset + read_u16(entry, 0)? as usize;
+            let number_of_materials = read_u8(entry, 2)? as usize;
+
+            Ok(read_slice(bytes, list_offset, number_of_materials)?.to_vec())
+        })
+    }
+}
+
+/// Reads a 3D vector of [`DsFixed32`] at the specified offset.
+fn read_vec_fx32(bytes: &[u8], offset: usize) -> Result<DsVecFixed32, G3dError> {
+    Ok(Vector3::new(
+        read_fx32(bytes, offset)?,
+        read_fx32(bytes, offset + 4)?,
+        read_fx32(bytes, offset + 8)?,
+    ))
+}
@@ -0,0 +1,607 @@
+//! Texture blocks (`TEX0`), which hold textures and palettes.
+//!
+//! A texture block can be embedded in a model file (See [`Nsbmd`](super::bmd::Nsbmd)), or stored
+//! on its own in a texture file.
+//!
+//! The block holds a dictionary of textures, a dictionary of palettes, and the raw data they
+//! refer to. Textures in the 4x4-texel compressed format have their data stored separately from
+//! the other textures, along with an additional palette index data.
+//!
+//! Textures can be decoded to RGBA images using [`Tex0::decode_texture`].
+
+use crate::DsRgb;
+use crate::image::RgbaImage;
+
+use super::dictionary::Dictionary;
+use super::{G3dError, read_slice, read_u16, read_u32};
+
+/// The magic number of texture blocks.
+pub const TEX0_MAGIC: [u8; 4] = *b"TEX0";
+
+/// The format of the texels of a texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// No texture.
+    None,
+
+    /// 3 bits of alpha, 5 bits of palette index (32 colors).
+    A3I5,
+
+    /// 2 bits of palette index (4 colors).
+    Palette4,
+
+    /// 4 bits of palette index (16 colors).
+    Palette16,
+
+    /// 8 bits of palette index (256 colors).
+    Palette256,
+
+    /// Blocks of 4x4 texels, compressed using per-block palettes.
+    Compressed4x4,
+
+    /// 5 bits of alpha, 3 bits of palette index (8 colors).
+    A5I3,
+
+    /// Direct 16-bit colors, with 1 bit of alpha.
+    Direct,
+}
+
+impl TextureFormat {
+    /// Returns the format corresponding to the 3-bit value found in the texture parameters.
+    pub fn from_raw(raw: u8) -> Self {
+        match raw & 0x07 {
+            1 => Self::A3I5,
+            2 => Self::Palette4,
+            3 => Self::Palette16,
+            4 => Self::Palette256,
+            5 => Self::Compressed4x4,
+            6 => Self::A5I3,
+            7 => Self::Direct,
+            _ => Self::None,
+        }
+    }
+
+    /// Returns the number of bits used to store a texel.
+    ///
+    /// For the compressed format, this is the number of bits per texel in the texel data, without
+    /// the palette index data.
+    pub fn bits_per_texel(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::A3I5 | Self::Palette256 | Self::A5I3 => 8,
+            Self::Palette4 | Self::Compressed4x4 => 2,
+            Self::Palette16 => 4,
+            Self::Direct => 16,
+        }
+    }
+
+    /// Returns whether the texels of this format are palette indices.
+    pub fn uses_palette(&self) -> bool {
+        !matches!(self, Self::None | Self::Direct)
+    }
+}
+
+/// Represents the parameters of a texture, as given to the `TEXIMAGE_PARAM` command.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureParams {
+    /// Offset of the texture data, in bytes.
+    pub offset: u32,
+
+    /// Whether the texture repeats in the S direction.
+    pub repeat_s: bool,
+
+    /// Whether the texture repeats in the T direction.
+    pub repeat_t: bool,
+
+    /// Whether the texture is flipped when repeated in the S direction.
+    pub flip_s: bool,
+
+    /// Whether the texture is flipped when repeated in the T direction.
+    pub flip_t: bool,
+
+    /// Width of the texture, in texels.
+    pub width: u16,
+
+    /// Height of the texture, in texels.
+    pub height: u16,
+
+    /// Format of the texels.
+    pub format: TextureFormat,
+
+    /// Whether the first color of the palette is transparent.
+    pub color0_transparent: bool,
+
+    /// Mode of transformation of the texture coordinates.
+    pub texcoord_transform_mode: u8,
+}
+
+impl TextureParams {
+    /// Parses the texture parameters from their raw value.
+    pub fn from_raw(raw: u32) -> Self {
+        Self {
+            offset: (raw & 0xFFFF) << 3,
+            repeat_s: raw & (1 << 16) != 0,
+            repeat_t: raw & (1 << 17) != 0,
+            flip_s: raw & (1 << 18) != 0,
+            flip_t: raw & (1 << 19) != 0,
+            width: 8 << ((raw >> 20) & 0x07),
+            height: 8 << ((raw >> 23) & 0x07),
+            format: TextureFormat::from_raw((raw >> 26) as u8),
+            color0_transparent: raw & (1 << 29) != 0,
+            texcoord_transform_mode: ((raw >> 30) & 0x03) as u8,
+        }
+    }
+
+    /// Returns the size of the texel data of the texture, in bytes.
+    pub fn data_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.format.bits_per_texel() / 8
+    }
+}
+
+/// Represents an entry of the texture dictionary.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureInfo {
+    /// Parameters of the texture.
+    pub params: TextureParams,
+
+    /// Additional parameters of the texture, used by the SDK.
+    pub extra_params: u32,
+}
+
+/// Represents an entry of the palette dictionary.
+#[derive(Debug, Clone, Copy)]
+pub struct PaletteInfo {
+    /// Offset of the palette data, in bytes.
+    pub offset: u32,
+
+    /// Flags of the palette.
+    ///
+    /// Bit 0 is set for palettes of 4-color textures, whose offset is expressed in different
+    /// units by the hardware.
+    pub flags: u16,
+}
+
+/// Represents a texture block.
+#[derive(Debug, Clone, Default)]
+pub struct Tex0 {
+    /// Dictionary of the textures.
+    pub textures: Dictionary<TextureInfo>,
+
+    /// Dictionary of the palettes.
+    pub palettes: Dictionary<PaletteInfo>,
+
+    /// Texel data of the textures, except the compressed ones.
+    pub texture_data: Vec<u8>,
+
+    /// Texel data of the compressed textures.
+    pub compressed_texture_data: Vec<u8>,
+
+    /// Palette index data of the compressed textures.
+    pub compressed_texture_index_data: Vec<u8>,
+
+    /// Data of the palettes, as 16-bit colors.
+    pub palette_data: Vec<u8>,
+}
+
+impl Tex0 {
+    /// Parses a texture block.
+    ///
+    /// It is expected that the slice starts at the beginning of the block, including its header.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, G3dError> {
+        // Read the texture info
+        let texture_data_size = (read_u16(bytes, 0x0C)? as usize) << 3;
+        let texture_dictionary_offset = read_u16(bytes, 0x0E)? as usize;
+        let texture_data_offset = read_u32(bytes, 0x14)? as usize;
+
+        // Read the compressed texture info
+        let compressed_texture_data_size = (read_u16(bytes, 0x1C)? as usize) << 3;
+        let compressed_texture_data_offset = read_u32(bytes, 0x24)? as usize;
+        let compressed_texture_index_data_offset = read_u32(bytes, 0x28)? as usize;
+
+        // Read the palette info
+        let palette_data_size = (read_u16(bytes, 0x30)? as usize) << 3;
+        let palette_dictionary_offset = read_u16(bytes, 0x34)? as usize;
+        let palette_data_offset = read_u32(bytes, 0x38)? as usize;
+
+        // Read the dictionaries
+        let textures = Dictionary::parse_bytes(bytes, texture_dictionary_offset, |entry| {
+            Ok(TextureInfo {
+                params: TextureParams::from_raw(read_u32(entry, 0)?),
+                extra_params: read_u32(entry, 4)?,
+            })
+        })?;
+
+        let palettes = Dictionary::parse_bytes(bytes, palette_dictionary_offset, |entry| {
+            Ok(PaletteInfo {
+                offset: (read_u16(entry, 0)? as u32) << 3,
+                flags: read_u16(entry, 2)?,
+            })
+        })?;
+
+        Ok(Self {
+            textures,
+            palettes,
+            texture_data: read_slice(bytes, texture_data_offset, texture_data_size)?.to_vec(),
+            compressed_texture_data: read_slice(
+                bytes,
+                compressed_texture_data_offset,
+                compressed_texture_data_size,
+            )?
+            .to_vec(),
+            compressed_texture_index_data: read_slice(
+                bytes,
+                compressed_texture_index_data_offset,
+                compressed_texture_data_size / 2,
+            )?
+            .to_vec(),
+            palette_data: read_slice(bytes, palette_data_offset, palette_data_size)?.to_vec(),
+        })
+    }
+
+    /// Decodes the texture with the specified name to an RGBA image, using the palette with the
+    /// specified name.
+    ///
+    /// The palette is only needed for formats that use one (See [`TextureFormat::uses_palette`]).
+    pub fn decode_texture(
+        &self,
+        texture_name: &str,
+        palette_name: Option<&str>,
+    ) -> Result<RgbaImage, G3dError> {
+        let texture = self
+            .textures
+            .get(texture_name)
+            .ok_or_else(|| G3dError::TextureNotFound(texture_name.to_owned()))?;
+
+        let palette = palette_name
+            .map(|palette_name| {
+                self.palettes
+                    .get(palette_name)
+                    .ok_or_else(|| G3dError::PaletteNotFound(palette_name.to_owned()))
+            })
+            .transpose()?;
+
+        self.decode(&texture.params, palette)
+    }
+
+    /// Decodes a texture to an RGBA image, using the specified palette.
+    ///
+    /// The palette is only needed for formats that use one (See [`TextureFormat::uses_palette`]).
+    pub fn decode(
+        &self,
+        params: &TextureParams,
+        palette: Option<&PaletteInfo>,
+    ) -> Result<RgbaImage, G3dError> {
+        let width = params.width as usize;
+        let height = params.height as usize;
+        let mut image = RgbaImage::new(width, height);
+
+        let palette = match palette {
+            Some(palette) => Some(palette),
+            None if params.format.uses_palette() => {
+                return Err(G3dError::MissingPalette(params.format));
+            }
+            None => None,
+        };
+
+        // Safety: a palette is always present for formats that use one
+        let palette_color = |index: usize| -> Result<[u8; 4], G3dError> {
+            let offset = palette.unwrap().offset as usize + index * 2;
+            Ok(rgba(read_u16(&self.palette_data, offset)?, 0xFF))
+        };
+
+        // Returns a color from the palette, handling the transparency of the first color
+        let indexed_color = |index: usize| -> Result<[u8; 4], G3dError> {
+            if index == 0 && params.color0_transparent {
+                Ok([0; 4])
+            } else {
+                palette_color(index)
+            }
+        };
+
+        let texture_offset = params.offset as usize;
+
+        match params.format {
+            TextureFormat::None => {}
+            TextureFormat::A3I5 | TextureFormat::A5I3 => {
+                let (index_bits, max_alpha) = if params.format == TextureFormat::A3I5 {
+                    (5, 7)
+                } else {
+                    (3, 31)
+                };
+
+                let data = read_slice(&self.texture_data, texture_offset, width * height)?;
+                for (i, &texel) in data.iter().enumerate() {
+                    let index = (texel & ((1 << index_bits) - 1)) as usize;
+                    let alpha = (texel >> index_bits) as u32 * 0xFF / max_alpha;
+
+                    let mut color = palette_color(index)?;
+                    color[3] = alpha as u8;
+                    image.set_pixel(i % width, i / width, color);
+                }
+            }
+            TextureFormat::Palette4 | TextureFormat::Palette16 | TextureFormat::Palette256 => {
+                let bits_per_texel = params.format.bits_per_texel();
+                let texels_per_byte = 8 / bits_per_texel;
+                let mask = ((1u16 << bits_per_texel) - 1) as u8;
+
+                let data = read_slice(&self.texture_data, texture_offset, params.data_size())?;
+                for i in 0..width * height {
+                    let byte = data[i / texels_per_byte];
+                    let index = (byte >> ((i % texels_per_byte) * bits_per_texel)) & mask;
+
+                    image.set_pixel(i % width, i / width, indexed_color(index as usize)?);
+                }
+            }
+            TextureFormat::Compressed4x4 => {
+                let blocks_per_row = width / 4;
+                let number_of_blocks = blocks_per_row * (height / 4);
+
+                let data = read_slice(
+                    &self.compressed_texture_data,
+                    texture_offset,
+                    number_of_blocks * 4,
+                )?;
+                let index_data = read_slice(
+                    &self.compressed_texture_index_data,
+                    texture_offset / 2,
+                    number_of_blocks * 2,
+                )?;
+
+                for block in 0..number_of_blocks {
+                    // Safety: slice length is explicitly specified
+                    let texels =
+                        u32::from_le_bytes(data[block * 4..block * 4 + 4].try_into().unwrap());
+                    let index = u16::from_le_bytes(
+                        index_data[block * 2..block * 2 + 2].try_into().unwrap(),
+                    );
+
+                    let colors = compressed_block_colors(index, |i| {
+                        palette_color((index as usize & 0x3FFF) * 2 + i)
+                    })?;
+
+                    let block_x = (block % blocks_per_row) * 4;
+                    let block_y = (block / blocks_per_row) * 4;
+
+                    for texel in 0..16 {
+                        let color_index = (texels >> (texel * 2)) & 0x03;
+                        image.set_pixel(
+                            block_x + texel % 4,
+                            block_y + texel / 4,
+                            colors[color_index as usize],
+                        );
+                    }
+                }
+            }
+            TextureFormat::Direct => {
+                let data = read_slice(&self.texture_data, texture_offset, params.data_size())?;
+                for (i, texel) in data.chunks_exact(2).enumerate() {
+                    let raw = u16::from_le_bytes([texel[0], texel[1]]);
+                    let alpha = if raw & 0x8000 != 0 { 0xFF } else { 0 };
+
+                    image.set_pixel(i % width, i / width, rgba(raw, alpha));
+                }
+            }
+        }
+
+        Ok(image)
+    }
+}
+
+/// Converts a 15-bit color to an RGBA color, with the specified alpha.
+fn rgba(raw: u16, alpha: u8) -> [u8; 4] {
+    let [red, green, blue] = DsRgb::from_bgr555(raw).to_rgb8();
+    [red, green, blue, alpha]
+}
+
+/// Returns the 4 colors of a block of a compressed texture.
+///
+/// The palette index data of the block selects the mode of the block, which determines how many
+/// colors are read from the palette, and how the remaining ones are computed.
+fn compressed_block_colors<F>(index: u16, mut palette_color: F) -> Result<[[u8; 4]; 4], G3dError>
+where
+    F: FnMut(usize) -> Result<[u8; 4], G3dError>,
+{
+    let blend = |a: [u8; 4], b: [u8; 4], weight_a: u16, weight_b: u16| {
+        let mut color = [0xFF; 4];
+        for i in 0..3 {
+            color[i] =
+                ((a[i] as u16 * weight_a + b[i] as u16 * weight_b) / (weight_a + weight_b)) as u8;
+        }
+
+        color
+    };
+
+    let color0 = palette_color(0)?;
+    let color1 = palette_color(1)?;
+
+    Ok(match index >> 14 {
+        // 3 colors, and transparency
+        0 => [color0, color1, palette_color(2)?, [0; 4]],
+        // 2 colors, their average, and transparency
+        1 => [color0, color1, blend(color0, color1, 1, 1), [0; 4]],
+        // 4 colors
+        2 => [color0, color1, palette_color(2)?, palette_color(3)?],
+        // 2 colors, and 2 colors interpolated between them
+        _ => [
+            color0,
+            color1,
+            blend(color0, color1, 5, 3),
+            blend(color0, color1, 3, 5),
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the color stored at the specified index of the test palette.
+    fn palette_entry(index: usize) -> u16 {
+        match index {
+            0 => 0x001F,
+            1 => 0x7C00,
+            2 => 0x03E0,
+            3 => 0x7FFF,
+            _ => index as u16,
+        }
+    }
+
+    fn texture_block(texture_data: Vec<u8>, compressed_texture_index_data: Vec<u8>) -> Tex0 {
+        Tex0 {
+            compressed_texture_data: texture_data.clone(),
+            texture_data,
+            compressed_texture_index_data,
+            palette_data: (0..256)
+                .flat_map(|index| palette_entry(index).to_le_bytes())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn texture_params(format: TextureFormat, width: u16, height: u16) -> TextureParams {
+        TextureParams {
+            offset: 0,
+            repeat_s: false,
+            repeat_t: false,
+            flip_s: false,
+            flip_t: false,
+            width,
+            height,
+            format,
+            color0_transparent: false,
+            texcoord_transform_mode: 0,
+        }
+    }
+
+    fn decode(tex0: &Tex0, params: &TextureParams) -> Vec<[u8; 4]> {
+        let palette = PaletteInfo {
+            offset: 0,
+            flags: 0,
+        };
+        let image = tex0.decode(params, Some(&palette)).unwrap();
+
+        (0..params.height as usize)
+            .flat_map(|y| (0..params.width as usize).map(move |x| (x, y)))
+            .map(|(x, y)| image.pixel(x, y).unwrap())
+            .collect()
+    }
+
+    fn palette_color(index: usize) -> [u8; 4] {
+        rgba(palette_entry(index), 0xFF)
+    }
+
+    #[test]
+    fn decode_no_texture() {
+        let tex0 = texture_block(Vec::new(), Vec::new());
+        let pixels = decode(&tex0, &texture_params(TextureFormat::None, 2, 1));
+
+        assert_eq!(pixels, [[0; 4]; 2]);
+    }
+
+    #[test]
+    fn decode_alpha_textures() {
+        let tex0 = texture_block(vec![0xE3, 0x01], Vec::new());
+        let pixels = decode(&tex0, &texture_params(TextureFormat::A3I5, 2, 1));
+        assert_eq!(
+            pixels,
+            [
+                palette_color(3),
+                [
+                    palette_color(1)[0],
+                    palette_color(1)[1],
+                    palette_color(1)[2],
+                    0
+                ]
+            ]
+        );
+
+        let tex0 = texture_block(vec![0xFD, (15 << 3) | 2], Vec::new());
+        let pixels = decode(&tex0, &texture_params(TextureFormat::A5I3, 2, 1));
+        assert_eq!(
+            pixels,
+            [
+                palette_color(5),
+                [
+                    palette_color(2)[0],
+                    palette_color(2)[1],
+                    palette_color(2)[2],
+                    123
+                ]
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_palette_textures() {
+        let tex0 = texture_block(vec![0xE4], Vec::new());
+        let mut params = texture_params(TextureFormat::Palette4, 4, 1);
+        params.color0_transparent = true;
+        assert_eq!(
+            decode(&tex0, &params),
+            [[0; 4], palette_color(1), palette_color(2), palette_color(3)]
+        );
+
+        let tex0 = texture_block(vec![0x21, 0xF0], Vec::new());
+        assert_eq!(
+            decode(&tex0, &texture_params(TextureFormat::Palette16, 4, 1)),
+            [
+                palette_color(1),
+                palette_color(2),
+                palette_color(0),
+                palette_color(15)
+            ]
+        );
+
+        let tex0 = texture_block(vec![0x00, 0x80, 0xFF], Vec::new());
+        assert_eq!(
+            decode(&tex0, &texture_params(TextureFormat::Palette256, 3, 1)),
+            [palette_color(0), palette_color(128), palette_color(255)]
+        );
+    }
+
+    #[test]
+    fn decode_compressed_texture() {
+        // One block per mode, each holding the texel indices 0, 1, 2 and 3 on every row
+        let texture_data = [0xE4; 16].to_vec();
+        let index_data = (0..4u16)
+            .flat_map(|mode| (mode << 14).to_le_bytes())
+            .collect();
+
+        let tex0 = texture_block(texture_data, index_data);
+        let image = tex0
+            .decode(
+                &texture_params(TextureFormat::Compressed4x4, 16, 4),
+                Some(&PaletteInfo {
+                    offset: 0,
+                    flags: 0,
+                }),
+            )
+            .unwrap();
+
+        let red = palette_color(0);
+        let blue = palette_color(1);
+        let expected_colors = [
+            [red, blue, palette_color(2), [0; 4]],
+            [red, blue, [127, 0, 127, 0xFF], [0; 4]],
+            [red, blue, palette_color(2), palette_color(3)],
+            [red, blue, [159, 0, 95, 0xFF], [95, 0, 159, 0xFF]],
+        ];
+
+        for (mode, colors) in expected_colors.iter().enumerate() {
+            for y in 0..4 {
+                for (x, color) in colors.iter().enumerate() {
+                    assert_eq!(image.pixel(mode * 4 + x, y), Some(*color), "mode {mode}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decode_direct_texture() {
+        let tex0 = texture_block(vec![0x1F, 0x80, 0xE0, 0x03], Vec::new());
+        assert_eq!(
+            decode(&tex0, &texture_params(TextureFormat::Direct, 2, 1)),
+            [[0xFF, 0, 0, 0xFF], [0, 0xFF, 0, 0]]
+        );
+    }
+}
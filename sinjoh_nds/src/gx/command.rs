@@ -0,0 +1,336 @@
+//! Commands of the geometry engine, and decoding of display lists.
+//!
+//! Display lists use the packed format: a word holding up to 4 opcodes is followed by the
+//! parameters of each of those commands, in order. Unused opcodes are set to 0 (`NOP`).
+
+use cgmath::Vector3;
+use fixed::types::{I7F9, I10F6, I12F4};
+
+use crate::{DsFixed16, DsFixed32, DsRgb, DsVecFixed32};
+
+use super::GxError;
+
+/// Represents the type of primitive described by a list of vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveType {
+    /// Separate triangles, made of 3 vertices each.
+    Triangles,
+
+    /// Separate quads, made of 4 vertices each.
+    Quads,
+
+    /// A strip of triangles, where each vertex after the first 2 makes a new triangle.
+    TriangleStrip,
+
+    /// A strip of quads, where each pair of vertices after the first 2 makes a new quad.
+    QuadStrip,
+}
+
+impl PrimitiveType {
+    /// Returns the primitive type corresponding to the 2-bit value given to `BEGIN_VTXS`.
+    pub fn from_raw(raw: u32) -> Self {
+        match raw & 0x03 {
+            0 => Self::Triangles,
+            1 => Self::Quads,
+            2 => Self::TriangleStrip,
+            _ => Self::QuadStrip,
+        }
+    }
+}
+
+/// Represents a command of the geometry engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GxCommand {
+    /// `MTX_MODE`: Sets the matrix that is affected by the matrix commands.
+    MatrixMode(u8),
+
+    /// `MTX_PUSH`: Pushes the current matrix on the matrix stack.
+    PushMatrix,
+
+    /// `MTX_POP`: Pops matrices from the matrix stack.
+    PopMatrix(i8),
+
+    /// `MTX_STORE`: Stores the current matrix in the matrix stack.
+    StoreMatrix(u8),
+
+    /// `MTX_RESTORE`: Restores the current matrix from the matrix stack.
+    RestoreMatrix(u8),
+
+    /// `MTX_IDENTITY`: Loads the identity matrix.
+    IdentityMatrix,
+
+    /// `MTX_LOAD_4x4`: Loads a 4x4 matrix (row-major).
+    LoadMatrix4x4([DsFixed32; 16]),
+
+    /// `MTX_LOAD_4x3`: Loads a 4x3 matrix (row-major).
+    LoadMatrix4x3([DsFixed32; 12]),
+
+    /// `MTX_MULT_4x4`: Multiplies the current matrix by a 4x4 matrix (row-major).
+    MultiplyMatrix4x4([DsFixed32; 16]),
+
+    /// `MTX_MULT_4x3`: Multiplies the current matrix by a 4x3 matrix (row-major).
+    MultiplyMatrix4x3([DsFixed32; 12]),
+
+    /// `MTX_MULT_3x3`: Multiplies the current matrix by a 3x3 matrix (row-major).
+    MultiplyMatrix3x3([DsFixed32; 9]),
+
+    /// `MTX_SCALE`: Multiplies the current matrix by a scale matrix.
+    Scale(DsVecFixed32),
+
+    /// `MTX_TRANS`: Multiplies the current matrix by a translation matrix.
+    Translate(DsVecFixed32),
+
+    /// `COLOR`: Sets the color of the next vertices.
+    Color(DsRgb),
+
+    /// `NORMAL`: Sets the normal of the next vertices.
+    Normal(Vector3<I7F9>),
+
+    /// `TEXCOORD`: Sets the texture coordinates of the next vertices, in texels.
+    TexCoord(I12F4, I12F4),
+
+    /// `VTX_16`: Describes a vertex.
+    Vertex16(Vector3<DsFixed16>),
+
+    /// `VTX_10`: Describes a vertex, with less precision.
+    Vertex10(Vector3<I10F6>),
+
+    /// `VTX_XY`: Describes a vertex, keeping the Z coordinate of the previous one.
+    VertexXY(DsFixed16, DsFixed16),
+
+    /// `VTX_XZ`: Describes a vertex, keeping the Y coordinate of the previous one.
+    VertexXZ(DsFixed16, DsFixed16),
+
+    /// `VTX_YZ`: Describes a vertex, keeping the X coordinate of the previous one.
+    VertexYZ(DsFixed16, DsFixed16),
+
+    /// `VTX_DIFF`: Describes a vertex, relative to the previous one.
+    VertexDiff(Vector3<DsFixed16>),
+
+    /// `POLYGON_ATTR`: Sets the attributes of the next polygons.
+    PolygonAttributes(u32),
+
+    /// `TEXIMAGE_PARAM`: Sets the parameters of the texture of the next polygons.
+    TextureParams(u32),
+
+    /// `PLTT_BASE`: Sets the base address of the palette of the next polygons.
+    PaletteBase(u32),
+
+    /// `DIF_AMB`: Sets the diffuse and ambient colors of the material.
+    DiffuseAmbient(u32),
+
+    /// `SPE_EMI`: Sets the specular and emission colors of the material.
+    SpecularEmission(u32),
+
+    /// `LIGHT_VECTOR`: Sets the direction of a light.
+    LightVector(u32),
+
+    /// `LIGHT_COLOR`: Sets the color of a light.
+    LightColor(u32),
+
+    /// `SHININESS`: Sets the specular reflection shininess table.
+    Shininess([u32; 32]),
+
+    /// `BEGIN_VTXS`: Starts a list of vertices.
+    BeginVertices(PrimitiveType),
+
+    /// `END_VTXS`: Ends a list of vertices.
+    EndVertices,
+
+    /// `SWAP_BUFFERS`: Swaps the rendering buffers.
+    SwapBuffers(u32),
+
+    /// `VIEWPORT`: Sets the viewport.
+    Viewport(u32),
+
+    /// `BOX_TEST`: Tests whether a box is inside of the view volume.
+    BoxTest([u32; 3]),
+
+    /// `POS_TEST`: Computes the clip coordinates of a position.
+    PositionTest([u32; 2]),
+
+    /// `VEC_TEST`: Transforms a directional vector.
+    VectorTest(u32),
+}
+
+/// Returns the number of parameters taken by a command, or `None` if the command is unknown.
+pub fn parameter_count(opcode: u8) -> Option<usize> {
+    Some(match opcode {
+        0x00 => 0,
+        0x10 => 1,
+        0x11 => 0,
+        0x12..=0x14 => 1,
+        0x15 => 0,
+        0x16 | 0x18 => 16,
+        0x17 | 0x19 => 12,
+        0x1A => 9,
+        0x1B | 0x1C => 3,
+        0x20..=0x22 => 1,
+        0x23 => 2,
+        0x24..=0x2B => 1,
+        0x30..=0x33 => 1,
+        0x34 => 32,
+        0x40 => 1,
+        0x41 => 0,
+        0x50 | 0x60 => 1,
+        0x70 => 3,
+        0x71 => 2,
+        0x72 => 1,
+        _ => return None,
+    })
+}
+
+/// Decodes a display list into a list of commands.
+///
+/// `NOP` commands, used as padding in the packed format, are not included.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sinjoh_nds::g3d::bmd::Nsbmd;
+/// use sinjoh_nds::gx::command;
+///
+/// let bytes = std::fs::read("model.nsbmd").unwrap();
+/// let nsbmd = Nsbmd::parse_bytes(&bytes)?;
+///
+/// for model in nsbmd.models.iter() {
+///     for shape in model.value.shapes.iter() {
+///         let commands = command::decode_display_list(&shape.value.display_list)?;
+///         println!("{}: {} commands", shape.name, commands.len());
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn decode_display_list(bytes: &[u8]) -> Result<Vec<GxCommand>, GxError> {
+    let mut commands = Vec::new();
+    let mut position = 0;
+
+    while position + 4 <= bytes.len() {
+        let opcodes = read_u32(bytes, position)?.to_le_bytes();
+        position += 4;
+
+        for opcode in opcodes {
+            let parameter_count =
+                parameter_count(opcode).ok_or(GxError::UnknownCommand(opcode, position - 4))?;
+
+            let parameters = (0..parameter_count)
+                .map(|i| read_u32(bytes, position + i * 4))
+                .collect::<Result<Vec<_>, GxError>>()?;
+            position += parameter_count * 4;
+
+            if let Some(command) = GxCommand::from_parameters(opcode, &parameters) {
+                commands.push(command);
+            }
+        }
+    }
+
+    Ok(commands)
+}
+
+impl GxCommand {
+    /// Builds a command from its opcode and its parameters.
+    ///
+    /// Returns `None` for `NOP` and unknown commands. It is expected that the number of parameters
+    /// is the one returned by [`parameter_count`].
+    pub fn from_parameters(opcode: u8, parameters: &[u32]) -> Option<Self> {
+        let parameter = parameters.first().copied().unwrap_or_default();
+        let fixed_array = || parameters.iter().map(|&p| DsFixed32::from_bits(p as i32));
+
+        Some(match opcode {
+            0x10 => Self::MatrixMode((parameter & 0x03) as u8),
+            0x11 => Self::PushMatrix,
+            0x12 => Self::PopMatrix(((parameter as u8) << 2) as i8 >> 2),
+            0x13 => Self::StoreMatrix((parameter & 0x1F) as u8),
+            0x14 => Self::RestoreMatrix((parameter & 0x1F) as u8),
+            0x15 => Self::IdentityMatrix,
+            0x16 => Self::LoadMatrix4x4(collect_array(fixed_array())?),
+            0x17 => Self::LoadMatrix4x3(collect_array(fixed_array())?),
+            0x18 => Self::MultiplyMatrix4x4(collect_array(fixed_array())?),
+            0x19 => Self::MultiplyMatrix4x3(collect_array(fixed_array())?),
+            0x1A => Self::MultiplyMatrix3x3(collect_array(fixed_array())?),
+            0x1B => {
+                let [x, y, z] = collect_array(fixed_array())?;
+                Self::Scale(Vector3::new(x, y, z))
+            }
+            0x1C => {
+                let [x, y, z] = collect_array(fixed_array())?;
+                Self::Translate(Vector3::new(x, y, z))
+            }
+            0x20 => Self::Color(DsRgb::from_bgr555(parameter as u16)),
+            0x21 => {
+                let [x, y, z] = split_10bit(parameter);
+                Self::Normal(Vector3::new(
+                    I7F9::from_bits(x),
+                    I7F9::from_bits(y),
+                    I7F9::from_bits(z),
+                ))
+            }
+            0x22 => Self::TexCoord(
+                I12F4::from_bits(parameter as i16),
+                I12F4::from_bits((parameter >> 16) as i16),
+            ),
+            0x23 => Self::Vertex16(Vector3::new(
+                DsFixed16::from_bits(parameter as i16),
+                DsFixed16::from_bits((parameter >> 16) as i16),
+                DsFixed16::from_bits(*parameters.get(1)? as i16),
+            )),
+            0x24 => {
+                let [x, y, z] = split_10bit(parameter);
+                Self::Vertex10(Vector3::new(
+                    I10F6::from_bits(x),
+                    I10F6::from_bits(y),
+                    I10F6::from_bits(z),
+                ))
+            }
+            0x25..=0x27 => {
+                let a = DsFixed16::from_bits(parameter as i16);
+                let b = DsFixed16::from_bits((parameter >> 16) as i16);
+
+                match opcode {
+                    0x25 => Self::VertexXY(a, b),
+                    0x26 => Self::VertexXZ(a, b),
+                    _ => Self::VertexYZ(a, b),
+                }
+            }
+            0x28 => {
+                let [x, y, z] = split_10bit(parameter);
+                Self::VertexDiff(Vector3::new(
+                    DsFixed16::from_bits(x),
+                    DsFixed16::from_bits(y),
+                    DsFixed16::from_bits(z),
+                ))
+            }
+            0x29 => Self::PolygonAttributes(parameter),
+            0x2A => Self::TextureParams(parameter),
+            0x2B => Self::PaletteBase(parameter),
+            0x30 => Self::DiffuseAmbient(parameter),
+            0x31 => Self::SpecularEmission(parameter),
+            0x32 => Self::LightVector(parameter),
+            0x33 => Self::LightColor(parameter),
+            0x34 => Self::Shininess(collect_array(parameters.iter().copied())?),
+            0x40 => Self::BeginVertices(PrimitiveType::from_raw(parameter)),
+            0x41 => Self::EndVertices,
+            0x50 => Self::SwapBuffers(parameter),
+            0x60 => Self::Viewport(parameter),
+            0x70 => Self::BoxTest(collect_array(parameters.iter().copied())?),
+            0x71 => Self::PositionTest(collect_array(parameters.iter().copied())?),
+            0x72 => Self::VectorTest(parameter),
+            _ => return None,
+        })
+    }
+}
+
+/// Collects an iterator into an array, returning `None` if the number of items does not match.
+fn collect_array<T, const N: usize>(iter: impl Iterator<Item = T>) -> Option<[T; N]> {
+    iter.collect::<Vec<_>>().try_into().ok()
+}
+
+/// Splits a parameter holding three signed 10-bit values, sign-extending them.
+fn split_10bit(parameter: u32) -> [i16; 3] {
+    [0, 10, 20].map(|shift| (((parameter >> shift) as u16) << 6) as i16 >> 6)
+}
+
+/// Reads a `u32` at the specified offset.
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, GxError> {
+    crate::nitro::read_u32(bytes, offset).ok_or(GxError::TruncatedData(offset))
+}
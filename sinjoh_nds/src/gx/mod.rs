@@ -0,0 +1,26 @@
+//! Utils for working with the commands of the geometry engine of the Nintendo DS.
+//!
+//! The geometry engine is fed with commands, which set up matrices, describe vertices, and
+//! configure how polygons are lit and textured. Lists of commands (display lists) are used by
+//! models to store their polygons (See [`Shape`](crate::g3d::model::Shape)).
+//!
+//! Display lists can be decoded to typed commands (See [`command::decode_display_list`]), which
+//! can then be run by a small emulator of the vertex pipeline to obtain triangles (See
+//! [`pipeline::VertexPipeline`]).
+
+use thiserror::Error;
+
+pub mod command;
+pub mod pipeline;
+
+/// Error type for geometry engine commands.
+#[derive(Error, Debug)]
+pub enum GxError {
+    /// The display list ended before the parameters of a command could be read.
+    #[error("the display list is truncated (tried to read at offset 0x{0:X})")]
+    TruncatedData(usize),
+
+    /// A command has an unknown opcode.
+    #[error("unknown command 0x{0:02X} (at offset 0x{1:X})")]
+    UnknownCommand(u8, usize),
+}
@@ -0,0 +1,387 @@
+//! A small emulator of the vertex pipeline of the geometry engine.
+//!
+//! The emulator keeps track of the current position matrix and of the matrix stack, transforms
+//! the vertices that are described to it, and assembles them into triangles.
+//!
+//! Only what is needed to extract meshes is emulated: lighting, the projection matrix, the
+//! texture matrix and clipping are ignored.
+
+use cgmath::{InnerSpace, Matrix3, Matrix4, SquareMatrix, Vector2, Vector3, Vector4, Zero};
+
+use crate::{DsFixed32, DsRgb};
+
+use super::command::{GxCommand, PrimitiveType};
+
+/// The number of matrices in the matrix stack.
+pub const MATRIX_STACK_SIZE: usize = 32;
+
+/// The matrix mode in which matrix commands affect the texture matrix.
+const MATRIX_MODE_TEXTURE: u8 = 3;
+
+/// The matrix mode in which matrix commands affect the projection matrix.
+const MATRIX_MODE_PROJECTION: u8 = 0;
+
+/// The matrix mode in which matrix commands affect both the position and the vector matrices.
+const MATRIX_MODE_POSITION_VECTOR: u8 = 2;
+
+/// Represents a vertex output by the vertex pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vertex {
+    /// Position of the vertex, transformed by the current matrix.
+    pub position: Vector3<f32>,
+
+    /// Normal of the vertex, transformed by the current matrix, if any was set.
+    pub normal: Option<Vector3<f32>>,
+
+    /// Texture coordinates of the vertex, in texels.
+    pub texcoord: Vector2<f32>,
+
+    /// Color of the vertex.
+    pub color: DsRgb,
+}
+
+/// Represents a triangle output by the vertex pipeline.
+///
+/// Vertices are in the winding order of the primitive they come from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    /// Vertices of the triangle.
+    pub vertices: [Vertex; 3],
+}
+
+/// An emulator of the vertex pipeline of the geometry engine.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sinjoh_nds::g3d::bmd::Nsbmd;
+/// use sinjoh_nds::gx::command;
+/// use sinjoh_nds::gx::pipeline::VertexPipeline;
+///
+/// let bytes = std::fs::read("model.nsbmd").unwrap();
+/// let nsbmd = Nsbmd::parse_bytes(&bytes)?;
+///
+/// let mut pipeline = VertexPipeline::new();
+/// for model in nsbmd.models.iter() {
+///     for shape in model.value.shapes.iter() {
+///         pipeline.run(&command::decode_display_list(&shape.value.display_list)?);
+///     }
+/// }
+///
+/// println!("{} triangles", pipeline.triangles().len());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct VertexPipeline {
+    matrix_mode: u8,
+    current_matrix: Matrix4<f32>,
+    matrix_stack: [Matrix4<f32>; MATRIX_STACK_SIZE],
+    stack_pointer: usize,
+    position_scale: f32,
+    color: DsRgb,
+    normal: Option<Vector3<f32>>,
+    texcoord: Vector2<f32>,
+    last_position: Vector3<f32>,
+    primitive_type: Option<PrimitiveType>,
+    primitive_vertices: Vec<Vertex>,
+    triangles: Vec<Triangle>,
+}
+
+impl Default for VertexPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VertexPipeline {
+    /// Creates a vertex pipeline, with identity matrices and a white vertex color.
+    ///
+    /// Matrix commands affect the position matrix, as is the case when display lists of models
+    /// are sent to the geometry engine.
+    pub fn new() -> Self {
+        Self {
+            matrix_mode: MATRIX_MODE_POSITION_VECTOR,
+            current_matrix: Matrix4::identity(),
+            matrix_stack: [Matrix4::identity(); MATRIX_STACK_SIZE],
+            stack_pointer: 0,
+            position_scale: 1.0,
+            color: DsRgb {
+                red: 0x1F,
+                green: 0x1F,
+                blue: 0x1F,
+            },
+            normal: None,
+            texcoord: Vector2::zero(),
+            last_position: Vector3::zero(),
+            primitive_type: None,
+            primitive_vertices: Vec::new(),
+            triangles: Vec::new(),
+        }
+    }
+
+    /// Returns the current position matrix.
+    pub fn current_matrix(&self) -> Matrix4<f32> {
+        self.current_matrix
+    }
+
+    /// Sets the current position matrix.
+    pub fn set_current_matrix(&mut self, matrix: Matrix4<f32>) {
+        self.current_matrix = matrix;
+    }
+
+    /// Returns the matrix at the specified index of the matrix stack, if any.
+    pub fn stack_matrix(&self, index: usize) -> Option<Matrix4<f32>> {
+        self.matrix_stack.get(index).copied()
+    }
+
+    /// Sets the matrix at the specified index of the matrix stack.
+    ///
+    /// Nothing is done if the index is outside of the matrix stack.
+    pub fn set_stack_matrix(&mut self, index: usize, matrix: Matrix4<f32>) {
+        if let Some(stack_matrix) = self.matrix_stack.get_mut(index) {
+            *stack_matrix = matrix;
+        }
+    }
+
+    /// Sets the scale applied to the positions of the vertices, before they are transformed.
+    ///
+    /// Models store their vertices scaled down, so that they fit in the range of the vertex
+    /// commands (See [`ModelInfo::position_scale`](crate::g3d::model::ModelInfo::position_scale)).
+    pub fn set_position_scale(&mut self, position_scale: f32) {
+        self.position_scale = position_scale;
+    }
+
+    /// Sets the color of the next vertices.
+    pub fn set_color(&mut self, color: DsRgb) {
+        self.color = color;
+    }
+
+    /// Returns the triangles that were output so far.
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
+    /// Returns the triangles that were output so far, clearing them from the pipeline.
+    pub fn take_triangles(&mut self) -> Vec<Triangle> {
+        std::mem::take(&mut self.triangles)
+    }
+
+    /// Runs a list of commands.
+    pub fn run(&mut self, commands: &[GxCommand]) {
+        for command in commands {
+            self.execute(command);
+        }
+    }
+
+    /// Runs a single command.
+    pub fn execute(&mut self, command: &GxCommand) {
+        match command {
+            GxCommand::MatrixMode(mode) => self.matrix_mode = *mode,
+            GxCommand::PushMatrix => {
+                if self.affects_position_matrix() {
+                    self.set_stack_matrix(self.stack_pointer, self.current_matrix);
+                    self.stack_pointer = (self.stack_pointer + 1).min(MATRIX_STACK_SIZE - 1);
+                }
+            }
+            GxCommand::PopMatrix(offset) => {
+                if self.affects_position_matrix() {
+                    self.stack_pointer = (self.stack_pointer as isize - *offset as isize)
+                        .clamp(0, MATRIX_STACK_SIZE as isize - 1)
+                        as usize;
+                    self.current_matrix = self.matrix_stack[self.stack_pointer];
+                }
+            }
+            GxCommand::StoreMatrix(index) => {
+                if self.affects_position_matrix() {
+                    self.set_stack_matrix(*index as usize, self.current_matrix);
+                }
+            }
+            GxCommand::RestoreMatrix(index) => {
+                if self.affects_position_matrix()
+                    && let Some(matrix) = self.stack_matrix(*index as usize)
+                {
+                    self.current_matrix = matrix;
+                }
+            }
+            GxCommand::IdentityMatrix => self.load_matrix(Matrix4::identity()),
+            GxCommand::LoadMatrix4x4(elements) => self.load_matrix(matrix_from_rows(elements, 4)),
+            GxCommand::LoadMatrix4x3(elements) => self.load_matrix(matrix_from_rows(elements, 3)),
+            GxCommand::MultiplyMatrix4x4(elements) => {
+                self.multiply_matrix(matrix_from_rows(elements, 4))
+            }
+            GxCommand::MultiplyMatrix4x3(elements) => {
+                self.multiply_matrix(matrix_from_rows(elements, 3))
+            }
+            GxCommand::MultiplyMatrix3x3(elements) => {
+                let m = elements.map(|element| element.to_num::<f32>());
+                self.multiply_matrix(
+                    Matrix3::new(m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8]).into(),
+                )
+            }
+            GxCommand::Scale(scale) => self.multiply_matrix(Matrix4::from_nonuniform_scale(
+                scale.x.to_num(),
+                scale.y.to_num(),
+                scale.z.to_num(),
+            )),
+            GxCommand::Translate(translation) => {
+                self.multiply_matrix(Matrix4::from_translation(Vector3::new(
+                    translation.x.to_num(),
+                    translation.y.to_num(),
+                    translation.z.to_num(),
+                )))
+            }
+            GxCommand::Color(color) => self.color = *color,
+            GxCommand::Normal(normal) => {
+                let normal = Vector3::new(normal.x.to_num(), normal.y.to_num(), normal.z.to_num());
+                self.normal = Some(normal);
+            }
+            GxCommand::TexCoord(s, t) => self.texcoord = Vector2::new(s.to_num(), t.to_num()),
+            GxCommand::Vertex16(position) => self.add_vertex(Vector3::new(
+                position.x.to_num(),
+                position.y.to_num(),
+                position.z.to_num(),
+            )),
+            GxCommand::Vertex10(position) => self.add_vertex(Vector3::new(
+                position.x.to_num(),
+                position.y.to_num(),
+                position.z.to_num(),
+            )),
+            GxCommand::VertexXY(x, y) => {
+                self.add_vertex(Vector3::new(x.to_num(), y.to_num(), self.last_position.z))
+            }
+            GxCommand::VertexXZ(x, z) => {
+                self.add_vertex(Vector3::new(x.to_num(), self.last_position.y, z.to_num()))
+            }
+            GxCommand::VertexYZ(y, z) => {
+                self.add_vertex(Vector3::new(self.last_position.x, y.to_num(), z.to_num()))
+            }
+            GxCommand::VertexDiff(difference) => self.add_vertex(
+                self.last_position
+                    + Vector3::new(
+                        difference.x.to_num(),
+                        difference.y.to_num(),
+                        difference.z.to_num(),
+                    ),
+            ),
+            GxCommand::BeginVertices(primitive_type) => {
+                self.primitive_type = Some(*primitive_type);
+                self.primitive_vertices.clear();
+            }
+            GxCommand::EndVertices => {
+                self.primitive_type = None;
+                self.primitive_vertices.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether matrix commands affect the position matrix in the current matrix mode.
+    fn affects_position_matrix(&self) -> bool {
+        self.matrix_mode != MATRIX_MODE_PROJECTION && self.matrix_mode != MATRIX_MODE_TEXTURE
+    }
+
+    /// Replaces the current matrix, if matrix commands affect the position matrix.
+    fn load_matrix(&mut self, matrix: Matrix4<f32>) {
+        if self.affects_position_matrix() {
+            self.current_matrix = matrix;
+        }
+    }
+
+    /// Multiplies the current matrix, if matrix commands affect the position matrix.
+    fn multiply_matrix(&mut self, matrix: Matrix4<f32>) {
+        if self.affects_position_matrix() {
+            self.current_matrix = self.current_matrix * matrix;
+        }
+    }
+
+    /// Transforms a vertex and adds it to the current primitive, outputting triangles when the
+    /// primitive allows it.
+    fn add_vertex(&mut self, position: Vector3<f32>) {
+        self.last_position = position;
+
+        let Some(primitive_type) = self.primitive_type else {
+            return;
+        };
+
+        let transformed_position =
+            self.current_matrix * (position * self.position_scale).extend(1.0);
+        let transformed_normal = self.normal.map(|normal| {
+            let normal =
+                (self.current_matrix * Vector4::new(normal.x, normal.y, normal.z, 0.0)).truncate();
+
+            if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            }
+        });
+
+        self.primitive_vertices.push(Vertex {
+            position: transformed_position.truncate(),
+            normal: transformed_normal,
+            texcoord: self.texcoord,
+            color: self.color,
+        });
+
+        let count = self.primitive_vertices.len();
+
+        match primitive_type {
+            PrimitiveType::Triangles => {
+                if count == 3 {
+                    self.output_triangle(0, 1, 2);
+                    self.primitive_vertices.clear();
+                }
+            }
+            PrimitiveType::Quads => {
+                if count == 4 {
+                    self.output_triangle(0, 1, 2);
+                    self.output_triangle(0, 2, 3);
+                    self.primitive_vertices.clear();
+                }
+            }
+            PrimitiveType::TriangleStrip => {
+                // Every other triangle has its first two vertices swapped, to keep the winding
+                // order consistent
+                if count >= 3 {
+                    if count % 2 == 1 {
+                        self.output_triangle(count - 3, count - 2, count - 1);
+                    } else {
+                        self.output_triangle(count - 2, count - 3, count - 1);
+                    }
+                }
+            }
+            PrimitiveType::QuadStrip => {
+                // Quads are made of the last 4 vertices, with the last 2 in reverse order
+                if count >= 4 && count.is_multiple_of(2) {
+                    self.output_triangle(count - 4, count - 3, count - 1);
+                    self.output_triangle(count - 4, count - 1, count - 2);
+                }
+            }
+        }
+    }
+
+    /// Outputs a triangle made of the vertices of the current primitive with the specified
+    /// indices.
+    fn output_triangle(&mut self, a: usize, b: usize, c: usize) {
+        let vertices = &self.primitive_vertices;
+        self.triangles.push(Triangle {
+            vertices: [vertices[a], vertices[b], vertices[c]],
+        });
+    }
+}
+
+/// Builds a matrix from the elements of a row-major matrix with 4 rows and either 3 or 4 columns.
+///
+/// The geometry engine multiplies row vectors by matrices, so each row of the matrix becomes a
+/// column of the resulting matrix.
+fn matrix_from_rows(elements: &[DsFixed32], columns: usize) -> Matrix4<f32> {
+    let mut matrix = Matrix4::identity();
+
+    for row in 0..elements.len() / columns {
+        for column in 0..columns {
+            matrix[row][column] = elements[row * columns + column].to_num();
+        }
+    }
+
+    matrix
+}
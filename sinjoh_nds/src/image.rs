@@ -0,0 +1,58 @@
+//! Utils for working with decoded images.
+
+/// The number of bytes used by a pixel of an [`RgbaImage`].
+pub const RGBA_PIXEL_SIZE: usize = 4;
+
+/// Represents an image with 8-bit RGBA pixels.
+///
+/// Pixels are stored row by row, starting from the top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    /// Width of the image, in pixels.
+    pub width: usize,
+
+    /// Height of the image, in pixels.
+    pub height: usize,
+
+    /// Pixels of the image, as RGBA components.
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Creates a fully transparent image of the specified size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * RGBA_PIXEL_SIZE],
+        }
+    }
+
+    /// Returns the pixel at the specified position, if it is inside of the image.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let offset = (y * self.width + x) * RGBA_PIXEL_SIZE;
+
+        // Safety: slice length is explicitly specified
+        Some(
+            self.pixels[offset..offset + RGBA_PIXEL_SIZE]
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    /// Sets the pixel at the specified position.
+    ///
+    /// Nothing is done if the position is outside of the image.
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: [u8; 4]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let offset = (y * self.width + x) * RGBA_PIXEL_SIZE;
+        self.pixels[offset..offset + RGBA_PIXEL_SIZE].copy_from_slice(&pixel);
+    }
+}
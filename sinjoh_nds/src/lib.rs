@@ -3,7 +3,18 @@
 use cgmath::Vector3;
 use fixed::types::{I4F12, I20F12};
 
+pub mod bytes;
+pub mod compression;
+pub mod crc;
+pub mod fnt;
+pub mod g2d;
+pub mod g3d;
+pub mod gx;
+pub mod image;
 pub mod narc;
+pub mod nitro;
+pub mod rom;
+pub mod sound;
 
 /// The size of a 32-bit fixed-point number.
 pub const DS_FIXED_32_SIZE: usize = 4;
@@ -14,7 +25,7 @@ pub const DS_VEC_FIXED_32_SIZE: usize = DS_FIXED_32_SIZE * 3;
 /// Represents an RBG color.
 ///
 /// Each color component should be 5-bit to follow what the Nintendo DS uses.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct DsRgb {
     /// The red color component.
     pub red: u8,
@@ -26,6 +37,39 @@ pub struct DsRgb {
     pub blue: u8,
 }
 
+impl DsRgb {
+    /// Creates a color from its 15-bit representation, as used by the Nintendo DS.
+    ///
+    /// Bits 0-4 hold the red component, bits 5-9 the green component, and bits 10-14 the blue
+    /// component. Bit 15 is ignored.
+    pub fn from_bgr555(raw: u16) -> Self {
+        Self {
+            red: (raw & 0x1F) as u8,
+            green: ((raw >> 5) & 0x1F) as u8,
+            blue: ((raw >> 10) & 0x1F) as u8,
+        }
+    }
+
+    /// Returns the 15-bit representation of the color, as used by the Nintendo DS.
+    pub fn to_bgr555(&self) -> u16 {
+        (self.red as u16 & 0x1F)
+            | ((self.green as u16 & 0x1F) << 5)
+            | ((self.blue as u16 & 0x1F) << 10)
+    }
+
+    /// Returns the color with 8-bit components.
+    ///
+    /// The 5-bit components are expanded so that the full 8-bit range is covered.
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        let expand = |component: u8| (component << 3) | (component >> 2);
+        [
+            expand(self.red & 0x1F),
+            expand(self.green & 0x1F),
+            expand(self.blue & 0x1F),
+        ]
+    }
+}
+
 /// A 16-bit signed fixed-point number with 1 sign bit, 3 integer bits and 12 fractional bits.
 pub type DsFixed16 = I4F12;
 
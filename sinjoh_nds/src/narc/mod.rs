@@ -6,7 +6,11 @@
 
 use thiserror::Error;
 
+use crate::fnt::FileNameTable;
+
 pub mod reader;
+pub mod typed;
+pub mod writer;
 
 #[derive(Debug, Error)]
 pub enum NarcByteOrderError {
@@ -46,6 +50,7 @@ pub struct NarcFileAllocationTableBlock {
 #[derive(Debug)]
 pub struct NarcFileNameTableBlock {
     pub chunk_size: u32,
+    pub file_name_table: FileNameTable,
 }
 
 #[derive(Debug)]
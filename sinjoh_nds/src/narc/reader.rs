@@ -3,13 +3,16 @@
 //! For more information, see [`NarcReader`].
 
 use std::fs::File;
-use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use log::warn;
 use thiserror::Error;
 
+use crate::compression::{self, CompressionError, CompressionType};
+use crate::fnt::{FileNameTable, FileNameTableError, FileNameTableFilesIter};
+
 use super::{
     NarcByteOrder, NarcFileAllocationTableBlock, NarcFileImageBlock, NarcFileNameTableBlock,
     NarcHeader,
@@ -65,6 +68,14 @@ pub enum NarcReaderError {
     #[error("this NARC does not have a File Image Block")]
     FimgBlockNotFound,
 
+    /// The NARC file does not have a File Name Table Block.
+    #[error("this NARC does not have a File Name Table Block")]
+    FntbBlockNotFound,
+
+    /// The File Name Table Block could not be parsed.
+    #[error("unable to parse the File Name Table Block")]
+    FntbParseError(#[source] FileNameTableError),
+
     /// The file at the specified index could not be found.
     #[error("the file at index {0} could not be found")]
     FileNotFound(u16),
@@ -72,19 +83,31 @@ pub enum NarcReaderError {
     /// The file at the specified index is too large to be handled.
     #[error("the file at index {0} is too large to be handled (size is {1})")]
     FileTooLarge(u16, u32),
+
+    /// The file at the specified index looks compressed, but could not be decompressed.
+    #[error("the file at index {0} could not be decompressed")]
+    FileDecompressionError(u16, #[source] CompressionError),
+
+    /// The file at the specified path could not be found.
+    #[error("the file at path {0:?} could not be found")]
+    FileNotFoundByPath(String),
+
+    /// A chunk is too large to be handled.
+    #[error("a chunk is too large to be handled (size is {0})")]
+    ChunkTooLarge(u32),
 }
 
 /// An iterator over the files in a NARC file.
 #[derive(Debug)]
-pub struct NarcReaderFilesIter<'a> {
+pub struct NarcReaderFilesIter<'a, R: Read + Seek> {
     /// The current index of the iterator.
     curr: u16,
 
     /// The NARC reader.
-    narc_reader: &'a mut NarcReader,
+    narc_reader: &'a mut NarcReader<R>,
 }
 
-impl Iterator for NarcReaderFilesIter<'_> {
+impl<R: Read + Seek> Iterator for NarcReaderFilesIter<'_, R> {
     type Item = Result<Vec<u8>, NarcReaderError>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -99,11 +122,11 @@ impl Iterator for NarcReaderFilesIter<'_> {
     }
 }
 
-impl<'a> NarcReaderFilesIter<'a> {
+impl<'a, R: Read + Seek> NarcReaderFilesIter<'a, R> {
     /// Creates a new iterator over the files in a NARC file.
     ///
     /// See [`NarcReader::files_iter`] for usage.
-    pub fn new(narc_reader: &'a mut NarcReader) -> Self {
+    pub fn new(narc_reader: &'a mut NarcReader<R>) -> Self {
         Self {
             curr: 0,
             narc_reader,
@@ -115,10 +138,20 @@ impl<'a> NarcReaderFilesIter<'a> {
 #[derive(Debug, Default)]
 pub struct NarcReaderFlags {
     /// Whether to skip the magic number check at the beginning of the NARC file.
-    skip_narc_magic_number_check: bool,
+    pub skip_narc_magic_number_check: bool,
 
     /// Whether to skip the BOM check at the beginning of the NARC file.
-    skip_bom_check: bool,
+    pub skip_bom_check: bool,
+
+    /// Whether to transparently decompress the files that are compressed with one of the formats
+    /// supported by the BIOS (See [`CompressionType`]).
+    ///
+    /// Files are detected as compressed from their header (See
+    /// [`CompressionType::detect_checked`]), and are returned as-is if they are not. An error is
+    /// returned if a file detected as compressed cannot be decompressed. NARCs holding short
+    /// uncompressed files that may start with the header byte of a format should be read without
+    /// this flag, decompressing their files with [`compression::decompress`] where needed.
+    pub decompress_files: bool,
 }
 
 /// A reader for NARC files.
@@ -126,6 +159,8 @@ pub struct NarcReaderFlags {
 /// This reader reads the header of a NARC file and keeps it in memory. The actual files contained
 /// in the NARC are lazily-loaded as needed.
 ///
+/// The NARC can be read from any source implementing [`Read`] and [`Seek`].
+///
 /// ## Reading a NARC from the file system
 ///
 /// The NARC reader allows reading the contents of a NARC file that is directly stored on the
@@ -135,7 +170,7 @@ pub struct NarcReaderFlags {
 ///
 /// Here's an example on how to use it:
 ///
-/// ```
+/// ```no_run
 /// use sinjoh_nds::narc::reader::{NarcReader, NarcReaderFlags};
 ///
 /// let mut narc_reader =
@@ -144,17 +179,38 @@ pub struct NarcReaderFlags {
 /// for file in narc_reader.files_iter() {
 ///     println!("{:#?}", file);
 /// }
+/// # Ok::<(), sinjoh_nds::narc::reader::NarcReaderError>(())
+/// ```
+///
+/// ## Reading a NARC from memory
+///
+/// If the NARC is already loaded in memory (for instance, after extracting it from a ROM or
+/// decompressing it), the NARC reader can directly read it from a buffer:
+///
+/// ```no_run
+/// use std::fs;
+///
+/// use sinjoh_nds::narc::reader::{NarcReader, NarcReaderFlags};
+///
+/// let narc_bytes = fs::read("/path/to/archive.narc")?;
+/// let mut narc_reader = NarcReader::from_bytes(narc_bytes, NarcReaderFlags::default())?;
+///
+/// let file = narc_reader.get_file(0)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 #[derive(Debug)]
-pub struct NarcReader {
-    /// The buffered reader for the NARC file.
-    reader: BufReader<File>,
+pub struct NarcReader<R: Read + Seek> {
+    /// The reader for the NARC file.
+    reader: R,
 
     /// The parsed header of the NARC file.
     narc_header: NarcHeader,
+
+    /// The flags configuring the behavior of the reader.
+    flags: NarcReaderFlags,
 }
 
-impl NarcReader {
+impl NarcReader<BufReader<File>> {
     /// Creates a new NARC reader from the given file.
     ///
     /// Flags can be used to configure the behavior of the reader.
@@ -166,20 +222,44 @@ impl NarcReader {
         flags: NarcReaderFlags,
     ) -> Result<Self, NarcReaderError> {
         let file = File::open(path).map_err(NarcReaderError::FileOpenError)?;
-        let mut reader = BufReader::new(file);
+        Self::from_reader(BufReader::new(file), flags)
+    }
+}
+
+impl<B: AsRef<[u8]>> NarcReader<Cursor<B>> {
+    /// Creates a new NARC reader from a buffer containing the whole NARC file.
+    ///
+    /// Flags can be used to configure the behavior of the reader.
+    pub fn from_bytes(bytes: B, flags: NarcReaderFlags) -> Result<Self, NarcReaderError> {
+        Self::from_reader(Cursor::new(bytes), flags)
+    }
+}
+
+impl<R: Read + Seek> NarcReader<R> {
+    /// Creates a new NARC reader from the given reader.
+    ///
+    /// Flags can be used to configure the behavior of the reader.
+    ///
+    /// The NARC is expected to start at the current position of the reader. The reader is kept
+    /// until the NARC reader is dropped, which allows for lazy-loading of the files contained in
+    /// the NARC.
+    pub fn from_reader(mut reader: R, flags: NarcReaderFlags) -> Result<Self, NarcReaderError> {
+        let narc_header = Self::read_header(&mut reader, &flags)?;
 
-        let narc = Self::read_header(&mut reader, &flags)?;
         Ok(Self {
             reader,
-            narc_header: narc,
+            narc_header,
+            flags,
         })
     }
 
+    /// Consumes the NARC reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
     /// Reads the header of the NARC file.
-    fn read_header(
-        reader: &mut BufReader<File>,
-        flags: &NarcReaderFlags,
-    ) -> Result<NarcHeader, NarcReaderError> {
+    fn read_header(reader: &mut R, flags: &NarcReaderFlags) -> Result<NarcHeader, NarcReaderError> {
         // Read the magic value
         if !flags.skip_narc_magic_number_check {
             let magic = reader
@@ -194,7 +274,7 @@ impl NarcReader {
         // Read the BOM
         let mut bom = [0; 2];
         reader
-            .read(&mut bom)
+            .read_exact(&mut bom)
             .map_err(NarcReaderError::FileReadError)?;
 
         let byte_order = {
@@ -250,7 +330,7 @@ impl NarcReader {
     ///
     /// This reads and parses the `FATB`, `FNTB`, and `FIMG` chunks.
     pub fn read_chunks(
-        reader: &mut BufReader<File>,
+        reader: &mut R,
         narc_header: &mut NarcHeader,
     ) -> Result<(), NarcReaderError> {
         for _ in 0..narc_header.number_of_chunks {
@@ -305,7 +385,7 @@ impl NarcReader {
     /// This chunk contains the file allocation table, which specifies the location of each file in
     /// the NARC.
     fn read_fatb_chunk(
-        reader: &mut BufReader<File>,
+        reader: &mut R,
         chunk_size: u32,
     ) -> Result<NarcFileAllocationTableBlock, NarcReaderError> {
         // Read the number of files
@@ -347,11 +427,29 @@ impl NarcReader {
     /// This chunk contains the file name table, which specifies the names of each file in the
     /// NARC.
     fn read_fntb_chunk(
-        _reader: &mut BufReader<File>,
+        reader: &mut R,
         chunk_size: u32,
     ) -> Result<NarcFileNameTableBlock, NarcReaderError> {
-        // TODO: Need an example to implement this correctly
-        Ok(NarcFileNameTableBlock { chunk_size })
+        // Read the whole table, as it contains offsets relative to its beginning
+        let table_size = chunk_size.saturating_sub(8);
+        let mut raw_table = vec![
+            0u8;
+            table_size
+                .try_into()
+                .map_err(|_| NarcReaderError::ChunkTooLarge(chunk_size))?
+        ];
+
+        reader
+            .read_exact(&mut raw_table)
+            .map_err(NarcReaderError::FileReadError)?;
+
+        let file_name_table =
+            FileNameTable::parse_bytes(&raw_table).map_err(NarcReaderError::FntbParseError)?;
+
+        Ok(NarcFileNameTableBlock {
+            chunk_size,
+            file_name_table,
+        })
     }
 
     /// Returns the parsed header of the NARC file.
@@ -371,6 +469,19 @@ impl NarcReader {
 
     /// Reads and returns the file at the specified index.
     pub fn get_file(&mut self, index: u16) -> Result<Vec<u8>, NarcReaderError> {
+        self.get_file_with_compression_type(index)
+            .map(|(file, _)| file)
+    }
+
+    /// Reads and returns the file at the specified index, along with the format it was
+    /// compressed with.
+    ///
+    /// The compression format is only reported for files that were transparently decompressed
+    /// (See [`NarcReaderFlags::decompress_files`]), and is `None` otherwise.
+    pub fn get_file_with_compression_type(
+        &mut self,
+        index: u16,
+    ) -> Result<(Vec<u8>, Option<CompressionType>), NarcReaderError> {
         // Get where the file is located in the NARC
         let fat = self
             .narc_header
@@ -408,13 +519,129 @@ impl NarcReader {
             .read_exact(&mut file)
             .map_err(NarcReaderError::FileReadError)?;
 
-        Ok(file)
+        // Decompress the file if requested
+        if !self.flags.decompress_files {
+            return Ok((file, None));
+        }
+
+        match CompressionType::detect_checked(&file) {
+            Some(compression_type) => compression::decompress(&file)
+                .map(|file| (file, Some(compression_type)))
+                .map_err(|e| NarcReaderError::FileDecompressionError(index, e)),
+            None => Ok((file, None)),
+        }
     }
 
     /// Returns an iterator over the files in the NARC file.
     ///
     /// This will sequentially load each file from the NARC file as needed.
-    pub fn files_iter(&mut self) -> NarcReaderFilesIter<'_> {
+    pub fn files_iter(&mut self) -> NarcReaderFilesIter<'_, R> {
         NarcReaderFilesIter::new(self)
     }
+
+    /// Returns the file name table of the NARC file.
+    ///
+    /// Convenience method for getting the file name table from the FNT block.
+    pub fn file_name_table(&self) -> Result<&FileNameTable, NarcReaderError> {
+        self.narc_header
+            .fnt
+            .as_ref()
+            .map(|fnt| &fnt.file_name_table)
+            .ok_or(NarcReaderError::FntbBlockNotFound)
+    }
+
+    /// Returns the index of the file at the specified path.
+    ///
+    /// Path components are separated by a `/`, such as in `a/b.bin`.
+    pub fn get_file_index_by_path(&self, path: &str) -> Result<u16, NarcReaderError> {
+        self.file_name_table()?
+            .get_file_id_by_path(path)
+            .ok_or_else(|| NarcReaderError::FileNotFoundByPath(path.to_string()))
+    }
+
+    /// Reads and returns the file at the specified path.
+    ///
+    /// Path components are separated by a `/`, such as in `a/b.bin`.
+    pub fn get_file_by_path(&mut self, path: &str) -> Result<Vec<u8>, NarcReaderError> {
+        let index = self.get_file_index_by_path(path)?;
+        self.get_file(index)
+    }
+
+    /// Returns an iterator over the named files in the NARC file.
+    ///
+    /// Each item is the path of a file, along with its index. Files that are not named by the
+    /// file name table are not visited.
+    pub fn file_paths_iter(&self) -> Result<FileNameTableFilesIter<'_>, NarcReaderError> {
+        Ok(self.file_name_table()?.files_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::writer::{NarcWriter, NarcWriterFlags};
+    use super::*;
+
+    fn decompressing_flags() -> NarcReaderFlags {
+        NarcReaderFlags {
+            decompress_files: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decompress_files() {
+        let data = b"hello hello hello, this is some data to compress".repeat(4);
+
+        // A raw file starting with the LZ10 header byte, which must be kept as-is
+        let mut map_matrix = vec![0x10, 0x01, 0x00, 0x00, 0x00];
+        map_matrix.extend(std::iter::repeat_n([0x01, 0x00], 16).flatten());
+
+        let files = vec![
+            compression::compress(&data, CompressionType::Lz10).unwrap(),
+            compression::compress(&data, CompressionType::Lz11).unwrap(),
+            map_matrix.clone(),
+        ];
+        let bytes = NarcWriter::from_files(files.clone(), NarcWriterFlags::default())
+            .to_bytes()
+            .unwrap();
+
+        let mut narc_reader = NarcReader::from_bytes(&bytes, decompressing_flags()).unwrap();
+        assert_eq!(
+            narc_reader.get_file_with_compression_type(0).unwrap(),
+            (data.clone(), Some(CompressionType::Lz10))
+        );
+        assert_eq!(
+            narc_reader.get_file_with_compression_type(1).unwrap(),
+            (data.clone(), Some(CompressionType::Lz11))
+        );
+        assert_eq!(
+            narc_reader.get_file_with_compression_type(2).unwrap(),
+            (map_matrix, None)
+        );
+
+        // Files are returned as-is without the flag
+        let mut narc_reader = NarcReader::from_bytes(&bytes, NarcReaderFlags::default()).unwrap();
+        assert_eq!(
+            narc_reader
+                .files_iter()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            files
+        );
+    }
+
+    #[test]
+    fn decompress_corrupted_file() {
+        // Valid LZ10 header, but the back-reference points before the beginning of the data
+        let file = vec![0x10, 0x03, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00];
+        let bytes = NarcWriter::from_files(vec![file], NarcWriterFlags::default())
+            .to_bytes()
+            .unwrap();
+
+        let mut narc_reader = NarcReader::from_bytes(&bytes, decompressing_flags()).unwrap();
+        assert!(matches!(
+            narc_reader.get_file(0),
+            Err(NarcReaderError::FileDecompressionError(0, _))
+        ));
+    }
 }
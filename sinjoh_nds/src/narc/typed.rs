@@ -0,0 +1,404 @@
+//! NARC archive whose files are all parsed to the same data structure.
+//!
+//! For more information, see [`TypedNarc`].
+
+use std::cell::OnceCell;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::bytes::{FromBytes, ToBytes};
+use crate::compression::{self, CompressionError, CompressionType};
+use crate::fnt::FileNameTable;
+
+use super::reader::{NarcReader, NarcReaderError, NarcReaderFlags};
+use super::writer::{NarcWriter, NarcWriterError, NarcWriterFlags};
+
+/// Error type for typed NARC archives.
+#[derive(Error, Debug)]
+pub enum TypedNarcError {
+    /// An error has occurred while reading the NARC.
+    #[error("unable to read the NARC")]
+    NarcReadError(#[source] NarcReaderError),
+
+    /// An error has occurred while writing the NARC.
+    #[error("unable to write the NARC")]
+    NarcWriteError(#[source] NarcWriterError),
+
+    /// A file of the NARC could not be parsed.
+    #[error("unable to parse file {0} of the NARC")]
+    ParseError(usize, #[source] Box<dyn Error + Send + Sync>),
+
+    /// A file of the NARC could not be serialized.
+    #[error("unable to serialize file {0} of the NARC")]
+    SerializeError(usize, #[source] Box<dyn Error + Send + Sync>),
+
+    /// A file of the NARC could not be compressed back to its original format.
+    #[error("unable to compress file {0} of the NARC")]
+    CompressionError(usize, #[source] CompressionError),
+
+    /// The specified file index is greater or equal than the amount of files in the NARC.
+    #[error(
+        "file index is greater or equal than file count (file index is {0}, file count is {1})"
+    )]
+    FileIndexTooBig(usize, usize),
+}
+
+/// Flags that can be used to configure the behavior of a typed NARC.
+#[derive(Debug, Default)]
+pub struct TypedNarcFlags {
+    /// Whether to parse the files only when they are first accessed, instead of parsing all of
+    /// them when the NARC is read.
+    pub lazy_parsing: bool,
+
+    /// The flags of the underlying NARC reader.
+    pub narc_reader_flags: NarcReaderFlags,
+}
+
+/// A file of a typed NARC, along with its raw bytes while it is not parsed.
+#[derive(Debug, Clone)]
+struct TypedNarcFile<T> {
+    /// The raw bytes of the file, emptied once the file is parsed eagerly.
+    raw: Vec<u8>,
+
+    /// The parsed file.
+    parsed: OnceCell<T>,
+
+    /// The format the file was compressed with in the NARC, if it was decompressed when read.
+    compression_type: Option<CompressionType>,
+}
+
+/// A NARC archive whose files are all parsed to the same data structure.
+///
+/// The files are parsed with their [`FromBytes`] implementation, either when the NARC is read or
+/// lazily when they are first accessed (See [`TypedNarcFlags::lazy_parsing`]). If the data
+/// structure implements [`ToBytes`], the NARC can be written back, where files that were never
+/// parsed are written as-is.
+///
+/// When the NARC is read with [`NarcReaderFlags::decompress_files`], the format of each
+/// decompressed file is remembered, and the file is compressed back to it when written.
+///
+/// ## Reading and editing a NARC
+///
+/// ```no_run
+/// use sinjoh_nds::bytes::{FromBytes, ToBytes};
+/// use sinjoh_nds::narc::typed::{TypedNarc, TypedNarcFlags};
+/// use sinjoh_nds::narc::writer::NarcWriterFlags;
+///
+/// # #[derive(Debug)]
+/// # struct Level(Vec<u8>);
+/// # impl FromBytes for Level {
+/// #     type Error = std::convert::Infallible;
+/// #     fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> { Ok(Self(bytes.to_vec())) }
+/// # }
+/// # impl ToBytes for Level {
+/// #     type Error = std::convert::Infallible;
+/// #     fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> { Ok(self.0.clone()) }
+/// # }
+/// let mut narc =
+///     TypedNarc::<Level>::read_from_file("/path/to/levels.narc", TypedNarcFlags::default())?;
+///
+/// narc.get_mut(0)?.0.push(0xFF);
+///
+/// narc.write_to_file("/path/to/levels.narc", NarcWriterFlags::default())?;
+/// # Ok::<(), sinjoh_nds::narc::typed::TypedNarcError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypedNarc<T> {
+    /// The files of the NARC.
+    files: Vec<TypedNarcFile<T>>,
+
+    /// The file name table of the NARC.
+    file_name_table: FileNameTable,
+}
+
+impl<T: FromBytes> TypedNarc<T> {
+    /// Reads a typed NARC from a file on the file system.
+    pub fn read_from_file<P: AsRef<Path>>(
+        path: P,
+        flags: TypedNarcFlags,
+    ) -> Result<Self, TypedNarcError> {
+        let mut narc_reader =
+            NarcReader::<BufReader<File>>::read_from_file(path, flags.narc_reader_flags)
+                .map_err(TypedNarcError::NarcReadError)?;
+
+        Self::from_narc_reader(&mut narc_reader, flags.lazy_parsing)
+    }
+
+    /// Reads a typed NARC from a byte slice.
+    pub fn from_bytes(bytes: &[u8], flags: TypedNarcFlags) -> Result<Self, TypedNarcError> {
+        let mut narc_reader = NarcReader::from_bytes(bytes, flags.narc_reader_flags)
+            .map_err(TypedNarcError::NarcReadError)?;
+
+        Self::from_narc_reader(&mut narc_reader, flags.lazy_parsing)
+    }
+
+    /// Reads a typed NARC from a NARC reader.
+    ///
+    /// If `lazy_parsing` is `true`, the files are only parsed when they are first accessed.
+    pub fn from_narc_reader<R: Read + Seek>(
+        narc_reader: &mut NarcReader<R>,
+        lazy_parsing: bool,
+    ) -> Result<Self, TypedNarcError> {
+        let file_name_table = narc_reader.file_name_table().cloned().unwrap_or_default();
+
+        let files = (0..narc_reader.number_of_files())
+            .map(|index| {
+                let (raw, compression_type) = narc_reader
+                    .get_file_with_compression_type(index)
+                    .map_err(TypedNarcError::NarcReadError)?;
+
+                if lazy_parsing {
+                    return Ok(TypedNarcFile {
+                        raw,
+                        parsed: OnceCell::new(),
+                        compression_type,
+                    });
+                }
+
+                Ok(TypedNarcFile {
+                    raw: Vec::new(),
+                    parsed: OnceCell::from(parse_file::<T>(index as usize, &raw)?),
+                    compression_type,
+                })
+            })
+            .collect::<Result<_, TypedNarcError>>()?;
+
+        Ok(Self {
+            files,
+            file_name_table,
+        })
+    }
+
+    /// Returns the parsed file at the specified index, parsing it if needed.
+    pub fn get(&self, index: usize) -> Result<&T, TypedNarcError> {
+        let file = self
+            .files
+            .get(index)
+            .ok_or(TypedNarcError::FileIndexTooBig(index, self.files.len()))?;
+
+        if let Some(parsed) = file.parsed.get() {
+            return Ok(parsed);
+        }
+
+        let parsed = parse_file(index, &file.raw)?;
+        Ok(file.parsed.get_or_init(|| parsed))
+    }
+
+    /// Returns the parsed file at the specified index as a mutable reference, parsing it if
+    /// needed.
+    pub fn get_mut(&mut self, index: usize) -> Result<&mut T, TypedNarcError> {
+        self.get(index)?;
+
+        // Safety: the file exists and was parsed above
+        Ok(self.files[index].parsed.get_mut().unwrap())
+    }
+
+    /// Returns an iterator over the parsed files, parsing them if needed.
+    pub fn iter(&self) -> impl Iterator<Item = Result<&T, TypedNarcError>> {
+        (0..self.files.len()).map(|index| self.get(index))
+    }
+
+    /// Parses every file that was not parsed yet, and returns them in order.
+    pub fn into_vec(self) -> Result<Vec<T>, TypedNarcError> {
+        self.files
+            .into_iter()
+            .enumerate()
+            .map(|(index, file)| match file.parsed.into_inner() {
+                Some(parsed) => Ok(parsed),
+                None => parse_file(index, &file.raw),
+            })
+            .collect()
+    }
+}
+
+impl<T> TypedNarc<T> {
+    /// Creates a typed NARC from an ordered list of files.
+    pub fn from_files(files: Vec<T>) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|file| TypedNarcFile {
+                    raw: Vec::new(),
+                    parsed: OnceCell::from(file),
+                    compression_type: None,
+                })
+                .collect(),
+            file_name_table: FileNameTable::default(),
+        }
+    }
+
+    /// Returns the number of files in the NARC.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns whether the NARC has no file.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Appends a file at the end of the NARC.
+    ///
+    /// Returns the index of the file in the NARC.
+    pub fn push(&mut self, file: T) -> usize {
+        self.files.push(TypedNarcFile {
+            raw: Vec::new(),
+            parsed: OnceCell::from(file),
+            compression_type: None,
+        });
+
+        self.files.len() - 1
+    }
+
+    /// Returns the file name table of the NARC.
+    pub fn file_name_table(&self) -> &FileNameTable {
+        &self.file_name_table
+    }
+}
+
+impl<T: ToBytes> TypedNarc<T> {
+    /// Serializes the files, and returns a NARC writer holding them.
+    ///
+    /// Files that were never parsed are kept as-is, and files that were decompressed when read are
+    /// compressed back to their original format.
+    pub fn to_narc_writer(&self, flags: NarcWriterFlags) -> Result<NarcWriter, TypedNarcError> {
+        let files = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let bytes = match file.parsed.get() {
+                    Some(parsed) => parsed
+                        .to_bytes()
+                        .map_err(|e| TypedNarcError::SerializeError(index, Box::new(e)))?,
+                    None => file.raw.clone(),
+                };
+
+                match file.compression_type {
+                    Some(compression_type) => compression::compress(&bytes, compression_type)
+                        .map_err(|e| TypedNarcError::CompressionError(index, e)),
+                    None => Ok(bytes),
+                }
+            })
+            .collect::<Result<_, TypedNarcError>>()?;
+
+        let mut narc_writer = NarcWriter::from_files(files, flags);
+        narc_writer.set_file_name_table(self.file_name_table.clone());
+
+        Ok(narc_writer)
+    }
+
+    /// Writes the NARC to the given file.
+    ///
+    /// The file will be created if it does not exist, and truncated if it does.
+    pub fn write_to_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        flags: NarcWriterFlags,
+    ) -> Result<(), TypedNarcError> {
+        self.to_narc_writer(flags)?
+            .write_to_file(path)
+            .map_err(TypedNarcError::NarcWriteError)
+    }
+
+    /// Writes the NARC to a byte vector.
+    pub fn to_bytes(&self, flags: NarcWriterFlags) -> Result<Vec<u8>, TypedNarcError> {
+        self.to_narc_writer(flags)?
+            .to_bytes()
+            .map_err(TypedNarcError::NarcWriteError)
+    }
+}
+
+/// Parses a file of a typed NARC.
+fn parse_file<T: FromBytes>(index: usize, raw: &[u8]) -> Result<T, TypedNarcError> {
+    T::from_bytes(raw).map_err(|e| TypedNarcError::ParseError(index, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct RawFile(Vec<u8>);
+
+    impl FromBytes for RawFile {
+        type Error = Infallible;
+
+        fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+            Ok(Self(bytes.to_vec()))
+        }
+    }
+
+    impl ToBytes for RawFile {
+        type Error = Infallible;
+
+        fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn decompressing_flags(lazy_parsing: bool) -> TypedNarcFlags {
+        TypedNarcFlags {
+            lazy_parsing,
+            narc_reader_flags: NarcReaderFlags {
+                decompress_files: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn compressed_file_round_trip() {
+        let decompressed = b"sinjoh".repeat(16);
+        let uncompressed = vec![0x00, 0x01, 0x02, 0x03, 0x04, 0x05];
+        let compressed = compression::compress(&decompressed, CompressionType::Lz10).unwrap();
+
+        let narc_bytes = NarcWriter::from_files(
+            vec![compressed, uncompressed.clone()],
+            NarcWriterFlags::default(),
+        )
+        .to_bytes()
+        .unwrap();
+
+        for lazy_parsing in [false, true] {
+            let narc =
+                TypedNarc::<RawFile>::from_bytes(&narc_bytes, decompressing_flags(lazy_parsing))
+                    .unwrap();
+
+            assert_eq!(narc.get(0).unwrap().0, decompressed);
+            assert_eq!(narc.get(1).unwrap().0, uncompressed);
+            assert_eq!(
+                narc.to_bytes(NarcWriterFlags::default()).unwrap(),
+                narc_bytes
+            );
+        }
+
+        // Edited files are compressed back as well
+        let mut narc =
+            TypedNarc::<RawFile>::from_bytes(&narc_bytes, decompressing_flags(false)).unwrap();
+        narc.get_mut(0).unwrap().0.extend_from_slice(b"platinum");
+
+        let mut narc_reader = NarcReader::from_bytes(
+            narc.to_bytes(NarcWriterFlags::default()).unwrap(),
+            NarcReaderFlags::default(),
+        )
+        .unwrap();
+
+        let file = narc_reader.get_file(0).unwrap();
+        assert_eq!(
+            CompressionType::detect_checked(&file),
+            Some(CompressionType::Lz10)
+        );
+        assert_eq!(
+            compression::decompress(&file).unwrap(),
+            [decompressed.as_slice(), b"platinum"].concat()
+        );
+        assert_eq!(narc_reader.get_file(1).unwrap(), uncompressed);
+    }
+}
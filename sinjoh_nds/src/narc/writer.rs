@@ -0,0 +1,397 @@
+//! NARC file writer.
+//!
+//! For more information, see [`NarcWriter`].
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::fnt::{FileNameTable, FileNameTableError};
+
+use super::reader::{FATB_MAGIC, FIMG_MAGIC, FNTB_MAGIC, NARC_MAGIC};
+
+/// The version written in the header of NARC files.
+pub const NARC_VERSION: u16 = 0x0100;
+
+/// The size of the NARC header, without its chunks.
+pub const NARC_HEADER_SIZE: u16 = 0x10;
+
+/// The number of chunks written in a NARC file.
+pub const NARC_CHUNKS_COUNT: u16 = 3;
+
+/// The size of a chunk header (magic number and chunk size).
+pub const NARC_CHUNK_HEADER_SIZE: u32 = 8;
+
+/// The size of a File Allocation Table entry.
+pub const NARC_FAT_ENTRY_SIZE: u32 = 8;
+
+/// The alignment of the files contained in the File Image Block, and of the chunks.
+pub const NARC_FILE_ALIGNMENT: u32 = 4;
+
+/// Error type for NARC file writing.
+#[derive(Error, Debug)]
+pub enum NarcWriterError {
+    /// An I/O error has occurred while trying to create the NARC file.
+    #[error("unable to create the NARC file ({0})")]
+    FileCreateError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to write to the NARC file.
+    #[error("failed to write the NARC file ({0})")]
+    FileWriteError(#[source] io::Error),
+
+    /// There are more files than what a NARC can hold.
+    #[error("too many files to fit in a NARC (got {0}, maximum is {max})", max = u16::MAX)]
+    TooManyFiles(usize),
+
+    /// The files are too large to fit in a NARC.
+    #[error("the files are too large to fit in a NARC (total size is {0})")]
+    ArchiveTooLarge(u64),
+
+    /// The file name table could not be serialized.
+    #[error("unable to write the File Name Table Block")]
+    FntbWriteError(#[source] FileNameTableError),
+}
+
+/// Flags that can be used to configure the behavior of the NARC writer.
+#[derive(Debug)]
+pub struct NarcWriterFlags {
+    /// The byte used to pad the file name table and the files in the File Image Block to their
+    /// alignment.
+    pub padding_byte: u8,
+}
+
+impl Default for NarcWriterFlags {
+    fn default() -> Self {
+        Self { padding_byte: 0xFF }
+    }
+}
+
+/// A writer for NARC files.
+///
+/// This writer takes an ordered list of files, and packs them in a NARC archive. The index of a
+/// file in the list is the index it will have in the archive.
+///
+/// By default, the files are not named: the File Name Table Block that is written only contains
+/// the root directory, which is what the game expects for most of its archives. A
+/// [`FileNameTable`] can be provided to name them instead (See
+/// [`NarcWriter::set_file_name_table`]).
+///
+/// ## Writing a NARC to the file system
+///
+/// Here's an example on how to use it:
+///
+/// ```no_run
+/// use sinjoh_nds::narc::writer::{NarcWriter, NarcWriterFlags};
+///
+/// let mut narc_writer = NarcWriter::new(NarcWriterFlags::default());
+/// narc_writer.add_file(vec![0x01, 0x02, 0x03]);
+/// narc_writer.add_file(vec![0x04, 0x05]);
+///
+/// narc_writer.write_to_file("/path/to/archive.narc")?;
+/// # Ok::<(), sinjoh_nds::narc::writer::NarcWriterError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct NarcWriter {
+    /// The files to pack in the NARC.
+    files: Vec<Vec<u8>>,
+
+    /// The file name table to write in the NARC.
+    file_name_table: FileNameTable,
+
+    /// The flags configuring the behavior of the writer.
+    flags: NarcWriterFlags,
+}
+
+impl NarcWriter {
+    /// Creates a new NARC writer, without any file.
+    ///
+    /// Flags can be used to configure the behavior of the writer.
+    pub fn new(flags: NarcWriterFlags) -> Self {
+        Self {
+            files: Vec::new(),
+            file_name_table: FileNameTable::default(),
+            flags,
+        }
+    }
+
+    /// Creates a new NARC writer from an ordered list of files.
+    ///
+    /// Flags can be used to configure the behavior of the writer.
+    pub fn from_files(files: Vec<Vec<u8>>, flags: NarcWriterFlags) -> Self {
+        Self {
+            files,
+            file_name_table: FileNameTable::default(),
+            flags,
+        }
+    }
+
+    /// Appends a file at the end of the NARC.
+    ///
+    /// Returns the index of the file in the NARC.
+    pub fn add_file(&mut self, file: Vec<u8>) -> usize {
+        self.files.push(file);
+        self.files.len() - 1
+    }
+
+    /// Returns the files that will be packed in the NARC.
+    pub fn files(&self) -> &[Vec<u8>] {
+        &self.files
+    }
+
+    /// Returns the files that will be packed in the NARC, as a mutable reference.
+    ///
+    /// This can be used to replace, insert or remove files before writing the NARC.
+    pub fn files_mut(&mut self) -> &mut Vec<Vec<u8>> {
+        &mut self.files
+    }
+
+    /// Returns the file name table that will be written in the NARC.
+    pub fn file_name_table(&self) -> &FileNameTable {
+        &self.file_name_table
+    }
+
+    /// Sets the file name table that will be written in the NARC.
+    ///
+    /// The file IDs referenced by the table are expected to be indices in the list of files of
+    /// this writer.
+    pub fn set_file_name_table(&mut self, file_name_table: FileNameTable) {
+        self.file_name_table = file_name_table;
+    }
+
+    /// Writes the NARC to the given file.
+    ///
+    /// The file will be created if it does not exist, and truncated if it does.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), NarcWriterError> {
+        let file = File::create(path).map_err(NarcWriterError::FileCreateError)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write_to(&mut writer)?;
+
+        writer.flush().map_err(NarcWriterError::FileWriteError)
+    }
+
+    /// Writes the NARC to a byte vector.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, NarcWriterError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes the NARC to the given writer.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), NarcWriterError> {
+        let number_of_files: u16 = self
+            .files
+            .len()
+            .try_into()
+            .map_err(|_| NarcWriterError::TooManyFiles(self.files.len()))?;
+
+        // Compute where each file will be located in the File Image Block
+        let mut file_addresses = Vec::with_capacity(self.files.len());
+        let mut fimg_data_size: u64 = 0;
+
+        for file in self.files.iter() {
+            let start_address = fimg_data_size;
+            let end_address = start_address + file.len() as u64;
+
+            file_addresses.push((start_address, end_address));
+            fimg_data_size = end_address.next_multiple_of(NARC_FILE_ALIGNMENT.into());
+        }
+
+        // Serialize the file name table
+        let mut raw_file_name_table = self
+            .file_name_table
+            .to_bytes()
+            .map_err(NarcWriterError::FntbWriteError)?;
+
+        let raw_file_name_table_size = raw_file_name_table.len() as u64;
+        raw_file_name_table.resize(
+            raw_file_name_table_size.next_multiple_of(NARC_FILE_ALIGNMENT.into()) as usize,
+            self.flags.padding_byte,
+        );
+
+        // Compute the chunk sizes
+        let fatb_size = NARC_CHUNK_HEADER_SIZE + 4 + NARC_FAT_ENTRY_SIZE * number_of_files as u32;
+        let fntb_size = NARC_CHUNK_HEADER_SIZE as u64 + raw_file_name_table.len() as u64;
+        let fimg_size = NARC_CHUNK_HEADER_SIZE as u64 + fimg_data_size;
+
+        let file_size = NARC_HEADER_SIZE as u64 + fatb_size as u64 + fntb_size + fimg_size;
+        let file_size: u32 = file_size
+            .try_into()
+            .map_err(|_| NarcWriterError::ArchiveTooLarge(file_size))?;
+
+        // Write the NARC header
+        let mut header = Vec::with_capacity(NARC_HEADER_SIZE.into());
+        header.extend_from_slice(&NARC_MAGIC.to_le_bytes());
+        header.extend_from_slice(&[0xFF, 0xFE]);
+        header.extend_from_slice(&NARC_VERSION.to_le_bytes());
+        header.extend_from_slice(&file_size.to_le_bytes());
+        header.extend_from_slice(&NARC_HEADER_SIZE.to_le_bytes());
+        header.extend_from_slice(&NARC_CHUNKS_COUNT.to_le_bytes());
+
+        writer
+            .write_all(&header)
+            .map_err(NarcWriterError::FileWriteError)?;
+
+        // Write the File Allocation Table Block
+        let mut fatb = Vec::with_capacity(fatb_size as usize);
+        fatb.extend_from_slice(&FATB_MAGIC.to_le_bytes());
+        fatb.extend_from_slice(&fatb_size.to_le_bytes());
+        fatb.extend_from_slice(&number_of_files.to_le_bytes());
+        fatb.extend_from_slice(&[0x00, 0x00]);
+
+        for (start_address, end_address) in file_addresses.iter() {
+            // Safety: addresses are smaller than the file size, which fits in a `u32`
+            fatb.extend_from_slice(&(*start_address as u32).to_le_bytes());
+            fatb.extend_from_slice(&(*end_address as u32).to_le_bytes());
+        }
+
+        writer
+            .write_all(&fatb)
+            .map_err(NarcWriterError::FileWriteError)?;
+
+        // Write the File Name Table Block
+        let mut fntb = Vec::with_capacity(fntb_size as usize);
+        fntb.extend_from_slice(&FNTB_MAGIC.to_le_bytes());
+        // Safety: the File Name Table Block is smaller than the file size, which fits in a `u32`
+        fntb.extend_from_slice(&(fntb_size as u32).to_le_bytes());
+        fntb.extend_from_slice(&raw_file_name_table);
+
+        writer
+            .write_all(&fntb)
+            .map_err(NarcWriterError::FileWriteError)?;
+
+        // Write the File Image Block
+        let mut fimg_header = Vec::with_capacity(NARC_CHUNK_HEADER_SIZE as usize);
+        fimg_header.extend_from_slice(&FIMG_MAGIC.to_le_bytes());
+        // Safety: the File Image Block is smaller than the file size, which fits in a `u32`
+        fimg_header.extend_from_slice(&(fimg_size as u32).to_le_bytes());
+
+        writer
+            .write_all(&fimg_header)
+            .map_err(NarcWriterError::FileWriteError)?;
+
+        for (file, (_, end_address)) in self.files.iter().zip(file_addresses.iter()) {
+            writer
+                .write_all(file)
+                .map_err(NarcWriterError::FileWriteError)?;
+
+            let padding_size =
+                end_address.next_multiple_of(NARC_FILE_ALIGNMENT.into()) - end_address;
+            let padding = vec![self.flags.padding_byte; padding_size as usize];
+
+            writer
+                .write_all(&padding)
+                .map_err(NarcWriterError::FileWriteError)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fnt::{FileNameTableDirectory, FileNameTableEntry, ROOT_DIRECTORY_ID};
+
+    use super::super::reader::{NarcReader, NarcReaderFlags};
+    use super::*;
+
+    /// Returns files of varied lengths, including empty ones and ones whose length is not a
+    /// multiple of the file alignment.
+    fn files() -> Vec<Vec<u8>> {
+        [0, 1, 3, 4, 5, 17, 0, 8]
+            .iter()
+            .enumerate()
+            .map(|(index, &length)| (0..length).map(|i| (index * 0x10 + i) as u8).collect())
+            .collect()
+    }
+
+    fn named_file_name_table() -> FileNameTable {
+        FileNameTable {
+            root: FileNameTableDirectory {
+                id: ROOT_DIRECTORY_ID,
+                name: String::new(),
+                first_file_id: 0,
+                entries: vec![
+                    FileNameTableEntry::File {
+                        name: "a.bin".to_string(),
+                        file_id: 0,
+                    },
+                    FileNameTableEntry::Directory(FileNameTableDirectory {
+                        id: ROOT_DIRECTORY_ID + 1,
+                        name: "dir".to_string(),
+                        first_file_id: 1,
+                        entries: vec![
+                            FileNameTableEntry::File {
+                                name: "b.bin".to_string(),
+                                file_id: 1,
+                            },
+                            FileNameTableEntry::File {
+                                name: "c.bin".to_string(),
+                                file_id: 2,
+                            },
+                        ],
+                    }),
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn write_and_read_files() {
+        let files = files();
+        let bytes = NarcWriter::from_files(files.clone(), NarcWriterFlags::default())
+            .to_bytes()
+            .unwrap();
+
+        let mut narc_reader = NarcReader::from_bytes(&bytes, NarcReaderFlags::default()).unwrap();
+        let narc_header = narc_reader.narc_header();
+
+        assert_eq!(narc_header.file_size as usize, bytes.len());
+        assert_eq!(narc_header.number_of_chunks, NARC_CHUNKS_COUNT);
+        assert!(
+            narc_header
+                .fat
+                .as_ref()
+                .unwrap()
+                .files
+                .iter()
+                .all(|entry| entry.start_address % NARC_FILE_ALIGNMENT == 0)
+        );
+        assert!(!narc_reader.file_name_table().unwrap().has_names());
+
+        assert_eq!(narc_reader.number_of_files() as usize, files.len());
+        let read_files = narc_reader
+            .files_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read_files, files);
+    }
+
+    #[test]
+    fn repack_named_archive() {
+        let mut narc_writer =
+            NarcWriter::from_files(files()[..3].to_vec(), NarcWriterFlags::default());
+        narc_writer.set_file_name_table(named_file_name_table());
+        let bytes = narc_writer.to_bytes().unwrap();
+
+        let mut narc_reader = NarcReader::from_bytes(&bytes, NarcReaderFlags::default()).unwrap();
+        assert_eq!(
+            narc_reader.get_file_by_path("dir/c.bin").unwrap(),
+            files()[2]
+        );
+
+        let file_name_table = narc_reader.file_name_table().unwrap().clone();
+        let read_files = narc_reader
+            .files_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let mut narc_writer = NarcWriter::from_files(read_files, NarcWriterFlags::default());
+        narc_writer.set_file_name_table(file_name_table);
+
+        assert_eq!(narc_writer.to_bytes().unwrap(), bytes);
+    }
+}
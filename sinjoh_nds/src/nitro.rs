@@ -0,0 +1,224 @@
+//! Utils for working with the common structure of Nitro files.
+//!
+//! Most of the files made with the Nintendo DS SDK (models, textures, palettes, sound data...)
+//! share a common structure: a header, holding a magic number, a BOM, a version, the size of the
+//! file and the number of blocks, followed by the blocks themselves. Each block starts with its
+//! own magic number and size.
+//!
+//! Depending on the kind of file, the blocks are either located using a table of offsets
+//! following the header (See [`NitroBlockLayout::OffsetTable`]), directly follow each other
+//! (See [`NitroBlockLayout::Sequential`]), or are located using a table inside of the header (See
+//! [`NitroBlockLayout::HeaderTable`]).
+
+use thiserror::Error;
+
+use crate::{DsFixed16, DsFixed32};
+
+/// The BOM of Nitro files, which indicates a little-endian file.
+pub const NITRO_BOM: u16 = 0xFEFF;
+
+/// The size of the header of a Nitro file.
+pub const NITRO_HEADER_SIZE: usize = 0x10;
+
+/// The size of the header of a block (magic number and block size).
+pub const NITRO_BLOCK_HEADER_SIZE: usize = 8;
+
+/// Error type for Nitro file parsing.
+#[derive(Error, Debug)]
+pub enum NitroError {
+    /// The data ended before a structure could be fully read.
+    #[error("the data is truncated (tried to read at offset 0x{0:X})")]
+    TruncatedData(usize),
+
+    /// The magic number of the file is not the expected one.
+    #[error(
+        "wrong magic number (expected {:?}, found {:?})",
+        String::from_utf8_lossy(.0),
+        String::from_utf8_lossy(.1)
+    )]
+    WrongMagic([u8; 4], [u8; 4]),
+
+    /// The BOM of the file is not the one of a little-endian file.
+    #[error("unsupported BOM (found 0x{0:04X})")]
+    UnsupportedBom(u16),
+
+    /// A block of the file is not located inside of the file.
+    #[error("the block at offset 0x{0:X} is outside of the file")]
+    InvalidBlock(usize),
+}
+
+/// How the blocks of a Nitro file are located.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NitroBlockLayout {
+    /// The header is followed by a table of offsets to each block.
+    ///
+    /// This is used by 3D files (models, textures and animations).
+    OffsetTable,
+
+    /// The blocks directly follow the header, one after the other.
+    ///
+    /// This is used by 2D files (palettes, graphics, screens...) and sound files.
+    Sequential,
+
+    /// The header holds the offset and size of each block, and blocks with an offset of 0 are
+    /// absent.
+    ///
+    /// This is used by sound archives.
+    HeaderTable,
+}
+
+/// Represents the header of a Nitro file.
+#[derive(Debug, Clone)]
+pub struct NitroFileHeader {
+    /// Magic number of the file, as stored in the file.
+    pub magic: [u8; 4],
+
+    /// Version of the file format.
+    pub version: u16,
+
+    /// Size of the whole file.
+    pub file_size: u32,
+
+    /// Size of the header.
+    pub header_size: u16,
+
+    /// Number of blocks in the file.
+    pub number_of_blocks: u16,
+}
+
+/// Represents a block of a Nitro file.
+#[derive(Debug, Clone, Copy)]
+pub struct NitroBlock<'a> {
+    /// Magic number of the block, as stored in the file.
+    pub magic: [u8; 4],
+
+    /// Offset of the block in the file.
+    pub offset: usize,
+
+    /// Data of the block, including its header.
+    pub data: &'a [u8],
+}
+
+/// A parsed Nitro file, referencing the data it was parsed from.
+#[derive(Debug, Clone)]
+pub struct NitroFile<'a> {
+    /// The header of the file.
+    pub header: NitroFileHeader,
+
+    /// The blocks of the file.
+    pub blocks: Vec<NitroBlock<'a>>,
+}
+
+impl<'a> NitroFile<'a> {
+    /// Parses the header and locates the blocks of a Nitro file.
+    ///
+    /// The magic number of the file is checked against the expected one.
+    pub fn parse_bytes(
+        bytes: &'a [u8],
+        expected_magic: [u8; 4],
+        layout: NitroBlockLayout,
+    ) -> Result<Self, NitroError> {
+        // Read the header
+        let magic = read_magic(bytes, 0x00).ok_or(NitroError::TruncatedData(0x00))?;
+        if magic != expected_magic {
+            return Err(NitroError::WrongMagic(expected_magic, magic));
+        }
+
+        let bom = read_u16(bytes, 0x04).ok_or(NitroError::TruncatedData(0x04))?;
+        if bom != NITRO_BOM {
+            return Err(NitroError::UnsupportedBom(bom));
+        }
+
+        let header = NitroFileHeader {
+            magic,
+            version: read_u16(bytes, 0x06).ok_or(NitroError::TruncatedData(0x06))?,
+            file_size: read_u32(bytes, 0x08).ok_or(NitroError::TruncatedData(0x08))?,
+            header_size: read_u16(bytes, 0x0C).ok_or(NitroError::TruncatedData(0x0C))?,
+            number_of_blocks: read_u16(bytes, 0x0E).ok_or(NitroError::TruncatedData(0x0E))?,
+        };
+
+        // Locate the blocks
+        let mut blocks = Vec::with_capacity(header.number_of_blocks.into());
+        let mut next_block_offset = header.header_size as usize;
+
+        for i in 0..header.number_of_blocks as usize {
+            let offset = match layout {
+                NitroBlockLayout::OffsetTable => {
+                    let table_offset = header.header_size as usize + i * 4;
+                    read_u32(bytes, table_offset).ok_or(NitroError::TruncatedData(table_offset))?
+                        as usize
+                }
+                NitroBlockLayout::Sequential => next_block_offset,
+                NitroBlockLayout::HeaderTable => {
+                    let table_offset = NITRO_HEADER_SIZE + i * 8;
+                    read_u32(bytes, table_offset).ok_or(NitroError::TruncatedData(table_offset))?
+                        as usize
+                }
+            };
+
+            if layout == NitroBlockLayout::HeaderTable && offset == 0 {
+                continue;
+            }
+
+            let block_magic = read_magic(bytes, offset).ok_or(NitroError::InvalidBlock(offset))?;
+            let block_size =
+                read_u32(bytes, offset + 4).ok_or(NitroError::InvalidBlock(offset))? as usize;
+            let data = bytes
+                .get(offset..offset + block_size)
+                .ok_or(NitroError::InvalidBlock(offset))?;
+
+            blocks.push(NitroBlock {
+                magic: block_magic,
+                offset,
+                data,
+            });
+
+            next_block_offset = offset + block_size;
+        }
+
+        Ok(Self { header, blocks })
+    }
+
+    /// Returns the first block with the specified magic number, if any.
+    pub fn block(&self, magic: [u8; 4]) -> Option<&NitroBlock<'a>> {
+        self.blocks.iter().find(|block| block.magic == magic)
+    }
+}
+
+/// Reads a magic number at the specified offset.
+pub(crate) fn read_magic(bytes: &[u8], offset: usize) -> Option<[u8; 4]> {
+    // Safety: slice length is explicitly specified
+    Some(bytes.get(offset..offset + 4)?.try_into().unwrap())
+}
+
+/// Reads a `u16` at the specified offset.
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    // Safety: slice length is explicitly specified
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads a `u32` at the specified offset.
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    // Safety: slice length is explicitly specified
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads a [`DsFixed16`] at the specified offset.
+pub(crate) fn read_fx16(bytes: &[u8], offset: usize) -> Option<DsFixed16> {
+    // Safety: slice length is explicitly specified
+    Some(DsFixed16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads a [`DsFixed32`] at the specified offset.
+pub(crate) fn read_fx32(bytes: &[u8], offset: usize) -> Option<DsFixed32> {
+    // Safety: slice length is explicitly specified
+    Some(DsFixed32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
@@ -0,0 +1,304 @@
+//! Utils for working with the ARM9 binary.
+//!
+//! The ARM9 binary contains a structure called the module params, which holds information about
+//! the binary, such as where its compressed part ends. In retail ROMs, the ARM9 binary is usually
+//! compressed with BLZ (See [`blz`](crate::compression::blz)), except for its beginning.
+//!
+//! The module params are located by searching for the two magic numbers (`0xDEC00621` and
+//! `0x2106C0DE`) that end the structure.
+
+use thiserror::Error;
+
+use crate::compression::blz::{self, BlzError};
+
+/// The size of the module params.
+pub const MODULE_PARAMS_SIZE: usize = 0x24;
+
+/// The first magic number ending the module params.
+pub const MODULE_PARAMS_NITROCODE_BE: u32 = 0xDEC00621;
+
+/// The second magic number ending the module params.
+pub const MODULE_PARAMS_NITROCODE_LE: u32 = 0x2106C0DE;
+
+/// The size of the beginning of the ARM9 binary that is left uncompressed.
+pub const ARM9_UNCOMPRESSED_PREFIX_SIZE: usize = 0x4000;
+
+/// Offset of the compressed static end address in the module params.
+const MODULE_PARAMS_COMPRESSED_STATIC_END_OFFSET: usize = 0x14;
+
+/// Error type for ARM9 binary manipulation.
+#[derive(Error, Debug)]
+pub enum Arm9Error {
+    /// The module params could not be found in the ARM9 binary.
+    #[error("the module params could not be found in the ARM9 binary")]
+    ModuleParamsNotFound,
+
+    /// The compressed static end address does not point inside of the ARM9 binary.
+    #[error("the compressed static end address (0x{0:08X}) is outside of the ARM9 binary")]
+    InvalidCompressedStaticEnd(u32),
+
+    /// An error has occurred while compressing or decompressing the ARM9 binary.
+    #[error("BLZ compression error")]
+    BlzError(#[source] BlzError),
+}
+
+/// Represents the module params of the ARM9 binary.
+#[derive(Debug, Clone, Copy)]
+pub struct ModuleParams {
+    /// Start address of the autoload list.
+    pub autoload_list_start: u32,
+
+    /// End address of the autoload list.
+    pub autoload_list_end: u32,
+
+    /// Start address of the autoload blocks.
+    pub autoload_start: u32,
+
+    /// Start address of the static BSS section.
+    pub static_bss_start: u32,
+
+    /// End address of the static BSS section.
+    pub static_bss_end: u32,
+
+    /// End address of the compressed part of the binary, or 0 if it is not compressed.
+    pub compressed_static_end: u32,
+
+    /// Version of the SDK the binary was built with.
+    pub sdk_version: u32,
+}
+
+impl ModuleParams {
+    /// Parses a [`ModuleParams`] from a byte array.
+    ///
+    /// It is expected that the array is in the same format as the one found in the ARM9 binary.
+    pub fn from_bytes(bytes: &[u8; MODULE_PARAMS_SIZE]) -> Self {
+        // Safety: slice length is explicitly specified, and the length of the `bytes` array is
+        // known
+        Self {
+            autoload_list_start: u32::from_le_bytes(bytes[0x00..=0x03].try_into().unwrap()),
+            autoload_list_end: u32::from_le_bytes(bytes[0x04..=0x07].try_into().unwrap()),
+            autoload_start: u32::from_le_bytes(bytes[0x08..=0x0B].try_into().unwrap()),
+            static_bss_start: u32::from_le_bytes(bytes[0x0C..=0x0F].try_into().unwrap()),
+            static_bss_end: u32::from_le_bytes(bytes[0x10..=0x13].try_into().unwrap()),
+            compressed_static_end: u32::from_le_bytes(bytes[0x14..=0x17].try_into().unwrap()),
+            sdk_version: u32::from_le_bytes(bytes[0x18..=0x1B].try_into().unwrap()),
+        }
+    }
+
+    /// Finds the module params in the given ARM9 binary.
+    ///
+    /// Returns the offset of the module params in the binary, along with the parsed module
+    /// params.
+    pub fn find(arm9: &[u8]) -> Option<(usize, Self)> {
+        let mut nitrocode = [0u8; 8];
+        nitrocode[0..=3].copy_from_slice(&MODULE_PARAMS_NITROCODE_BE.to_le_bytes());
+        nitrocode[4..=7].copy_from_slice(&MODULE_PARAMS_NITROCODE_LE.to_le_bytes());
+
+        let offset = arm9
+            .windows(nitrocode.len())
+            .position(|window| window == nitrocode)?
+            .checked_sub(MODULE_PARAMS_SIZE - nitrocode.len())?;
+
+        // Safety: slice length is explicitly specified, and the module params were found in the
+        // binary
+        let module_params = Self::from_bytes(
+            arm9[offset..offset + MODULE_PARAMS_SIZE]
+                .try_into()
+                .unwrap(),
+        );
+
+        Some((offset, module_params))
+    }
+}
+
+/// Decompresses the ARM9 binary, if it is compressed.
+///
+/// The RAM address is the address where the binary is loaded (See
+/// [`RomBinaryInfo::ram_address`](super::RomBinaryInfo::ram_address)).
+///
+/// The compressed static end address of the module params of the returned binary is set to 0, as
+/// it is no longer compressed.
+pub fn decompress_arm9(arm9: &[u8], ram_address: u32) -> Result<Vec<u8>, Arm9Error> {
+    let (module_params_offset, module_params) =
+        ModuleParams::find(arm9).ok_or(Arm9Error::ModuleParamsNotFound)?;
+
+    if module_params.compressed_static_end == 0 {
+        return Ok(arm9.to_vec());
+    }
+
+    // Decompress the compressed part, and keep what follows it as-is
+    let compressed_end = module_params
+        .compressed_static_end
+        .checked_sub(ram_address)
+        .map(|compressed_end| compressed_end as usize)
+        .filter(|&compressed_end| compressed_end <= arm9.len())
+        .ok_or(Arm9Error::InvalidCompressedStaticEnd(
+            module_params.compressed_static_end,
+        ))?;
+
+    let mut decompressed = blz::decompress(&arm9[..compressed_end]).map_err(Arm9Error::BlzError)?;
+    decompressed.extend_from_slice(&arm9[compressed_end..]);
+
+    set_compressed_static_end(&mut decompressed, module_params_offset, 0);
+
+    Ok(decompressed)
+}
+
+/// Compresses the ARM9 binary.
+///
+/// The RAM address is the address where the binary is loaded (See
+/// [`RomBinaryInfo::ram_address`](super::RomBinaryInfo::ram_address)). The first 16 KiB of the
+/// binary are left uncompressed.
+///
+/// The compressed static end address of the module params of the returned binary is updated
+/// accordingly. If the binary is already compressed, or if compressing it would not make it
+/// smaller, it is returned as-is.
+pub fn compress_arm9(arm9: &[u8], ram_address: u32) -> Result<Vec<u8>, Arm9Error> {
+    let (module_params_offset, module_params) =
+        ModuleParams::find(arm9).ok_or(Arm9Error::ModuleParamsNotFound)?;
+
+    if module_params.compressed_static_end != 0 {
+        return Ok(arm9.to_vec());
+    }
+
+    let Some(mut compressed) = blz::compress(
+        arm9,
+        ARM9_UNCOMPRESSED_PREFIX_SIZE.max(module_params_offset + MODULE_PARAMS_SIZE),
+    )
+    .map_err(Arm9Error::BlzError)?
+    else {
+        return Ok(arm9.to_vec());
+    };
+
+    let compressed_static_end = ram_address + compressed.len() as u32;
+    set_compressed_static_end(&mut compressed, module_params_offset, compressed_static_end);
+
+    Ok(compressed)
+}
+
+/// Sets the compressed static end address in the module params located at the given offset.
+fn set_compressed_static_end(arm9: &mut [u8], module_params_offset: usize, value: u32) {
+    let offset = module_params_offset + MODULE_PARAMS_COMPRESSED_STATIC_END_OFFSET;
+    arm9[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The address where the test binary is loaded.
+    const RAM_ADDRESS: u32 = 0x0200_0000;
+
+    /// The offset of the module params in the test binary.
+    const MODULE_PARAMS_OFFSET: usize = 0x800;
+
+    /// Returns an uncompressed ARM9 binary, with module params in its uncompressed prefix.
+    fn arm9() -> Vec<u8> {
+        let mut arm9: Vec<u8> = (0..0x8000u32)
+            .map(|i| {
+                if (i as usize) < ARM9_UNCOMPRESSED_PREFIX_SIZE {
+                    (i.wrapping_mul(2_654_435_761) >> 24) as u8
+                } else {
+                    (i % 0x1F) as u8
+                }
+            })
+            .collect();
+
+        let mut module_params = Vec::with_capacity(MODULE_PARAMS_SIZE);
+        for value in [
+            0x0200_1000,
+            0x0200_1010,
+            0x0200_2000,
+            0x0200_3000,
+            0x0200_4000,
+            0,
+            0x0503_7531,
+            MODULE_PARAMS_NITROCODE_BE,
+            MODULE_PARAMS_NITROCODE_LE,
+        ] {
+            module_params.extend_from_slice(&u32::to_le_bytes(value));
+        }
+
+        arm9[MODULE_PARAMS_OFFSET..MODULE_PARAMS_OFFSET + MODULE_PARAMS_SIZE]
+            .copy_from_slice(&module_params);
+
+        arm9
+    }
+
+    #[test]
+    fn find_module_params() {
+        let (offset, module_params) = ModuleParams::find(&arm9()).unwrap();
+
+        // The structure starts 0x1C bytes before the magic numbers ending it
+        assert_eq!(offset, MODULE_PARAMS_OFFSET);
+        assert_eq!(module_params.autoload_list_start, 0x0200_1000);
+        assert_eq!(module_params.static_bss_end, 0x0200_4000);
+        assert_eq!(module_params.compressed_static_end, 0);
+        assert_eq!(module_params.sdk_version, 0x0503_7531);
+
+        // Magic numbers found too close to the beginning can't end the structure
+        let arm9 = arm9()[MODULE_PARAMS_OFFSET + 0x04..].to_vec();
+        assert!(ModuleParams::find(&arm9).is_none());
+        assert!(ModuleParams::find(&[0x00; 0x100]).is_none());
+    }
+
+    #[test]
+    fn arm9_round_trip() {
+        let arm9 = arm9();
+
+        let compressed = compress_arm9(&arm9, RAM_ADDRESS).unwrap();
+        assert!(compressed.len() < arm9.len());
+
+        // The prefix is left uncompressed, except for the compressed static end address
+        let compressed_static_end_offset =
+            MODULE_PARAMS_OFFSET + MODULE_PARAMS_COMPRESSED_STATIC_END_OFFSET;
+        assert_eq!(
+            compressed[..compressed_static_end_offset],
+            arm9[..compressed_static_end_offset]
+        );
+        assert_eq!(
+            compressed[compressed_static_end_offset + 4..ARM9_UNCOMPRESSED_PREFIX_SIZE],
+            arm9[compressed_static_end_offset + 4..ARM9_UNCOMPRESSED_PREFIX_SIZE]
+        );
+
+        let (_, module_params) = ModuleParams::find(&compressed).unwrap();
+        assert_eq!(
+            module_params.compressed_static_end,
+            RAM_ADDRESS + compressed.len() as u32
+        );
+
+        // Compressing again keeps the binary as-is
+        assert_eq!(compress_arm9(&compressed, RAM_ADDRESS).unwrap(), compressed);
+
+        // Data after the compressed static end is kept as-is
+        let mut compressed = compressed;
+        compressed.extend_from_slice(&[0xAB; 0x10]);
+
+        let decompressed = decompress_arm9(&compressed, RAM_ADDRESS).unwrap();
+        assert_eq!(decompressed, [arm9.as_slice(), &[0xAB; 0x10]].concat());
+
+        let (_, module_params) = ModuleParams::find(&decompressed).unwrap();
+        assert_eq!(module_params.compressed_static_end, 0);
+
+        // Decompressing again keeps the binary as-is
+        assert_eq!(
+            decompress_arm9(&decompressed, RAM_ADDRESS).unwrap(),
+            decompressed
+        );
+    }
+
+    #[test]
+    fn decompress_invalid_compressed_static_end() {
+        let mut arm9 = arm9();
+        set_compressed_static_end(&mut arm9, MODULE_PARAMS_OFFSET, RAM_ADDRESS + 0x10000);
+
+        assert!(matches!(
+            decompress_arm9(&arm9, RAM_ADDRESS),
+            Err(Arm9Error::InvalidCompressedStaticEnd(0x0201_0000))
+        ));
+        assert!(matches!(
+            decompress_arm9(&[0x00; 0x100], RAM_ADDRESS),
+            Err(Arm9Error::ModuleParamsNotFound)
+        ));
+    }
+}
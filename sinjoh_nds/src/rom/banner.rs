@@ -0,0 +1,224 @@
+//! ROM banner, holding the icon and titles of the software.
+//!
+//! The banner is displayed by the system menu of the Nintendo DS. It holds a 32x32 icon, made of
+//! 4x4 tiles of 8x8 pixels using a 16-color palette, followed by the title of the software in each
+//! language supported by the system. Later versions of the banner add more languages and an
+//! animated icon, which are not parsed.
+
+use thiserror::Error;
+
+use crate::DsRgb;
+use crate::crc::crc16;
+use crate::image::RgbaImage;
+
+/// The size of the first version of the banner, which every version starts with.
+pub const BANNER_SIZE: usize = 0x840;
+
+/// The width and height of the icon, in pixels.
+pub const ICON_SIZE: usize = 32;
+
+/// The number of characters of a title, including its null terminator.
+pub const TITLE_LENGTH: usize = 0x80;
+
+/// The offset of the bitmap of the icon in the banner.
+const ICON_BITMAP_OFFSET: usize = 0x20;
+
+/// The size of the bitmap of the icon.
+const ICON_BITMAP_SIZE: usize = ICON_SIZE * ICON_SIZE / 2;
+
+/// The offset of the palette of the icon in the banner.
+const ICON_PALETTE_OFFSET: usize = 0x220;
+
+/// The number of colors of the palette of the icon.
+const ICON_PALETTE_SIZE: usize = 16;
+
+/// The offset of the first title in the banner.
+const TITLES_OFFSET: usize = 0x240;
+
+/// The width and height of a tile of the icon, in pixels.
+const TILE_SIZE: usize = 8;
+
+/// Error type for banner parsing.
+#[derive(Error, Debug)]
+pub enum BannerError {
+    /// The data ended before the banner could be fully read.
+    #[error("the banner is truncated (expected at least 0x{expected:X} bytes, found 0x{found:X})")]
+    TruncatedData { expected: usize, found: usize },
+}
+
+/// A language in which the title of the software is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerLanguage {
+    /// Japanese title.
+    Japanese,
+
+    /// English title.
+    English,
+
+    /// French title.
+    French,
+
+    /// German title.
+    German,
+
+    /// Italian title.
+    Italian,
+
+    /// Spanish title.
+    Spanish,
+}
+
+impl BannerLanguage {
+    /// Every language of the first version of the banner, in the order of their titles.
+    pub const ALL: [Self; 6] = [
+        Self::Japanese,
+        Self::English,
+        Self::French,
+        Self::German,
+        Self::Italian,
+        Self::Spanish,
+    ];
+}
+
+/// Returns the size of a banner, based on its version.
+pub fn banner_size(version: u16) -> u32 {
+    match version {
+        0x0002 => 0x940,
+        0x0003 => 0xA40,
+        0x0103 => 0x23C0,
+        _ => BANNER_SIZE as u32,
+    }
+}
+
+/// Represents the banner of a ROM.
+#[derive(Debug, Clone)]
+pub struct Banner {
+    /// Version of the banner.
+    pub version: u16,
+
+    /// CRC16 of the first version of the banner, from the icon to the last title.
+    pub crc16: u16,
+
+    /// Bitmap of the icon, as 4x4 tiles of 8x8 pixels, where each pixel is a 4-bit index in the
+    /// palette of the icon.
+    pub icon_bitmap: [u8; ICON_BITMAP_SIZE],
+
+    /// Palette of the icon.
+    pub icon_palette: [DsRgb; ICON_PALETTE_SIZE],
+
+    /// Titles of the software, in the order of [`BannerLanguage::ALL`].
+    ///
+    /// Each title is made of up to 3 lines, separated by line feeds (usually the name of the
+    /// software followed by the name of its publisher).
+    pub titles: [String; 6],
+}
+
+impl Banner {
+    /// Parses a banner.
+    ///
+    /// Only the first version of the banner is parsed, which is the start of every later
+    /// version.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::rom::banner::BannerLanguage;
+    /// use sinjoh_nds::rom::reader::RomReader;
+    ///
+    /// let mut rom_reader = RomReader::read_from_file("/path/to/rom.nds")?;
+    /// let banner = rom_reader.banner()?;
+    ///
+    /// println!("{}", banner.title(BannerLanguage::English));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BannerError> {
+        if bytes.len() < BANNER_SIZE {
+            return Err(BannerError::TruncatedData {
+                expected: BANNER_SIZE,
+                found: bytes.len(),
+            });
+        }
+
+        // Safety: the length of the data is checked above, and slice lengths are explicitly
+        // specified
+        let icon_bitmap = bytes[ICON_BITMAP_OFFSET..ICON_BITMAP_OFFSET + ICON_BITMAP_SIZE]
+            .try_into()
+            .unwrap();
+
+        let icon_palette = std::array::from_fn(|i| {
+            let offset = ICON_PALETTE_OFFSET + i * 2;
+            DsRgb::from_bgr555(u16::from_le_bytes([bytes[offset], bytes[offset + 1]]))
+        });
+
+        let titles = std::array::from_fn(|i| {
+            let offset = TITLES_OFFSET + i * TITLE_LENGTH * 2;
+            let characters = bytes[offset..offset + TITLE_LENGTH * 2]
+                .chunks_exact(2)
+                .map(|character| u16::from_le_bytes([character[0], character[1]]))
+                .take_while(|&character| character != 0)
+                .collect::<Vec<_>>();
+
+            String::from_utf16_lossy(&characters)
+        });
+
+        Ok(Self {
+            version: u16::from_le_bytes([bytes[0x00], bytes[0x01]]),
+            crc16: u16::from_le_bytes([bytes[0x02], bytes[0x03]]),
+            icon_bitmap,
+            icon_palette,
+            titles,
+        })
+    }
+
+    /// Returns the title of the software in the specified language.
+    pub fn title(&self, language: BannerLanguage) -> &str {
+        // Safety: `BannerLanguage::ALL` has as many languages as there are titles
+        let index = BannerLanguage::ALL
+            .iter()
+            .position(|&other| other == language)
+            .unwrap();
+
+        &self.titles[index]
+    }
+
+    /// Returns whether the CRC16 of the banner matches the specified bytes of the banner, as
+    /// passed to [`Banner::from_bytes`].
+    pub fn is_crc16_valid(&self, bytes: &[u8]) -> bool {
+        bytes
+            .get(ICON_BITMAP_OFFSET..BANNER_SIZE)
+            .is_some_and(|bytes| crc16(bytes) == self.crc16)
+    }
+
+    /// Decodes the icon to a 32x32 RGBA image.
+    ///
+    /// The first color of the palette is transparent.
+    pub fn icon(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(ICON_SIZE, ICON_SIZE);
+        let tiles_per_row = ICON_SIZE / TILE_SIZE;
+
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                let tile_index = (y / TILE_SIZE) * tiles_per_row + x / TILE_SIZE;
+                let pixel_index = tile_index * TILE_SIZE * TILE_SIZE
+                    + (y % TILE_SIZE) * TILE_SIZE
+                    + x % TILE_SIZE;
+
+                // The low nibble holds the first pixel
+                let byte = self.icon_bitmap[pixel_index / 2];
+                let color_index = match pixel_index % 2 {
+                    0 => byte & 0x0F,
+                    _ => byte >> 4,
+                };
+
+                if color_index == 0 {
+                    continue;
+                }
+
+                let [red, green, blue] = self.icon_palette[color_index as usize].to_rgb8();
+                image.set_pixel(x, y, [red, green, blue, 0xFF]);
+            }
+        }
+
+        image
+    }
+}
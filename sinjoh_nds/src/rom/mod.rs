@@ -0,0 +1,262 @@
+//! Utils for working with Nintendo DS ROM images.
+//!
+//! A ROM image (`.nds` file) contains the header of the cartridge, the ARM9 and ARM7 binaries
+//! along with their overlays, the banner, and a file system (NitroFS) that holds the files used
+//! by the software.
+//!
+//! The file system is made of a File Name Table, which names the files (See
+//! [`FileNameTable`](crate::fnt::FileNameTable)), and a File Allocation Table, which tells where
+//! each file is located in the ROM.
+
+pub mod arm9;
+pub mod banner;
+pub mod reader;
+pub mod writer;
+
+/// The size of the part of the ROM header that is parsed.
+pub const ROM_HEADER_SIZE: usize = 0x200;
+
+/// The size of an entry in the File Allocation Table of a ROM.
+pub const ROM_FAT_ENTRY_SIZE: u32 = 8;
+
+/// The size of an entry in the overlay tables of a ROM.
+pub const ROM_OVERLAY_TABLE_ENTRY_SIZE: u32 = 32;
+
+/// Offset of the header CRC16 in the ROM header.
+pub const ROM_HEADER_CRC16_OFFSET: usize = 0x15E;
+
+/// Information about where an executable binary is stored in the ROM, and where it is loaded in
+/// memory.
+#[derive(Debug, Clone, Copy)]
+pub struct RomBinaryInfo {
+    /// Offset of the binary in the ROM.
+    pub rom_offset: u32,
+
+    /// Address of the entry point of the binary.
+    pub entry_address: u32,
+
+    /// Address where the binary is loaded in RAM.
+    pub ram_address: u32,
+
+    /// Size of the binary.
+    pub size: u32,
+}
+
+impl RomBinaryInfo {
+    /// Parses a [`RomBinaryInfo`] from a byte array.
+    ///
+    /// It is expected that the array is in the same format as the one found in the ROM header.
+    pub fn from_bytes(bytes: &[u8; 16]) -> Self {
+        // Safety: slice length is explicitly specified, and the length of the `bytes` array is
+        // known
+        Self {
+            rom_offset: u32::from_le_bytes(bytes[0..=3].try_into().unwrap()),
+            entry_address: u32::from_le_bytes(bytes[4..=7].try_into().unwrap()),
+            ram_address: u32::from_le_bytes(bytes[8..=11].try_into().unwrap()),
+            size: u32::from_le_bytes(bytes[12..=15].try_into().unwrap()),
+        }
+    }
+}
+
+/// Represents the header of a ROM.
+///
+/// Only the fields that are relevant for reading the content of the ROM are exposed here.
+#[derive(Debug, Clone)]
+pub struct RomHeader {
+    /// Title of the game, in uppercase ASCII.
+    pub title: String,
+
+    /// Code of the game, which identifies the game and its region (such as `CPUE`).
+    pub game_code: String,
+
+    /// Code of the maker of the game (such as `01` for Nintendo).
+    pub maker_code: String,
+
+    /// Code of the unit the game runs on (0 for the Nintendo DS).
+    pub unit_code: u8,
+
+    /// Capacity of the cartridge, where the capacity is `128 KiB << device_capacity`.
+    pub device_capacity: u8,
+
+    /// Version of the ROM.
+    pub rom_version: u8,
+
+    /// Information about the ARM9 binary.
+    pub arm9: RomBinaryInfo,
+
+    /// Information about the ARM7 binary.
+    pub arm7: RomBinaryInfo,
+
+    /// Offset of the File Name Table in the ROM.
+    pub fnt_offset: u32,
+
+    /// Size of the File Name Table.
+    pub fnt_size: u32,
+
+    /// Offset of the File Allocation Table in the ROM.
+    pub fat_offset: u32,
+
+    /// Size of the File Allocation Table.
+    pub fat_size: u32,
+
+    /// Offset of the ARM9 overlay table in the ROM.
+    pub arm9_overlay_offset: u32,
+
+    /// Size of the ARM9 overlay table.
+    pub arm9_overlay_size: u32,
+
+    /// Offset of the ARM7 overlay table in the ROM.
+    pub arm7_overlay_offset: u32,
+
+    /// Size of the ARM7 overlay table.
+    pub arm7_overlay_size: u32,
+
+    /// Offset of the banner (icon and titles) in the ROM.
+    pub banner_offset: u32,
+
+    /// Total size of the ROM that is actually used.
+    pub total_used_rom_size: u32,
+
+    /// Size of the ROM header.
+    pub rom_header_size: u32,
+
+    /// CRC16 of the ROM header.
+    pub header_crc16: u16,
+}
+
+impl RomHeader {
+    /// Parses a [`RomHeader`] from a byte array.
+    ///
+    /// It is expected that the array is in the same format as the one found at the beginning of
+    /// the ROM.
+    pub fn from_bytes(bytes: &[u8; ROM_HEADER_SIZE]) -> Self {
+        // Safety: slice length is explicitly specified, and the length of the `bytes` array is
+        // known
+        Self {
+            title: Self::parse_string(&bytes[0x00..0x0C]),
+            game_code: Self::parse_string(&bytes[0x0C..0x10]),
+            maker_code: Self::parse_string(&bytes[0x10..0x12]),
+            unit_code: bytes[0x12],
+            device_capacity: bytes[0x14],
+            rom_version: bytes[0x1E],
+            arm9: RomBinaryInfo::from_bytes(bytes[0x20..0x30].try_into().unwrap()),
+            arm7: RomBinaryInfo::from_bytes(bytes[0x30..0x40].try_into().unwrap()),
+            fnt_offset: u32::from_le_bytes(bytes[0x40..=0x43].try_into().unwrap()),
+            fnt_size: u32::from_le_bytes(bytes[0x44..=0x47].try_into().unwrap()),
+            fat_offset: u32::from_le_bytes(bytes[0x48..=0x4B].try_into().unwrap()),
+            fat_size: u32::from_le_bytes(bytes[0x4C..=0x4F].try_into().unwrap()),
+            arm9_overlay_offset: u32::from_le_bytes(bytes[0x50..=0x53].try_into().unwrap()),
+            arm9_overlay_size: u32::from_le_bytes(bytes[0x54..=0x57].try_into().unwrap()),
+            arm7_overlay_offset: u32::from_le_bytes(bytes[0x58..=0x5B].try_into().unwrap()),
+            arm7_overlay_size: u32::from_le_bytes(bytes[0x5C..=0x5F].try_into().unwrap()),
+            banner_offset: u32::from_le_bytes(bytes[0x68..=0x6B].try_into().unwrap()),
+            total_used_rom_size: u32::from_le_bytes(bytes[0x80..=0x83].try_into().unwrap()),
+            rom_header_size: u32::from_le_bytes(bytes[0x84..=0x87].try_into().unwrap()),
+            header_crc16: u16::from_le_bytes(bytes[0x15E..=0x15F].try_into().unwrap()),
+        }
+    }
+
+    /// Parses a null-padded ASCII string from the header.
+    fn parse_string(bytes: &[u8]) -> String {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    }
+
+    /// Returns the number of files described by the File Allocation Table.
+    pub fn number_of_files(&self) -> u32 {
+        self.fat_size / ROM_FAT_ENTRY_SIZE
+    }
+}
+
+/// Represents an entry of the File Allocation Table of a ROM.
+#[derive(Debug, Clone, Copy)]
+pub struct RomFileAllocationTableEntry {
+    /// Offset of the beginning of the file in the ROM.
+    pub start_address: u32,
+
+    /// Offset of the end of the file in the ROM (exclusive).
+    pub end_address: u32,
+}
+
+impl RomFileAllocationTableEntry {
+    /// Parses a [`RomFileAllocationTableEntry`] from a byte array.
+    ///
+    /// It is expected that the array is in the same format as the one found in the File
+    /// Allocation Table of the ROM.
+    pub fn from_bytes(bytes: &[u8; ROM_FAT_ENTRY_SIZE as usize]) -> Self {
+        // Safety: slice length is explicitly specified, and the length of the `bytes` array is
+        // known
+        Self {
+            start_address: u32::from_le_bytes(bytes[0..=3].try_into().unwrap()),
+            end_address: u32::from_le_bytes(bytes[4..=7].try_into().unwrap()),
+        }
+    }
+
+    /// Returns the size of the file.
+    pub fn size(&self) -> u32 {
+        self.end_address.saturating_sub(self.start_address)
+    }
+}
+
+/// Represents an entry of an overlay table (for the ARM9 or ARM7) of a ROM.
+#[derive(Debug, Clone, Copy)]
+pub struct RomOverlayTableEntry {
+    /// ID of the overlay.
+    pub overlay_id: u32,
+
+    /// Address where the overlay is loaded in RAM.
+    pub ram_address: u32,
+
+    /// Size of the overlay once loaded in RAM.
+    pub ram_size: u32,
+
+    /// Size of the BSS section of the overlay.
+    pub bss_size: u32,
+
+    /// Start address of the static initializers of the overlay.
+    pub static_initializer_start_address: u32,
+
+    /// End address of the static initializers of the overlay.
+    pub static_initializer_end_address: u32,
+
+    /// ID of the file containing the overlay in the File Allocation Table.
+    pub file_id: u32,
+
+    /// Size of the overlay when compressed.
+    ///
+    /// This is only meaningful when [`RomOverlayTableEntry::compressed`] is set.
+    pub compressed_size: u32,
+
+    /// Whether the overlay is compressed.
+    pub compressed: bool,
+
+    /// Whether the overlay is verified with an authentication code.
+    pub authenticated: bool,
+}
+
+impl RomOverlayTableEntry {
+    /// Parses a [`RomOverlayTableEntry`] from a byte array.
+    ///
+    /// It is expected that the array is in the same format as the one found in the overlay tables
+    /// of the ROM.
+    pub fn from_bytes(bytes: &[u8; ROM_OVERLAY_TABLE_ENTRY_SIZE as usize]) -> Self {
+        // Safety: slice length is explicitly specified, and the length of the `bytes` array is
+        // known
+        let compressed_size_and_flags = u32::from_le_bytes(bytes[28..=31].try_into().unwrap());
+
+        Self {
+            overlay_id: u32::from_le_bytes(bytes[0..=3].try_into().unwrap()),
+            ram_address: u32::from_le_bytes(bytes[4..=7].try_into().unwrap()),
+            ram_size: u32::from_le_bytes(bytes[8..=11].try_into().unwrap()),
+            bss_size: u32::from_le_bytes(bytes[12..=15].try_into().unwrap()),
+            static_initializer_start_address: u32::from_le_bytes(
+                bytes[16..=19].try_into().unwrap(),
+            ),
+            static_initializer_end_address: u32::from_le_bytes(bytes[20..=23].try_into().unwrap()),
+            file_id: u32::from_le_bytes(bytes[24..=27].try_into().unwrap()),
+            compressed_size: compressed_size_and_flags & 0x00FF_FFFF,
+            compressed: (compressed_size_and_flags >> 24) & 0x01 != 0,
+            authenticated: (compressed_size_and_flags >> 24) & 0x02 != 0,
+        }
+    }
+}
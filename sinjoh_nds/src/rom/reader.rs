@@ -0,0 +1,356 @@
+//! ROM image reader.
+//!
+//! For more information, see [`RomReader`].
+
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::compression::blz::{self, BlzError};
+use crate::fnt::{FileNameTable, FileNameTableError, FileNameTableFilesIter};
+
+use super::arm9::{self, Arm9Error};
+use super::banner::{self, Banner, BannerError};
+use super::{
+    ROM_FAT_ENTRY_SIZE, ROM_HEADER_SIZE, ROM_OVERLAY_TABLE_ENTRY_SIZE, RomFileAllocationTableEntry,
+    RomHeader, RomOverlayTableEntry,
+};
+
+/// Error type for ROM image parsing.
+#[derive(Error, Debug)]
+pub enum RomReaderError {
+    /// An I/O error has occurred while trying to open the ROM file.
+    #[error("unable to open the ROM file ({0})")]
+    FileOpenError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to read from the ROM file.
+    #[error("failed to read the ROM file ({0})")]
+    FileReadError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to seek in the ROM file.
+    #[error("failed to seek the ROM file ({0})")]
+    FileSeekError(#[source] io::Error),
+
+    /// The File Name Table could not be parsed.
+    #[error("unable to parse the File Name Table")]
+    FntParseError(#[source] FileNameTableError),
+
+    /// A region of the ROM is too large to be handled.
+    #[error("a region of the ROM is too large to be handled (size is {0})")]
+    RegionTooLarge(u32),
+
+    /// The file with the specified ID could not be found.
+    #[error("the file with ID {0} could not be found")]
+    FileNotFound(u16),
+
+    /// The file at the specified path could not be found.
+    #[error("the file at path {0:?} could not be found")]
+    FileNotFoundByPath(String),
+
+    /// The ARM9 binary could not be decompressed.
+    #[error("unable to decompress the ARM9 binary")]
+    Arm9DecompressionError(#[source] Arm9Error),
+
+    /// The overlay with the specified ID could not be decompressed.
+    #[error("unable to decompress the overlay with ID {0}")]
+    OverlayDecompressionError(u32, #[source] BlzError),
+
+    /// The File Allocation Table entry of a file is invalid.
+    #[error("the file with ID {0} has an invalid location (start is 0x{1:X}, end is 0x{2:X})")]
+    InvalidFileLocation(u16, u32, u32),
+
+    /// The ROM has no banner.
+    #[error("the ROM has no banner")]
+    MissingBanner,
+
+    /// The banner could not be parsed.
+    #[error("unable to parse the banner")]
+    BannerParseError(#[source] BannerError),
+}
+
+/// A reader for Nintendo DS ROM images.
+///
+/// This reader reads the header, the File Name Table and the File Allocation Table of a ROM and
+/// keeps them in memory. The actual files contained in the ROM are lazily-loaded as needed.
+///
+/// The ROM can be read from any source implementing [`Read`] and [`Seek`].
+///
+/// ## Reading a file from a ROM
+///
+/// Files of the ROM file system (NitroFS) can be opened using their path:
+///
+/// ```no_run
+/// use sinjoh_nds::narc::reader::{NarcReader, NarcReaderFlags};
+/// use sinjoh_nds::rom::reader::RomReader;
+///
+/// let mut rom_reader = RomReader::read_from_file("/path/to/rom.nds")?;
+/// println!("Opened {}", rom_reader.header().game_code);
+///
+/// let land_data = rom_reader.get_file_by_path("fielddata/land_data/land_data.narc")?;
+/// let mut narc_reader = NarcReader::from_bytes(land_data, NarcReaderFlags::default())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct RomReader<R: Read + Seek> {
+    /// The reader for the ROM file.
+    reader: R,
+
+    /// The parsed header of the ROM.
+    header: RomHeader,
+
+    /// The parsed File Allocation Table of the ROM.
+    fat: Vec<RomFileAllocationTableEntry>,
+
+    /// The parsed File Name Table of the ROM.
+    file_name_table: FileNameTable,
+}
+
+impl RomReader<BufReader<File>> {
+    /// Creates a new ROM reader from the given file.
+    ///
+    /// The file will stay open until the reader is dropped. This allows for lazy-loading of the
+    /// files contained in the ROM.
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, RomReaderError> {
+        let file = File::open(path).map_err(RomReaderError::FileOpenError)?;
+        Self::from_reader(BufReader::new(file))
+    }
+}
+
+impl<B: AsRef<[u8]>> RomReader<Cursor<B>> {
+    /// Creates a new ROM reader from a buffer containing the whole ROM.
+    pub fn from_bytes(bytes: B) -> Result<Self, RomReaderError> {
+        Self::from_reader(Cursor::new(bytes))
+    }
+}
+
+impl<R: Read + Seek> RomReader<R> {
+    /// Creates a new ROM reader from the given reader.
+    ///
+    /// The reader is kept until the ROM reader is dropped, which allows for lazy-loading of the
+    /// files contained in the ROM.
+    pub fn from_reader(mut reader: R) -> Result<Self, RomReaderError> {
+        // Read the header
+        let mut raw_header = [0; ROM_HEADER_SIZE];
+        reader
+            .seek(SeekFrom::Start(0))
+            .map_err(RomReaderError::FileSeekError)?;
+
+        reader
+            .read_exact(&mut raw_header)
+            .map_err(RomReaderError::FileReadError)?;
+
+        let header = RomHeader::from_bytes(&raw_header);
+
+        // Read the File Allocation Table
+        let raw_fat = Self::read_region(&mut reader, header.fat_offset, header.fat_size)?;
+        let fat = raw_fat
+            .chunks_exact(ROM_FAT_ENTRY_SIZE as usize)
+            .map(|raw_entry| RomFileAllocationTableEntry::from_bytes(raw_entry.try_into().unwrap()))
+            .collect();
+
+        // Read the File Name Table
+        let raw_fnt = Self::read_region(&mut reader, header.fnt_offset, header.fnt_size)?;
+        let file_name_table =
+            FileNameTable::parse_bytes(&raw_fnt).map_err(RomReaderError::FntParseError)?;
+
+        Ok(Self {
+            reader,
+            header,
+            fat,
+            file_name_table,
+        })
+    }
+
+    /// Reads a region of the ROM.
+    fn read_region(reader: &mut R, offset: u32, size: u32) -> Result<Vec<u8>, RomReaderError> {
+        reader
+            .seek(SeekFrom::Start(offset.into()))
+            .map_err(RomReaderError::FileSeekError)?;
+
+        let mut region = vec![
+            0u8;
+            size.try_into()
+                .map_err(|_| RomReaderError::RegionTooLarge(size))?
+        ];
+
+        reader
+            .read_exact(&mut region)
+            .map_err(RomReaderError::FileReadError)?;
+
+        Ok(region)
+    }
+
+    /// Consumes the ROM reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the parsed header of the ROM.
+    pub fn header(&self) -> &RomHeader {
+        &self.header
+    }
+
+    /// Returns the parsed File Allocation Table of the ROM.
+    pub fn fat(&self) -> &[RomFileAllocationTableEntry] {
+        &self.fat
+    }
+
+    /// Returns the parsed File Name Table of the ROM.
+    pub fn file_name_table(&self) -> &FileNameTable {
+        &self.file_name_table
+    }
+
+    /// Returns the number of files in the ROM.
+    ///
+    /// This includes the overlays, which are stored in the File Allocation Table without being
+    /// named by the File Name Table.
+    pub fn number_of_files(&self) -> u16 {
+        self.fat.len().try_into().unwrap_or(u16::MAX)
+    }
+
+    /// Reads and returns a region of the ROM.
+    pub fn read_bytes(&mut self, offset: u32, size: u32) -> Result<Vec<u8>, RomReaderError> {
+        Self::read_region(&mut self.reader, offset, size)
+    }
+
+    /// Reads and returns the ARM9 binary.
+    pub fn arm9(&mut self) -> Result<Vec<u8>, RomReaderError> {
+        let arm9 = self.header.arm9;
+        self.read_bytes(arm9.rom_offset, arm9.size)
+    }
+
+    /// Reads and returns the ARM7 binary.
+    pub fn arm7(&mut self) -> Result<Vec<u8>, RomReaderError> {
+        let arm7 = self.header.arm7;
+        self.read_bytes(arm7.rom_offset, arm7.size)
+    }
+
+    /// Reads and returns the ARM9 binary, decompressing it if needed.
+    ///
+    /// See [`decompress_arm9`](arm9::decompress_arm9) for more information.
+    pub fn decompressed_arm9(&mut self) -> Result<Vec<u8>, RomReaderError> {
+        let arm9 = self.arm9()?;
+        arm9::decompress_arm9(&arm9, self.header.arm9.ram_address)
+            .map_err(RomReaderError::Arm9DecompressionError)
+    }
+
+    /// Reads and returns the raw banner, whose size depends on its version.
+    pub fn raw_banner(&mut self) -> Result<Vec<u8>, RomReaderError> {
+        let banner_offset = self.header.banner_offset;
+        if banner_offset == 0 {
+            return Err(RomReaderError::MissingBanner);
+        }
+
+        let raw_version = self.read_bytes(banner_offset, 2)?;
+
+        // Safety: the size of the read region is explicitly specified
+        let version = u16::from_le_bytes(raw_version[0..=1].try_into().unwrap());
+
+        self.read_bytes(banner_offset, banner::banner_size(version))
+    }
+
+    /// Reads and parses the banner, holding the icon and titles of the software.
+    pub fn banner(&mut self) -> Result<Banner, RomReaderError> {
+        let raw_banner = self.raw_banner()?;
+        Banner::from_bytes(&raw_banner).map_err(RomReaderError::BannerParseError)
+    }
+
+    /// Reads and returns the overlay described by the given overlay table entry, decompressing
+    /// it if needed.
+    pub fn get_overlay(&mut self, entry: &RomOverlayTableEntry) -> Result<Vec<u8>, RomReaderError> {
+        let file_id = entry
+            .file_id
+            .try_into()
+            .map_err(|_| RomReaderError::FileNotFound(u16::MAX))?;
+        let overlay = self.get_file(file_id)?;
+
+        if !entry.compressed {
+            return Ok(overlay);
+        }
+
+        blz::decompress(&overlay)
+            .map_err(|err| RomReaderError::OverlayDecompressionError(entry.overlay_id, err))
+    }
+
+    /// Reads and parses the ARM9 overlay table.
+    pub fn arm9_overlay_table(&mut self) -> Result<Vec<RomOverlayTableEntry>, RomReaderError> {
+        let (offset, size) = (
+            self.header.arm9_overlay_offset,
+            self.header.arm9_overlay_size,
+        );
+        self.read_overlay_table(offset, size)
+    }
+
+    /// Reads and parses the ARM7 overlay table.
+    pub fn arm7_overlay_table(&mut self) -> Result<Vec<RomOverlayTableEntry>, RomReaderError> {
+        let (offset, size) = (
+            self.header.arm7_overlay_offset,
+            self.header.arm7_overlay_size,
+        );
+        self.read_overlay_table(offset, size)
+    }
+
+    /// Reads and parses an overlay table.
+    fn read_overlay_table(
+        &mut self,
+        offset: u32,
+        size: u32,
+    ) -> Result<Vec<RomOverlayTableEntry>, RomReaderError> {
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let raw_overlay_table = self.read_bytes(offset, size)?;
+
+        Ok(raw_overlay_table
+            .chunks_exact(ROM_OVERLAY_TABLE_ENTRY_SIZE as usize)
+            .map(|raw_entry| RomOverlayTableEntry::from_bytes(raw_entry.try_into().unwrap()))
+            .collect())
+    }
+
+    /// Reads and returns the file with the specified ID.
+    pub fn get_file(&mut self, id: u16) -> Result<Vec<u8>, RomReaderError> {
+        let fat_entry = *self
+            .fat
+            .get(id as usize)
+            .ok_or(RomReaderError::FileNotFound(id))?;
+
+        if fat_entry.end_address < fat_entry.start_address {
+            return Err(RomReaderError::InvalidFileLocation(
+                id,
+                fat_entry.start_address,
+                fat_entry.end_address,
+            ));
+        }
+
+        self.read_bytes(fat_entry.start_address, fat_entry.size())
+    }
+
+    /// Returns the ID of the file at the specified path.
+    ///
+    /// Path components are separated by a `/`, such as in
+    /// `fielddata/land_data/land_data.narc`.
+    pub fn get_file_id_by_path(&self, path: &str) -> Result<u16, RomReaderError> {
+        self.file_name_table
+            .get_file_id_by_path(path)
+            .ok_or_else(|| RomReaderError::FileNotFoundByPath(path.to_string()))
+    }
+
+    /// Reads and returns the file at the specified path.
+    ///
+    /// Path components are separated by a `/`, such as in
+    /// `fielddata/land_data/land_data.narc`.
+    pub fn get_file_by_path(&mut self, path: &str) -> Result<Vec<u8>, RomReaderError> {
+        let id = self.get_file_id_by_path(path)?;
+        self.get_file(id)
+    }
+
+    /// Returns an iterator over the files of the ROM file system.
+    ///
+    /// Each item is the path of a file, along with its ID.
+    pub fn file_paths_iter(&self) -> FileNameTableFilesIter<'_> {
+        self.file_name_table.files_iter()
+    }
+}
@@ -0,0 +1,594 @@
+//! ROM image writer.
+//!
+//! For more information, see [`RomWriter`].
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::crc::crc16;
+
+use super::banner;
+use super::reader::{RomReader, RomReaderError};
+use super::{ROM_FAT_ENTRY_SIZE, ROM_HEADER_CRC16_OFFSET, ROM_HEADER_SIZE};
+
+/// The alignment of the files of the file system in the ROM.
+pub const ROM_FILE_ALIGNMENT: u32 = 0x200;
+
+/// The smallest cartridge capacity, which is the capacity of a cartridge whose device capacity
+/// is 0.
+pub const ROM_MIN_DEVICE_CAPACITY: u64 = 128 * 1024;
+
+/// Offset of the device capacity in the ROM header.
+const ROM_HEADER_DEVICE_CAPACITY_OFFSET: usize = 0x14;
+
+/// Offset of the total used ROM size in the ROM header.
+const ROM_HEADER_TOTAL_USED_ROM_SIZE_OFFSET: usize = 0x80;
+
+/// Error type for ROM image writing.
+#[derive(Error, Debug)]
+pub enum RomWriterError {
+    /// An I/O error has occurred while trying to create the ROM file.
+    #[error("unable to create the ROM file ({0})")]
+    FileCreateError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to write to the ROM file.
+    #[error("failed to write the ROM file ({0})")]
+    FileWriteError(#[source] io::Error),
+
+    /// An error has occurred while reading the source ROM.
+    #[error("failed to read the source ROM")]
+    SourceReadError(#[source] RomReaderError),
+
+    /// The file with the specified ID could not be found.
+    #[error("the file with ID {0} could not be found")]
+    FileNotFound(u16),
+
+    /// The file with the specified ID is an overlay, which cannot be replaced.
+    #[error("the file with ID {0} is an overlay, and cannot be replaced")]
+    OverlayNotReplaceable(u16),
+
+    /// The rebuilt ROM is too large.
+    #[error("the rebuilt ROM is too large (total size is {0})")]
+    RomTooLarge(u64),
+}
+
+/// Flags that can be used to configure the behavior of the ROM writer.
+#[derive(Debug)]
+pub struct RomWriterFlags {
+    /// The byte used to pad the files of the file system to their alignment.
+    pub padding_byte: u8,
+}
+
+impl Default for RomWriterFlags {
+    fn default() -> Self {
+        Self { padding_byte: 0xFF }
+    }
+}
+
+/// A writer for rebuilding Nintendo DS ROM images.
+///
+/// This writer takes a source ROM, and rebuilds it with some files of its file system (NitroFS)
+/// replaced. Replacement files can have a different size than the original ones.
+///
+/// Everything that is not a file of the file system is kept as-is: this includes the ARM9 and
+/// ARM7 binaries, their overlays, the File Name Table and the banner. The files of the file
+/// system are laid out again after those, and the File Allocation Table is updated accordingly.
+/// Finally, the header is updated to reflect the new size of the ROM, and its CRC16 is
+/// recomputed.
+///
+/// ## Replacing a file in a ROM
+///
+/// Here's an example on how to use it:
+///
+/// ```no_run
+/// use sinjoh_nds::rom::reader::RomReader;
+/// use sinjoh_nds::rom::writer::{RomWriter, RomWriterFlags};
+///
+/// let rom_reader = RomReader::read_from_file("/path/to/rom.nds")?;
+/// let mut rom_writer = RomWriter::new(rom_reader, RomWriterFlags::default());
+///
+/// rom_writer.replace_file_by_path("fielddata/land_data/land_data.narc", vec![0x01, 0x02])?;
+/// rom_writer.write_to_file("/path/to/rebuilt_rom.nds")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct RomWriter<R: Read + Seek> {
+    /// The reader for the source ROM.
+    rom_reader: RomReader<R>,
+
+    /// The files to replace, indexed by their ID.
+    replaced_files: BTreeMap<u16, Vec<u8>>,
+
+    /// The flags configuring the behavior of the writer.
+    flags: RomWriterFlags,
+}
+
+impl<R: Read + Seek> RomWriter<R> {
+    /// Creates a new ROM writer, which rebuilds the ROM read by the given reader.
+    ///
+    /// Flags can be used to configure the behavior of the writer.
+    pub fn new(rom_reader: RomReader<R>, flags: RomWriterFlags) -> Self {
+        Self {
+            rom_reader,
+            replaced_files: BTreeMap::new(),
+            flags,
+        }
+    }
+
+    /// Consumes the ROM writer, returning the reader of the source ROM.
+    pub fn into_inner(self) -> RomReader<R> {
+        self.rom_reader
+    }
+
+    /// Returns the reader of the source ROM.
+    pub fn rom_reader(&self) -> &RomReader<R> {
+        &self.rom_reader
+    }
+
+    /// Replaces the content of the file with the specified ID.
+    ///
+    /// Overlays cannot be replaced using this method.
+    pub fn replace_file(&mut self, id: u16, data: Vec<u8>) -> Result<(), RomWriterError> {
+        if id >= self.rom_reader.number_of_files() {
+            return Err(RomWriterError::FileNotFound(id));
+        }
+
+        if self.overlay_file_ids()?.contains(&id) {
+            return Err(RomWriterError::OverlayNotReplaceable(id));
+        }
+
+        self.replaced_files.insert(id, data);
+
+        Ok(())
+    }
+
+    /// Replaces the content of the file at the specified path.
+    ///
+    /// Path components are separated by a `/`, such as in
+    /// `fielddata/land_data/land_data.narc`.
+    pub fn replace_file_by_path(
+        &mut self,
+        path: &str,
+        data: Vec<u8>,
+    ) -> Result<(), RomWriterError> {
+        let id = self
+            .rom_reader
+            .get_file_id_by_path(path)
+            .map_err(RomWriterError::SourceReadError)?;
+
+        self.replace_file(id, data)
+    }
+
+    /// Returns the content of the file with the specified ID, as it will be written in the ROM.
+    pub fn get_file(&mut self, id: u16) -> Result<Vec<u8>, RomWriterError> {
+        match self.replaced_files.get(&id) {
+            Some(data) => Ok(data.clone()),
+            None => self
+                .rom_reader
+                .get_file(id)
+                .map_err(RomWriterError::SourceReadError),
+        }
+    }
+
+    /// Writes the rebuilt ROM to the given file.
+    ///
+    /// The file will be created if it does not exist, and truncated if it does.
+    pub fn write_to_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RomWriterError> {
+        let file = File::create(path).map_err(RomWriterError::FileCreateError)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write_to(&mut writer)?;
+
+        writer.flush().map_err(RomWriterError::FileWriteError)
+    }
+
+    /// Writes the rebuilt ROM to a byte vector.
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>, RomWriterError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes the rebuilt ROM to the given writer.
+    pub fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), RomWriterError> {
+        let header = self.rom_reader.header().clone();
+        let overlay_file_ids = self.overlay_file_ids()?;
+
+        // Find the end of the regions that are kept as-is
+        let mut preserved_regions = vec![
+            (0, ROM_HEADER_SIZE as u32),
+            (header.arm9.rom_offset, header.arm9.size),
+            (header.arm7.rom_offset, header.arm7.size),
+            (header.arm9_overlay_offset, header.arm9_overlay_size),
+            (header.arm7_overlay_offset, header.arm7_overlay_size),
+            (header.fnt_offset, header.fnt_size),
+            (header.fat_offset, header.fat_size),
+        ];
+
+        if header.banner_offset != 0 {
+            let banner_size = self.banner_size(header.banner_offset)?;
+            preserved_regions.push((header.banner_offset, banner_size));
+        }
+
+        for (id, fat_entry) in self.rom_reader.fat().iter().enumerate() {
+            if overlay_file_ids.contains(&(id as u16)) {
+                preserved_regions.push((fat_entry.start_address, fat_entry.size()));
+            }
+        }
+
+        let preserved_end = preserved_regions
+            .iter()
+            .map(|&(offset, size)| offset as u64 + size as u64)
+            .max()
+            .unwrap_or(ROM_HEADER_SIZE as u64);
+
+        // Lay out the files of the file system after the preserved regions, keeping their
+        // original order
+        let mut relocated_file_ids: Vec<u16> = (0..self.rom_reader.number_of_files())
+            .filter(|id| !overlay_file_ids.contains(id))
+            .collect();
+
+        relocated_file_ids.sort_by_key(|&id| self.rom_reader.fat()[id as usize].start_address);
+
+        let mut new_fat = self.rom_reader.fat().to_vec();
+        let mut current_address = preserved_end.next_multiple_of(ROM_FILE_ALIGNMENT.into());
+        let mut total_used_rom_size = preserved_end;
+
+        for &id in relocated_file_ids.iter() {
+            let file_size = match self.replaced_files.get(&id) {
+                Some(data) => data.len() as u64,
+                None => self.rom_reader.fat()[id as usize].size() as u64,
+            };
+
+            let start_address = current_address;
+            let end_address = start_address + file_size;
+
+            new_fat[id as usize].start_address = start_address
+                .try_into()
+                .map_err(|_| RomWriterError::RomTooLarge(end_address))?;
+            new_fat[id as usize].end_address = end_address
+                .try_into()
+                .map_err(|_| RomWriterError::RomTooLarge(end_address))?;
+
+            total_used_rom_size = end_address;
+            current_address = end_address.next_multiple_of(ROM_FILE_ALIGNMENT.into());
+        }
+
+        // Safety: the total used ROM size was checked to fit in a `u32` above, when computing
+        // the addresses of the files
+        let total_used_rom_size = total_used_rom_size as u32;
+
+        // Read the preserved part of the ROM, and patch it
+        let mut preserved_part = self
+            .rom_reader
+            .read_bytes(0, preserved_end as u32)
+            .map_err(RomWriterError::SourceReadError)?;
+
+        for (id, fat_entry) in new_fat.iter().enumerate() {
+            let offset = header.fat_offset as usize + id * ROM_FAT_ENTRY_SIZE as usize;
+
+            preserved_part[offset..offset + 4]
+                .copy_from_slice(&fat_entry.start_address.to_le_bytes());
+            preserved_part[offset + 4..offset + 8]
+                .copy_from_slice(&fat_entry.end_address.to_le_bytes());
+        }
+
+        preserved_part
+            [ROM_HEADER_TOTAL_USED_ROM_SIZE_OFFSET..ROM_HEADER_TOTAL_USED_ROM_SIZE_OFFSET + 4]
+            .copy_from_slice(&total_used_rom_size.to_le_bytes());
+
+        let mut device_capacity = header.device_capacity;
+        while (ROM_MIN_DEVICE_CAPACITY << device_capacity) < total_used_rom_size as u64 {
+            device_capacity += 1;
+        }
+
+        preserved_part[ROM_HEADER_DEVICE_CAPACITY_OFFSET] = device_capacity;
+
+        let header_crc16 = crc16(&preserved_part[..ROM_HEADER_CRC16_OFFSET]);
+        preserved_part[ROM_HEADER_CRC16_OFFSET..ROM_HEADER_CRC16_OFFSET + 2]
+            .copy_from_slice(&header_crc16.to_le_bytes());
+
+        writer
+            .write_all(&preserved_part)
+            .map_err(RomWriterError::FileWriteError)?;
+
+        // Write the files of the file system
+        let mut written_size = preserved_end;
+
+        for &id in relocated_file_ids.iter() {
+            let fat_entry = new_fat[id as usize];
+
+            let padding = vec![
+                self.flags.padding_byte;
+                (fat_entry.start_address as u64 - written_size) as usize
+            ];
+            writer
+                .write_all(&padding)
+                .map_err(RomWriterError::FileWriteError)?;
+
+            let file = self.get_file(id)?;
+            writer
+                .write_all(&file)
+                .map_err(RomWriterError::FileWriteError)?;
+
+            written_size = fat_entry.end_address as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the IDs of the files that contain overlays.
+    fn overlay_file_ids(&mut self) -> Result<HashSet<u16>, RomWriterError> {
+        let arm9_overlay_table = self
+            .rom_reader
+            .arm9_overlay_table()
+            .map_err(RomWriterError::SourceReadError)?;
+
+        let arm7_overlay_table = self
+            .rom_reader
+            .arm7_overlay_table()
+            .map_err(RomWriterError::SourceReadError)?;
+
+        Ok(arm9_overlay_table
+            .iter()
+            .chain(arm7_overlay_table.iter())
+            .filter_map(|entry| entry.file_id.try_into().ok())
+            .collect())
+    }
+
+    /// Returns the size of the banner located at the specified offset, based on its version.
+    fn banner_size(&mut self, banner_offset: u32) -> Result<u32, RomWriterError> {
+        let raw_version = self
+            .rom_reader
+            .read_bytes(banner_offset, 2)
+            .map_err(RomWriterError::SourceReadError)?;
+
+        // Safety: the size of the read region is explicitly specified
+        let version = u16::from_le_bytes(raw_version[0..=1].try_into().unwrap());
+
+        Ok(banner::banner_size(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fnt::{
+        FileNameTable, FileNameTableDirectory, FileNameTableEntry, ROOT_DIRECTORY_ID,
+    };
+
+    use super::super::ROM_OVERLAY_TABLE_ENTRY_SIZE;
+    use super::*;
+
+    /// Returns bytes that are different for each region of the test ROM.
+    fn region(seed: u8, size: usize) -> Vec<u8> {
+        (0..size).map(|i| seed.wrapping_add(i as u8)).collect()
+    }
+
+    /// Builds a small ROM, with an overlay (file 0) and three named files (files 1 to 3).
+    ///
+    /// | Offset   | Content                  |
+    /// | -------- | ------------------------ |
+    /// | `0x0000` | Header                   |
+    /// | `0x0200` | ARM9 binary              |
+    /// | `0x0300` | ARM9 overlay table       |
+    /// | `0x0400` | ARM7 binary              |
+    /// | `0x0500` | File Name Table          |
+    /// | `0x0600` | File Allocation Table    |
+    /// | `0x0800` | Banner                   |
+    /// | `0x1200` | Overlay (file 0)         |
+    /// | `0x1400` | `a.bin` (file 1)         |
+    /// | `0x1600` | `b.bin` (file 2)         |
+    /// | `0x1800` | `c.bin` (file 3)         |
+    fn rom() -> Vec<u8> {
+        let mut rom = vec![0xFF; 0x1820];
+
+        let file_name_table = FileNameTable {
+            root: FileNameTableDirectory {
+                id: ROOT_DIRECTORY_ID,
+                name: String::new(),
+                first_file_id: 1,
+                entries: ["a.bin", "b.bin", "c.bin"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| FileNameTableEntry::File {
+                        name: name.to_string(),
+                        file_id: i as u16 + 1,
+                    })
+                    .collect(),
+            },
+        }
+        .to_bytes()
+        .unwrap();
+
+        let files = [
+            (0x1200, 0x40),
+            (0x1400, 0x10),
+            (0x1600, 0x30),
+            (0x1800, 0x20),
+        ];
+        let fat: Vec<u8> = files
+            .iter()
+            .flat_map(|&(start, size): &(u32, u32)| {
+                [start.to_le_bytes(), (start + size).to_le_bytes()].concat()
+            })
+            .collect();
+
+        let mut overlay_table = vec![0; ROM_OVERLAY_TABLE_ENTRY_SIZE as usize];
+        overlay_table[4..8].copy_from_slice(&0x0215_0000u32.to_le_bytes());
+        overlay_table[8..12].copy_from_slice(&0x40u32.to_le_bytes());
+
+        let mut banner = region(0xB0, banner::BANNER_SIZE);
+        banner[0..2].copy_from_slice(&0x0001u16.to_le_bytes());
+
+        // Write the header
+        let mut header = vec![0; ROM_HEADER_SIZE];
+        header[0x00..0x0C].copy_from_slice(b"POKEMON PL\0\0");
+        header[0x0C..0x10].copy_from_slice(b"CPUE");
+        header[0x10..0x12].copy_from_slice(b"01");
+
+        for (offset, value) in [
+            (0x20, 0x0200),
+            (0x24, 0x0200_0800),
+            (0x28, 0x0200_0000),
+            (0x2C, 0x0100),
+            (0x30, 0x0400),
+            (0x34, 0x0238_0000),
+            (0x38, 0x0238_0000),
+            (0x3C, 0x0080),
+            (0x40, 0x0500),
+            (0x44, file_name_table.len() as u32),
+            (0x48, 0x0600),
+            (0x4C, fat.len() as u32),
+            (0x50, 0x0300),
+            (0x54, overlay_table.len() as u32),
+            (0x68, 0x0800),
+            (0x80, 0x1820),
+            (0x84, 0x4000),
+        ] {
+            header[offset..offset + 4].copy_from_slice(&u32::to_le_bytes(value));
+        }
+
+        let header_crc16 = crc16(&header[..ROM_HEADER_CRC16_OFFSET]);
+        header[ROM_HEADER_CRC16_OFFSET..ROM_HEADER_CRC16_OFFSET + 2]
+            .copy_from_slice(&header_crc16.to_le_bytes());
+
+        // Write the regions
+        for (offset, data) in
+            [
+                (0x0000, header),
+                (0x0200, region(0x90, 0x100)),
+                (0x0300, overlay_table),
+                (0x0400, region(0x70, 0x80)),
+                (0x0500, file_name_table),
+                (0x0600, fat),
+                (0x0800, banner),
+            ]
+            .into_iter()
+            .chain(files.iter().enumerate().map(|(id, &(start, size))| {
+                (start as usize, region(id as u8 * 0x10, size as usize))
+            }))
+        {
+            rom[offset..offset + data.len()].copy_from_slice(&data);
+        }
+
+        rom
+    }
+
+    #[test]
+    fn replace_file_with_larger_file() {
+        let rom = rom();
+        let mut original = RomReader::from_bytes(rom.clone()).unwrap();
+        assert_eq!(
+            original.header().header_crc16,
+            crc16(&rom[..ROM_HEADER_CRC16_OFFSET])
+        );
+
+        let replacement = region(0xC0, 0x250);
+        let mut rom_writer = RomWriter::new(
+            RomReader::from_bytes(rom.clone()).unwrap(),
+            RomWriterFlags::default(),
+        );
+        rom_writer
+            .replace_file_by_path("b.bin", replacement.clone())
+            .unwrap();
+
+        let rebuilt = rom_writer.to_bytes().unwrap();
+        let mut rebuilt_reader = RomReader::from_bytes(rebuilt.clone()).unwrap();
+
+        // Everything that is not a file of the file system is kept as-is
+        assert_eq!(rebuilt_reader.arm9().unwrap(), original.arm9().unwrap());
+        assert_eq!(rebuilt_reader.arm7().unwrap(), original.arm7().unwrap());
+        assert_eq!(
+            rebuilt_reader.raw_banner().unwrap(),
+            original.raw_banner().unwrap()
+        );
+
+        let overlay_table = rebuilt_reader.arm9_overlay_table().unwrap();
+        assert_eq!(overlay_table.len(), 1);
+        assert_eq!(
+            rebuilt_reader.get_overlay(&overlay_table[0]).unwrap(),
+            original.get_overlay(&overlay_table[0]).unwrap()
+        );
+
+        // Files are laid out again after the overlay, aligned to 0x200 bytes
+        let fat: Vec<(u32, u32)> = rebuilt_reader
+            .fat()
+            .iter()
+            .map(|entry| (entry.start_address, entry.end_address))
+            .collect();
+        assert_eq!(
+            fat,
+            [
+                (0x1200, 0x1240),
+                (0x1400, 0x1410),
+                (0x1600, 0x1850),
+                (0x1A00, 0x1A20)
+            ]
+        );
+
+        assert_eq!(
+            rebuilt_reader.get_file_by_path("a.bin").unwrap(),
+            original.get_file_by_path("a.bin").unwrap()
+        );
+        assert_eq!(
+            rebuilt_reader.get_file_by_path("b.bin").unwrap(),
+            replacement
+        );
+        assert_eq!(
+            rebuilt_reader.get_file_by_path("c.bin").unwrap(),
+            original.get_file_by_path("c.bin").unwrap()
+        );
+
+        // The header is updated, and its CRC16 is recomputed
+        let header = rebuilt_reader.header();
+        assert_eq!(rebuilt.len(), 0x1A20);
+        assert_eq!(header.total_used_rom_size, 0x1A20);
+        assert_eq!(header.device_capacity, 0);
+        assert_eq!(
+            header.header_crc16,
+            crc16(&rebuilt[..ROM_HEADER_CRC16_OFFSET])
+        );
+    }
+
+    #[test]
+    fn replace_file_past_device_capacity() {
+        let mut rom_writer = RomWriter::new(
+            RomReader::from_bytes(rom()).unwrap(),
+            RomWriterFlags::default(),
+        );
+        rom_writer
+            .replace_file(3, vec![0x00; ROM_MIN_DEVICE_CAPACITY as usize])
+            .unwrap();
+
+        let rebuilt = rom_writer.to_bytes().unwrap();
+        let rebuilt_reader = RomReader::from_bytes(rebuilt.clone()).unwrap();
+
+        assert_eq!(rebuilt_reader.header().device_capacity, 1);
+        assert_eq!(
+            rebuilt_reader.header().header_crc16,
+            crc16(&rebuilt[..ROM_HEADER_CRC16_OFFSET])
+        );
+    }
+
+    #[test]
+    fn replace_invalid_file() {
+        let mut rom_writer = RomWriter::new(
+            RomReader::from_bytes(rom()).unwrap(),
+            RomWriterFlags::default(),
+        );
+
+        assert!(matches!(
+            rom_writer.replace_file(0, Vec::new()),
+            Err(RomWriterError::OverlayNotReplaceable(0))
+        ));
+        assert!(matches!(
+            rom_writer.replace_file(4, Vec::new()),
+            Err(RomWriterError::FileNotFound(4))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! A minimal writer for Standard MIDI Files.
+
+/// The status byte of a note-off event.
+pub(crate) const NOTE_OFF: u8 = 0x80;
+
+/// The status byte of a note-on event.
+pub(crate) const NOTE_ON: u8 = 0x90;
+
+/// The status byte of a control change event.
+pub(crate) const CONTROL_CHANGE: u8 = 0xB0;
+
+/// The status byte of a program change event.
+pub(crate) const PROGRAM_CHANGE: u8 = 0xC0;
+
+/// The status byte of a pitch bend event.
+pub(crate) const PITCH_BEND: u8 = 0xE0;
+
+/// The type of a tempo meta event.
+const META_TEMPO: u8 = 0x51;
+
+/// The type of a track name meta event.
+const META_TRACK_NAME: u8 = 0x03;
+
+/// The type of an end of track meta event.
+const META_END_OF_TRACK: u8 = 0x2F;
+
+/// An event of a MIDI track, at an absolute time.
+#[derive(Debug, Clone)]
+struct MidiEvent {
+    /// Time of the event, in ticks.
+    tick: u32,
+
+    /// Whether the event is a note-off event, which is sorted before the other events happening
+    /// at the same time.
+    note_off: bool,
+
+    /// Data of the event, without its delta time.
+    data: Vec<u8>,
+}
+
+/// A MIDI track, whose events can be added in any order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MidiTrack {
+    events: Vec<MidiEvent>,
+
+    /// Time of the end of the track, in ticks, if it is after the last event.
+    end_tick: u32,
+}
+
+impl MidiTrack {
+    /// Adds a channel event at the specified time.
+    pub fn push_channel_event(&mut self, tick: u32, status: u8, channel: u8, data: &[u8]) {
+        let mut event = vec![status | (channel & 0x0F)];
+        event.extend_from_slice(data);
+
+        self.events.push(MidiEvent {
+            tick,
+            note_off: status == NOTE_OFF,
+            data: event,
+        });
+    }
+
+    /// Adds a tempo event at the specified time, in beats per minute.
+    pub fn push_tempo(&mut self, tick: u32, bpm: u16) {
+        let microseconds_per_beat = 60_000_000 / u32::from(bpm.max(1));
+        self.push_meta_event(tick, META_TEMPO, &microseconds_per_beat.to_be_bytes()[1..]);
+    }
+
+    /// Adds a track name event at the start of the track.
+    pub fn push_track_name(&mut self, name: &str) {
+        self.push_meta_event(0, META_TRACK_NAME, name.as_bytes());
+    }
+
+    /// Extends the track up to the specified time, if it ends before it.
+    pub fn extend_to(&mut self, tick: u32) {
+        self.end_tick = self.end_tick.max(tick);
+    }
+
+    /// Adds a meta event at the specified time.
+    fn push_meta_event(&mut self, tick: u32, kind: u8, data: &[u8]) {
+        let mut event = vec![0xFF, kind];
+        write_variable_length(&mut event, data.len() as u32);
+        event.extend_from_slice(data);
+
+        self.events.push(MidiEvent {
+            tick,
+            note_off: false,
+            data: event,
+        });
+    }
+
+    /// Returns the data of the track chunk.
+    fn to_chunk(&self) -> Vec<u8> {
+        let mut events = self.events.clone();
+        events.sort_by_key(|event| (event.tick, !event.note_off));
+
+        let mut data = Vec::new();
+        let mut last_tick = 0;
+
+        for event in events {
+            write_variable_length(&mut data, event.tick - last_tick);
+            data.extend_from_slice(&event.data);
+            last_tick = event.tick;
+        }
+
+        write_variable_length(&mut data, self.end_tick.saturating_sub(last_tick));
+        data.extend_from_slice(&[0xFF, META_END_OF_TRACK, 0x00]);
+
+        let mut chunk = b"MTrk".to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend(data);
+        chunk
+    }
+}
+
+/// Writes a Type-1 MIDI file made of the specified tracks, with the specified number of ticks
+/// per quarter note.
+pub(crate) fn write_midi_file(tracks: &[MidiTrack], ticks_per_quarter_note: u16) -> Vec<u8> {
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend_from_slice(&6u32.to_be_bytes());
+    bytes.extend_from_slice(&1u16.to_be_bytes());
+    bytes.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(&ticks_per_quarter_note.to_be_bytes());
+
+    for track in tracks {
+        bytes.extend(track.to_chunk());
+    }
+
+    bytes
+}
+
+/// Writes a variable-length quantity, as used by MIDI files.
+fn write_variable_length(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+
+    while value != 0 {
+        groups.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.extend(groups.iter().rev());
+}
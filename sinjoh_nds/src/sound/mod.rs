@@ -0,0 +1,78 @@
+//! Utils for working with the sound formats of the Nintendo DS SDK.
+//!
+//! The following formats are supported:
+//!
+//! - SDAT (`SDAT`), which is the sound archive holding every sequence, bank, wave archive and
+//!   stream of a game, along with their settings and names (See [`sdat::Sdat`]).
+//! - SSEQ (`SSEQ`), which holds a sequence of commands playing music (See [`sseq::Sseq`]).
+//! - SWAR (`SWAR`), which holds the waves used by the instruments of banks (See
+//!   [`swar::Swar`]).
+//! - SWAV (`SWAV`), which holds a single wave (See [`swav::Swav`]).
+//!
+//! Sound archives are made of blocks whose offsets are stored inside of the header (See
+//! [`nitro`](crate::nitro)), while the files they contain are made of blocks that directly follow
+//! each other. Once parsed, a sequence can be converted to a MIDI file (See
+//! [`sseq::Sseq::to_midi`]), and a wave to a WAV file (See [`swav::Swav::to_wav`]).
+
+use thiserror::Error;
+
+use crate::nitro::{self, NitroError};
+
+mod midi;
+pub mod sdat;
+pub mod sseq;
+pub mod swar;
+pub mod swav;
+
+/// Error type for sound file parsing.
+#[derive(Error, Debug)]
+pub enum SoundError {
+    /// The file structure could not be parsed.
+    #[error("unable to parse the file structure")]
+    NitroError(#[source] NitroError),
+
+    /// The data ended before a structure could be fully read.
+    #[error("the data is truncated (tried to read at offset 0x{0:X})")]
+    TruncatedData(usize),
+
+    /// A block required by the file is missing.
+    #[error("the block {:?} is missing", String::from_utf8_lossy(.0))]
+    MissingBlock([u8; 4]),
+
+    /// A file of a sound archive is not located inside of the archive.
+    #[error("the file {0} is outside of the archive")]
+    InvalidFile(usize),
+
+    /// A sequence holds a command that is not known.
+    #[error("unknown command 0x{0:02X} at offset 0x{1:X}")]
+    UnknownCommand(u8, usize),
+
+    /// The encoding of a wave is not supported.
+    #[error("unsupported wave encoding {0}")]
+    UnsupportedWaveEncoding(u8),
+}
+
+/// Reads a `u8` at the specified offset.
+pub(crate) fn read_u8(bytes: &[u8], offset: usize) -> Result<u8, SoundError> {
+    bytes
+        .get(offset)
+        .copied()
+        .ok_or(SoundError::TruncatedData(offset))
+}
+
+/// Reads a `u16` at the specified offset.
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, SoundError> {
+    nitro::read_u16(bytes, offset).ok_or(SoundError::TruncatedData(offset))
+}
+
+/// Reads a `u32` at the specified offset.
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, SoundError> {
+    nitro::read_u32(bytes, offset).ok_or(SoundError::TruncatedData(offset))
+}
+
+/// Returns the slice of the specified size, at the specified offset.
+pub(crate) fn read_slice(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8], SoundError> {
+    bytes
+        .get(offset..offset + size)
+        .ok_or(SoundError::TruncatedData(offset))
+}
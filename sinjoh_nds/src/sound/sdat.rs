@@ -0,0 +1,557 @@
+//! Sound archive files (SDAT, `SDAT`), which hold the sound data of a game.
+//!
+//! A sound archive contains the following blocks:
+//!
+//! - A symbol block (`SYMB`), holding the names of the entries. This block is optional.
+//! - An info block (`INFO`), holding the settings of the sequences, sequence archives, banks,
+//!   wave archives, players, groups, stream players and streams, along with the ID of the file
+//!   holding their data.
+//! - A file allocation table block (`FAT`), holding the location of each file in the archive.
+//! - A file block (`FILE`), holding the data of the files.
+//!
+//! Entries are identified by their index in the list of entries of their kind, which is the ID
+//! used by the game to refer to them (for instance, the music of a map is the ID of a sequence).
+
+use crate::bytes::FromBytes;
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::{SoundError, read_slice, read_u8, read_u16, read_u32};
+
+/// The magic number of sound archive files.
+pub const SDAT_MAGIC: [u8; 4] = *b"SDAT";
+
+/// The magic number of symbol blocks.
+pub const SYMB_MAGIC: [u8; 4] = *b"SYMB";
+
+/// The magic number of info blocks.
+pub const INFO_MAGIC: [u8; 4] = *b"INFO";
+
+/// The magic number of file allocation table blocks.
+pub const FAT_MAGIC: [u8; 4] = *b"FAT ";
+
+/// The size of an entry of the file allocation table.
+const FAT_ENTRY_SIZE: usize = 16;
+
+/// The size of an entry of a group.
+const GROUP_ENTRY_SIZE: usize = 8;
+
+/// The maximum number of channels of a stream player.
+const STREAM_PLAYER_CHANNEL_COUNT: usize = 16;
+
+/// The value of a wave archive ID of a bank which is not used.
+const UNUSED_WAVE_ARCHIVE_ID: u16 = 0xFFFF;
+
+/// The kinds of entries of a sound archive, in the order of their records in the symbol and info
+/// blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordKind {
+    Sequence,
+    SequenceArchive,
+    Bank,
+    WaveArchive,
+    Player,
+    Group,
+    StreamPlayer,
+    Stream,
+}
+
+/// Represents an entry of a sound archive.
+#[derive(Debug, Clone)]
+pub struct SdatEntry<T> {
+    /// Name of the entry (for instance `SEQ_CITY03`), if the archive has a symbol block.
+    pub name: Option<String>,
+
+    /// Settings of the entry.
+    pub info: T,
+}
+
+/// Represents the settings of a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceInfo {
+    /// ID of the file holding the sequence (SSEQ).
+    pub file_id: u16,
+
+    /// ID of the bank used by the sequence.
+    pub bank_id: u16,
+
+    /// Volume of the sequence.
+    pub volume: u8,
+
+    /// Priority of the channels used by the sequence.
+    pub channel_priority: u8,
+
+    /// Priority of the sequence inside of its player.
+    pub player_priority: u8,
+
+    /// ID of the player playing the sequence.
+    pub player_id: u8,
+}
+
+/// Represents the settings of a sequence archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceArchiveInfo {
+    /// ID of the file holding the sequence archive (SSAR).
+    pub file_id: u16,
+
+    /// Names of the sequences of the archive, if the archive has a symbol block.
+    pub sequence_names: Vec<Option<String>>,
+}
+
+/// Represents the settings of a bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BankInfo {
+    /// ID of the file holding the bank (SBNK).
+    pub file_id: u16,
+
+    /// IDs of the wave archives holding the samples used by the instruments of the bank.
+    pub wave_archive_ids: [Option<u16>; 4],
+}
+
+/// Represents the settings of a wave archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaveArchiveInfo {
+    /// ID of the file holding the wave archive (SWAR).
+    pub file_id: u16,
+
+    /// Loading flags of the wave archive.
+    pub flags: u16,
+}
+
+/// Represents the settings of a sequence player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerInfo {
+    /// Maximum number of sequences played at the same time.
+    pub max_sequences: u8,
+
+    /// Mask of the channels the player is allowed to use.
+    pub channel_mask: u16,
+
+    /// Size of the heap allocated to the player.
+    pub heap_size: u32,
+}
+
+/// Represents an entry of a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupEntry {
+    /// Kind of the referenced entry (sequence, bank, wave archive or sequence archive), as stored
+    /// in the file.
+    pub kind: u8,
+
+    /// Loading flags of the referenced entry.
+    pub flags: u8,
+
+    /// ID of the referenced entry.
+    pub index: u32,
+}
+
+/// Represents the settings of a group, which is a set of entries loaded together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    /// Entries of the group.
+    pub entries: Vec<GroupEntry>,
+}
+
+/// Represents the settings of a stream player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamPlayerInfo {
+    /// Channels used by the stream player.
+    pub channels: Vec<u8>,
+}
+
+/// Represents the settings of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// ID of the file holding the stream (STRM).
+    pub file_id: u16,
+
+    /// Volume of the stream.
+    pub volume: u8,
+
+    /// Priority of the stream inside of its player.
+    pub player_priority: u8,
+
+    /// ID of the stream player playing the stream.
+    pub player_id: u8,
+}
+
+/// The name of a sequence archive, along with the names of the sequences it contains.
+type SequenceArchiveNames = (Option<String>, Vec<Option<String>>);
+
+/// A list of entries of a sound archive, indexed by their ID.
+///
+/// Some IDs are not used by any entry.
+pub type SdatEntries<T> = Vec<Option<SdatEntry<T>>>;
+
+/// Represents a sound archive file.
+#[derive(Debug, Clone)]
+pub struct Sdat {
+    /// Sequences of the archive.
+    pub sequences: SdatEntries<SequenceInfo>,
+
+    /// Sequence archives of the archive.
+    pub sequence_archives: SdatEntries<SequenceArchiveInfo>,
+
+    /// Banks of the archive.
+    pub banks: SdatEntries<BankInfo>,
+
+    /// Wave archives of the archive.
+    pub wave_archives: SdatEntries<WaveArchiveInfo>,
+
+    /// Sequence players of the archive.
+    pub players: SdatEntries<PlayerInfo>,
+
+    /// Groups of the archive.
+    pub groups: SdatEntries<GroupInfo>,
+
+    /// Stream players of the archive.
+    pub stream_players: SdatEntries<StreamPlayerInfo>,
+
+    /// Streams of the archive.
+    pub streams: SdatEntries<StreamInfo>,
+
+    /// Data of the files of the archive, indexed by their ID.
+    pub files: Vec<Vec<u8>>,
+}
+
+impl Sdat {
+    /// Parses a sound archive file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::sound::sdat::Sdat;
+    ///
+    /// let bytes = std::fs::read("sound_data.sdat").unwrap();
+    /// let sdat = Sdat::parse_bytes(&bytes)?;
+    ///
+    /// for (id, sequence) in sdat.sequences.iter().enumerate() {
+    ///     if let Some(sequence) = sequence {
+    ///         println!("{id}: {:?} (bank {})", sequence.name, sequence.info.bank_id);
+    ///     }
+    /// }
+    /// # Ok::<(), sinjoh_nds::sound::SoundError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, SoundError> {
+        let file = NitroFile::parse_bytes(bytes, SDAT_MAGIC, NitroBlockLayout::HeaderTable)
+            .map_err(SoundError::NitroError)?;
+
+        let symbol_block = file.block(SYMB_MAGIC).map(|block| block.data);
+        let info_block = file
+            .block(INFO_MAGIC)
+            .ok_or(SoundError::MissingBlock(INFO_MAGIC))?
+            .data;
+        let fat_block = file
+            .block(FAT_MAGIC)
+            .ok_or(SoundError::MissingBlock(FAT_MAGIC))?
+            .data;
+
+        // Read the names of the entries
+        let record_names = |kind: RecordKind| -> Result<Vec<Option<String>>, SoundError> {
+            match symbol_block {
+                Some(block) => parse_symbol_record(block, kind),
+                None => Ok(Vec::new()),
+            }
+        };
+
+        // Read the files, whose offsets are relative to the start of the archive
+        let file_count = read_u32(fat_block, 0x08)? as usize;
+        let files = (0..file_count)
+            .map(|i| {
+                let entry_offset = 0x0C + i * FAT_ENTRY_SIZE;
+                let offset = read_u32(fat_block, entry_offset)? as usize;
+                let size = read_u32(fat_block, entry_offset + 0x04)? as usize;
+
+                bytes
+                    .get(offset..offset + size)
+                    .map(<[u8]>::to_vec)
+                    .ok_or(SoundError::InvalidFile(i))
+            })
+            .collect::<Result<_, SoundError>>()?;
+
+        // Read the settings of the entries
+        let sequences = parse_info_record(
+            info_block,
+            RecordKind::Sequence,
+            record_names(RecordKind::Sequence)?,
+            |bytes, offset| {
+                Ok(SequenceInfo {
+                    file_id: read_u16(bytes, offset)?,
+                    bank_id: read_u16(bytes, offset + 0x04)?,
+                    volume: read_u8(bytes, offset + 0x06)?,
+                    channel_priority: read_u8(bytes, offset + 0x07)?,
+                    player_priority: read_u8(bytes, offset + 0x08)?,
+                    player_id: read_u8(bytes, offset + 0x09)?,
+                })
+            },
+        )?;
+
+        let sequence_archive_names = match symbol_block {
+            Some(block) => parse_sequence_archive_symbol_record(block)?,
+            None => Vec::new(),
+        };
+        let (names, sequence_names): (Vec<_>, Vec<_>) = sequence_archive_names.into_iter().unzip();
+        let sequence_archives = parse_info_record(
+            info_block,
+            RecordKind::SequenceArchive,
+            names,
+            |bytes, offset| {
+                Ok(SequenceArchiveInfo {
+                    file_id: read_u16(bytes, offset)?,
+                    sequence_names: Vec::new(),
+                })
+            },
+        )?
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            entry.map(|mut entry| {
+                entry.info.sequence_names = sequence_names.get(i).cloned().unwrap_or_default();
+                entry
+            })
+        })
+        .collect();
+
+        let banks = parse_info_record(
+            info_block,
+            RecordKind::Bank,
+            record_names(RecordKind::Bank)?,
+            |bytes, offset| {
+                let mut wave_archive_ids = [None; 4];
+                for (i, wave_archive_id) in wave_archive_ids.iter_mut().enumerate() {
+                    let id = read_u16(bytes, offset + 0x04 + i * 2)?;
+                    if id != UNUSED_WAVE_ARCHIVE_ID {
+                        *wave_archive_id = Some(id);
+                    }
+                }
+
+                Ok(BankInfo {
+                    file_id: read_u16(bytes, offset)?,
+                    wave_archive_ids,
+                })
+            },
+        )?;
+
+        let wave_archives = parse_info_record(
+            info_block,
+            RecordKind::WaveArchive,
+            record_names(RecordKind::WaveArchive)?,
+            |bytes, offset| {
+                Ok(WaveArchiveInfo {
+                    file_id: read_u16(bytes, offset)?,
+                    flags: read_u16(bytes, offset + 0x02)?,
+                })
+            },
+        )?;
+
+        let players = parse_info_record(
+            info_block,
+            RecordKind::Player,
+            record_names(RecordKind::Player)?,
+            |bytes, offset| {
+                Ok(PlayerInfo {
+                    max_sequences: read_u8(bytes, offset)?,
+                    channel_mask: read_u16(bytes, offset + 0x02)?,
+                    heap_size: read_u32(bytes, offset + 0x04)?,
+                })
+            },
+        )?;
+
+        let groups = parse_info_record(
+            info_block,
+            RecordKind::Group,
+            record_names(RecordKind::Group)?,
+            |bytes, offset| {
+                let count = read_u32(bytes, offset)? as usize;
+                let entries = (0..count)
+                    .map(|i| {
+                        let offset = offset + 0x04 + i * GROUP_ENTRY_SIZE;
+
+                        Ok(GroupEntry {
+                            kind: read_u8(bytes, offset)?,
+                            flags: read_u8(bytes, offset + 0x01)?,
+                            index: read_u32(bytes, offset + 0x04)?,
+                        })
+                    })
+                    .collect::<Result<_, SoundError>>()?;
+
+                Ok(GroupInfo { entries })
+            },
+        )?;
+
+        let stream_players = parse_info_record(
+            info_block,
+            RecordKind::StreamPlayer,
+            record_names(RecordKind::StreamPlayer)?,
+            |bytes, offset| {
+                let count = (read_u8(bytes, offset)? as usize).min(STREAM_PLAYER_CHANNEL_COUNT);
+                let channels = read_slice(bytes, offset + 0x01, count)?.to_vec();
+
+                Ok(StreamPlayerInfo { channels })
+            },
+        )?;
+
+        let streams = parse_info_record(
+            info_block,
+            RecordKind::Stream,
+            record_names(RecordKind::Stream)?,
+            |bytes, offset| {
+                Ok(StreamInfo {
+                    file_id: read_u16(bytes, offset)?,
+                    volume: read_u8(bytes, offset + 0x04)?,
+                    player_priority: read_u8(bytes, offset + 0x05)?,
+                    player_id: read_u8(bytes, offset + 0x06)?,
+                })
+            },
+        )?;
+
+        Ok(Self {
+            sequences,
+            sequence_archives,
+            banks,
+            wave_archives,
+            players,
+            groups,
+            stream_players,
+            streams,
+            files,
+        })
+    }
+
+    /// Returns the data of the file with the specified ID, if any.
+    pub fn file(&self, file_id: usize) -> Option<&[u8]> {
+        self.files.get(file_id).map(Vec::as_slice)
+    }
+
+    /// Returns the name of the sequence with the specified ID, if it exists and has one.
+    pub fn sequence_name(&self, sequence_id: usize) -> Option<&str> {
+        self.sequences.get(sequence_id)?.as_ref()?.name.as_deref()
+    }
+
+    /// Returns the ID of the sequence with the specified name, if any.
+    pub fn sequence_id(&self, name: &str) -> Option<usize> {
+        find_by_name(&self.sequences, name)
+    }
+}
+
+/// Returns the ID of the entry with the specified name in a list of entries, if any.
+pub fn find_by_name<T>(entries: &[Option<SdatEntry<T>>], name: &str) -> Option<usize> {
+    entries.iter().position(|entry| {
+        entry
+            .as_ref()
+            .is_some_and(|entry| entry.name.as_deref() == Some(name))
+    })
+}
+
+/// Reads a null-terminated string at the specified offset.
+fn read_string(bytes: &[u8], offset: usize) -> Result<String, SoundError> {
+    let data = bytes
+        .get(offset..)
+        .ok_or(SoundError::TruncatedData(offset))?;
+    let length = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or(SoundError::TruncatedData(bytes.len()))?;
+
+    Ok(String::from_utf8_lossy(&data[..length]).into_owned())
+}
+
+/// Returns the offset of a record of the symbol or info block.
+///
+/// The offsets of the records follow the header of the block, and are relative to the start of
+/// the block.
+fn record_offset(block: &[u8], kind: RecordKind) -> Result<usize, SoundError> {
+    Ok(read_u32(block, 0x08 + kind as usize * 4)? as usize)
+}
+
+/// Reads the offsets of a list of a symbol or info block.
+///
+/// A list is made of a count followed by the offsets of the items, relative to the start of the
+/// block. An offset of 0 means that the item is absent.
+fn read_offset_list(block: &[u8], offset: usize) -> Result<Vec<Option<usize>>, SoundError> {
+    let count = read_u32(block, offset)? as usize;
+
+    (0..count)
+        .map(|i| {
+            let item_offset = read_u32(block, offset + 0x04 + i * 4)? as usize;
+            Ok((item_offset != 0).then_some(item_offset))
+        })
+        .collect()
+}
+
+/// Parses the names of a record of the symbol block.
+fn parse_symbol_record(block: &[u8], kind: RecordKind) -> Result<Vec<Option<String>>, SoundError> {
+    parse_name_list(block, record_offset(block, kind)?)
+}
+
+/// Parses a list of names of the symbol block.
+fn parse_name_list(block: &[u8], offset: usize) -> Result<Vec<Option<String>>, SoundError> {
+    read_offset_list(block, offset)?
+        .into_iter()
+        .map(|name_offset| {
+            name_offset
+                .map(|name_offset| read_string(block, name_offset))
+                .transpose()
+        })
+        .collect()
+}
+
+/// Parses the record of the symbol block holding the names of the sequence archives, along with
+/// the names of the sequences they contain.
+fn parse_sequence_archive_symbol_record(
+    block: &[u8],
+) -> Result<Vec<SequenceArchiveNames>, SoundError> {
+    let offset = record_offset(block, RecordKind::SequenceArchive)?;
+    let count = read_u32(block, offset)? as usize;
+
+    (0..count)
+        .map(|i| {
+            let entry_offset = offset + 0x04 + i * 8;
+            let name_offset = read_u32(block, entry_offset)? as usize;
+            let sequences_offset = read_u32(block, entry_offset + 0x04)? as usize;
+
+            let name = (name_offset != 0)
+                .then(|| read_string(block, name_offset))
+                .transpose()?;
+            let sequence_names = match sequences_offset {
+                0 => Vec::new(),
+                _ => parse_name_list(block, sequences_offset)?,
+            };
+
+            Ok((name, sequence_names))
+        })
+        .collect()
+}
+
+/// Parses the entries of a record of the info block, naming them with the specified names.
+fn parse_info_record<T>(
+    block: &[u8],
+    kind: RecordKind,
+    names: Vec<Option<String>>,
+    parse_entry: impl Fn(&[u8], usize) -> Result<T, SoundError>,
+) -> Result<SdatEntries<T>, SoundError> {
+    let mut names = names.into_iter();
+
+    read_offset_list(block, record_offset(block, kind)?)?
+        .into_iter()
+        .map(|entry_offset| {
+            let name = names.next().flatten();
+
+            entry_offset
+                .map(|entry_offset| {
+                    Ok(SdatEntry {
+                        name,
+                        info: parse_entry(block, entry_offset)?,
+                    })
+                })
+                .transpose()
+        })
+        .collect()
+}
+
+impl FromBytes for Sdat {
+    type Error = SoundError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
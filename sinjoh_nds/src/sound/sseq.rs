@@ -0,0 +1,797 @@
+//! Sequence files (SSEQ, `SSEQ`), which hold music as a stream of commands.
+//!
+//! A sequence file contains a data block (`DATA`), holding the commands of the sequence. The
+//! sequence starts with a single track, which can open up to 15 other tracks, each one running
+//! its own commands (notes, rests, jumps, calls, loops, tempo and volume changes...).
+//!
+//! Sequences can be converted to Type-1 MIDI files (See [`Sseq::to_midi`]).
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::midi::{self, CONTROL_CHANGE, MidiTrack, NOTE_OFF, NOTE_ON, PITCH_BEND, PROGRAM_CHANGE};
+use super::{SoundError, read_u32};
+
+/// The magic number of sequence files.
+pub const SSEQ_MAGIC: [u8; 4] = *b"SSEQ";
+
+/// The magic number of data blocks.
+pub const DATA_MAGIC: [u8; 4] = *b"DATA";
+
+/// The number of ticks in a quarter note.
+pub const TICKS_PER_QUARTER_NOTE: u16 = 48;
+
+/// The maximum number of tracks of a sequence.
+pub const MAX_TRACK_COUNT: usize = 16;
+
+/// The tempo of a sequence until it is changed, in beats per minute.
+const DEFAULT_TEMPO: u16 = 120;
+
+/// The maximum number of commands executed by a track when converting it, which stops tracks
+/// which never end.
+const MAX_EXECUTED_COMMANDS: usize = 1_000_000;
+
+/// The maximum depth of the call and loop stacks of a track.
+const MAX_STACK_DEPTH: usize = 3;
+
+/// An operation applied to a variable of a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableOperation {
+    /// Sets the variable to the value.
+    Set,
+
+    /// Adds the value to the variable.
+    Add,
+
+    /// Subtracts the value from the variable.
+    Subtract,
+
+    /// Multiplies the variable by the value.
+    Multiply,
+
+    /// Divides the variable by the value.
+    Divide,
+
+    /// Shifts the variable to the left by the value, or to the right if it is negative.
+    Shift,
+
+    /// Sets the variable to a random value between 0 and the value.
+    Random,
+
+    /// Sets the condition flag if the variable is equal to the value.
+    Equal,
+
+    /// Sets the condition flag if the variable is greater than or equal to the value.
+    GreaterOrEqual,
+
+    /// Sets the condition flag if the variable is greater than the value.
+    Greater,
+
+    /// Sets the condition flag if the variable is less than or equal to the value.
+    LessOrEqual,
+
+    /// Sets the condition flag if the variable is less than the value.
+    Less,
+
+    /// Sets the condition flag if the variable is not equal to the value.
+    NotEqual,
+
+    /// Unknown operation.
+    Unknown(u8),
+}
+
+impl VariableOperation {
+    /// Returns the operation corresponding to a command byte between `0xB0` and `0xBD`.
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            0xB0 => Self::Set,
+            0xB1 => Self::Add,
+            0xB2 => Self::Subtract,
+            0xB3 => Self::Multiply,
+            0xB4 => Self::Divide,
+            0xB5 => Self::Shift,
+            0xB6 => Self::Random,
+            0xB8 => Self::Equal,
+            0xB9 => Self::GreaterOrEqual,
+            0xBA => Self::Greater,
+            0xBB => Self::LessOrEqual,
+            0xBC => Self::Less,
+            0xBD => Self::NotEqual,
+            _ => Self::Unknown(raw),
+        }
+    }
+}
+
+/// A command of a sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseqCommand {
+    /// Plays a note for a number of ticks.
+    Note {
+        key: u8,
+        velocity: u8,
+        duration: u32,
+    },
+
+    /// Waits for a number of ticks.
+    Rest(u32),
+
+    /// Changes the instrument of the track, as an index in the bank of the sequence.
+    ProgramChange(u32),
+
+    /// Opens a track, starting at an offset in the data of the sequence.
+    OpenTrack { track: u8, offset: u32 },
+
+    /// Jumps to an offset in the data of the sequence.
+    Jump(u32),
+
+    /// Calls the commands at an offset in the data of the sequence, until a return command.
+    Call(u32),
+
+    /// Executes a command whose last argument is a random value between `min` and `max`.
+    ///
+    /// The last argument of the command is set to `min`.
+    Random {
+        command: Box<SseqCommand>,
+        min: i16,
+        max: i16,
+    },
+
+    /// Executes a command whose last argument is the value of a variable.
+    ///
+    /// The last argument of the command is set to 0.
+    FromVariable {
+        command: Box<SseqCommand>,
+        variable: u8,
+    },
+
+    /// Executes the next command only if the condition flag is set.
+    If,
+
+    /// Applies an operation to a variable.
+    Variable {
+        operation: VariableOperation,
+        variable: u8,
+        value: i16,
+    },
+
+    /// Changes the pan of the track (0 is left, 64 is center, 127 is right).
+    Pan(u8),
+
+    /// Changes the volume of the track.
+    Volume(u8),
+
+    /// Changes the volume of the whole sequence.
+    MasterVolume(u8),
+
+    /// Changes the number of semitones added to the keys of the notes.
+    Transpose(i8),
+
+    /// Changes the pitch bend of the track.
+    PitchBend(i8),
+
+    /// Changes the range of the pitch bend, in semitones.
+    PitchBendRange(u8),
+
+    /// Changes the priority of the track.
+    Priority(u8),
+
+    /// Changes whether the track waits for the end of each note before executing the next
+    /// command.
+    NoteWait(bool),
+
+    /// Changes whether the notes of the track are tied together.
+    Tie(bool),
+
+    /// Changes the key the portamento starts from.
+    PortamentoKey(u8),
+
+    /// Changes the depth of the modulation.
+    ModulationDepth(u8),
+
+    /// Changes the speed of the modulation.
+    ModulationSpeed(u8),
+
+    /// Changes the type of the modulation (pitch, volume or pan).
+    ModulationType(u8),
+
+    /// Changes the range of the modulation.
+    ModulationRange(u8),
+
+    /// Changes whether the portamento is enabled.
+    Portamento(bool),
+
+    /// Changes the duration of the portamento.
+    PortamentoTime(u8),
+
+    /// Changes the attack rate of the envelope.
+    Attack(u8),
+
+    /// Changes the decay rate of the envelope.
+    Decay(u8),
+
+    /// Changes the sustain level of the envelope.
+    Sustain(u8),
+
+    /// Changes the release rate of the envelope.
+    Release(u8),
+
+    /// Starts a loop played a number of times, or infinitely if the count is 0.
+    LoopStart(u8),
+
+    /// Changes the expression of the track.
+    Expression(u8),
+
+    /// Prints the value of a variable (only used for debugging).
+    PrintVariable(u8),
+
+    /// Changes the delay before the modulation starts.
+    ModulationDelay(u16),
+
+    /// Changes the tempo of the sequence, in beats per minute.
+    Tempo(u16),
+
+    /// Changes the pitch sweep of the track.
+    SweepPitch(i16),
+
+    /// Ends a loop.
+    LoopEnd,
+
+    /// Returns from a call.
+    Return,
+
+    /// Allocates the tracks whose bits are set, at the start of the sequence.
+    AllocateTracks(u16),
+
+    /// Ends the track.
+    EndOfTrack,
+}
+
+impl SseqCommand {
+    /// Returns the command with its last argument replaced by the specified value, as done by
+    /// the random and variable commands.
+    ///
+    /// Commands without arguments are returned unchanged.
+    pub fn with_last_argument(self, value: i16) -> Self {
+        let duration = value.max(0) as u32;
+
+        match self {
+            Self::Note { key, velocity, .. } => Self::Note {
+                key,
+                velocity,
+                duration,
+            },
+            Self::Rest(_) => Self::Rest(duration),
+            Self::ProgramChange(_) => Self::ProgramChange(duration),
+            Self::Variable {
+                operation,
+                variable,
+                ..
+            } => Self::Variable {
+                operation,
+                variable,
+                value,
+            },
+            Self::Pan(_) => Self::Pan(value as u8),
+            Self::Volume(_) => Self::Volume(value as u8),
+            Self::MasterVolume(_) => Self::MasterVolume(value as u8),
+            Self::Transpose(_) => Self::Transpose(value as i8),
+            Self::PitchBend(_) => Self::PitchBend(value as i8),
+            Self::PitchBendRange(_) => Self::PitchBendRange(value as u8),
+            Self::Priority(_) => Self::Priority(value as u8),
+            Self::NoteWait(_) => Self::NoteWait(value != 0),
+            Self::Tie(_) => Self::Tie(value != 0),
+            Self::PortamentoKey(_) => Self::PortamentoKey(value as u8),
+            Self::ModulationDepth(_) => Self::ModulationDepth(value as u8),
+            Self::ModulationSpeed(_) => Self::ModulationSpeed(value as u8),
+            Self::ModulationType(_) => Self::ModulationType(value as u8),
+            Self::ModulationRange(_) => Self::ModulationRange(value as u8),
+            Self::Portamento(_) => Self::Portamento(value != 0),
+            Self::PortamentoTime(_) => Self::PortamentoTime(value as u8),
+            Self::Attack(_) => Self::Attack(value as u8),
+            Self::Decay(_) => Self::Decay(value as u8),
+            Self::Sustain(_) => Self::Sustain(value as u8),
+            Self::Release(_) => Self::Release(value as u8),
+            Self::LoopStart(_) => Self::LoopStart(value as u8),
+            Self::Expression(_) => Self::Expression(value as u8),
+            Self::PrintVariable(_) => Self::PrintVariable(value as u8),
+            Self::ModulationDelay(_) => Self::ModulationDelay(value as u16),
+            Self::Tempo(_) => Self::Tempo(value as u16),
+            Self::SweepPitch(_) => Self::SweepPitch(value),
+            command => command,
+        }
+    }
+}
+
+/// Reads the arguments of commands.
+struct CommandReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+
+    /// Whether the last argument of the command is skipped, as it is given by a random or
+    /// variable command.
+    skip_last_argument: bool,
+}
+
+impl CommandReader<'_> {
+    fn u8(&mut self) -> Result<u8, SoundError> {
+        let value = *self
+            .data
+            .get(self.offset)
+            .ok_or(SoundError::TruncatedData(self.offset))?;
+        self.offset += 1;
+
+        Ok(value)
+    }
+
+    fn u16(&mut self) -> Result<u16, SoundError> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u24(&mut self) -> Result<u32, SoundError> {
+        Ok(u32::from_le_bytes([self.u8()?, self.u8()?, self.u8()?, 0]))
+    }
+
+    fn variable_length(&mut self) -> Result<u32, SoundError> {
+        let mut value = 0;
+
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn last_u8(&mut self) -> Result<u8, SoundError> {
+        match self.skip_last_argument {
+            true => Ok(0),
+            false => self.u8(),
+        }
+    }
+
+    fn last_u16(&mut self) -> Result<u16, SoundError> {
+        match self.skip_last_argument {
+            true => Ok(0),
+            false => self.u16(),
+        }
+    }
+
+    fn last_variable_length(&mut self) -> Result<u32, SoundError> {
+        match self.skip_last_argument {
+            true => Ok(0),
+            false => self.variable_length(),
+        }
+    }
+
+    /// Reads a command, starting with its command byte.
+    fn command(&mut self) -> Result<SseqCommand, SoundError> {
+        let offset = self.offset;
+        let command = self.u8()?;
+
+        Ok(match command {
+            0x00..=0x7F => SseqCommand::Note {
+                key: command,
+                velocity: self.u8()?,
+                duration: self.last_variable_length()?,
+            },
+            0x80 => SseqCommand::Rest(self.last_variable_length()?),
+            0x81 => SseqCommand::ProgramChange(self.last_variable_length()?),
+            0x93 => SseqCommand::OpenTrack {
+                track: self.u8()?,
+                offset: self.u24()?,
+            },
+            0x94 => SseqCommand::Jump(self.u24()?),
+            0x95 => SseqCommand::Call(self.u24()?),
+            0xA0 => {
+                let command = self.prefixed_command()?;
+                let min = self.u16()? as i16;
+                let max = self.u16()? as i16;
+
+                SseqCommand::Random {
+                    command: Box::new(command.with_last_argument(min)),
+                    min,
+                    max,
+                }
+            }
+            0xA1 => {
+                let command = self.prefixed_command()?;
+                let variable = self.u8()?;
+
+                SseqCommand::FromVariable {
+                    command: Box::new(command),
+                    variable,
+                }
+            }
+            0xA2 => SseqCommand::If,
+            0xB0..=0xBD => SseqCommand::Variable {
+                operation: VariableOperation::from_raw(command),
+                variable: self.u8()?,
+                value: self.last_u16()? as i16,
+            },
+            0xC0 => SseqCommand::Pan(self.last_u8()?),
+            0xC1 => SseqCommand::Volume(self.last_u8()?),
+            0xC2 => SseqCommand::MasterVolume(self.last_u8()?),
+            0xC3 => SseqCommand::Transpose(self.last_u8()? as i8),
+            0xC4 => SseqCommand::PitchBend(self.last_u8()? as i8),
+            0xC5 => SseqCommand::PitchBendRange(self.last_u8()?),
+            0xC6 => SseqCommand::Priority(self.last_u8()?),
+            0xC7 => SseqCommand::NoteWait(self.last_u8()? != 0),
+            0xC8 => SseqCommand::Tie(self.last_u8()? != 0),
+            0xC9 => SseqCommand::PortamentoKey(self.last_u8()?),
+            0xCA => SseqCommand::ModulationDepth(self.last_u8()?),
+            0xCB => SseqCommand::ModulationSpeed(self.last_u8()?),
+            0xCC => SseqCommand::ModulationType(self.last_u8()?),
+            0xCD => SseqCommand::ModulationRange(self.last_u8()?),
+            0xCE => SseqCommand::Portamento(self.last_u8()? != 0),
+            0xCF => SseqCommand::PortamentoTime(self.last_u8()?),
+            0xD0 => SseqCommand::Attack(self.last_u8()?),
+            0xD1 => SseqCommand::Decay(self.last_u8()?),
+            0xD2 => SseqCommand::Sustain(self.last_u8()?),
+            0xD3 => SseqCommand::Release(self.last_u8()?),
+            0xD4 => SseqCommand::LoopStart(self.last_u8()?),
+            0xD5 => SseqCommand::Expression(self.last_u8()?),
+            0xD6 => SseqCommand::PrintVariable(self.last_u8()?),
+            0xE0 => SseqCommand::ModulationDelay(self.last_u16()?),
+            0xE1 => SseqCommand::Tempo(self.last_u16()?),
+            0xE3 => SseqCommand::SweepPitch(self.last_u16()? as i16),
+            0xFC => SseqCommand::LoopEnd,
+            0xFD => SseqCommand::Return,
+            0xFE => SseqCommand::AllocateTracks(self.u16()?),
+            0xFF => SseqCommand::EndOfTrack,
+            _ => return Err(SoundError::UnknownCommand(command, offset)),
+        })
+    }
+
+    /// Reads the command following a random or variable command, without its last argument.
+    fn prefixed_command(&mut self) -> Result<SseqCommand, SoundError> {
+        let skip_last_argument = self.skip_last_argument;
+
+        self.skip_last_argument = true;
+        let command = self.command();
+        self.skip_last_argument = skip_last_argument;
+
+        command
+    }
+}
+
+/// An entry of the call and loop stack of a track.
+#[derive(Debug, Clone, Copy)]
+enum StackEntry {
+    /// A call, returning to the offset.
+    Call(usize),
+
+    /// A loop, starting at the offset, with the number of times the loop is still played (or
+    /// `None` for an infinite loop) and the number of times it was played.
+    Loop(usize, Option<u8>, u32),
+}
+
+/// The state of a track, while converting it to MIDI.
+struct TrackPlayer<'a> {
+    sequence: &'a Sseq,
+    channel: u8,
+    loop_count: u32,
+    offset: usize,
+    tick: u32,
+    note_wait: bool,
+    transpose: i8,
+    condition: bool,
+    variables: [i16; 256],
+    stack: Vec<StackEntry>,
+    backward_jump_counts: HashMap<usize, u32>,
+}
+
+impl TrackPlayer<'_> {
+    /// Plays the track, adding its events to the MIDI tracks.
+    ///
+    /// Tempo changes are added to the conductor track, and tracks opened by this track are added
+    /// to the list of tracks.
+    fn play(
+        &mut self,
+        track: &mut MidiTrack,
+        conductor: &mut MidiTrack,
+        opened_tracks: &mut BTreeMap<u8, usize>,
+    ) -> Result<(), SoundError> {
+        for _ in 0..MAX_EXECUTED_COMMANDS {
+            let command_offset = self.offset;
+            let (command, next_offset) = self.sequence.command_at(command_offset)?;
+            self.offset = next_offset;
+
+            // Resolve the commands whose last argument is given separately
+            let command = match command {
+                SseqCommand::Random { command, .. } => *command,
+                SseqCommand::FromVariable { command, variable } => {
+                    command.with_last_argument(self.variables[variable as usize])
+                }
+                SseqCommand::If => {
+                    if !self.condition {
+                        self.offset = self.sequence.command_at(self.offset)?.1;
+                    }
+
+                    continue;
+                }
+                command => command,
+            };
+
+            match command {
+                SseqCommand::Note {
+                    key,
+                    velocity,
+                    duration,
+                } => {
+                    let key = (key as i16 + self.transpose as i16).clamp(0, 127) as u8;
+                    let velocity = velocity.clamp(1, 127);
+
+                    track.push_channel_event(self.tick, NOTE_ON, self.channel, &[key, velocity]);
+                    track.push_channel_event(
+                        self.tick + duration,
+                        NOTE_OFF,
+                        self.channel,
+                        &[key, 0],
+                    );
+
+                    if self.note_wait {
+                        self.tick += duration;
+                    }
+                }
+                SseqCommand::Rest(duration) => self.tick += duration,
+                SseqCommand::ProgramChange(program) => {
+                    // Programs above 127 are selected using the bank select controller
+                    self.control_change(track, 0x00, (program >> 7) as u8);
+                    track.push_channel_event(
+                        self.tick,
+                        PROGRAM_CHANGE,
+                        self.channel,
+                        &[(program & 0x7F) as u8],
+                    );
+                }
+                SseqCommand::OpenTrack {
+                    track: track_index,
+                    offset,
+                } => {
+                    opened_tracks.insert(track_index, offset as usize);
+                }
+                SseqCommand::Jump(offset) => {
+                    // Backward jumps loop the track forever, so they are only followed a limited
+                    // number of times
+                    let offset = offset as usize;
+                    if offset <= command_offset {
+                        let count = self.backward_jump_counts.entry(command_offset).or_default();
+                        *count += 1;
+
+                        if *count >= self.loop_count {
+                            break;
+                        }
+                    }
+
+                    self.offset = offset;
+                }
+                SseqCommand::Call(offset) => {
+                    if self.stack.len() < MAX_STACK_DEPTH {
+                        self.stack.push(StackEntry::Call(self.offset));
+                        self.offset = offset as usize;
+                    }
+                }
+                SseqCommand::Return => {
+                    if let Some(StackEntry::Call(offset)) = self.stack.last().copied() {
+                        self.stack.pop();
+                        self.offset = offset;
+                    }
+                }
+                SseqCommand::LoopStart(count) => {
+                    if self.stack.len() < MAX_STACK_DEPTH {
+                        let count = (count != 0).then_some(count);
+                        self.stack.push(StackEntry::Loop(self.offset, count, 1));
+                    }
+                }
+                SseqCommand::LoopEnd => {
+                    if let Some(StackEntry::Loop(start, count, played)) = self.stack.last().copied()
+                    {
+                        let play_again = match count {
+                            Some(count) => played < count as u32,
+                            None => played < self.loop_count,
+                        };
+
+                        self.stack.pop();
+                        if play_again {
+                            self.stack.push(StackEntry::Loop(start, count, played + 1));
+                            self.offset = start;
+                        }
+                    }
+                }
+                SseqCommand::Variable {
+                    operation,
+                    variable,
+                    value,
+                } => self.apply_variable_operation(operation, variable, value),
+                SseqCommand::Pan(pan) => self.control_change(track, 0x0A, pan),
+                SseqCommand::Volume(volume) => self.control_change(track, 0x07, volume),
+                SseqCommand::Expression(expression) => self.control_change(track, 0x0B, expression),
+                SseqCommand::ModulationDepth(depth) => self.control_change(track, 0x01, depth),
+                SseqCommand::Portamento(enabled) => {
+                    self.control_change(track, 0x41, if enabled { 127 } else { 0 })
+                }
+                SseqCommand::PortamentoTime(time) => self.control_change(track, 0x05, time),
+                SseqCommand::Transpose(transpose) => self.transpose = transpose,
+                SseqCommand::PitchBend(bend) => {
+                    let value = (8192 + bend as i32 * 64).clamp(0, 16383) as u16;
+                    track.push_channel_event(
+                        self.tick,
+                        PITCH_BEND,
+                        self.channel,
+                        &[(value & 0x7F) as u8, (value >> 7) as u8],
+                    );
+                }
+                SseqCommand::PitchBendRange(range) => {
+                    // Set the pitch bend sensitivity registered parameter
+                    self.control_change(track, 0x65, 0);
+                    self.control_change(track, 0x64, 0);
+                    self.control_change(track, 0x06, range);
+                }
+                SseqCommand::NoteWait(note_wait) => self.note_wait = note_wait,
+                SseqCommand::Tempo(tempo) => conductor.push_tempo(self.tick, tempo),
+                SseqCommand::EndOfTrack => break,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a control change event to the track.
+    fn control_change(&self, track: &mut MidiTrack, controller: u8, value: u8) {
+        track.push_channel_event(
+            self.tick,
+            CONTROL_CHANGE,
+            self.channel,
+            &[controller, value.min(127)],
+        );
+    }
+
+    /// Applies an operation to a variable of the track.
+    fn apply_variable_operation(&mut self, operation: VariableOperation, variable: u8, value: i16) {
+        let variable = &mut self.variables[variable as usize];
+
+        match operation {
+            VariableOperation::Set => *variable = value,
+            VariableOperation::Add => *variable = variable.wrapping_add(value),
+            VariableOperation::Subtract => *variable = variable.wrapping_sub(value),
+            VariableOperation::Multiply => *variable = variable.wrapping_mul(value),
+            VariableOperation::Divide => {
+                if value != 0 {
+                    *variable = variable.wrapping_div(value);
+                }
+            }
+            VariableOperation::Shift => {
+                *variable = match value >= 0 {
+                    true => variable.wrapping_shl(value as u32),
+                    false => variable.wrapping_shr(value.unsigned_abs() as u32),
+                }
+            }
+            // Random values are replaced by their lower bound
+            VariableOperation::Random => *variable = 0,
+            VariableOperation::Equal => self.condition = *variable == value,
+            VariableOperation::GreaterOrEqual => self.condition = *variable >= value,
+            VariableOperation::Greater => self.condition = *variable > value,
+            VariableOperation::LessOrEqual => self.condition = *variable <= value,
+            VariableOperation::Less => self.condition = *variable < value,
+            VariableOperation::NotEqual => self.condition = *variable != value,
+            VariableOperation::Unknown(_) => {}
+        }
+    }
+}
+
+/// Represents a sequence file.
+#[derive(Debug, Clone)]
+pub struct Sseq {
+    /// Commands of the sequence, as stored in the file.
+    ///
+    /// The offsets used by the commands are relative to the start of this data.
+    pub data: Vec<u8>,
+}
+
+impl Sseq {
+    /// Parses a sequence file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::sound::sdat::Sdat;
+    /// use sinjoh_nds::sound::sseq::Sseq;
+    ///
+    /// let sdat = Sdat::parse_bytes(&std::fs::read("sound_data.sdat").unwrap())?;
+    /// let sequence = sdat.sequences[1000].as_ref().unwrap();
+    /// let sseq = Sseq::parse_bytes(sdat.file(sequence.info.file_id.into()).unwrap())?;
+    ///
+    /// std::fs::write("sequence.mid", sseq.to_midi(2)?).unwrap();
+    /// # Ok::<(), sinjoh_nds::sound::SoundError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, SoundError> {
+        let file = NitroFile::parse_bytes(bytes, SSEQ_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(SoundError::NitroError)?;
+
+        let block = file
+            .block(DATA_MAGIC)
+            .ok_or(SoundError::MissingBlock(DATA_MAGIC))?;
+
+        // The offset of the commands is relative to the start of the file
+        let data_offset = read_u32(block.data, 0x08)? as usize;
+        let data = bytes
+            .get(data_offset..block.offset + block.data.len())
+            .ok_or(SoundError::TruncatedData(data_offset))?
+            .to_vec();
+
+        Ok(Self { data })
+    }
+
+    /// Decodes the command at the specified offset of the data of the sequence.
+    ///
+    /// Returns the command, along with the offset of the next command.
+    pub fn command_at(&self, offset: usize) -> Result<(SseqCommand, usize), SoundError> {
+        let mut reader = CommandReader {
+            data: &self.data,
+            offset,
+            skip_last_argument: false,
+        };
+
+        let command = reader.command()?;
+        Ok((command, reader.offset))
+    }
+
+    /// Converts the sequence to a Type-1 MIDI file.
+    ///
+    /// The first track of the MIDI file holds the tempo changes, and each track of the sequence
+    /// is converted to its own MIDI track, using the MIDI channel with the index of the track.
+    ///
+    /// Sequences usually loop forever, so their infinite loops are played `loop_count` times.
+    /// Random values are replaced by their lower bound, and the instruments are the indices of
+    /// the instruments in the bank of the sequence, as they do not match the General MIDI
+    /// instruments.
+    pub fn to_midi(&self, loop_count: u32) -> Result<Vec<u8>, SoundError> {
+        let mut conductor = MidiTrack::default();
+        conductor.push_tempo(0, DEFAULT_TEMPO);
+
+        // The first track starts at the start of the data, and can open the other tracks
+        let mut opened_tracks = BTreeMap::new();
+        let mut tracks = BTreeMap::new();
+        let mut pending_tracks = vec![(0, 0)];
+
+        while let Some((track_index, offset)) = pending_tracks.pop() {
+            let mut track = MidiTrack::default();
+            track.push_track_name(&format!("Track {track_index}"));
+
+            let mut player = TrackPlayer {
+                sequence: self,
+                channel: track_index,
+                loop_count: loop_count.max(1),
+                offset,
+                tick: 0,
+                note_wait: true,
+                transpose: 0,
+                condition: true,
+                variables: [0; 256],
+                stack: Vec::new(),
+                backward_jump_counts: HashMap::new(),
+            };
+            player.play(&mut track, &mut conductor, &mut opened_tracks)?;
+            track.extend_to(player.tick);
+            tracks.insert(track_index, track);
+
+            for (track_index, offset) in std::mem::take(&mut opened_tracks) {
+                if (track_index as usize) < MAX_TRACK_COUNT && !tracks.contains_key(&track_index) {
+                    pending_tracks.push((track_index, offset));
+                }
+            }
+        }
+
+        let midi_tracks = std::iter::once(conductor)
+            .chain(tracks.into_values())
+            .collect::<Vec<_>>();
+
+        Ok(midi::write_midi_file(&midi_tracks, TICKS_PER_QUARTER_NOTE))
+    }
+}
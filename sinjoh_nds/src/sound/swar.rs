@@ -0,0 +1,71 @@
+//! Wave archive files (SWAR, `SWAR`), which hold the samples used by the instruments of banks.
+//!
+//! A wave archive contains a data block (`DATA`), holding the offsets of the waves of the archive
+//! followed by the waves themselves, each one made of its settings and its data (See [`Swav`]).
+
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::swav::Swav;
+use super::{SoundError, read_u32};
+
+/// The magic number of wave archive files.
+pub const SWAR_MAGIC: [u8; 4] = *b"SWAR";
+
+/// The magic number of data blocks.
+pub const DATA_MAGIC: [u8; 4] = *b"DATA";
+
+/// Represents a wave archive file.
+#[derive(Debug, Clone)]
+pub struct Swar {
+    /// Waves of the archive, indexed by their ID.
+    pub waves: Vec<Swav>,
+}
+
+impl Swar {
+    /// Parses a wave archive file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::sound::swar::Swar;
+    ///
+    /// let bytes = std::fs::read("waves.swar").unwrap();
+    /// let swar = Swar::parse_bytes(&bytes)?;
+    ///
+    /// for (i, wave) in swar.waves.iter().enumerate() {
+    ///     std::fs::write(format!("wave_{i}.wav"), wave.to_wav()).unwrap();
+    /// }
+    /// # Ok::<(), sinjoh_nds::sound::SoundError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, SoundError> {
+        let file = NitroFile::parse_bytes(bytes, SWAR_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(SoundError::NitroError)?;
+
+        let block = file
+            .block(DATA_MAGIC)
+            .ok_or(SoundError::MissingBlock(DATA_MAGIC))?;
+
+        // The offsets of the waves are relative to the start of the file, and each wave ends
+        // where the next one starts
+        let wave_count = read_u32(block.data, 0x28)? as usize;
+        let offsets = (0..wave_count)
+            .map(|i| Ok(read_u32(block.data, 0x2C + i * 4)? as usize))
+            .collect::<Result<Vec<_>, SoundError>>()?;
+
+        let block_end = block.offset + block.data.len();
+        let waves = offsets
+            .iter()
+            .enumerate()
+            .map(|(i, &offset)| {
+                let end = offsets.get(i + 1).copied().unwrap_or(block_end);
+                let wave = bytes
+                    .get(offset..end.max(offset))
+                    .ok_or(SoundError::TruncatedData(offset))?;
+
+                Swav::parse_wave(wave)
+            })
+            .collect::<Result<_, SoundError>>()?;
+
+        Ok(Self { waves })
+    }
+}
@@ -0,0 +1,267 @@
+//! Wave files (SWAV, `SWAV`), which hold a single sample played by instruments.
+//!
+//! A wave file contains a data block (`DATA`), holding the settings of the sample (encoding,
+//! sample rate and loop points) followed by its data. The same layout is used by the waves
+//! stored in wave archives (See [`Swar`](super::swar::Swar)).
+//!
+//! Waves can be decoded to 16-bit samples (See [`Swav::decode`]), and converted to WAV files
+//! keeping their loop points (See [`Swav::to_wav`]).
+
+use crate::nitro::{NitroBlockLayout, NitroFile};
+
+use super::{SoundError, read_u8, read_u16, read_u32};
+
+/// The magic number of wave files.
+pub const SWAV_MAGIC: [u8; 4] = *b"SWAV";
+
+/// The magic number of data blocks.
+pub const DATA_MAGIC: [u8; 4] = *b"DATA";
+
+/// The size of the settings of a wave, preceding its data.
+pub const WAVE_INFO_SIZE: usize = 12;
+
+/// The size of the header of IMA-ADPCM data, holding the initial sample and step index.
+const ADPCM_HEADER_SIZE: usize = 4;
+
+/// The step sizes of the IMA-ADPCM encoding.
+const ADPCM_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// The changes of the step index of the IMA-ADPCM encoding, by nibble (without its sign bit).
+const ADPCM_INDEX_TABLE: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// The encoding of the samples of a wave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaveEncoding {
+    /// Signed 8-bit PCM.
+    Pcm8,
+
+    /// Signed 16-bit PCM.
+    Pcm16,
+
+    /// 4-bit IMA-ADPCM.
+    ImaAdpcm,
+}
+
+impl WaveEncoding {
+    /// Returns the encoding corresponding to the value found in the settings of a wave.
+    pub fn from_raw(raw: u8) -> Result<Self, SoundError> {
+        match raw {
+            0 => Ok(Self::Pcm8),
+            1 => Ok(Self::Pcm16),
+            2 => Ok(Self::ImaAdpcm),
+            _ => Err(SoundError::UnsupportedWaveEncoding(raw)),
+        }
+    }
+}
+
+/// Represents a wave.
+#[derive(Debug, Clone)]
+pub struct Swav {
+    /// Encoding of the samples.
+    pub encoding: WaveEncoding,
+
+    /// Whether the wave loops.
+    pub looped: bool,
+
+    /// Sample rate of the wave, in Hz.
+    pub sample_rate: u16,
+
+    /// Value of the timer of the sound channel playing the wave at its sample rate.
+    pub timer: u16,
+
+    /// Start of the loop, in 32-bit words from the start of the data.
+    pub loop_offset: u16,
+
+    /// Length of the loop, in 32-bit words.
+    pub loop_length: u32,
+
+    /// Data of the samples, as stored in the file.
+    pub data: Vec<u8>,
+}
+
+impl Swav {
+    /// Parses a wave file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::sound::swav::Swav;
+    ///
+    /// let bytes = std::fs::read("sample.swav").unwrap();
+    /// let swav = Swav::parse_bytes(&bytes)?;
+    ///
+    /// std::fs::write("sample.wav", swav.to_wav()).unwrap();
+    /// # Ok::<(), sinjoh_nds::sound::SoundError>(())
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, SoundError> {
+        let file = NitroFile::parse_bytes(bytes, SWAV_MAGIC, NitroBlockLayout::Sequential)
+            .map_err(SoundError::NitroError)?;
+
+        let block = file
+            .block(DATA_MAGIC)
+            .ok_or(SoundError::MissingBlock(DATA_MAGIC))?;
+
+        let wave = block
+            .data
+            .get(0x08..)
+            .ok_or(SoundError::TruncatedData(block.offset))?;
+
+        Self::parse_wave(wave)
+    }
+
+    /// Parses a wave made of its settings followed by its data, as stored in wave files and
+    /// wave archives.
+    pub fn parse_wave(bytes: &[u8]) -> Result<Self, SoundError> {
+        let encoding = WaveEncoding::from_raw(read_u8(bytes, 0x00)?)?;
+        let looped = read_u8(bytes, 0x01)? != 0;
+        let sample_rate = read_u16(bytes, 0x02)?;
+        let timer = read_u16(bytes, 0x04)?;
+        let loop_offset = read_u16(bytes, 0x06)?;
+        let loop_length = read_u32(bytes, 0x08)?;
+
+        // The data is limited to the end of the loop, as some waves are followed by padding
+        let data_size = (loop_offset as usize + loop_length as usize) * 4;
+        let data = &bytes[WAVE_INFO_SIZE..];
+        let data = data.get(..data_size).unwrap_or(data).to_vec();
+
+        Ok(Self {
+            encoding,
+            looped,
+            sample_rate,
+            timer,
+            loop_offset,
+            loop_length,
+            data,
+        })
+    }
+
+    /// Returns the number of samples stored in the specified number of bytes of data.
+    fn bytes_to_samples(&self, size: usize) -> usize {
+        match self.encoding {
+            WaveEncoding::Pcm8 => size,
+            WaveEncoding::Pcm16 => size / 2,
+            WaveEncoding::ImaAdpcm => size.saturating_sub(ADPCM_HEADER_SIZE) * 2,
+        }
+    }
+
+    /// Returns the index of the first sample of the loop.
+    pub fn loop_start_sample(&self) -> usize {
+        self.bytes_to_samples(self.loop_offset as usize * 4)
+    }
+
+    /// Returns the number of samples of the wave.
+    pub fn sample_count(&self) -> usize {
+        self.bytes_to_samples(self.data.len())
+    }
+
+    /// Decodes the samples of the wave to signed 16-bit samples.
+    pub fn decode(&self) -> Vec<i16> {
+        match self.encoding {
+            WaveEncoding::Pcm8 => self
+                .data
+                .iter()
+                .map(|&sample| ((sample as i8) as i16) << 8)
+                .collect(),
+            WaveEncoding::Pcm16 => self
+                .data
+                .chunks_exact(2)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                .collect(),
+            WaveEncoding::ImaAdpcm => decode_ima_adpcm(&self.data),
+        }
+    }
+
+    /// Converts the wave to a mono 16-bit PCM WAV file.
+    ///
+    /// If the wave loops, the loop points are stored in a sampler (`smpl`) chunk.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let samples = self.decode();
+        let data_size = samples.len() * 2;
+
+        let mut chunks = Vec::new();
+
+        // Format chunk
+        chunks.extend_from_slice(b"fmt ");
+        chunks.extend_from_slice(&16u32.to_le_bytes());
+        chunks.extend_from_slice(&1u16.to_le_bytes());
+        chunks.extend_from_slice(&1u16.to_le_bytes());
+        chunks.extend_from_slice(&(self.sample_rate as u32).to_le_bytes());
+        chunks.extend_from_slice(&(self.sample_rate as u32 * 2).to_le_bytes());
+        chunks.extend_from_slice(&2u16.to_le_bytes());
+        chunks.extend_from_slice(&16u16.to_le_bytes());
+
+        // Data chunk
+        chunks.extend_from_slice(b"data");
+        chunks.extend_from_slice(&(data_size as u32).to_le_bytes());
+        for sample in samples {
+            chunks.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        // Sampler chunk, holding the loop points (the end of the loop is inclusive)
+        if self.looped && self.loop_length != 0 {
+            let loop_start = self.loop_start_sample() as u32;
+            let loop_end = (self.sample_count() as u32).saturating_sub(1);
+            let sample_period = 1_000_000_000 / u32::from(self.sample_rate.max(1));
+
+            chunks.extend_from_slice(b"smpl");
+            chunks.extend_from_slice(&60u32.to_le_bytes());
+            for value in [0, 0, sample_period, 60, 0, 0, 0, 1, 0] {
+                chunks.extend_from_slice(&u32::to_le_bytes(value));
+            }
+            for value in [0, 0, loop_start, loop_end, 0, 0] {
+                chunks.extend_from_slice(&u32::to_le_bytes(value));
+            }
+        }
+
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(4 + chunks.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend(chunks);
+        bytes
+    }
+}
+
+/// Decodes IMA-ADPCM data, starting with its header, to signed 16-bit samples.
+fn decode_ima_adpcm(data: &[u8]) -> Vec<i16> {
+    if data.len() < ADPCM_HEADER_SIZE {
+        return Vec::new();
+    }
+
+    let mut predictor = i16::from_le_bytes([data[0], data[1]]) as i32;
+    let mut step_index = (data[2] as i32).clamp(0, 88);
+
+    let mut samples = Vec::with_capacity((data.len() - ADPCM_HEADER_SIZE) * 2);
+    for &byte in &data[ADPCM_HEADER_SIZE..] {
+        // The low nibble holds the first sample
+        for nibble in [byte & 0x0F, byte >> 4] {
+            let step = ADPCM_STEP_TABLE[step_index as usize];
+
+            let mut difference = step >> 3;
+            if nibble & 0x01 != 0 {
+                difference += step >> 2;
+            }
+            if nibble & 0x02 != 0 {
+                difference += step >> 1;
+            }
+            if nibble & 0x04 != 0 {
+                difference += step;
+            }
+
+            predictor = match nibble & 0x08 != 0 {
+                true => (predictor - difference).max(-0x7FFF),
+                false => (predictor + difference).min(0x7FFF),
+            };
+            step_index = (step_index + ADPCM_INDEX_TABLE[(nibble & 0x07) as usize]).clamp(0, 88);
+
+            samples.push(predictor as i16);
+        }
+    }
+
+    samples
+}
@@ -1,4 +1,4 @@
-//! Data structure and parser for area data files.
+//! Data structure, parser and writer for area data files.
 //!
 //! Those are the files contained in the `area_data.narc` archive.
 //!
@@ -7,6 +7,23 @@
 //! They are used to group maps together, and a map matrix can have multiple areas. On the contrary,
 //! a map can only belong to one area.
 
+use std::convert::Infallible;
+
+use thiserror::Error;
+
+use sinjoh_nds::bytes::{FromBytes, ToBytes};
+
+/// The size of an area data file.
+pub const AREA_DATA_SIZE: usize = 8;
+
+/// Error type for area data parsing.
+#[derive(Error, Debug)]
+pub enum AreaDataError {
+    /// The area data file does not have the expected size.
+    #[error("wrong area data size (expected {AREA_DATA_SIZE}, found {0})")]
+    WrongSize(usize),
+}
+
 /// Represents an area data file.
 #[derive(Debug, Clone, Copy)]
 pub struct AreaData {
@@ -28,7 +45,7 @@ impl AreaData {
     ///
     /// It is expected that the slice is in the same format as the one found in the `area_data.narc`
     /// archive.
-    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+    pub fn from_bytes(bytes: [u8; AREA_DATA_SIZE]) -> Self {
         // Safety: slice length is explicitly specified, and the length of the `bytes` array is
         // known
         Self {
@@ -38,4 +55,50 @@ impl AreaData {
             dummy: u16::from_le_bytes(bytes[4..=5].try_into().unwrap()),
         }
     }
+
+    /// Serializes an [`AreaData`] to a byte array.
+    ///
+    /// The array is in the same format as the one found in the `area_data.narc` archive.
+    pub fn to_bytes(&self) -> [u8; AREA_DATA_SIZE] {
+        let mut bytes = [0; AREA_DATA_SIZE];
+
+        bytes[0..=1].copy_from_slice(&self.map_prop_archives_id.to_le_bytes());
+        bytes[2..=3].copy_from_slice(&self.map_texture_archive_id.to_le_bytes());
+        bytes[4..=5].copy_from_slice(&self.dummy.to_le_bytes());
+        bytes[6..=7].copy_from_slice(&self.area_light_archive_id.to_le_bytes());
+
+        bytes
+    }
+}
+
+impl FromBytes for AreaData {
+    type Error = AreaDataError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| AreaDataError::WrongSize(bytes.len()))?;
+
+        Ok(Self::from_bytes(bytes))
+    }
+}
+
+impl ToBytes for AreaData {
+    type Error = Infallible;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Ok(Self::to_bytes(self).to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn area_data_round_trip() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+        assert_eq!(AreaData::from_bytes(bytes).to_bytes(), bytes);
+    }
 }
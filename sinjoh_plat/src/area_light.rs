@@ -1,8 +1,13 @@
-//! Data structure and parser for area light files.
+//! Data structure, parser and writer for area light files.
 //!
 //! Those are the files contained in the `arealight.narc` archive.
+//!
+//! Area light files are text files, made of blocks of comma-separated values that are separated
+//! by empty lines, and terminated by an `EOF` line.
 
 use std::{
+    convert::Infallible,
+    fmt::{self, Write},
     iter::Enumerate,
     num::ParseIntError,
     str::{Split, Utf8Error},
@@ -10,7 +15,19 @@ use std::{
 
 use thiserror::Error;
 
-use sinjoh_nds::{DsFixed16, DsRgb, DsVecFixed16};
+use sinjoh_nds::{
+    DsFixed16, DsRgb, DsVecFixed16,
+    bytes::{FromBytes, ToBytes},
+};
+
+/// The line ending used by area light files.
+pub const AREA_LIGHT_LINE_ENDING: &str = "\r\n";
+
+/// The line marking the end of an area light file.
+pub const AREA_LIGHT_EOF_LINE: &str = "EOF";
+
+/// The number of time units in a day, where a time unit is two seconds.
+pub const AREA_LIGHT_TIME_UNITS_PER_DAY: u32 = 24 * 60 * 60 / 2;
 
 /// Represents the different lines that are found in an area light block.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -154,11 +171,140 @@ pub struct AreaLightBlock {
     pub emission_color: DsRgb,
 }
 
+impl AreaLightProperties {
+    /// Returns the direction vector of the light, clamped to align it with how the game would
+    /// interpret it.
+    pub fn fixed_direction(&self) -> DsVecFixed16 {
+        DsVecFixed16 {
+            x: self.direction.x.clamp(DsFixed16::NEG_ONE, DsFixed16::ONE),
+            y: self.direction.y.clamp(DsFixed16::NEG_ONE, DsFixed16::ONE),
+            z: self.direction.z.clamp(DsFixed16::NEG_ONE, DsFixed16::ONE),
+        }
+    }
+}
+
+impl AreaLightBlock {
+    /// Returns the lights of the block, indexed by their Nintendo DS light ID.
+    pub fn lights(&self) -> [Option<AreaLightProperties>; 4] {
+        [self.light_0, self.light_1, self.light_2, self.light_3]
+    }
+}
+
+/// Represents the lighting of an area at a given time.
+#[derive(Debug, Clone, Copy)]
+pub struct AreaLighting {
+    /// Properties of the Nintendo DS lights, indexed by their light ID. A light that is not
+    /// active is `None`.
+    pub lights: [Option<AreaLightProperties>; 4],
+
+    /// The color of the diffuse reflection.
+    pub diffuse_reflect_color: DsRgb,
+
+    /// The color of the ambient reflection.
+    pub ambient_reflect_color: DsRgb,
+
+    /// The color of the specular reflection.
+    pub specular_reflect_color: DsRgb,
+
+    /// The color of the emitted light.
+    pub emission_color: DsRgb,
+}
+
+impl AreaLighting {
+    /// Returns the lighting set by an area light block.
+    pub fn from_block(block: &AreaLightBlock) -> Self {
+        Self {
+            lights: block.lights(),
+            diffuse_reflect_color: block.diffuse_reflect_color,
+            ambient_reflect_color: block.ambient_reflect_color,
+            specular_reflect_color: block.specular_reflect_color,
+            emission_color: block.emission_color,
+        }
+    }
+
+    /// Interpolates linearly between two lightings, where a `factor` of 0 gives `self` and a
+    /// `factor` of 1 gives `other`.
+    ///
+    /// Lights are only interpolated if they are active in both lightings, otherwise the light of
+    /// `self` is kept.
+    pub fn lerp(&self, other: &Self, factor: f32) -> Self {
+        let factor = factor.clamp(0.0, 1.0);
+
+        Self {
+            lights: std::array::from_fn(|i| match (self.lights[i], other.lights[i]) {
+                (Some(light), Some(other_light)) => Some(AreaLightProperties {
+                    color: lerp_color(light.color, other_light.color, factor),
+                    direction: DsVecFixed16 {
+                        x: lerp_fixed(light.direction.x, other_light.direction.x, factor),
+                        y: lerp_fixed(light.direction.y, other_light.direction.y, factor),
+                        z: lerp_fixed(light.direction.z, other_light.direction.z, factor),
+                    },
+                }),
+                (light, _) => light,
+            }),
+            diffuse_reflect_color: lerp_color(
+                self.diffuse_reflect_color,
+                other.diffuse_reflect_color,
+                factor,
+            ),
+            ambient_reflect_color: lerp_color(
+                self.ambient_reflect_color,
+                other.ambient_reflect_color,
+                factor,
+            ),
+            specular_reflect_color: lerp_color(
+                self.specular_reflect_color,
+                other.specular_reflect_color,
+                factor,
+            ),
+            emission_color: lerp_color(self.emission_color, other.emission_color, factor),
+        }
+    }
+}
+
+/// Interpolates linearly between two colors, rounding each component to the nearest value.
+fn lerp_color(from: DsRgb, to: DsRgb, factor: f32) -> DsRgb {
+    let lerp = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * factor).round() as u8;
+
+    DsRgb {
+        red: lerp(from.red, to.red),
+        green: lerp(from.green, to.green),
+        blue: lerp(from.blue, to.blue),
+    }
+}
+
+/// Interpolates linearly between two fixed-point numbers, rounding to the nearest value.
+fn lerp_fixed(from: DsFixed16, to: DsFixed16, factor: f32) -> DsFixed16 {
+    let from = from.to_bits() as f32;
+    let to = to.to_bits() as f32;
+
+    DsFixed16::from_bits((from + (to - from) * factor).round() as i16)
+}
+
+/// Options that can be used to configure how an area light file is written.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AreaLightWriteOptions {
+    /// Whether to clamp the direction vectors of the lights, like [`AreaLight::fix`] does.
+    ///
+    /// When `false`, the raw values are written as they are, which keeps the file identical to
+    /// the one it was parsed from.
+    pub clamp_directions: bool,
+}
+
 /// Represents an area light file.
 #[derive(Debug, Clone)]
 pub struct AreaLight {
     /// The blocks of the area light file.
     pub blocks: Vec<AreaLightBlock>,
+
+    /// The raw lines of the lights that were specified as invalid, indexed by block, then by
+    /// Nintendo DS light ID.
+    ///
+    /// The parameters of an invalid light are ignored by the game, but they are kept here so that
+    /// they can be written back as-is. A line is only written if the associated light is still
+    /// `None`. Entries are matched with blocks by their index, so they should be inserted or
+    /// removed along with the blocks.
+    pub invalid_light_lines: Vec<[Option<String>; 4]>,
 }
 
 impl AreaLight {
@@ -179,7 +325,9 @@ impl AreaLight {
     /// archive.
     pub fn parse_string(str: &str) -> Result<Self, AreaLightError> {
         let mut blocks = Vec::new();
+        let mut invalid_light_lines = Vec::new();
         let mut current_block = AreaLightBlock::default();
+        let mut current_invalid_light_lines: [Option<String>; 4] = Default::default();
         let mut current_block_line = AreaLightBlockLine::EndTime;
 
         // Parse the file line-by-line
@@ -210,16 +358,32 @@ impl AreaLight {
                     })?;
                 }
                 AreaLightBlockLine::Light0 => {
-                    current_block.light_0 = Self::parse_light_line(line, &current_block_line, i)?
+                    current_block.light_0 = Self::parse_light_line(line, &current_block_line, i)?;
+
+                    if current_block.light_0.is_none() {
+                        current_invalid_light_lines[0] = Some(line.to_string());
+                    }
                 }
                 AreaLightBlockLine::Light1 => {
-                    current_block.light_1 = Self::parse_light_line(line, &current_block_line, i)?
+                    current_block.light_1 = Self::parse_light_line(line, &current_block_line, i)?;
+
+                    if current_block.light_1.is_none() {
+                        current_invalid_light_lines[1] = Some(line.to_string());
+                    }
                 }
                 AreaLightBlockLine::Light2 => {
-                    current_block.light_2 = Self::parse_light_line(line, &current_block_line, i)?
+                    current_block.light_2 = Self::parse_light_line(line, &current_block_line, i)?;
+
+                    if current_block.light_2.is_none() {
+                        current_invalid_light_lines[2] = Some(line.to_string());
+                    }
                 }
                 AreaLightBlockLine::Light3 => {
-                    current_block.light_3 = Self::parse_light_line(line, &current_block_line, i)?
+                    current_block.light_3 = Self::parse_light_line(line, &current_block_line, i)?;
+
+                    if current_block.light_3.is_none() {
+                        current_invalid_light_lines[3] = Some(line.to_string());
+                    }
                 }
                 AreaLightBlockLine::DiffuseReflectColor => {
                     current_block.diffuse_reflect_color =
@@ -245,12 +409,16 @@ impl AreaLight {
 
             if current_block_line == AreaLightBlockLine::End {
                 blocks.push(current_block);
+                invalid_light_lines.push(std::mem::take(&mut current_invalid_light_lines));
                 current_block = AreaLightBlock::default();
                 current_block_line = AreaLightBlockLine::EndTime;
             }
         }
 
-        Ok(Self { blocks })
+        Ok(Self {
+            blocks,
+            invalid_light_lines,
+        })
     }
 
     /// Parses a light line from an area light block.
@@ -418,7 +586,199 @@ impl AreaLight {
         })
     }
 
+    /// Converts a clock time to the time unit used by area light blocks (seconds divided by 2,
+    /// since midnight).
+    ///
+    /// Times past the end of the day wrap around.
+    pub fn time_from_clock(hours: u32, minutes: u32, seconds: u32) -> u32 {
+        ((hours * 60 * 60 + minutes * 60 + seconds) / 2) % AREA_LIGHT_TIME_UNITS_PER_DAY
+    }
+
+    /// Returns the index of the block active at the specified time (in seconds divided by 2,
+    /// since midnight).
+    ///
+    /// Like the game, this is the first block whose end time is after the specified time. If
+    /// there is none, the last block stays active.
+    pub fn block_index_at(&self, time: u32) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| time < block.end_time)
+            .or_else(|| self.blocks.len().checked_sub(1))
+    }
+
+    /// Returns the block active at the specified time (in seconds divided by 2, since midnight).
+    ///
+    /// See [`AreaLight::block_index_at`] for more information.
+    pub fn block_at(&self, time: u32) -> Option<&AreaLightBlock> {
+        self.block_index_at(time).map(|index| &self.blocks[index])
+    }
+
+    /// Returns the lighting at the specified time (in seconds divided by 2, since midnight), as
+    /// set by the game.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_plat::area_light::AreaLight;
+    ///
+    /// let area_light = AreaLight::parse_bytes(&std::fs::read("arealight_0.txt")?)?.fixed();
+    ///
+    /// // Lighting at dusk
+    /// if let Some(lighting) = area_light.lighting_at(AreaLight::time_from_clock(18, 30, 0)) {
+    ///     println!("Diffuse color: {:?}", lighting.diffuse_reflect_color);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn lighting_at(&self, time: u32) -> Option<AreaLighting> {
+        self.block_at(time).map(AreaLighting::from_block)
+    }
+
+    /// Returns the lighting at the specified time (in seconds divided by 2, since midnight),
+    /// interpolated between the active block and the next one.
+    ///
+    /// The lighting goes from the values of the active block at its start, to the values of the
+    /// next block at its end, wrapping around to the first block after the last one. The game
+    /// switches between blocks without any transition, so this is only meant for previews.
+    pub fn interpolated_lighting_at(&self, time: u32) -> Option<AreaLighting> {
+        let index = self.block_index_at(time)?;
+        let block = &self.blocks[index];
+        let next_block = &self.blocks[(index + 1) % self.blocks.len()];
+
+        let start_time = match index {
+            0 => 0,
+            _ => self.blocks[index - 1].end_time,
+        };
+
+        let duration = block.end_time.saturating_sub(start_time);
+        let factor = match duration {
+            0 => 0.0,
+            _ => time.saturating_sub(start_time) as f32 / duration as f32,
+        };
+
+        Some(AreaLighting::from_block(block).lerp(&AreaLighting::from_block(next_block), factor))
+    }
+
+    /// Serializes an [`AreaLight`] to a string, with the specified options.
+    ///
+    /// The string is in the same format as the one found in the `arealight.narc` archive: each
+    /// block is followed by an empty line, and the file ends with an `EOF` line. Invalid lights
+    /// are written from their raw line (See [`AreaLight::invalid_light_lines`]), or with all
+    /// their parameters set to zero if they have none.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_plat::area_light::{AreaLight, AreaLightWriteOptions};
+    ///
+    /// let mut area_light = AreaLight::parse_bytes(&std::fs::read("arealight_0.txt")?)?;
+    /// area_light.blocks[0].end_time += 900;
+    ///
+    /// let options = AreaLightWriteOptions::default();
+    /// std::fs::write("arealight_0.txt", area_light.to_string_with_options(options))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_string_with_options(&self, options: AreaLightWriteOptions) -> String {
+        let mut string = String::new();
+
+        // Safety: writing to a string never fails
+        self.write_string(&mut string, options).unwrap();
+
+        string
+    }
+
+    /// Serializes an [`AreaLight`] to a byte vector, keeping the raw values.
+    ///
+    /// The bytes are in the same format as the one found in the `arealight.narc` archive.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Writes an [`AreaLight`] to the given writer, with the specified options.
+    fn write_string<W: Write>(
+        &self,
+        writer: &mut W,
+        options: AreaLightWriteOptions,
+    ) -> fmt::Result {
+        let no_invalid_light_lines = Default::default();
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            write!(writer, "{},{AREA_LIGHT_LINE_ENDING}", block.end_time)?;
+
+            let invalid_light_lines = self
+                .invalid_light_lines
+                .get(i)
+                .unwrap_or(&no_invalid_light_lines);
+
+            for (light, invalid_light_line) in block.lights().iter().zip(invalid_light_lines) {
+                match (light, invalid_light_line) {
+                    (None, Some(invalid_light_line)) => {
+                        write!(writer, "{invalid_light_line}{AREA_LIGHT_LINE_ENDING}")?
+                    }
+                    _ => Self::write_light_line(writer, light.as_ref(), options)?,
+                }
+            }
+
+            for color in [
+                block.diffuse_reflect_color,
+                block.ambient_reflect_color,
+                block.specular_reflect_color,
+                block.emission_color,
+            ] {
+                write!(
+                    writer,
+                    "{},{},{},{AREA_LIGHT_LINE_ENDING}",
+                    color.red, color.green, color.blue
+                )?;
+            }
+
+            writer.write_str(AREA_LIGHT_LINE_ENDING)?;
+        }
+
+        write!(writer, "{AREA_LIGHT_EOF_LINE}{AREA_LIGHT_LINE_ENDING}")
+    }
+
+    /// Writes a light line of an area light block.
+    fn write_light_line<W: Write>(
+        writer: &mut W,
+        light: Option<&AreaLightProperties>,
+        options: AreaLightWriteOptions,
+    ) -> fmt::Result {
+        let Some(light) = light else {
+            return write!(writer, "0,0,0,0,0,0,0,{AREA_LIGHT_LINE_ENDING}");
+        };
+
+        let direction = match options.clamp_directions {
+            true => light.fixed_direction(),
+            false => light.direction,
+        };
+
+        write!(
+            writer,
+            "1,{},{},{},{},{},{},{AREA_LIGHT_LINE_ENDING}",
+            light.color.red,
+            light.color.green,
+            light.color.blue,
+            direction.x.to_bits(),
+            direction.y.to_bits(),
+            direction.z.to_bits()
+        )
+    }
+
+    /// Returns a copy of the area light file, with its values aligned with how the game would
+    /// interpret them.
+    ///
+    /// Unlike [`AreaLight::fix`], the raw values of this area light file are kept.
+    pub fn fixed(&self) -> Self {
+        let mut area_light = self.clone();
+        area_light.fix();
+
+        area_light
+    }
+
     /// Fixes the area light file to align its values with how the game would interpret them.
+    ///
+    /// This replaces the raw values in place: use [`AreaLight::fixed`] to keep them, for
+    /// instance to write the file back.
     pub fn fix(&mut self) {
         for block in self.blocks.iter_mut() {
             // Fix all lights in the block
@@ -443,8 +803,191 @@ impl AreaLight {
     /// Fixes a light to align its values with how the game would interpret them.
     fn fix_light(light: &mut AreaLightProperties) {
         // Clamp the direction vector components
-        light.direction.x = light.direction.x.clamp(DsFixed16::NEG_ONE, DsFixed16::ONE);
-        light.direction.y = light.direction.y.clamp(DsFixed16::NEG_ONE, DsFixed16::ONE);
-        light.direction.z = light.direction.z.clamp(DsFixed16::NEG_ONE, DsFixed16::ONE);
+        light.direction = light.fixed_direction();
+    }
+}
+
+impl fmt::Display for AreaLight {
+    /// Writes the area light file in the same format as the one found in the `arealight.narc`
+    /// archive, keeping the raw values.
+    ///
+    /// See [`AreaLight::to_string_with_options`] for more information.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_string(f, AreaLightWriteOptions::default())
+    }
+}
+
+impl FromBytes for AreaLight {
+    type Error = AreaLightError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for AreaLight {
+    type Error = Infallible;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Ok(Self::to_bytes(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn area_light_round_trip() {
+        let string = [
+            "21600,",
+            "1,20,20,22,0,-4096,0,",
+            "0,10,10,10,0,0,-4096,",
+            "0,0,0,0,0,0,0,",
+            "1,31,31,31,-2048,-5000,0,",
+            "19,19,19,",
+            "10,10,10,",
+            "0,0,0,",
+            "0,0,0,",
+            "",
+            "EOF",
+            "",
+        ]
+        .join(AREA_LIGHT_LINE_ENDING);
+
+        let area_light = AreaLight::parse_string(&string).unwrap();
+
+        assert!(area_light.blocks[0].light_1.is_none());
+        assert_eq!(
+            area_light.invalid_light_lines[0][1].as_deref(),
+            Some("0,10,10,10,0,0,-4096,")
+        );
+        assert_eq!(area_light.to_string(), string);
+
+        // Invalid lights without a raw line are written with all their parameters set to zero
+        let area_light = AreaLight {
+            invalid_light_lines: Vec::new(),
+            ..area_light
+        };
+        assert_eq!(
+            area_light.to_string(),
+            string.replace("0,10,10,10,0,0,-4096,", "0,0,0,0,0,0,0,")
+        );
+    }
+
+    /// Returns a gray color, whose components are all equal to the specified value.
+    fn gray(value: u8) -> DsRgb {
+        DsRgb {
+            red: value,
+            green: value,
+            blue: value,
+        }
+    }
+
+    /// Returns a light of the specified gray color, pointing downwards.
+    fn light(value: u8) -> AreaLightProperties {
+        AreaLightProperties {
+            color: gray(value),
+            direction: DsVecFixed16::new(DsFixed16::ZERO, DsFixed16::NEG_ONE, DsFixed16::ZERO),
+        }
+    }
+
+    /// Builds an area light file with blocks ending at the specified times, whose diffuse colors
+    /// are the specified values.
+    fn area_light(blocks: &[(u32, u8)]) -> AreaLight {
+        AreaLight {
+            blocks: blocks
+                .iter()
+                .map(|&(end_time, value)| AreaLightBlock {
+                    end_time,
+                    diffuse_reflect_color: gray(value),
+                    ..Default::default()
+                })
+                .collect(),
+            invalid_light_lines: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn time_from_clock() {
+        assert_eq!(AreaLight::time_from_clock(0, 0, 0), 0);
+        assert_eq!(AreaLight::time_from_clock(18, 30, 1), 33300);
+        assert_eq!(AreaLight::time_from_clock(24, 0, 2), 1);
+    }
+
+    #[test]
+    fn active_block() {
+        let area_light = area_light(&[(100, 0), (200, 10), (300, 20)]);
+
+        assert_eq!(area_light.block_index_at(0), Some(0));
+        assert_eq!(area_light.block_index_at(99), Some(0));
+
+        // A block is no longer active at its end time
+        assert_eq!(area_light.block_index_at(100), Some(1));
+        assert_eq!(area_light.block_index_at(200), Some(2));
+
+        // The last block stays active past its end time
+        assert_eq!(area_light.block_index_at(300), Some(2));
+        assert_eq!(
+            area_light.block_index_at(AREA_LIGHT_TIME_UNITS_PER_DAY),
+            Some(2)
+        );
+
+        assert_eq!(area_light.block_at(150).unwrap().end_time, 200);
+        assert_eq!(
+            area_light.lighting_at(250).unwrap().diffuse_reflect_color,
+            gray(20)
+        );
+    }
+
+    #[test]
+    fn empty_area_light() {
+        let area_light = area_light(&[]);
+
+        assert_eq!(area_light.block_index_at(0), None);
+        assert!(area_light.block_at(0).is_none());
+        assert!(area_light.lighting_at(0).is_none());
+        assert!(area_light.interpolated_lighting_at(0).is_none());
+    }
+
+    #[test]
+    fn interpolated_lighting() {
+        let area_light = area_light(&[(100, 0), (200, 10), (400, 30)]);
+        let diffuse_color_at = |time| {
+            area_light
+                .interpolated_lighting_at(time)
+                .unwrap()
+                .diffuse_reflect_color
+        };
+
+        assert_eq!(diffuse_color_at(0), gray(0));
+        assert_eq!(diffuse_color_at(50), gray(5));
+        assert_eq!(diffuse_color_at(150), gray(20));
+
+        // The last block goes back to the first one
+        assert_eq!(diffuse_color_at(300), gray(15));
+        assert_eq!(diffuse_color_at(400), gray(0));
+    }
+
+    #[test]
+    fn interpolated_lighting_with_inactive_lights() {
+        let mut area_light = area_light(&[(100, 0), (200, 10)]);
+        area_light.blocks[0].light_0 = Some(light(10));
+        area_light.blocks[1].light_1 = Some(light(20));
+
+        // Lights that are only active in one of the blocks are taken from the active block
+        let lighting = area_light.interpolated_lighting_at(50).unwrap();
+        assert_eq!(lighting.lights[0].unwrap().color, gray(10));
+        assert!(lighting.lights[1].is_none());
+
+        let lighting = area_light.interpolated_lighting_at(150).unwrap();
+        assert!(lighting.lights[0].is_none());
+        assert_eq!(lighting.lights[1].unwrap().color, gray(20));
+
+        // Lights active in both blocks are interpolated
+        area_light.blocks[1].light_0 = Some(light(20));
+
+        let lighting = area_light.interpolated_lighting_at(50).unwrap();
+        assert_eq!(lighting.lights[0].unwrap().color, gray(15));
     }
 }
@@ -1,18 +1,28 @@
-//! Data structure and parser for area map props files.
+//! Data structure, parser and writer for area map props files.
 //!
 //! Those are the files contained in the `area_build.narc` archive.
 
-use std::io::{self, Cursor};
+use std::io::{self, Cursor, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
-/// Error type for area map props parsing.
+use sinjoh_nds::bytes::{FromBytes, ToBytes};
+
+/// Error type for area map props parsing and writing.
 #[derive(Error, Debug)]
 pub enum AreaMapPropsError {
     /// An I/O error has occurred while trying to read from the buffer.
     #[error("an error has occurred while reading the buffer")]
     ReadError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to write to the buffer.
+    #[error("an error has occurred while writing to the buffer")]
+    WriteError(#[source] io::Error),
+
+    /// There are more map prop IDs than what an area map props file can hold.
+    #[error("too many map prop IDs (got {0}, maximum is {max})", max = u16::MAX)]
+    TooManyMapProps(usize),
 }
 
 /// Represents an area map props file.
@@ -48,4 +58,69 @@ impl AreaMapProps {
 
         Ok(Self { map_props_ids })
     }
+
+    /// Serializes an [`AreaMapProps`] to a byte vector.
+    ///
+    /// The bytes are in the same format as the one found in the `area_build.narc` archive.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AreaMapPropsError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes an [`AreaMapProps`] to the given writer.
+    ///
+    /// The bytes are in the same format as the one found in the `area_build.narc` archive.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), AreaMapPropsError> {
+        // Write the map prop IDs count
+        let map_props_ids_count: u16 = self
+            .map_props_ids
+            .len()
+            .try_into()
+            .map_err(|_| AreaMapPropsError::TooManyMapProps(self.map_props_ids.len()))?;
+
+        writer
+            .write_u16::<LittleEndian>(map_props_ids_count)
+            .map_err(AreaMapPropsError::WriteError)?;
+
+        // Write the map prop IDs
+        for map_props_id in self.map_props_ids.iter() {
+            writer
+                .write_u16::<LittleEndian>(*map_props_id)
+                .map_err(AreaMapPropsError::WriteError)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromBytes for AreaMapProps {
+    type Error = AreaMapPropsError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for AreaMapProps {
+    type Error = AreaMapPropsError;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Self::to_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn area_map_props_round_trip() {
+        let bytes = [0x03, 0x00, 0x10, 0x00, 0xFF, 0xFF, 0x34, 0x12];
+
+        let area_map_props = AreaMapProps::parse_bytes(&bytes).unwrap();
+
+        assert_eq!(area_map_props.to_bytes().unwrap(), bytes);
+    }
 }
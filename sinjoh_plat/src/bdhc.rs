@@ -1,13 +1,16 @@
-//! Data structure and parser for BDHC data.
+//! Data structure, parser and writer for BDHC data.
 //!
 //! Those are embedded in the files contained in the `land_data.narc` archive.
 
-use std::io::{self, Cursor, Read};
+use std::io::{self, Cursor, Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
-use sinjoh_nds::{DS_FIXED_32_SIZE, DS_VEC_FIXED_32_SIZE, DsFixed32, DsVecFixed32};
+use sinjoh_nds::{
+    DS_FIXED_32_SIZE, DS_VEC_FIXED_32_SIZE, DsFixed32, DsVecFixed32,
+    bytes::{FromBytes, ToBytes},
+};
 
 /// The magic number at the beginning of a BDHC file. Corresponds to "BDHC" in little-endian.
 pub const BDHC_MAGIC: u32 = 0x43484442;
@@ -64,6 +67,22 @@ impl BdhcHeader {
             access_list_count: u16::from_le_bytes(bytes[10..=11].try_into().unwrap()),
         }
     }
+
+    /// Serializes a [`BdhcHeader`] to a byte array.
+    ///
+    /// The array is in the same format as the one found in the BDHC data.
+    pub fn to_bytes(&self) -> [u8; BDHC_HEADER_SIZE] {
+        let mut bytes = [0; BDHC_HEADER_SIZE];
+
+        bytes[0..=1].copy_from_slice(&self.points_count.to_le_bytes());
+        bytes[2..=3].copy_from_slice(&self.normals_count.to_le_bytes());
+        bytes[4..=5].copy_from_slice(&self.constants_count.to_le_bytes());
+        bytes[6..=7].copy_from_slice(&self.plates_count.to_le_bytes());
+        bytes[8..=9].copy_from_slice(&self.strips_count.to_le_bytes());
+        bytes[10..=11].copy_from_slice(&self.access_list_count.to_le_bytes());
+
+        bytes
+    }
 }
 
 /// Represents a point in BDHC data.
@@ -94,6 +113,18 @@ impl BdhcPoint {
             z: DsFixed32::from_le_bytes(bytes[4..=7].try_into().unwrap()),
         }
     }
+
+    /// Serializes a [`BdhcPoint`] to a byte array.
+    ///
+    /// The array is in the same format as the one found in the BDHC data.
+    pub fn to_bytes(&self) -> [u8; BDHC_POINT_SIZE] {
+        let mut bytes = [0; BDHC_POINT_SIZE];
+
+        bytes[0..=3].copy_from_slice(&self.x.to_le_bytes());
+        bytes[4..=7].copy_from_slice(&self.z.to_le_bytes());
+
+        bytes
+    }
 }
 
 /// Represents a plate in BDHC data.
@@ -126,6 +157,20 @@ impl BdhcPlate {
             constant_index: u16::from_le_bytes(bytes[6..=7].try_into().unwrap()),
         }
     }
+
+    /// Serializes a [`BdhcPlate`] to a byte array.
+    ///
+    /// The array is in the same format as the one found in the BDHC data.
+    pub fn to_bytes(&self) -> [u8; BDHC_PLATE_SIZE] {
+        let mut bytes = [0; BDHC_PLATE_SIZE];
+
+        bytes[0..=1].copy_from_slice(&self.first_point_index.to_le_bytes());
+        bytes[2..=3].copy_from_slice(&self.second_point_index.to_le_bytes());
+        bytes[4..=5].copy_from_slice(&self.normal_index.to_le_bytes());
+        bytes[6..=7].copy_from_slice(&self.constant_index.to_le_bytes());
+
+        bytes
+    }
 }
 
 /// Represents a strip in BDHC data.
@@ -159,18 +204,39 @@ impl BdhcStrip {
             access_list_start_index: u16::from_le_bytes(bytes[6..=7].try_into().unwrap()),
         }
     }
+
+    /// Serializes a [`BdhcStrip`] to a byte array.
+    ///
+    /// The array is in the same format as the one found in the BDHC data.
+    pub fn to_bytes(&self) -> [u8; BDHC_STRIP_SIZE] {
+        let mut bytes = [0; BDHC_STRIP_SIZE];
+
+        bytes[0..=3].copy_from_slice(&self.scanline.to_le_bytes());
+        bytes[4..=5].copy_from_slice(&self.access_list_element_count.to_le_bytes());
+        bytes[6..=7].copy_from_slice(&self.access_list_start_index.to_le_bytes());
+
+        bytes
+    }
 }
 
-/// Error type for BDHC parsing.
+/// Error type for BDHC parsing and writing.
 #[derive(Error, Debug)]
 pub enum BdhcError {
     /// An I/O error has occurred while trying to read from the buffer.
     #[error("an error has occurred while reading the buffer")]
     ReadError(#[source] io::Error),
 
+    /// An I/O error has occurred while trying to write to the buffer.
+    #[error("an error has occurred while writing to the buffer")]
+    WriteError(#[source] io::Error),
+
     /// The BDHC magic number is wrong.
     #[error("wrong BDHC magic number (expected 0x{BDHC_MAGIC:X}, found 0x{0:X})")]
     WrongBdhcMagic(u32),
+
+    /// A section has more elements than what BDHC data can hold.
+    #[error("too many elements in a section (got {0}, maximum is {max})", max = u16::MAX)]
+    TooManyElements(usize),
 }
 
 /// Represents a BDHC file.
@@ -319,4 +385,360 @@ impl Bdhc {
             access_list,
         })
     }
+
+    /// Returns the strip containing the plates that can be found at the specified Z coordinate.
+    ///
+    /// Strips are sorted by their scanline, and a strip holds the plates found between the
+    /// scanline of the previous strip and its own scanline. This is found with a binary search,
+    /// like the game does.
+    pub fn strip_at(&self, z: DsFixed32) -> Option<&BdhcStrip> {
+        let index = self.strips.partition_point(|strip| strip.scanline < z);
+        self.strips.get(index)
+    }
+
+    /// Returns the heights of every plate found at the specified position, in the order of the
+    /// access list.
+    ///
+    /// The coordinates are in the same space as the points of the BDHC data. The height of a
+    /// plate is found by solving the equation of its plane, with the same fixed-point arithmetic
+    /// as the game.
+    pub fn heights_at(&self, x: DsFixed32, z: DsFixed32) -> Vec<DsFixed32> {
+        let Some(strip) = self.strip_at(z) else {
+            return Vec::new();
+        };
+
+        let start_index: usize = strip.access_list_start_index.into();
+        let end_index = start_index + usize::from(strip.access_list_element_count);
+
+        self.access_list
+            .get(start_index..end_index)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|&plate_index| self.plates.get(usize::from(plate_index)))
+            .filter_map(|plate| self.plate_height_at(plate, x, z))
+            .collect()
+    }
+
+    /// Returns the height of the ground at the specified position, if a plate is found there.
+    ///
+    /// If multiple plates are found (for instance, under a bridge), the first one in the access
+    /// list is used. Use [`Bdhc::height_near`] to pick the plate the game would pick for an
+    /// object at a given height.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sinjoh_nds::DsFixed32;
+    /// use sinjoh_plat::land_data::LandData;
+    ///
+    /// let land_data = LandData::parse_bytes(&std::fs::read("/path/to/land_data/0000.bin")?)?;
+    ///
+    /// let height = land_data
+    ///     .bdhc
+    ///     .height_at(DsFixed32::from_num(8), DsFixed32::from_num(-24));
+    ///
+    /// println!("Height: {:?}", height);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn height_at(&self, x: DsFixed32, z: DsFixed32) -> Option<DsFixed32> {
+        self.heights_at(x, z).first().copied()
+    }
+
+    /// Returns the height of the ground at the specified position that is the closest to the
+    /// specified height, if a plate is found there.
+    ///
+    /// This is how the game picks the plate an object stands on when multiple plates are found at
+    /// its position, `current_height` being the height of the object.
+    pub fn height_near(
+        &self,
+        x: DsFixed32,
+        z: DsFixed32,
+        current_height: DsFixed32,
+    ) -> Option<DsFixed32> {
+        self.heights_at(x, z)
+            .into_iter()
+            .min_by_key(|height| (height.to_bits() as i64 - current_height.to_bits() as i64).abs())
+    }
+
+    /// Returns the height of a plate at the specified position, if the position is within the
+    /// bounds of the plate and the height fits in a fixed-point number.
+    fn plate_height_at(&self, plate: &BdhcPlate, x: DsFixed32, z: DsFixed32) -> Option<DsFixed32> {
+        let first_point = self.points.get(usize::from(plate.first_point_index))?;
+        let second_point = self.points.get(usize::from(plate.second_point_index))?;
+
+        // The first point is the corner with the lowest coordinates, and the second point the one
+        // with the highest coordinates
+        if x < first_point.x || x > second_point.x || z < first_point.z || z > second_point.z {
+            return None;
+        }
+
+        let normal = self.normals.get(usize::from(plate.normal_index))?;
+        let constant = self.constants.get(usize::from(plate.constant_index))?;
+
+        // A vertical plate has no height
+        if normal.y == DsFixed32::ZERO {
+            return None;
+        }
+
+        // Solve `normal.x * x + normal.y * y + normal.z * z + constant = 0` for `y`, where the dot
+        // product is rounded to the nearest value like `VEC_DotProduct` does
+        let dot_product = (normal.x.to_bits() as i64 * x.to_bits() as i64
+            + normal.z.to_bits() as i64 * z.to_bits() as i64
+            + (1 << (DsFixed32::FRAC_NBITS - 1)))
+            >> DsFixed32::FRAC_NBITS;
+
+        let numerator = -(dot_product + constant.to_bits() as i64);
+
+        // Like `FX_Div`, the hardware divider gives a quotient with 32 fractional bits (truncated
+        // towards zero), which is then rounded to the nearest fixed-point number
+        let quotient = ((numerator as i128) << 32) / normal.y.to_bits() as i128;
+        let rounding_shift = 32 - DsFixed32::FRAC_NBITS;
+        let height = (quotient + (1 << (rounding_shift - 1))) >> rounding_shift;
+
+        // A nearly horizontal normal gives a height that cannot be represented
+        i32::try_from(height).ok().map(DsFixed32::from_bits)
+    }
+
+    /// Serializes a [`Bdhc`] to a byte vector.
+    ///
+    /// The bytes are in the same format as the one found in the BDHC data.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BdhcError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes a [`Bdhc`] to the given writer.
+    ///
+    /// The bytes are in the same format as the one found in the BDHC data.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), BdhcError> {
+        let section_count = |count: usize| -> Result<u16, BdhcError> {
+            count
+                .try_into()
+                .map_err(|_| BdhcError::TooManyElements(count))
+        };
+
+        let header = BdhcHeader {
+            points_count: section_count(self.points.len())?,
+            normals_count: section_count(self.normals.len())?,
+            constants_count: section_count(self.constants.len())?,
+            plates_count: section_count(self.plates.len())?,
+            strips_count: section_count(self.strips.len())?,
+            access_list_count: section_count(self.access_list.len())?,
+        };
+
+        // Write the magic
+        writer
+            .write_u32::<LittleEndian>(BDHC_MAGIC)
+            .map_err(BdhcError::WriteError)?;
+
+        // Write the section counts
+        writer
+            .write_all(&header.to_bytes())
+            .map_err(BdhcError::WriteError)?;
+
+        // Write the points
+        for point in self.points.iter() {
+            writer
+                .write_all(&point.to_bytes())
+                .map_err(BdhcError::WriteError)?;
+        }
+
+        // Write the normals
+        for normal in self.normals.iter() {
+            for coordinate in [normal.x, normal.y, normal.z] {
+                writer
+                    .write_all(&coordinate.to_le_bytes())
+                    .map_err(BdhcError::WriteError)?;
+            }
+        }
+
+        // Write the constants
+        for constant in self.constants.iter() {
+            writer
+                .write_all(&constant.to_le_bytes())
+                .map_err(BdhcError::WriteError)?;
+        }
+
+        // Write the plates
+        for plate in self.plates.iter() {
+            writer
+                .write_all(&plate.to_bytes())
+                .map_err(BdhcError::WriteError)?;
+        }
+
+        // Write the strips
+        for strip in self.strips.iter() {
+            writer
+                .write_all(&strip.to_bytes())
+                .map_err(BdhcError::WriteError)?;
+        }
+
+        // Write the access list
+        for access_list_element in self.access_list.iter() {
+            writer
+                .write_u16::<LittleEndian>(*access_list_element)
+                .map_err(BdhcError::WriteError)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromBytes for Bdhc {
+    type Error = BdhcError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for Bdhc {
+    type Error = BdhcError;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Self::to_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bdhc_round_trip() {
+        let mut bytes = BDHC_MAGIC.to_le_bytes().to_vec();
+
+        // Header: 2 points, 1 normal, 1 constant, 1 plate, 1 strip, 2 access list elements
+        for count in [2u16, 1, 1, 1, 1, 2] {
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+
+        // Points, normal and constant
+        for value in [-0x10000i32, -0x8000, 0x10000, 0x8000, 0, 0x1000, 0, -0x2000] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // Plate, strip and access list
+        for value in [0u16, 1, 0, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&0x8000i32.to_le_bytes());
+        for value in [2u16, 0, 0, 0xFFFF] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let bdhc = Bdhc::parse_bytes(&bytes).unwrap();
+
+        assert_eq!(bdhc.to_bytes().unwrap(), bytes);
+    }
+
+    /// Converts a number to a fixed-point number.
+    fn fixed(value: f32) -> DsFixed32 {
+        DsFixed32::from_num(value)
+    }
+
+    /// Builds BDHC data with two plates covering the same square from (-4, -4) to (4, 4): a flat
+    /// plate at a height of 2, and a sloped plate following `y = -x / 2`.
+    fn bridge_bdhc() -> Bdhc {
+        Bdhc {
+            points: vec![
+                BdhcPoint {
+                    x: fixed(-4.0),
+                    z: fixed(-4.0),
+                },
+                BdhcPoint {
+                    x: fixed(4.0),
+                    z: fixed(4.0),
+                },
+            ],
+            normals: vec![
+                DsVecFixed32::new(fixed(0.0), fixed(1.0), fixed(0.0)),
+                DsVecFixed32::new(fixed(0.5), fixed(1.0), fixed(0.0)),
+            ],
+            constants: vec![fixed(-2.0), fixed(0.0)],
+            plates: vec![
+                BdhcPlate {
+                    first_point_index: 0,
+                    second_point_index: 1,
+                    normal_index: 0,
+                    constant_index: 0,
+                },
+                BdhcPlate {
+                    first_point_index: 0,
+                    second_point_index: 1,
+                    normal_index: 1,
+                    constant_index: 1,
+                },
+            ],
+            strips: vec![BdhcStrip {
+                scanline: fixed(4.0),
+                access_list_element_count: 2,
+                access_list_start_index: 0,
+            }],
+            access_list: vec![0, 1],
+        }
+    }
+
+    #[test]
+    fn bdhc_heights() {
+        let bdhc = bridge_bdhc();
+        assert_eq!(
+            bdhc.heights_at(fixed(2.0), fixed(0.0)),
+            [fixed(2.0), fixed(-1.0)]
+        );
+        assert_eq!(bdhc.height_at(fixed(2.0), fixed(0.0)), Some(fixed(2.0)));
+        assert_eq!(bdhc.height_at(fixed(-3.0), fixed(1.5)), Some(fixed(2.0)));
+        assert_eq!(
+            bdhc.height_near(fixed(2.0), fixed(0.0), fixed(-0.5)),
+            Some(fixed(-1.0))
+        );
+        assert_eq!(
+            bdhc.height_near(fixed(-4.0), fixed(4.0), fixed(0.0)),
+            Some(fixed(2.0))
+        );
+    }
+
+    #[test]
+    fn bdhc_height_rounding() {
+        // Sloped plate following `y = -x / 3`, whose heights are not representable exactly
+        let mut bdhc = bridge_bdhc();
+        bdhc.normals[1] = DsVecFixed32::new(fixed(1.0), fixed(3.0), fixed(0.0));
+
+        // 2/3 is 2730.67 / 4096: the quotient is rounded to the nearest value, and not truncated
+        assert_eq!(
+            bdhc.heights_at(fixed(-2.0), fixed(0.0))[1],
+            DsFixed32::from_bits(2731)
+        );
+        assert_eq!(
+            bdhc.heights_at(fixed(2.0), fixed(0.0))[1],
+            DsFixed32::from_bits(-2731)
+        );
+
+        // 1/3 is 1365.33 / 4096
+        assert_eq!(
+            bdhc.heights_at(fixed(-1.0), fixed(0.0))[1],
+            DsFixed32::from_bits(1365)
+        );
+    }
+
+    #[test]
+    fn bdhc_heights_outside_of_plates() {
+        let bdhc = bridge_bdhc();
+        // Outside of the bounds of the plates, and past the scanline of the last strip
+        assert_eq!(bdhc.height_at(fixed(5.0), fixed(0.0)), None);
+        assert_eq!(bdhc.height_at(fixed(0.0), fixed(5.0)), None);
+    }
+
+    #[test]
+    fn bdhc_height_overflow() {
+        let mut bdhc = bridge_bdhc();
+        bdhc.normals[0].y = DsFixed32::from_bits(1);
+        bdhc.constants[0] = DsFixed32::from_num(-1000);
+
+        let heights = bdhc.heights_at(DsFixed32::from_num(2), DsFixed32::ZERO);
+
+        assert_eq!(heights, [DsFixed32::from_num(-1)]);
+    }
 }
@@ -1,16 +1,19 @@
-//! Data structure and parser for land data files.
+//! Data structure, parser and writer for land data files.
 //!
 //! Those are the files contained in the `land_data.narc` archive.
 
 use std::{
-    io::{self, Cursor, Read, Seek, SeekFrom},
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
     num::TryFromIntError,
 };
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
-use sinjoh_nds::{DsFixed32, DsVecFixed32};
+use sinjoh_nds::{
+    DsFixed32, DsVecFixed32,
+    bytes::{FromBytes, ToBytes},
+};
 
 use super::bdhc::{Bdhc, BdhcError};
 
@@ -26,6 +29,9 @@ pub const TERRAIN_ATTRIBUTES_ELEM_TILE_BEHAVIOR_MASK: u16 = 0x00FF;
 /// The mask to get the collision boolean from a terrain attributes element.
 pub const TERRAIN_ATTRIBUTES_ELEM_COLLISION_MASK: u16 = 0x8000;
 
+/// The mask to get the bits of a terrain attributes element that have no known meaning.
+pub const TERRAIN_ATTRIBUTES_ELEM_UNKNOWN_MASK: u16 = 0x7F00;
+
 /// The size of the land data header, that contains the section sizes.
 pub const LAND_DATA_HEADER_SIZE: usize = 16;
 
@@ -50,6 +56,9 @@ pub struct TerrainAttributes {
 
     /// Whether the tile can be walked on or not.
     pub has_collision: bool,
+
+    /// Unknown: bits 8 to 14 of the raw value, kept as-is so that they can be written back.
+    pub unknown_bits: u16,
 }
 
 impl TerrainAttributes {
@@ -63,8 +72,23 @@ impl TerrainAttributes {
         Self {
             tile_behavior: raw_value & TERRAIN_ATTRIBUTES_ELEM_TILE_BEHAVIOR_MASK,
             has_collision: (raw_value & TERRAIN_ATTRIBUTES_ELEM_COLLISION_MASK) != 0,
+            unknown_bits: raw_value & TERRAIN_ATTRIBUTES_ELEM_UNKNOWN_MASK,
         }
     }
+
+    /// Serializes a [`TerrainAttributes`] to a raw value.
+    ///
+    /// The value is in the same format as the one found in the `land_data.narc` archive.
+    pub fn to_raw(&self) -> u16 {
+        let collision = match self.has_collision {
+            true => TERRAIN_ATTRIBUTES_ELEM_COLLISION_MASK,
+            false => 0,
+        };
+
+        (self.tile_behavior & TERRAIN_ATTRIBUTES_ELEM_TILE_BEHAVIOR_MASK)
+            | (self.unknown_bits & TERRAIN_ATTRIBUTES_ELEM_UNKNOWN_MASK)
+            | collision
+    }
 }
 
 /// Represents an instance of a map prop.
@@ -119,9 +143,32 @@ impl MapPropInstance {
             ],
         }
     }
+
+    /// Serializes a [`MapPropInstance`] to a byte array.
+    ///
+    /// The array is in the same format as the one found in the `land_data.narc` archive.
+    pub fn to_bytes(&self) -> [u8; MAP_PROPS_ELEM_SIZE] {
+        let mut bytes = [0; MAP_PROPS_ELEM_SIZE];
+
+        bytes[0..=3].copy_from_slice(&self.map_prop_model_id.to_le_bytes());
+
+        let vectors = [self.position, self.rotation, self.scale];
+        for (vector_index, vector) in vectors.iter().enumerate() {
+            for (coordinate_index, coordinate) in [vector.x, vector.y, vector.z].iter().enumerate()
+            {
+                let offset = 4 + vector_index * 12 + coordinate_index * 4;
+                bytes[offset..offset + 4].copy_from_slice(&coordinate.to_le_bytes());
+            }
+        }
+
+        bytes[40..=43].copy_from_slice(&self.dummy[0].to_le_bytes());
+        bytes[44..=47].copy_from_slice(&self.dummy[1].to_le_bytes());
+
+        bytes
+    }
 }
 
-/// Error type for land data parsing.
+/// Error type for land data parsing and writing.
 #[derive(Error, Debug)]
 pub enum LandDataError {
     /// An I/O error has occurred while trying to read from the buffer.
@@ -132,6 +179,10 @@ pub enum LandDataError {
     #[error("a seek error has occurred while seeking in the buffer")]
     SeekError(#[source] io::Error),
 
+    /// An I/O error has occurred while trying to write to the buffer.
+    #[error("an error has occurred while writing to the buffer")]
+    WriteError(#[source] io::Error),
+
     /// Terrain attributes are too large to load into memory.
     #[error("terrain attributes are too large to load into memory (size is {0})")]
     TerrainAttributesTooBig(u32, #[source] TryFromIntError),
@@ -152,6 +203,14 @@ pub enum LandDataError {
     #[error("unable to parse BDHC data")]
     BdhcParseError(#[source] BdhcError),
 
+    /// An error has occurred while writing the BDHC data.
+    #[error("unable to write BDHC data")]
+    BdhcWriteError(#[source] BdhcError),
+
+    /// A section is too large to be written.
+    #[error("section is too large to be written (size is {0})")]
+    SectionTooLarge(usize),
+
     /// The specified tile index is greater or equal than the tile count in a map.
     #[error(
         "tile index is greater or equal than tile count (tile index is {0}, tile count is {MAP_TILES_COUNT})"
@@ -283,6 +342,69 @@ impl LandData {
         })
     }
 
+    /// Serializes a [`LandData`] to a byte vector.
+    ///
+    /// The bytes are in the same format as the one found in the `land_data.narc` archive.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, LandDataError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes a [`LandData`] to the given writer.
+    ///
+    /// The bytes are in the same format as the one found in the `land_data.narc` archive.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), LandDataError> {
+        let raw_bdhc = self
+            .bdhc
+            .to_bytes()
+            .map_err(LandDataError::BdhcWriteError)?;
+
+        // Write the section sizes
+        let section_sizes = [
+            self.terrain_attributes.len() * TERRAIN_ATTRIBUTES_ELEM_SIZE,
+            self.map_props.len() * MAP_PROPS_ELEM_SIZE,
+            self.map_model.len(),
+            raw_bdhc.len(),
+        ];
+
+        for section_size in section_sizes {
+            let raw_value = u32::try_from(section_size)
+                .map_err(|_| LandDataError::SectionTooLarge(section_size))?;
+
+            writer
+                .write_u32::<LittleEndian>(raw_value)
+                .map_err(LandDataError::WriteError)?;
+        }
+
+        // Write the terrain attributes
+        for terrain_attributes_elem in self.terrain_attributes.iter() {
+            writer
+                .write_u16::<LittleEndian>(terrain_attributes_elem.to_raw())
+                .map_err(LandDataError::WriteError)?;
+        }
+
+        // Write the map props
+        for map_prop in self.map_props.iter() {
+            writer
+                .write_all(&map_prop.to_bytes())
+                .map_err(LandDataError::WriteError)?;
+        }
+
+        // Write the map model
+        writer
+            .write_all(&self.map_model)
+            .map_err(LandDataError::WriteError)?;
+
+        // Write the BDHC data
+        writer
+            .write_all(&raw_bdhc)
+            .map_err(LandDataError::WriteError)?;
+
+        Ok(())
+    }
+
     /// Parses the terrain attributes from the reader.
     fn parse_terrain_attributes(
         reader: &mut Cursor<&[u8]>,
@@ -329,3 +451,54 @@ impl LandData {
         }
     }
 }
+
+impl FromBytes for LandData {
+    type Error = LandDataError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for LandData {
+    type Error = LandDataError;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Self::to_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terrain_attributes_round_trip() {
+        for raw_value in [0x0000, 0x0102, 0x7F00, 0x80FF, 0xFFFF] {
+            assert_eq!(TerrainAttributes::from_raw(raw_value).to_raw(), raw_value);
+        }
+    }
+
+    #[test]
+    fn land_data_round_trip() {
+        let terrain_attributes = [0x0102u16, 0xFFFF];
+        let map_props: Vec<u8> = (0..MAP_PROPS_ELEM_SIZE as u8).collect();
+        let map_model = [0xAA, 0xBB, 0xCC];
+        let bdhc = [b'B', b'D', b'H', b'C', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let mut bytes = Vec::new();
+        for section_size in [4, map_props.len(), map_model.len(), bdhc.len()] {
+            bytes.extend_from_slice(&(section_size as u32).to_le_bytes());
+        }
+        for terrain_attributes_elem in terrain_attributes {
+            bytes.extend_from_slice(&terrain_attributes_elem.to_le_bytes());
+        }
+        bytes.extend_from_slice(&map_props);
+        bytes.extend_from_slice(&map_model);
+        bytes.extend_from_slice(&bdhc);
+
+        let land_data = LandData::parse_bytes(&bytes).unwrap();
+
+        assert_eq!(land_data.to_bytes().unwrap(), bytes);
+    }
+}
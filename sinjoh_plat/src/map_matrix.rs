@@ -1,22 +1,28 @@
-//! Data structure and parser for map matrix files.
+//! Data structure, parser and writer for map matrix files.
 //!
 //! Those are the files contained in the `map_matrix.narc` archive.
 
 use std::{
-    io::{self, Cursor, Read},
+    io::{self, Cursor, Read, Write},
     string::FromUtf8Error,
 };
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
-/// Error type for map matrix parsing.
+use sinjoh_nds::bytes::{FromBytes, ToBytes};
+
+/// Error type for map matrix parsing and writing.
 #[derive(Error, Debug)]
 pub enum MapMatrixError {
     /// An I/O error has occurred while trying to read from the buffer.
     #[error("an error has occurred while reading the buffer")]
     ReadError(#[source] io::Error),
 
+    /// An I/O error has occurred while trying to write to the buffer.
+    #[error("an error has occurred while writing to the buffer")]
+    WriteError(#[source] io::Error),
+
     /// Error while converting the model name prefix to a UTF-8 string.
     #[error("unable to convert the model name prefix into a string")]
     ModelNamePrefixConversion(#[source] FromUtf8Error),
@@ -24,6 +30,14 @@ pub enum MapMatrixError {
     /// The specified map index is greater or equal than the amount of maps in the matrix.
     #[error("map index is greater or equal than map count (map index is {0}, map count is {1})")]
     MapIndexTooBig(u16, u16),
+
+    /// The model name prefix is longer than what a map matrix can hold.
+    #[error("model name prefix is too long (length is {0}, maximum is {max})", max = u8::MAX)]
+    ModelNamePrefixTooLong(usize),
+
+    /// The length of a section does not match the amount of maps in the matrix.
+    #[error("section length does not match map count (section length is {0}, map count is {1})")]
+    SectionLengthMismatch(usize, usize),
 }
 
 /// Represents a map matrix file.
@@ -149,6 +163,95 @@ impl MapMatrix {
         })
     }
 
+    /// Serializes a [`MapMatrix`] to a byte vector.
+    ///
+    /// The bytes are in the same format as the one found in the `map_matrix.narc` archive.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MapMatrixError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes a [`MapMatrix`] to the given writer.
+    ///
+    /// The bytes are in the same format as the one found in the `map_matrix.narc` archive.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), MapMatrixError> {
+        let matrix_size = self.height as usize * self.width as usize;
+
+        // Check that every section has one element per map
+        let section_lengths = [
+            self.map_header_ids.as_ref().map(Vec::len),
+            self.altitudes.as_ref().map(Vec::len),
+            Some(self.land_data_ids.len()),
+        ];
+
+        if let Some(section_length) = section_lengths
+            .into_iter()
+            .flatten()
+            .find(|&section_length| section_length != matrix_size)
+        {
+            return Err(MapMatrixError::SectionLengthMismatch(
+                section_length,
+                matrix_size,
+            ));
+        }
+
+        let model_name_prefix_length: u8 =
+            self.model_name_prefix.len().try_into().map_err(|_| {
+                MapMatrixError::ModelNamePrefixTooLong(self.model_name_prefix.len())
+            })?;
+
+        // Write the map matrix size
+        writer
+            .write_u8(self.height)
+            .map_err(MapMatrixError::WriteError)?;
+        writer
+            .write_u8(self.width)
+            .map_err(MapMatrixError::WriteError)?;
+
+        // Write whether some sections are present
+        writer
+            .write_u8(self.map_header_ids.is_some().into())
+            .map_err(MapMatrixError::WriteError)?;
+        writer
+            .write_u8(self.altitudes.is_some().into())
+            .map_err(MapMatrixError::WriteError)?;
+
+        // Write the model name prefix
+        writer
+            .write_u8(model_name_prefix_length)
+            .map_err(MapMatrixError::WriteError)?;
+        writer
+            .write_all(self.model_name_prefix.as_bytes())
+            .map_err(MapMatrixError::WriteError)?;
+
+        // Write the map header IDs
+        if let Some(map_header_ids) = &self.map_header_ids {
+            for map_header_id in map_header_ids.iter() {
+                writer
+                    .write_u16::<LittleEndian>(*map_header_id)
+                    .map_err(MapMatrixError::WriteError)?;
+            }
+        }
+
+        // Write the altitudes
+        if let Some(altitudes) = &self.altitudes {
+            writer
+                .write_all(altitudes)
+                .map_err(MapMatrixError::WriteError)?;
+        }
+
+        // Write the land data IDs
+        for land_data_id in self.land_data_ids.iter() {
+            writer
+                .write_u16::<LittleEndian>(*land_data_id)
+                .map_err(MapMatrixError::WriteError)?;
+        }
+
+        Ok(())
+    }
+
     /// Transforms a map index into 2D coordinates.
     pub fn map_index_to_coords(&self, index: u16) -> Result<(u16, u16), MapMatrixError> {
         let map_width = self.width as u16;
@@ -162,3 +265,46 @@ impl MapMatrix {
         }
     }
 }
+
+impl FromBytes for MapMatrix {
+    type Error = MapMatrixError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for MapMatrix {
+    type Error = MapMatrixError;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Self::to_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_matrix_round_trip() {
+        // 1x2 matrix with every section and a 3-character model name prefix
+        let bytes = [
+            0x01, 0x02, 0x01, 0x01, 0x03, b'a', b'b', b'c', 0x10, 0x00, 0x11, 0x00, 0x05, 0x06,
+            0x20, 0x00, 0xFF, 0xFF,
+        ];
+
+        let map_matrix = MapMatrix::parse_bytes(&bytes).unwrap();
+
+        assert_eq!(map_matrix.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn map_matrix_without_optional_sections_round_trip() {
+        let bytes = [0x02, 0x01, 0x00, 0x00, 0x00, 0x20, 0x00, 0x21, 0x00];
+
+        let map_matrix = MapMatrix::parse_bytes(&bytes).unwrap();
+
+        assert_eq!(map_matrix.to_bytes().unwrap(), bytes);
+    }
+}
@@ -1,12 +1,14 @@
-//! Data structure and parser for map prop animation list files.
+//! Data structure, parser and writer for map prop animation list files.
 //!
 //! Those are the files contained in the `bm_anime_list.narc` archive.
 
-use std::io::{self, Cursor, Seek};
+use std::io::{self, Cursor, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
+use sinjoh_nds::bytes::{FromBytes, ToBytes};
+
 /// The mask to get the deferred loading flag from the raw map prop animation list flags.
 pub const FLAG_DEFERRED_LOADING_MASK: u8 = 0x01;
 
@@ -20,7 +22,7 @@ pub const MAX_MAP_PROP_ANIMATIONS: u8 = 4;
 /// The ID for an invalid map prop animation.
 pub const INVALID_MAP_PROP_ANIMATION_ID: u32 = 0xFFFFFFFF;
 
-/// Error type for map prop animation list parsing.
+/// Error type for map prop animation list parsing and writing.
 #[derive(Error, Debug)]
 pub enum MapPropAnimationListError {
     /// An I/O error has occurred while trying to read from the buffer.
@@ -30,18 +32,25 @@ pub enum MapPropAnimationListError {
     /// An I/O error has occurred while trying to seek in the buffer.
     #[error("a seek error has occurred while seeking in the buffer")]
     SeekError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to write to the buffer.
+    #[error("an error has occurred while writing to the buffer")]
+    WriteError(#[source] io::Error),
 }
 
 /// Represents a map prop animation list file.
 #[derive(Debug, Clone)]
 pub struct MapPropAnimationList {
+    /// The raw value of the boolean field that informs if there are animations.
+    ///
+    /// This value is written back as-is, and is not updated when the animation IDs change.
+    pub raw_has_animations: u8,
+
     /// IDs of the animations that can be loaded for a map prop.
     ///
     /// Array of indexes in the `bm_anime.narc` NARC. Each map prop model supports up to 4
-    /// animations.
-    ///
-    /// This array can be empty if the map prop does not have any animations.
-    pub map_prop_animation_ids: Vec<u32>,
+    /// animations, and the unused slots are set to [`INVALID_MAP_PROP_ANIMATION_ID`].
+    pub map_prop_animation_ids: [u32; MAX_MAP_PROP_ANIMATIONS as usize],
 
     /// Whether loading the animations is deferred.
     ///
@@ -59,8 +68,21 @@ pub struct MapPropAnimationList {
     /// shaking).
     pub deferred_add_to_render_object: bool,
 
+    /// Unknown: bits of the raw flags other than the known ones, kept as-is so that they can be
+    /// written back.
+    pub unknown_flags: u8,
+
     /// Whether the map prop model is a slope for the bicycle.
     pub is_bicycle_slope: bool,
+
+    /// The raw value of the bicycle slope field.
+    ///
+    /// The game only checks whether this value is zero, so it is written back as-is when it agrees
+    /// with [`MapPropAnimationList::is_bicycle_slope`].
+    pub raw_is_bicycle_slope: u8,
+
+    /// Unknown: unused in the code.
+    pub dummy: u8,
 }
 
 impl MapPropAnimationList {
@@ -71,47 +93,119 @@ impl MapPropAnimationList {
     pub fn parse_bytes(bytes: &[u8]) -> Result<Self, MapPropAnimationListError> {
         let mut reader = Cursor::new(bytes);
 
-        // Skip the boolean field that informs if there are animations
-        reader
-            .seek_relative(1)
-            .map_err(MapPropAnimationListError::SeekError)?;
+        // Read the boolean field that informs if there are animations
+        let raw_has_animations = reader
+            .read_u8()
+            .map_err(MapPropAnimationListError::ReadError)?;
 
         // Read the flags
-        let (deferred_loading, deferred_add_to_render_object) = Self::parse_flags(&mut reader)?;
+        let (deferred_loading, deferred_add_to_render_object, unknown_flags) =
+            Self::parse_flags(&mut reader)?;
         let raw_is_bicycle_slope = reader
             .read_u8()
             .map_err(MapPropAnimationListError::ReadError)?;
 
-        // Skip the dummy field
-        reader
-            .seek_relative(1)
-            .map_err(MapPropAnimationListError::SeekError)?;
+        // Read the dummy field
+        let dummy = reader
+            .read_u8()
+            .map_err(MapPropAnimationListError::ReadError)?;
 
         // Read the animation IDs
-        let mut map_prop_animation_ids = Vec::with_capacity(MAX_MAP_PROP_ANIMATIONS.into());
+        let mut map_prop_animation_ids =
+            [INVALID_MAP_PROP_ANIMATION_ID; MAX_MAP_PROP_ANIMATIONS as usize];
 
-        for _ in 0..MAX_MAP_PROP_ANIMATIONS {
-            let map_prop_animation_id = reader
+        for map_prop_animation_id in map_prop_animation_ids.iter_mut() {
+            *map_prop_animation_id = reader
                 .read_u32::<LittleEndian>()
                 .map_err(MapPropAnimationListError::ReadError)?;
-
-            if map_prop_animation_id == INVALID_MAP_PROP_ANIMATION_ID {
-                break;
-            }
-
-            map_prop_animation_ids.push(map_prop_animation_id);
         }
 
         Ok(Self {
+            raw_has_animations,
             map_prop_animation_ids,
             deferred_loading,
             deferred_add_to_render_object,
+            unknown_flags,
             is_bicycle_slope: raw_is_bicycle_slope != 0,
+            raw_is_bicycle_slope,
+            dummy,
         })
     }
 
+    /// Returns the IDs of the animations that can be loaded for a map prop, skipping the unused
+    /// slots.
+    pub fn valid_map_prop_animation_ids(&self) -> impl Iterator<Item = u32> {
+        self.map_prop_animation_ids
+            .into_iter()
+            .filter(|&map_prop_animation_id| map_prop_animation_id != INVALID_MAP_PROP_ANIMATION_ID)
+    }
+
+    /// Serializes a [`MapPropAnimationList`] to a byte vector.
+    ///
+    /// The bytes are in the same format as the one found in the `bm_anime_list.narc` archive.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MapPropAnimationListError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes a [`MapPropAnimationList`] to the given writer.
+    ///
+    /// The bytes are in the same format as the one found in the `bm_anime_list.narc` archive.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), MapPropAnimationListError> {
+        // Write the boolean field that informs if there are animations
+        writer
+            .write_u8(self.raw_has_animations)
+            .map_err(MapPropAnimationListError::WriteError)?;
+
+        // Write the flags
+        let mut raw_flags = self.unknown_flags
+            & !(FLAG_DEFERRED_LOADING_MASK | FLAG_DEFERRED_ADD_TO_RENDER_OBJECT_MASK);
+        if self.deferred_loading {
+            raw_flags |= FLAG_DEFERRED_LOADING_MASK;
+        }
+        if self.deferred_add_to_render_object {
+            raw_flags |= FLAG_DEFERRED_ADD_TO_RENDER_OBJECT_MASK;
+        }
+
+        writer
+            .write_u8(raw_flags)
+            .map_err(MapPropAnimationListError::WriteError)?;
+
+        // Write the bicycle slope field, keeping its raw value if it is still accurate
+        let raw_is_bicycle_slope = match (self.is_bicycle_slope, self.raw_is_bicycle_slope) {
+            (true, 0) => 1,
+            (true, raw_value) => raw_value,
+            (false, _) => 0,
+        };
+
+        writer
+            .write_u8(raw_is_bicycle_slope)
+            .map_err(MapPropAnimationListError::WriteError)?;
+
+        // Write the dummy field
+        writer
+            .write_u8(self.dummy)
+            .map_err(MapPropAnimationListError::WriteError)?;
+
+        // Write the animation IDs
+        for map_prop_animation_id in self.map_prop_animation_ids {
+            writer
+                .write_u32::<LittleEndian>(map_prop_animation_id)
+                .map_err(MapPropAnimationListError::WriteError)?;
+        }
+
+        Ok(())
+    }
+
     /// Parses the animations flags from the reader.
-    fn parse_flags(reader: &mut Cursor<&[u8]>) -> Result<(bool, bool), MapPropAnimationListError> {
+    ///
+    /// Returns the deferred loading flag, the deferred add to render object flag, and the unknown
+    /// bits of the flags.
+    fn parse_flags(
+        reader: &mut Cursor<&[u8]>,
+    ) -> Result<(bool, bool, u8), MapPropAnimationListError> {
         // Read the raw flags
         let raw_flags = reader
             .read_u8()
@@ -121,10 +215,60 @@ impl MapPropAnimationList {
         let deferred_loading_flag = raw_flags & FLAG_DEFERRED_LOADING_MASK;
         let deferred_add_to_render_object_flag =
             raw_flags & FLAG_DEFERRED_ADD_TO_RENDER_OBJECT_MASK;
+        let unknown_flags =
+            raw_flags & !(FLAG_DEFERRED_LOADING_MASK | FLAG_DEFERRED_ADD_TO_RENDER_OBJECT_MASK);
 
         Ok((
             deferred_loading_flag != 0,
             deferred_add_to_render_object_flag != 0,
+            unknown_flags,
         ))
     }
 }
+
+impl FromBytes for MapPropAnimationList {
+    type Error = MapPropAnimationListError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for MapPropAnimationList {
+    type Error = MapPropAnimationListError;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Self::to_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_prop_animation_list_round_trip() {
+        let mut bytes = vec![0x01, 0x07, 0x02, 0x00];
+        for map_prop_animation_id in [
+            5,
+            INVALID_MAP_PROP_ANIMATION_ID,
+            6,
+            INVALID_MAP_PROP_ANIMATION_ID,
+        ] {
+            bytes.extend_from_slice(&u32::to_le_bytes(map_prop_animation_id));
+        }
+
+        let map_prop_animation_list = MapPropAnimationList::parse_bytes(&bytes).unwrap();
+
+        assert!(map_prop_animation_list.deferred_loading);
+        assert!(map_prop_animation_list.deferred_add_to_render_object);
+        assert!(map_prop_animation_list.is_bicycle_slope);
+        assert_eq!(
+            map_prop_animation_list
+                .valid_map_prop_animation_ids()
+                .collect::<Vec<_>>(),
+            [5, 6]
+        );
+        assert_eq!(map_prop_animation_list.to_bytes().unwrap(), bytes);
+    }
+}
@@ -1,12 +1,14 @@
-//! Data structure and parser for map prop material & shapes files.
+//! Data structure, parser and writer for map prop material & shapes files.
 //!
 //! Those are the files contained in the `build_model_matshp.dat` file.
 
-use std::io::{self, Cursor};
+use std::io::{self, Cursor, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
+use sinjoh_nds::bytes::{FromBytes, ToBytes};
+
 /// Represents IDs locators for finding the material and shapes IDs associated with a map prop.
 #[derive(Debug, Clone, Copy)]
 pub struct MapPropMaterialShapesLocators {
@@ -27,12 +29,20 @@ pub struct MapPropMaterialShapesIDs {
     pub shape_id: u16,
 }
 
-/// Error type for map prop material & shapes parsing.
+/// Error type for map prop material & shapes parsing and writing.
 #[derive(Error, Debug)]
 pub enum MapPropMaterialShapesError {
     /// An I/O error has occurred while trying to read from the buffer.
     #[error("an error has occurred while reading the buffer")]
     ReadError(#[source] io::Error),
+
+    /// An I/O error has occurred while trying to write to the buffer.
+    #[error("an error has occurred while writing to the buffer")]
+    WriteError(#[source] io::Error),
+
+    /// There are more map props or IDs than what the file can hold.
+    #[error("too many elements in a list (got {0}, maximum is {max})", max = u16::MAX)]
+    TooManyElements(usize),
 }
 
 /// Represents the material and shapes associated with a map prop.
@@ -51,6 +61,40 @@ impl MapPropMaterialShapes {
     /// It is expected that the slice is in the same format as the one found in the
     /// `build_model_matshp.dat` file.
     pub fn parse_bytes(bytes: &[u8]) -> Result<Vec<Option<Self>>, MapPropMaterialShapesError> {
+        MapPropMaterialShapesList::parse_bytes(bytes).map(|list| list.map_prop_material_shapes)
+    }
+}
+
+/// Represents a whole map prop material & shapes file.
+///
+/// The material and shapes of a map prop model are at the index of the model in the
+/// `build_model.narc` NARC, or `None` if the model has none.
+///
+/// The locators and the IDs list of the file are also kept as they were parsed, so that the parts
+/// of the file that are not referenced by any map prop are written back as-is.
+#[derive(Debug, Clone, Default)]
+pub struct MapPropMaterialShapesList {
+    /// The material and shapes associated with each map prop model.
+    pub map_prop_material_shapes: Vec<Option<MapPropMaterialShapes>>,
+
+    /// The IDs locators, as stored in the file.
+    ///
+    /// When writing the file, those are used to locate the map prop models without material &
+    /// shapes.
+    pub raw_locators: Vec<MapPropMaterialShapesLocators>,
+
+    /// The IDs list, as stored in the file.
+    ///
+    /// When writing the file, the IDs of each map prop are written over this list.
+    pub raw_ids: Vec<MapPropMaterialShapesIDs>,
+}
+
+impl MapPropMaterialShapesList {
+    /// Parses a [`MapPropMaterialShapesList`] from a byte slice.
+    ///
+    /// It is expected that the slice is in the same format as the one found in the
+    /// `build_model_matshp.dat` file.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, MapPropMaterialShapesError> {
         let mut reader = Cursor::new(bytes);
 
         // Read the header
@@ -63,27 +107,127 @@ impl MapPropMaterialShapes {
             .map_err(MapPropMaterialShapesError::ReadError)?;
 
         // Read the data
-        let locators = Self::parse_locators(&mut reader, locators_count)?;
-        let ids = Self::parse_ids(&mut reader, ids_count)?;
+        let raw_locators = Self::parse_locators(&mut reader, locators_count)?;
+        let raw_ids = Self::parse_ids(&mut reader, ids_count)?;
 
         // Transform the data into something nicer
-        let mut map_prop_material_shapes = Vec::with_capacity(locators.len());
+        let mut map_prop_material_shapes = Vec::with_capacity(raw_locators.len());
 
-        for locator in locators.into_iter() {
+        for locator in raw_locators.iter() {
             if locator.ids_count > 0 {
                 let start_index: usize = locator.ids_index.into();
                 let end_index: usize = (locator.ids_index + locator.ids_count - 1).into();
 
-                map_prop_material_shapes.push(Some(Self {
+                map_prop_material_shapes.push(Some(MapPropMaterialShapes {
                     ids_index: locator.ids_index,
-                    ids: ids[start_index..=end_index].to_vec(),
+                    ids: raw_ids[start_index..=end_index].to_vec(),
                 }));
             } else {
                 map_prop_material_shapes.push(None);
             }
         }
 
-        Ok(map_prop_material_shapes)
+        Ok(Self {
+            map_prop_material_shapes,
+            raw_locators,
+            raw_ids,
+        })
+    }
+
+    /// Serializes a [`MapPropMaterialShapesList`] to a byte vector.
+    ///
+    /// The bytes are in the same format as the one found in the `build_model_matshp.dat` file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MapPropMaterialShapesError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Writes a [`MapPropMaterialShapesList`] to the given writer.
+    ///
+    /// The IDs of each map prop are written over the original IDs list (See
+    /// [`MapPropMaterialShapesList::raw_ids`]), where they were originally located (See
+    /// [`MapPropMaterialShapes::ids_index`]), so that map props can keep sharing them. Map props
+    /// without material & shapes keep their original locator, or are located at the start of the
+    /// list if they had none.
+    ///
+    /// The bytes are in the same format as the one found in the `build_model_matshp.dat` file.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), MapPropMaterialShapesError> {
+        // Rebuild the locators and the IDs list
+        let mut locators = Vec::with_capacity(self.map_prop_material_shapes.len());
+        let mut ids = self.raw_ids.clone();
+
+        for (index, material_shapes) in self.map_prop_material_shapes.iter().enumerate() {
+            let Some(material_shapes) = material_shapes else {
+                let ids_index = self
+                    .raw_locators
+                    .get(index)
+                    .filter(|locator| locator.ids_count == 0)
+                    .map_or(0, |locator| locator.ids_index);
+
+                locators.push(MapPropMaterialShapesLocators {
+                    ids_count: 0,
+                    ids_index,
+                });
+
+                continue;
+            };
+
+            let start_index: usize = material_shapes.ids_index.into();
+            let end_index = start_index + material_shapes.ids.len();
+
+            if ids.len() < end_index {
+                ids.resize(
+                    end_index,
+                    MapPropMaterialShapesIDs {
+                        material_id: 0,
+                        shape_id: 0,
+                    },
+                );
+            }
+
+            ids[start_index..end_index].copy_from_slice(&material_shapes.ids);
+
+            locators.push(MapPropMaterialShapesLocators {
+                ids_count: material_shapes.ids.len().try_into().map_err(|_| {
+                    MapPropMaterialShapesError::TooManyElements(material_shapes.ids.len())
+                })?,
+                ids_index: material_shapes.ids_index,
+            });
+        }
+
+        // Write the header
+        for count in [locators.len(), ids.len()] {
+            let count: u16 = count
+                .try_into()
+                .map_err(|_| MapPropMaterialShapesError::TooManyElements(count))?;
+
+            writer
+                .write_u16::<LittleEndian>(count)
+                .map_err(MapPropMaterialShapesError::WriteError)?;
+        }
+
+        // Write the data
+        for locator in locators.iter() {
+            writer
+                .write_u16::<LittleEndian>(locator.ids_count)
+                .map_err(MapPropMaterialShapesError::WriteError)?;
+            writer
+                .write_u16::<LittleEndian>(locator.ids_index)
+                .map_err(MapPropMaterialShapesError::WriteError)?;
+        }
+
+        for id in ids.iter() {
+            writer
+                .write_u16::<LittleEndian>(id.material_id)
+                .map_err(MapPropMaterialShapesError::WriteError)?;
+            writer
+                .write_u16::<LittleEndian>(id.shape_id)
+                .map_err(MapPropMaterialShapesError::WriteError)?;
+        }
+
+        Ok(())
     }
 
     /// Parses the IDs locators from the reader.
@@ -136,3 +280,48 @@ impl MapPropMaterialShapes {
         Ok(ids)
     }
 }
+
+impl From<Vec<Option<MapPropMaterialShapes>>> for MapPropMaterialShapesList {
+    fn from(map_prop_material_shapes: Vec<Option<MapPropMaterialShapes>>) -> Self {
+        Self {
+            map_prop_material_shapes,
+            ..Default::default()
+        }
+    }
+}
+
+impl FromBytes for MapPropMaterialShapesList {
+    type Error = MapPropMaterialShapesError;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl ToBytes for MapPropMaterialShapesList {
+    type Error = MapPropMaterialShapesError;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
+        Self::to_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_prop_material_shapes_list_round_trip() {
+        // The second locator is empty but not at the start of the list, and the last ID is not
+        // referenced by any locator
+        let mut bytes = Vec::new();
+        for value in [2u16, 3, 2, 0, 0, 1, 10, 11, 12, 13, 14, 15] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let map_prop_material_shapes_list = MapPropMaterialShapesList::parse_bytes(&bytes).unwrap();
+
+        assert!(map_prop_material_shapes_list.map_prop_material_shapes[1].is_none());
+        assert_eq!(map_prop_material_shapes_list.to_bytes().unwrap(), bytes);
+    }
+}